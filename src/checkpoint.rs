@@ -0,0 +1,158 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Progress of an `all` run as saved to disk
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CheckpointState {
+    music_dir: String,
+    completed_steps: BTreeSet<String>,
+    /// Albums already handled by steps that had not finished yet
+    completed_albums: BTreeMap<String, BTreeSet<PathBuf>>,
+}
+
+/// Records which `all` steps and albums finished, so an interrupted run can be resumed
+pub struct Checkpoint {
+    path: PathBuf,
+    state: Mutex<CheckpointState>,
+}
+
+/// Checkpoint file for a music directory, under `$XDG_CACHE_HOME/mfutil/checkpoints`
+pub fn checkpoint_path(music_dir: &str) -> PathBuf {
+    let cache_home = std::env::var("XDG_CACHE_HOME")
+        .unwrap_or_else(|_| format!("{}/.cache", std::env::var("HOME").unwrap_or_default()));
+    Path::new(&cache_home)
+        .join("mfutil")
+        .join("checkpoints")
+        .join(format!("{}.json", urlencoding::encode(music_dir)))
+}
+
+/// Ask on the terminal whether to resume an interrupted run; never resumes when not interactive
+pub fn prompt_resume(music_dir: &str) -> Result<bool> {
+    if !io::stdin().is_terminal() {
+        tracing::info!(
+            "Found an interrupted `all` run for {}; starting over (pass --resume to continue it)",
+            music_dir
+        );
+        return Ok(false);
+    }
+
+    print!(
+        "An earlier `all` run on {} was interrupted. Resume where it left off? [y/N] ",
+        music_dir
+    );
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+impl Checkpoint {
+    /// Open the checkpoint stored at `path`
+    /// Saved progress is only picked up when `resume` is set; otherwise the run starts afresh
+    pub fn open(path: PathBuf, music_dir: &str, resume: bool) -> Result<Self> {
+        let state = match fs::read_to_string(&path) {
+            Ok(json) if resume => serde_json::from_str(&json)
+                .with_context(|| format!("Failed to read checkpoint: {}", path.display()))?,
+            _ => CheckpointState {
+                music_dir: music_dir.to_string(),
+                ..CheckpointState::default()
+            },
+        };
+        Ok(Self {
+            path,
+            state: Mutex::new(state),
+        })
+    }
+
+    pub fn step_done(&self, step: &str) -> bool {
+        self.state.lock().unwrap().completed_steps.contains(step)
+    }
+
+    pub fn album_done(&self, step: &str, album: &Path) -> bool {
+        let state = self.state.lock().unwrap();
+        state
+            .completed_albums
+            .get(step)
+            .is_some_and(|albums| albums.contains(album))
+    }
+
+    pub fn mark_album(&self, step: &str, album: &Path) {
+        let mut state = self.state.lock().unwrap();
+        state
+            .completed_albums
+            .entry(step.to_string())
+            .or_default()
+            .insert(album.to_path_buf());
+    }
+
+    /// Mark a whole step as finished; its per-album progress is no longer needed
+    pub fn mark_step(&self, step: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.completed_albums.remove(step);
+        state.completed_steps.insert(step.to_string());
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(&*self.state.lock().unwrap())?;
+        fs::write(&self.path, json)
+            .with_context(|| format!("Failed to write checkpoint: {}", self.path.display()))
+    }
+
+    /// Remove the checkpoint once a run has completed
+    pub fn clear(&self) -> Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_checkpoint_path_is_keyed_by_music_dir() {
+        assert_ne!(
+            checkpoint_path("/srv/music"),
+            checkpoint_path("/srv/music/other")
+        );
+        assert!(checkpoint_path("/srv/music").ends_with("%2Fsrv%2Fmusic.json"));
+    }
+
+    #[test]
+    fn test_checkpoint_resume_round_trip() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("checkpoints").join("music.json");
+        let album = Path::new("/srv/music/Artists/A/B");
+
+        let checkpoint = Checkpoint::open(path.clone(), "/srv/music", true)?;
+        checkpoint.mark_step("sync");
+        checkpoint.mark_album("album-art", album);
+        checkpoint.save()?;
+
+        let resumed = Checkpoint::open(path.clone(), "/srv/music", true)?;
+        assert!(resumed.step_done("sync"));
+        assert!(!resumed.step_done("album-art"));
+        assert!(resumed.album_done("album-art", album));
+
+        // Without --resume the saved progress is ignored
+        let fresh = Checkpoint::open(path.clone(), "/srv/music", false)?;
+        assert!(!fresh.step_done("sync"));
+
+        resumed.clear()?;
+        assert!(!path.exists());
+        resumed.clear()?;
+
+        Ok(())
+    }
+}
@@ -1,8 +0,0 @@
-pub mod albums;
-pub mod art;
-pub mod cd;
-pub mod import;
-pub mod organize;
-pub mod reorganize;
-pub mod sync;
-pub mod tracks;
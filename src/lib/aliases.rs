@@ -0,0 +1,145 @@
+use anyhow::{Context, Result};
+use rustc_hash::FxHashMap;
+use std::path::Path;
+use unicode_normalization::UnicodeNormalization;
+
+/// Raw-to-canonical artist and album names, loaded from an aliases file
+///
+/// The file is CSV with one alias per line: `artist,<raw>,<canonical>` or
+/// `album,<raw>,<canonical>`. Fields containing commas can be double-quoted; blank lines and
+/// lines starting with `#` are ignored. Raw names match case-insensitively after Unicode
+/// (NFKC) normalization, so "motley crue" matches "Motley Crue" and a precomposed "ö"
+/// matches an "o" followed by a combining diaeresis.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Aliases {
+    artists: FxHashMap<String, String>,
+    albums: FxHashMap<String, String>,
+}
+
+impl Aliases {
+    /// Read an aliases file
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read aliases file: {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("Invalid aliases file: {}", path.display()))
+    }
+
+    /// Parse aliases from CSV text
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut aliases = Self::default();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields = split_csv_line(line);
+            let [kind, raw, canonical] = fields.as_slice() else {
+                anyhow::bail!(
+                    "line {}: expected 3 fields (kind,raw,canonical), found {}",
+                    index + 1,
+                    fields.len()
+                );
+            };
+            let table = match kind.to_ascii_lowercase().as_str() {
+                "artist" => &mut aliases.artists,
+                "album" => &mut aliases.albums,
+                other => anyhow::bail!(
+                    "line {}: unknown alias kind '{}' (expected artist or album)",
+                    index + 1,
+                    other
+                ),
+            };
+            if raw.is_empty() || canonical.is_empty() {
+                anyhow::bail!("line {}: names must not be empty", index + 1);
+            }
+            table.insert(alias_key(raw), canonical.to_string());
+        }
+        Ok(aliases)
+    }
+
+    /// The canonical name for `artist`, or `artist` itself if it has no alias
+    pub fn artist<'a>(&'a self, artist: &'a str) -> &'a str {
+        lookup(&self.artists, artist)
+    }
+
+    /// The canonical name for `album`, or `album` itself if it has no alias
+    pub fn album<'a>(&'a self, album: &'a str) -> &'a str {
+        lookup(&self.albums, album)
+    }
+}
+
+fn lookup<'a>(table: &'a FxHashMap<String, String>, name: &'a str) -> &'a str {
+    if table.is_empty() {
+        return name;
+    }
+    table.get(&alias_key(name)).map_or(name, String::as_str)
+}
+
+/// Matching key for a name: NFKC-normalized, lowercased, with whitespace collapsed
+fn alias_key(name: &str) -> String {
+    let normalized: String = name.nfkc().collect::<String>().to_lowercase();
+    normalized.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Split a CSV line into trimmed fields, honouring double quotes (`""` is a literal quote)
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut field).trim().to_string()),
+            c => field.push(c),
+        }
+    }
+    fields.push(field.trim().to_string());
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aliases_match_case_and_unicode_insensitively() -> Result<()> {
+        let aliases = Aliases::parse(
+            "# kind,raw,canonical\n\
+             artist,Beyonce,Beyonc\u{e9}\n\
+             artist,Mo\u{308}tley Cru\u{308}e,Motley Crue\n\
+             artist,\"Crosby, Stills  and Nash\",\"Crosby, Stills & Nash\"\n\
+             \n\
+             album,Sgt Peppers,Sgt. Pepper's Lonely Hearts Club Band\n",
+        )?;
+
+        assert_eq!(aliases.artist("BEYONCE"), "Beyonc\u{e9}");
+        // Full-width and precomposed forms normalize to the same key as the raw name
+        assert_eq!(aliases.artist("\u{ff22}eyonce"), "Beyonc\u{e9}");
+        assert_eq!(aliases.artist("M\u{f6}tley Cr\u{fc}e"), "Motley Crue");
+        assert_eq!(
+            aliases.artist("crosby, stills and nash"),
+            "Crosby, Stills & Nash"
+        );
+        assert_eq!(
+            aliases.album("sgt peppers"),
+            "Sgt. Pepper's Lonely Hearts Club Band"
+        );
+        // Artist and album aliases are kept apart, and unknown names pass through
+        assert_eq!(aliases.album("Beyonce"), "Beyonce");
+        assert_eq!(aliases.artist("The The"), "The The");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_aliases_parse_rejects_malformed_lines() {
+        assert!(Aliases::parse("artist,Only Two").is_err());
+        assert!(Aliases::parse("label,Raw,Canonical").is_err());
+        assert!(Aliases::parse("album,,Canonical").is_err());
+    }
+}
@@ -1,3 +1,7 @@
+use lofty::file::{AudioFile, FileType, TaggedFileExt};
+use lofty::probe::Probe;
+use std::fs::File;
+use std::io::BufReader;
 use std::path::Path;
 
 /// Audio file format constants used across the application
@@ -21,6 +25,14 @@ pub const AIFF_EXTENSIONS: &[&str] = &["aiff", "aif"];
 /// WAV audio formats
 pub const WAV_EXTENSIONS: &[&str] = &["wav"];
 
+/// Common non-audio extensions found in music folders that are never sniffed, including the
+/// sidecars mfutil writes (lyrics, checksum manifests, tag backups) and the `.part` files copies
+/// and rips are written under until complete
+pub const NON_AUDIO_EXTENSIONS: &[&str] = &[
+    "jpg", "jpeg", "png", "gif", "bmp", "webp", "txt", "nfo", "log", "cue", "m3u", "m3u8", "pls",
+    "pdf", "sfv", "md5", "db", "ini", "lrc", "sha256", "json", "part",
+];
+
 /// Get all supported audio file extensions as a combined vector
 pub fn get_all_audio_extensions() -> Vec<&'static str> {
     ID3_EXTENSIONS
@@ -34,7 +46,9 @@ pub fn get_all_audio_extensions() -> Vec<&'static str> {
         .collect()
 }
 
-/// Check if a file path has a supported audio extension
+/// Check if a file path is a supported audio file
+/// The extension is checked first; files with a missing or unrecognized
+/// extension fall back to sniffing their contents with `detect_audio_type`
 pub fn is_audio_file<P: AsRef<Path>>(path: P) -> bool {
     let path = path.as_ref();
     let ext = path
@@ -43,9 +57,90 @@ pub fn is_audio_file<P: AsRef<Path>>(path: P) -> bool {
         .map(|e| e.to_lowercase())
         .unwrap_or_default();
 
-    get_all_audio_extensions()
+    if get_all_audio_extensions()
         .iter()
         .any(|&supported_ext| supported_ext == ext)
+    {
+        return true;
+    }
+
+    if NON_AUDIO_EXTENSIONS.contains(&ext.as_str()) {
+        return false;
+    }
+
+    detect_audio_type(path).is_some()
+}
+
+/// Detect the audio format of a file from its contents, ignoring the extension
+/// Returns `None` if the file can't be read or isn't a recognized audio format
+pub fn detect_audio_type<P: AsRef<Path>>(path: P) -> Option<FileType> {
+    let file = File::open(path.as_ref()).ok()?;
+    Probe::new(BufReader::new(file))
+        .guess_file_type()
+        .ok()?
+        .file_type()
+}
+
+/// The format a file's contents are in, when its extension names a different one, such as an
+/// MP3 saved as `.flac`
+/// Only files with an audio extension are opened. Ogg streams count as one format, since
+/// `.ogg` is used for Vorbis, Opus and Speex alike
+pub fn mislabeled_audio_type<P: AsRef<Path>>(path: P) -> Option<FileType> {
+    let path = path.as_ref();
+    let claimed = FileType::from_path(path)?;
+    let actual = detect_audio_type(path)?;
+    let is_ogg = |file_type: &FileType| {
+        matches!(
+            file_type,
+            FileType::Vorbis | FileType::Opus | FileType::Speex
+        )
+    };
+    (actual != claimed && !(is_ogg(&actual) && is_ogg(&claimed))).then_some(actual)
+}
+
+/// Lowest bitrate and sample rate a file may have, e.g. to keep 96 kbps rips out of a library;
+/// lossless files are exempt from the bitrate floor but not the sample rate one
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QualityFloor {
+    /// Minimum audio bitrate in kbps
+    pub min_bitrate: Option<u32>,
+    /// Minimum sample rate in Hz
+    pub min_sample_rate: Option<u32>,
+}
+
+impl QualityFloor {
+    /// Why the file at `path` falls short of the floor, or None if it meets it
+    /// Files whose properties can't be read are let through, for the tag checks to judge
+    pub fn shortfall(&self, path: &Path) -> Option<String> {
+        if self.min_bitrate.is_none() && self.min_sample_rate.is_none() {
+            return None;
+        }
+        let tagged_file = lofty::read_from_path(path).ok()?;
+        let properties = tagged_file.properties();
+        // Only lossless streams report a bit depth, which also catches ALAC in MP4
+        let lossless =
+            is_lossless_type(tagged_file.file_type()) || properties.bit_depth().is_some();
+
+        if let (Some(min), Some(bitrate)) = (self.min_bitrate, properties.audio_bitrate()) {
+            if !lossless && bitrate < min {
+                return Some(format!("bitrate {} kbps below {} kbps", bitrate, min));
+            }
+        }
+        if let (Some(min), Some(rate)) = (self.min_sample_rate, properties.sample_rate()) {
+            if rate < min {
+                return Some(format!("sample rate {} Hz below {} Hz", rate, min));
+            }
+        }
+        None
+    }
+}
+
+/// Whether files of `file_type` are always lossless
+fn is_lossless_type(file_type: FileType) -> bool {
+    matches!(
+        file_type,
+        FileType::Flac | FileType::Wav | FileType::Aiff | FileType::Ape | FileType::WavPack
+    )
 }
 
 /// Get audio extension categories for a given file extension
@@ -96,6 +191,67 @@ mod tests {
         assert!(!is_audio_file("test"));
     }
 
+    #[test]
+    fn test_detect_audio_type_extensionless_flac() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let flac_path = temp_dir.path().join("track01");
+        let mut data = b"fLaC".to_vec();
+        // Last-metadata-block flag + STREAMINFO type, followed by a 34 byte block
+        data.extend_from_slice(&[0x80, 0x00, 0x00, 0x22]);
+        data.extend_from_slice(&[0u8; 34]);
+        std::fs::write(&flac_path, data).unwrap();
+
+        assert_eq!(detect_audio_type(&flac_path), Some(FileType::Flac));
+        assert!(is_audio_file(&flac_path));
+    }
+
+    #[test]
+    fn test_is_audio_file_does_not_sniff_non_audio() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let notes_path = temp_dir.path().join("README");
+        std::fs::write(&notes_path, b"not audio").unwrap();
+        let disguised_path = temp_dir.path().join("cover.jpg");
+        std::fs::write(&disguised_path, b"fLaC").unwrap();
+
+        assert_eq!(detect_audio_type(&notes_path), None);
+        assert!(!is_audio_file(&notes_path));
+        assert!(!is_audio_file(&disguised_path));
+    }
+
+    #[test]
+    fn test_mislabeled_audio_type_names_the_real_format() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let wav_path = temp_dir.path().join("01 Track.wav");
+        crate::test_support::write_silent_wav(&wav_path)?;
+        let mislabeled_path = temp_dir.path().join("02 Track.flac");
+        std::fs::copy(&wav_path, &mislabeled_path)?;
+        let unreadable_path = temp_dir.path().join("03 Track.mp3");
+        std::fs::write(&unreadable_path, b"not audio")?;
+
+        assert_eq!(mislabeled_audio_type(&wav_path), None);
+        assert_eq!(mislabeled_audio_type(&mislabeled_path), Some(FileType::Wav));
+        assert_eq!(mislabeled_audio_type(&unreadable_path), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sidecars_and_part_files_are_not_sniffed() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        for name in [
+            "Track.flac.part",
+            "Track.lrc",
+            "checksums.sha256",
+            "Track.flac.json",
+        ] {
+            let path = temp_dir.path().join(name);
+            crate::test_support::write_silent_wav(&path)?;
+            assert!(!is_audio_file(&path), "{} counted as audio", name);
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn test_get_extension_category() {
         assert_eq!(get_extension_category("mp3"), Some("id3"));
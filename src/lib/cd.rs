@@ -1,19 +1,28 @@
 use anyhow::{Context, Result};
-use musicbrainz_rs::{entity::release::Release, prelude::*, ApiRequest, MusicBrainzClient};
-
-use crate::utils;
-use flacenc::component::BitRepr;
-use flacenc::error::Verify;
-use lofty::{self, file::TaggedFileExt, tag::ItemKey};
+use musicbrainz_rs::{entity::release::Release, prelude::*, ApiRequest};
+
+use crate::checksums::ChecksumAlgorithm;
+use crate::musicbrainz::Network;
+use crate::provenance::{self, Source};
+use crate::{gapless, metadata, utils};
+use lofty::{self, tag::ItemKey};
+use serde::Deserialize;
 use serde_json;
-use std::path::Path;
+use std::collections::{BTreeMap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc;
+use std::time::Duration;
 use tracing::warn;
 
 #[cfg(feature = "cd-ripping")]
 use cdparanoia;
 #[cfg(feature = "cd-ripping")]
 use discid::DiscId;
+#[cfg(feature = "cd-ripping")]
+use flacenc::component::BitRepr;
+#[cfg(feature = "cd-ripping")]
+use flacenc::error::Verify;
 
 /// Information about a CD track
 #[derive(Debug, Clone)]
@@ -34,6 +43,243 @@ pub struct CdInfo {
     pub tracks: Vec<CdTrack>,
     pub total_duration: u64,
     pub release_id: Option<String>,
+    /// Position of this disc within a multi-disc release, once known from MusicBrainz
+    pub disc_number: Option<u32>,
+}
+
+/// Stereo frames per CD sector
+const FRAMES_PER_SECTOR: u64 = 588;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// Running checksums over a track's audio, used to verify rips
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrackChecksum {
+    /// AccurateRip v1 CRC, for comparison against the AccurateRip database
+    pub accuraterip_crc: u32,
+    /// Hash of every PCM frame, for comparing two reads of the same track
+    pub pcm_hash: u64,
+    pub frames: u64,
+    check_from: u64,
+    check_to: u64,
+}
+
+impl TrackChecksum {
+    /// Start checksums for a track of `total_frames`
+    /// As in AccurateRip, the first and last tracks of a disc skip five sectors at the disc edges
+    pub fn new(total_frames: u64, first_track: bool, last_track: bool) -> Self {
+        let edge = 5 * FRAMES_PER_SECTOR;
+        Self {
+            accuraterip_crc: 0,
+            pcm_hash: FNV_OFFSET_BASIS,
+            frames: 0,
+            check_from: if first_track { edge } else { 1 },
+            check_to: if last_track {
+                total_frames.saturating_sub(edge)
+            } else {
+                total_frames
+            },
+        }
+    }
+
+    /// Add interleaved 16-bit stereo samples
+    pub fn update(&mut self, samples: &[i16]) {
+        for frame in samples.chunks_exact(2) {
+            self.frames += 1;
+            let value = (frame[0] as u16 as u32) | ((frame[1] as u16 as u32) << 16);
+            if (self.check_from..=self.check_to).contains(&self.frames) {
+                self.accuraterip_crc = self
+                    .accuraterip_crc
+                    .wrapping_add(value.wrapping_mul(self.frames as u32));
+            }
+            for byte in value.to_le_bytes() {
+                self.pcm_hash = (self.pcm_hash ^ byte as u64).wrapping_mul(FNV_PRIME);
+            }
+        }
+    }
+}
+
+/// CD audio sample rate, in frames per second
+const CD_SAMPLE_RATE: u64 = 44100;
+
+/// How `--trim-silence` decides what silence to cut from the ends of a ripped track
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SilenceTrim {
+    /// Level, in dBFS, below which both channels of a frame count as silent
+    pub threshold_db: f64,
+    /// Silence kept at each end of the track, so short intentional gaps survive
+    pub keep: Duration,
+}
+
+impl Default for SilenceTrim {
+    fn default() -> Self {
+        SilenceTrim {
+            threshold_db: -60.0,
+            keep: Duration::from_secs(2),
+        }
+    }
+}
+
+/// Cuts leading and trailing silence from interleaved 16-bit stereo samples as they stream
+/// through, beyond the [`SilenceTrim::keep`] allowance. Silence between sounds is never cut
+#[derive(Debug, Clone)]
+pub struct SilenceTrimmer {
+    threshold: i32,
+    keep_samples: usize,
+    started: bool,
+    /// The most recent `keep_samples` of leading silence
+    leading: VecDeque<i32>,
+    /// Silence since the last sound, written out only if more sound follows it
+    held: Vec<i32>,
+}
+
+impl SilenceTrimmer {
+    pub fn new(trim: SilenceTrim) -> Self {
+        let amplitude = 32768.0 * 10f64.powf(trim.threshold_db / 20.0);
+        let keep_frames = (trim.keep.as_secs_f64() * CD_SAMPLE_RATE as f64) as usize;
+        Self {
+            threshold: amplitude.clamp(0.0, 32768.0) as i32,
+            keep_samples: keep_frames * 2,
+            started: false,
+            leading: VecDeque::new(),
+            held: Vec::new(),
+        }
+    }
+
+    /// Pass `samples` through, appending what survives trimming so far to `out`
+    pub fn push(&mut self, samples: &[i32], out: &mut Vec<i32>) {
+        for frame in samples.chunks_exact(2) {
+            let silent = frame.iter().all(|sample| sample.abs() <= self.threshold);
+            if !self.started {
+                if silent {
+                    self.leading.extend(frame);
+                    let excess = self.leading.len().saturating_sub(self.keep_samples);
+                    self.leading.drain(..excess);
+                    continue;
+                }
+                self.started = true;
+                out.extend(self.leading.drain(..));
+            }
+            if silent {
+                self.held.extend(frame);
+            } else {
+                out.append(&mut self.held);
+                out.extend(frame);
+            }
+        }
+    }
+
+    /// End of the track: append the allowed part of any trailing silence to `out`
+    /// A track that is silent throughout keeps just the allowance
+    pub fn finish(mut self, out: &mut Vec<i32>) {
+        if !self.started {
+            out.extend(self.leading.drain(..));
+            return;
+        }
+        self.held.truncate(self.keep_samples);
+        out.append(&mut self.held);
+    }
+}
+
+/// Outcome of ripping a single track
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrackRip {
+    pub checksum: TrackChecksum,
+    /// Whether a second read matched the rip, or `None` when verification wasn't requested
+    pub verified: Option<bool>,
+}
+
+/// Album and track text read from a disc's CD-TEXT
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CdText {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub tracks: BTreeMap<u32, CdTextTrack>,
+}
+
+/// CD-TEXT fields for a single track
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CdTextTrack {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+}
+
+impl CdText {
+    /// Whether any album or track text was found
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none() && self.artist.is_none() && self.tracks.is_empty()
+    }
+}
+
+/// Resolve the device to read from, auto-detecting the drive when `device` is "auto"
+pub fn resolve_cd_device(device: &str) -> Result<String> {
+    if device != "auto" {
+        return Ok(device.to_string());
+    }
+
+    optical_device_candidates(Path::new("/dev"))
+        .into_iter()
+        .find(|path| fs::File::open(path).is_ok())
+        .map(|path| path.display().to_string())
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "No readable optical drive found (tried /dev/sr*, /dev/cdrom and /dev/disk/by-id/*). \
+                 Make sure a disc is inserted, or pass the device path explicitly, e.g. `mfutil cd /dev/sr0`"
+            )
+        })
+}
+
+/// List likely optical drives under `dev_dir`, in probing order and without duplicates
+fn optical_device_candidates(dev_dir: &Path) -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+
+    // SCSI CD-ROM nodes (/dev/sr0, /dev/sr1, ...)
+    let mut scsi_nodes: Vec<PathBuf> = fs::read_dir(dev_dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|path| is_optical_node(path))
+        .collect();
+    scsi_nodes.sort();
+    candidates.extend(scsi_nodes);
+
+    candidates.push(dev_dir.join("cdrom"));
+
+    // Persistent names that resolve to an optical node
+    let mut by_id: Vec<PathBuf> = fs::read_dir(dev_dir.join("disk").join("by-id"))
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|path| fs::canonicalize(path).is_ok_and(|target| is_optical_node(&target)))
+        .collect();
+    by_id.sort();
+    candidates.extend(by_id);
+
+    let mut seen = Vec::new();
+    candidates.retain(|path| {
+        if !path.exists() {
+            return false;
+        }
+        let target = fs::canonicalize(path).unwrap_or_else(|_| path.clone());
+        if seen.contains(&target) {
+            return false;
+        }
+        seen.push(target);
+        true
+    });
+
+    candidates
+}
+
+/// Whether a device node is named like a SCSI CD-ROM (`sr0`, `sr1`, ...)
+fn is_optical_node(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .and_then(|n| n.strip_prefix("sr"))
+        .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
 }
 
 /// Read CD Table of Contents and calculate Disc ID using discid
@@ -111,14 +357,133 @@ pub async fn read_cd_from_device(device: &str, tx: mpsc::Sender<String>) -> Resu
 
     let total_duration = tracks.iter().map(|t| t.duration).sum();
 
-    Ok(CdInfo {
+    let mut cd_info = CdInfo {
         disc_id: disc_id_str,
-        title: "Unknown Album".to_string(), // Will be filled by MusicBrainz
-        artist: "Unknown Artist".to_string(), // Will be filled by MusicBrainz
+        title: "Unknown Album".to_string(), // Filled from CD-TEXT, then refined by MusicBrainz
+        artist: "Unknown Artist".to_string(), // Filled from CD-TEXT, then refined by MusicBrainz
         tracks,
         total_duration,
         release_id: None,
-    })
+        disc_number: None,
+    };
+
+    match read_cd_text(device) {
+        Some(cd_text) => {
+            apply_cd_text(&mut cd_info, &cd_text);
+            tx.send(format!(
+                "Found CD-TEXT: {} - {}",
+                cd_info.artist, cd_info.title
+            ))
+            .context("Failed to send CD-TEXT message")?;
+        }
+        None => {
+            tx.send("No CD-TEXT found on disc".to_string())
+                .context("Failed to send CD-TEXT message")?;
+        }
+    }
+
+    Ok(cd_info)
+}
+
+/// Read CD-TEXT using libcdio's `cd-info` tool, if it is installed
+/// Returns `None` when the tool is unavailable or the disc carries no CD-TEXT
+#[cfg(feature = "cd-ripping")]
+fn read_cd_text(device: &str) -> Option<CdText> {
+    let output = std::process::Command::new("cd-info")
+        .args(["--no-header", "--no-device-info", "--no-cddb"])
+        .arg(format!("--cdrom-device={}", device))
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    let cd_text = parse_cd_text(&String::from_utf8_lossy(&output.stdout));
+    (!cd_text.is_empty()).then_some(cd_text)
+}
+
+/// Parse the "CD-TEXT for Disc/Track N" sections of `cd-info` output
+/// Only the first language block is used when a disc carries several
+pub fn parse_cd_text(output: &str) -> CdText {
+    let mut cd_text = CdText::default();
+    // None while outside a CD-TEXT section, Some(None) for the disc, Some(Some(n)) for track n
+    let mut section: Option<Option<u32>> = None;
+
+    for line in output.lines() {
+        let trimmed = line.trim();
+        if let Some(rest) = trimmed.strip_prefix("CD-TEXT for ") {
+            let name = rest.trim_end_matches(':').trim();
+            section = if name == "Disc" {
+                Some(None)
+            } else {
+                name.strip_prefix("Track")
+                    .and_then(|n| n.trim().parse().ok())
+                    .map(Some)
+            };
+            continue;
+        }
+
+        // Section contents are indented; anything else ends the section
+        if !line.starts_with(char::is_whitespace) {
+            section = None;
+            continue;
+        }
+
+        let Some(current) = section else {
+            continue;
+        };
+        let Some((key, value)) = trimmed.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        if value.is_empty() {
+            continue;
+        }
+
+        let is_title = match key.trim() {
+            "TITLE" => true,
+            "PERFORMER" => false,
+            _ => continue,
+        };
+        let (title, artist) = match current {
+            None => (&mut cd_text.title, &mut cd_text.artist),
+            Some(number) => {
+                let track = cd_text.tracks.entry(number).or_default();
+                (&mut track.title, &mut track.artist)
+            }
+        };
+        let field = if is_title { title } else { artist };
+        field.get_or_insert_with(|| value.to_string());
+    }
+
+    cd_text
+}
+
+/// Pre-populate album, artist and track titles from CD-TEXT
+/// Fields missing from the CD-TEXT keep their current values
+pub fn apply_cd_text(cd_info: &mut CdInfo, cd_text: &CdText) {
+    if let Some(title) = &cd_text.title {
+        cd_info.title = title.clone();
+    }
+    if let Some(artist) = &cd_text.artist {
+        cd_info.artist = artist.clone();
+    }
+
+    for track in &mut cd_info.tracks {
+        let track_text = cd_text.tracks.get(&track.number);
+        if let Some(title) = track_text.and_then(|t| t.title.as_ref()) {
+            track.title = title.clone();
+            track.filename = format!(
+                "{:02} {}.flac",
+                track.number,
+                utils::sanitize_filename(title)
+            );
+        }
+        track.artist = track_text
+            .and_then(|t| t.artist.clone())
+            .unwrap_or_else(|| cd_info.artist.clone());
+    }
 }
 
 #[cfg(not(feature = "cd-ripping"))]
@@ -128,15 +493,91 @@ pub async fn read_cd_from_device(_device: &str, tx: mpsc::Sender<String>) -> Res
     Err(anyhow::anyhow!("CD ripping feature is not enabled."))
 }
 
+/// MusicBrainz `discid` lookup response, limited to the fields a rip uses
+#[derive(Debug, Deserialize)]
+struct DiscIdResponse {
+    /// Releases the disc appears on, best match first
+    releases: Vec<DiscIdRelease>,
+}
+
+/// A release in a [`DiscIdResponse`]
+#[derive(Debug, Deserialize)]
+struct DiscIdRelease {
+    id: String,
+    title: Option<String>,
+    #[serde(rename = "artist-credit", default)]
+    artist_credit: Vec<DiscIdArtistCredit>,
+    #[serde(default)]
+    media: Vec<DiscIdMedium>,
+}
+
+impl DiscIdRelease {
+    /// The first credited artist, as the release is filed under
+    fn artist(&self) -> &str {
+        self.artist_credit
+            .first()
+            .map_or("Unknown Artist", |credit| credit.name.as_str())
+    }
+
+    fn title(&self) -> &str {
+        self.title.as_deref().unwrap_or("Unknown Album")
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct DiscIdArtistCredit {
+    name: String,
+}
+
+/// One disc of a release, with the disc IDs known for it
+#[derive(Debug, Deserialize)]
+struct DiscIdMedium {
+    position: Option<u32>,
+    #[serde(rename = "track-count")]
+    track_count: Option<usize>,
+    #[serde(default)]
+    discs: Vec<DiscIdDisc>,
+    /// Missing unless the lookup asked for recordings
+    tracks: Option<Vec<DiscIdTrack>>,
+}
+
+impl DiscIdMedium {
+    fn track_count(&self) -> Option<usize> {
+        self.track_count
+            .or_else(|| self.tracks.as_ref().map(Vec::len))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct DiscIdDisc {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct DiscIdTrack {
+    /// Track number as printed, which need not be numeric (`A1`)
+    number: Option<String>,
+    title: Option<String>,
+    /// Length in milliseconds
+    length: Option<u64>,
+}
+
 /// Look up CD information from MusicBrainz
-pub async fn lookup_cd_info(cd_info: &CdInfo, tx: mpsc::Sender<String>) -> Result<CdInfo> {
+pub async fn lookup_cd_info(
+    network: &Network,
+    cd_info: &CdInfo,
+    tx: mpsc::Sender<String>,
+) -> Result<CdInfo> {
+    if network.is_offline() {
+        tx.send("Offline: keeping the CD's own track information".to_string())
+            .context("Failed to send offline message")?;
+        return Ok(cd_info.clone());
+    }
+
     tx.send("Looking up CD information from MusicBrainz...".to_string())
         .context("Failed to send MusicBrainz lookup message")?;
 
-    let mut client = MusicBrainzClient::default();
-    client
-        .set_user_agent("mfutil/0.1.1 ( https://github.com/anoraktrend/music-folder-utils )")
-        .context("Failed to set user agent")?;
+    let client = network.musicbrainz_client()?;
 
     // First try to lookup by discid using the direct discid endpoint
     tx.send(format!("Attempting lookup by DiscID: {}", cd_info.disc_id))
@@ -144,55 +585,40 @@ pub async fn lookup_cd_info(cd_info: &CdInfo, tx: mpsc::Sender<String>) -> Resul
 
     // Use raw API request to lookup release by discid
     let discid_url = format!(
-        "https://musicbrainz.org/ws/2/discid/{}?fmt=json&inc=artists+release-groups+recordings",
+        "{}/discid/{}?fmt=json&inc=artists+release-groups+recordings",
+        client.api_root(),
         cd_info.disc_id
     );
     let request = ApiRequest::new(discid_url);
 
     match request.get_json(&client).await {
         Ok(discid_response) => {
-            // Parse the discid response to extract release information
-            if let Some(releases) = discid_response.get("releases") {
-                if let Some(release_data) = releases.get(0) {
-                    if let Some(release_id) = release_data.get("id").and_then(|id| id.as_str()) {
-                        // We already have the full release data from the discid response
-                        // Extract artist and title from the discid response
-                        let artist_credit = release_data
-                            .get("artist-credit")
-                            .and_then(|ac| ac.as_array())
-                            .and_then(|ac| ac.first())
-                            .and_then(|a| a.get("name"))
-                            .and_then(|n| n.as_str())
-                            .unwrap_or("Unknown Artist");
-
-                        let title = release_data
-                            .get("title")
-                            .and_then(|t| t.as_str())
-                            .unwrap_or("Unknown Album");
-
-                        tx.send(format!(
-                            "Found release: {} - {} ({})",
-                            artist_credit, title, release_id
-                        ))
-                        .context("Failed to send release found message")?;
-
-                        // Create CdInfo from the discid response data with full track information
-                        let cd_info = cd_info_from_discid_response(release_data, cd_info)?;
-                        Ok(cd_info)
-                    } else {
-                        tx.send("No release ID found in discid response".to_string())
-                            .context("Failed to send error message")?;
-                        Ok(cd_info.clone())
-                    }
-                } else {
+            let discid_response: DiscIdResponse = match serde_json::from_value(discid_response) {
+                Ok(parsed) => parsed,
+                Err(e) => {
+                    tx.send(format!("Invalid discid response format: {}", e))
+                        .context("Failed to send error message")?;
+                    return Ok(cd_info.clone());
+                }
+            };
+            match discid_response.releases.first() {
+                Some(release_data) => {
+                    tx.send(format!(
+                        "Found release: {} - {} ({})",
+                        release_data.artist(),
+                        release_data.title(),
+                        release_data.id
+                    ))
+                    .context("Failed to send release found message")?;
+
+                    // Create CdInfo from the discid response data with full track information
+                    cd_info_from_discid_response(release_data, cd_info)
+                }
+                None => {
                     tx.send("No releases found for this discid".to_string())
                         .context("Failed to send error message")?;
                     Ok(cd_info.clone())
                 }
-            } else {
-                tx.send("Invalid discid response format".to_string())
-                    .context("Failed to send error message")?;
-                Ok(cd_info.clone())
             }
         }
         Err(e) => {
@@ -234,6 +660,7 @@ pub async fn lookup_cd_info(cd_info: &CdInfo, tx: mpsc::Sender<String>) -> Resul
                             tracks: cd_info.tracks.clone(), // Keep original tracks for fallback
                             total_duration: cd_info.total_duration,
                             release_id: Some(release.id.clone()),
+                            disc_number: cd_info.disc_number,
                         };
                         Ok(cd_info)
                     } else {
@@ -257,121 +684,47 @@ pub async fn lookup_cd_info(cd_info: &CdInfo, tx: mpsc::Sender<String>) -> Resul
     }
 }
 
-/// Create CdInfo from a MusicBrainz discid response
-fn cd_info_from_discid_response(
-    release_data: &serde_json::Value,
-    cd_info: &CdInfo,
-) -> Result<CdInfo> {
-    let release_id = release_data
-        .get("id")
-        .and_then(|id| id.as_str())
-        .unwrap_or("");
-
-    let artist = release_data
-        .get("artist-credit")
-        .and_then(|ac| ac.as_array())
-        .and_then(|ac| ac.first())
-        .and_then(|a| a.get("name"))
-        .and_then(|n| n.as_str())
-        .unwrap_or("Unknown Artist");
-
-    let title = release_data
-        .get("title")
-        .and_then(|t| t.as_str())
-        .unwrap_or("Unknown Album");
-
-    // Extract track information from media - this is the key part
-    let tracks: Vec<CdTrack> = if let Some(media) = release_data.get("media") {
-        if let Some(media_array) = media.as_array() {
-            if let Some(first_medium) = media_array.first() {
-                if let Some(tracks) = first_medium.get("tracks") {
-                    if let Some(tracks_array) = tracks.as_array() {
-                        tracks_array
-                            .iter()
-                            .enumerate()
-                            .map(|(i, track_data)| {
-                                let number = track_data
-                                    .get("number")
-                                    .and_then(|n| n.as_str())
-                                    .and_then(|n| n.parse::<u32>().ok())
-                                    .unwrap_or((i + 1) as u32);
-
-                                let default_title = format!("Track {:02}", number);
-                                let track_title = track_data
-                                    .get("title")
-                                    .and_then(|t| t.as_str())
-                                    .unwrap_or(&default_title);
-
-                                let duration = track_data
-                                    .get("length")
-                                    .and_then(|l| l.as_u64())
-                                    .map(|l| l / 1000) // Convert from milliseconds to seconds
-                                    .unwrap_or(0);
-
-                                CdTrack {
-                                    number,
-                                    title: track_title.to_string(),
-                                    artist: artist.to_string(),
-                                    duration,
-                                    filename: format!(
-                                        "{:02} {}.flac",
-                                        number,
-                                        utils::sanitize_filename(track_title)
-                                    ),
-                                }
-                            })
-                            .collect()
-                    } else {
-                        // Fallback: create basic tracks if parsing fails
-                        (1..=11)
-                            .map(|i| CdTrack {
-                                number: i,
-                                title: format!("Track {:02}", i),
-                                artist: artist.to_string(),
-                                duration: 180, // Default 3 minutes
-                                filename: format!("{:02} Track {:02}.flac", i, i),
-                            })
-                            .collect()
-                    }
-                } else {
-                    // Fallback: create basic tracks
-                    (1..=11)
-                        .map(|i| CdTrack {
-                            number: i,
-                            title: format!("Track {:02}", i),
-                            artist: artist.to_string(),
-                            duration: 180, // Default 3 minutes
-                            filename: format!("{:02} Track {:02}.flac", i, i),
-                        })
-                        .collect()
-                }
-            } else {
-                // Fallback: create basic tracks
-                (1..=11)
-                    .map(|i| CdTrack {
-                        number: i,
-                        title: format!("Track {:02}", i),
-                        artist: artist.to_string(),
-                        duration: 180, // Default 3 minutes
-                        filename: format!("{:02} Track {:02}.flac", i, i),
-                    })
-                    .collect()
-            }
-        } else {
-            // Fallback: create basic tracks
-            (1..=11)
-                .map(|i| CdTrack {
-                    number: i,
-                    title: format!("Track {:02}", i),
+/// Create CdInfo from a release in a MusicBrainz discid response
+/// Multi-disc releases list every medium, so the tracks come from the one matching this disc
+fn cd_info_from_discid_response(release_data: &DiscIdRelease, cd_info: &CdInfo) -> Result<CdInfo> {
+    let artist = release_data.artist();
+    let medium = select_medium(&release_data.media, &cd_info.disc_id, cd_info.tracks.len());
+
+    // Extract track information from the matched medium - this is the key part
+    let tracks: Vec<CdTrack> = match medium.and_then(|m| m.tracks.as_ref()) {
+        Some(tracks) => tracks
+            .iter()
+            .enumerate()
+            .map(|(i, track_data)| {
+                let number = track_data
+                    .number
+                    .as_deref()
+                    .and_then(|n| n.parse::<u32>().ok())
+                    .unwrap_or((i + 1) as u32);
+
+                let track_title = track_data
+                    .title
+                    .clone()
+                    .unwrap_or_else(|| format!("Track {:02}", number));
+
+                // Convert from milliseconds to seconds
+                let duration = track_data.length.map(|l| l / 1000).unwrap_or(0);
+
+                CdTrack {
+                    number,
+                    filename: format!(
+                        "{:02} {}.flac",
+                        number,
+                        utils::sanitize_filename(&track_title)
+                    ),
+                    title: track_title,
                     artist: artist.to_string(),
-                    duration: 180, // Default 3 minutes
-                    filename: format!("{:02} Track {:02}.flac", i, i),
-                })
-                .collect()
-        }
-    } else {
+                    duration,
+                }
+            })
+            .collect(),
         // Fallback: create basic tracks
-        (1..=11)
+        None => (1..=11)
             .map(|i| CdTrack {
                 number: i,
                 title: format!("Track {:02}", i),
@@ -379,22 +732,58 @@ fn cd_info_from_discid_response(
                 duration: 180, // Default 3 minutes
                 filename: format!("{:02} Track {:02}.flac", i, i),
             })
-            .collect()
+            .collect(),
     };
 
+    let disc_number = medium.and_then(|m| m.position);
+
     let total_duration = tracks.iter().map(|t| t.duration).sum();
 
     Ok(CdInfo {
         disc_id: cd_info.disc_id.clone(),
-        title: title.to_string(),
+        title: release_data.title().to_string(),
         artist: artist.to_string(),
         tracks,
         total_duration,
-        release_id: Some(release_id.to_string()),
+        release_id: Some(release_data.id.clone()),
+        disc_number,
     })
 }
 
-/// Import a single track from CD with actual CD reading
+/// Pick the medium of a release that is the disc in the drive
+/// A medium listing `disc_id` wins; otherwise the first whose track count matches the TOC's,
+/// and failing that the first medium
+fn select_medium<'a>(
+    media: &'a [DiscIdMedium],
+    disc_id: &str,
+    toc_track_count: usize,
+) -> Option<&'a DiscIdMedium> {
+    media
+        .iter()
+        .find(|medium| medium.discs.iter().any(|disc| disc.id == disc_id))
+        .or_else(|| {
+            media
+                .iter()
+                .find(|medium| medium.track_count() == Some(toc_track_count))
+        })
+        .or_else(|| media.first())
+}
+
+/// How [`import_cd_track`] rips a track; the defaults rip it once, whole
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RipOptions {
+    /// Read the track a second time and compare it against the rip
+    pub verify: bool,
+    /// Leave silence at the ends out of the FLAC file; the checksums still cover the whole
+    /// track as read
+    pub trim_silence: Option<SilenceTrim>,
+    /// Tag the FLAC file as ripped by mfutil (see [`provenance`])
+    pub provenance: bool,
+    /// Write a checksum manifest in the album folder once every track is ripped
+    pub checksums: ChecksumAlgorithm,
+}
+
+/// Import a single track from CD with actual CD reading, as `options` say
 #[cfg(feature = "cd-ripping")]
 pub async fn import_cd_track(
     device: &str,
@@ -402,61 +791,96 @@ pub async fn import_cd_track(
     track: &CdTrack,
     album_dir: &Path,
     tx: mpsc::Sender<String>,
-    cover_art: Option<&Vec<u8>>,
-) -> Result<()> {
+    options: RipOptions,
+) -> Result<TrackRip> {
+    let RipOptions {
+        verify,
+        trim_silence,
+        provenance,
+        ..
+    } = options;
     tx.send(format!("Importing track: {}", track.title))
         .context("Failed to send track import message")?;
 
     let track_path = album_dir.join(&track.filename);
+    let first_track = cd_info.tracks.first().map(|t| t.number) == Some(track.number);
+    let last_track = cd_info.tracks.last().map(|t| t.number) == Some(track.number);
 
-    // Read actual audio data from CD
-    let audio_data = match read_cd_data(device, track, &tx).await {
-        Ok(data) => {
-            tx.send(format!(
-                "Read {} bytes of audio data for track {}",
-                data.len(),
-                track.title
-            ))
-            .context("Failed to send audio read message")?;
-            data
-        }
-        Err(e) => {
-            tx.send(format!(
-                "ERROR: Failed to read audio data for track {}: {}",
-                track.title, e
-            ))
-            .context("Failed to send track read error message")?;
-            return Err(e);
-        }
-    };
-
-    // Write the audio data to FLAC file
-    match write_flac_file(&track_path, &audio_data, track, cover_art) {
-        Ok(()) => {
-            tx.send(format!("Encoded FLAC file: {}", track_path.display()))
-                .context("Failed to send FLAC encoding message")?;
+    // Rip the track straight into a FLAC file, sector by sector
+    let checksum = match rip_track_to_flac(
+        device,
+        track,
+        &track_path,
+        first_track,
+        last_track,
+        trim_silence,
+        &tx,
+    ) {
+        Ok((sectors_read, checksum)) => {
+            // Progress messages are best-effort from here on: the track is finished and tagged
+            // even if the display has gone away
+            let _ = tx.send(format!(
+                "Encoded FLAC file: {} ({} sectors)",
+                track_path.display(),
+                sectors_read
+            ));
+            checksum
         }
         Err(e) => {
-            tx.send(format!(
-                "ERROR: Failed to encode FLAC for track {}: {}",
-                track.title, e
-            ))
-            .context("Failed to send FLAC encoding error message")?;
+            tx.send(format!("ERROR: Failed to rip track {}: {}", track.title, e))
+                .context("Failed to send track rip error message")?;
             return Err(e);
         }
     };
 
-    // Set metadata tags
-    set_audio_metadata(
+    // Set metadata tags; an untagged rip is removed rather than left looking finished
+    if let Err(e) = set_audio_metadata(
         &track_path,
         track,
         &cd_info.title,
         &cd_info.artist,
         cd_info.release_id.as_deref(),
-    )
-    .with_context(|| format!("Failed to set metadata for: {:?}", track_path))?;
+        cd_info.disc_number,
+        provenance,
+    ) {
+        let _ = std::fs::remove_file(&track_path);
+        return Err(e.context(format!("Failed to set metadata for: {:?}", track_path)));
+    }
 
-    Ok(())
+    tracing::info!(
+        "AccurateRip CRC for track {:02} ({}): {:08X}",
+        track.number,
+        track.title,
+        checksum.accuraterip_crc
+    );
+    let _ = tx.send(format!(
+        "AccurateRip CRC for track {:02}: {:08X}",
+        track.number, checksum.accuraterip_crc
+    ));
+
+    let verified = if verify {
+        let reread = read_track_checksum(device, track, first_track, last_track, &tx)
+            .with_context(|| {
+                format!("Failed to re-read track {} for verification", track.number)
+            })?;
+        let matched = reread == checksum;
+        if !matched {
+            warn!(
+                "Verification failed for track {}: second read differs from the rip",
+                track.title
+            );
+            tx.send(format!(
+                "ERROR: Verification failed for track {}: second read differs from the rip",
+                track.title
+            ))
+            .context("Failed to send verification error message")?;
+        }
+        Some(matched)
+    } else {
+        None
+    };
+
+    Ok(TrackRip { checksum, verified })
 }
 
 #[cfg(not(feature = "cd-ripping"))]
@@ -466,8 +890,8 @@ pub async fn import_cd_track(
     track: &CdTrack,
     _album_dir: &Path,
     tx: mpsc::Sender<String>,
-    _cover_art: Option<&Vec<u8>>,
-) -> Result<()> {
+    _options: RipOptions,
+) -> Result<TrackRip> {
     tx.send(format!(
         "CD ripping feature is not enabled. Skipping import of track: {}",
         track.title
@@ -476,9 +900,98 @@ pub async fn import_cd_track(
     Err(anyhow::anyhow!("CD ripping feature is not enabled."))
 }
 
-/// Read a single track's audio data from the CD using cdparanoia
+/// Streams a track's sectors from cdparanoia into the FLAC encoder one block at a time
+#[cfg(feature = "cd-ripping")]
+struct CdTrackSource<'a> {
+    paranoia: &'a cdparanoia::CdromParanoia,
+    track_number: u32,
+    total_sectors: u64,
+    sectors_read: &'a mut u64,
+    checksum: &'a mut TrackChecksum,
+    /// Interleaved samples read from the disc but not yet handed to the encoder
+    pending: Vec<i32>,
+    /// Set with --trim-silence; taken once the track has been read to the end
+    trimmer: Option<SilenceTrimmer>,
+    tx: &'a mpsc::Sender<String>,
+}
+
+#[cfg(feature = "cd-ripping")]
+impl CdTrackSource<'_> {
+    /// Read the next sector into `pending`, returning false at the end of the track
+    fn read_sector(&mut self) -> bool {
+        let sector = if *self.sectors_read < self.total_sectors {
+            read_next_sector(self.paranoia)
+        } else {
+            None
+        };
+        let Some(sector) = sector else {
+            // End of read; let through whatever trailing silence is kept
+            if let Some(trimmer) = self.trimmer.take() {
+                trimmer.finish(&mut self.pending);
+            }
+            return false;
+        };
+        self.checksum.update(sector);
+        let samples: Vec<i32> = sector.iter().map(|&s| s as i32).collect();
+        match &mut self.trimmer {
+            Some(trimmer) => trimmer.push(&samples, &mut self.pending),
+            None => self.pending.extend(samples),
+        }
+        *self.sectors_read += 1;
+
+        // Progress logging every 100 sectors through TUI
+        if self.sectors_read.is_multiple_of(100) {
+            let progress = (*self.sectors_read * 100) / self.total_sectors;
+            let _ = self.tx.send(format!(
+                "PROGRESS: Reading track {}: {}% complete ({} sectors)",
+                self.track_number, progress, self.sectors_read
+            ));
+        }
+
+        true
+    }
+}
+
+#[cfg(feature = "cd-ripping")]
+impl flacenc::source::Source for CdTrackSource<'_> {
+    fn channels(&self) -> usize {
+        2
+    }
+
+    fn bits_per_sample(&self) -> usize {
+        16
+    }
+
+    fn sample_rate(&self) -> usize {
+        44100
+    }
+
+    fn read_samples<F: flacenc::source::Fill>(
+        &mut self,
+        block_size: usize,
+        dest: &mut F,
+    ) -> Result<usize, flacenc::error::SourceError> {
+        let wanted = block_size * self.channels();
+        while self.pending.len() < wanted && self.read_sector() {}
+
+        let available = wanted.min(self.pending.len());
+        if available > 0 {
+            dest.fill_interleaved(&self.pending[..available])?;
+            self.pending.drain(..available);
+        }
+        Ok(available / self.channels())
+    }
+
+    fn len_hint(&self) -> Option<usize> {
+        let frames_per_sector = cdparanoia::CD_FRAMEWORDS as usize / self.channels();
+        Some(self.total_sectors as usize * frames_per_sector)
+    }
+}
+
+/// Open the drive and position cdparanoia at the start of a track
+/// Returns the paranoia handle and the number of sectors in the track
 #[cfg(feature = "cd-ripping")]
-async fn read_cd_data(device: &str, track: &CdTrack, tx: &mpsc::Sender<String>) -> Result<Vec<u8>> {
+fn open_track(device: &str, track: &CdTrack) -> Result<(cdparanoia::CdromParanoia, u64)> {
     let device_cstr =
         std::ffi::CString::new(device).context("Failed to create CString for device")?;
     let drive = cdparanoia::CdromDrive::identify(&device_cstr, cdparanoia::Verbosity::LogIt)
@@ -494,34 +1007,87 @@ async fn read_cd_data(device: &str, track: &CdTrack, tx: &mpsc::Sender<String>)
         .seek(std::io::SeekFrom::Start(first_sector))
         .with_context(|| format!("Failed to seek to track {}", track.number))?;
 
-    let mut samples_i16 = Vec::new();
-    let mut sectors_read = 0;
-    let total_sectors = last_sector - first_sector + 1;
-
-    for _sector in first_sector..=last_sector {
-        // The callback function is a C function pointer, we can pass a dummy one or a proper logger.
-        // For now, using a simple extern "C" fn is sufficient.
-        extern "C" fn callback(_: i64, _: i32) {}
-        let sector_ptr =
-            unsafe { cdparanoia::cdparanoia_sys::paranoia_read(paranoia.as_raw(), Some(callback)) };
-        if sector_ptr.is_null() {
-            break; // End of read
-        }
-        samples_i16.extend_from_slice(unsafe {
-            std::slice::from_raw_parts(sector_ptr as *const i16, cdparanoia::CD_FRAMEWORDS as usize)
-        });
-        sectors_read += 1;
+    Ok((paranoia, last_sector - first_sector + 1))
+}
+
+/// Read the next sector's interleaved samples, or `None` once cdparanoia has nothing more
+#[cfg(feature = "cd-ripping")]
+fn read_next_sector(paranoia: &cdparanoia::CdromParanoia) -> Option<&[i16]> {
+    // The callback function is a C function pointer, we can pass a dummy one or a proper logger.
+    // For now, using a simple extern "C" fn is sufficient.
+    extern "C" fn callback(_: i64, _: i32) {}
+    let sector_ptr =
+        unsafe { cdparanoia::cdparanoia_sys::paranoia_read(paranoia.as_raw(), Some(callback)) };
+    if sector_ptr.is_null() {
+        return None;
+    }
+    Some(unsafe {
+        std::slice::from_raw_parts(sector_ptr as *const i16, cdparanoia::CD_FRAMEWORDS as usize)
+    })
+}
+
+/// Re-read a track from the disc and checksum it without encoding
+#[cfg(feature = "cd-ripping")]
+fn read_track_checksum(
+    device: &str,
+    track: &CdTrack,
+    first_track: bool,
+    last_track: bool,
+    tx: &mpsc::Sender<String>,
+) -> Result<TrackChecksum> {
+    let (paranoia, total_sectors) = open_track(device, track)?;
+    let mut checksum =
+        TrackChecksum::new(total_sectors * FRAMES_PER_SECTOR, first_track, last_track);
+
+    for sectors_read in 1..=total_sectors {
+        let Some(sector) = read_next_sector(&paranoia) else {
+            break;
+        };
+        checksum.update(sector);
 
-        // Progress logging every 100 sectors through TUI
         if sectors_read % 100 == 0 {
-            let progress = (sectors_read * 100) / total_sectors;
             let _ = tx.send(format!(
-                "PROGRESS: Reading track {}: {}% complete ({} sectors)",
-                track.number, progress, sectors_read
+                "PROGRESS: Verifying track {}: {}% complete ({} sectors)",
+                track.number,
+                (sectors_read * 100) / total_sectors,
+                sectors_read
             ));
         }
     }
 
+    Ok(checksum)
+}
+
+/// Rip a single track from the CD into a FLAC file using cdparanoia
+/// Sectors are encoded as they are read, so the track's PCM is never held in memory
+/// Returns the number of sectors read and the checksums of the ripped audio
+#[cfg(feature = "cd-ripping")]
+fn rip_track_to_flac(
+    device: &str,
+    track: &CdTrack,
+    path: &Path,
+    first_track: bool,
+    last_track: bool,
+    trim_silence: Option<SilenceTrim>,
+    tx: &mpsc::Sender<String>,
+) -> Result<(u64, TrackChecksum)> {
+    let (paranoia, total_sectors) = open_track(device, track)?;
+
+    let mut sectors_read = 0;
+    let mut checksum =
+        TrackChecksum::new(total_sectors * FRAMES_PER_SECTOR, first_track, last_track);
+    let source = CdTrackSource {
+        paranoia: &paranoia,
+        track_number: track.number,
+        total_sectors,
+        sectors_read: &mut sectors_read,
+        checksum: &mut checksum,
+        pending: Vec::new(),
+        trimmer: trim_silence.map(SilenceTrimmer::new),
+        tx,
+    };
+    let flac_stream = encode_flac(source)?;
+
     if sectors_read == 0 {
         return Err(anyhow::anyhow!(
             "Failed to read any audio data from track {}",
@@ -534,59 +1100,28 @@ async fn read_cd_data(device: &str, track: &CdTrack, tx: &mpsc::Sender<String>)
         sectors_read, track.number
     ));
 
-    // Convert Vec<i16> to Vec<u8> for the rest of the pipeline
-    let mut byte_buffer = Vec::with_capacity(samples_i16.len() * 2);
-    for sample in samples_i16 {
-        byte_buffer.extend_from_slice(&sample.to_le_bytes());
-    }
-    Ok(byte_buffer)
-}
+    write_flac_stream(path, &flac_stream)?;
 
-#[cfg(not(feature = "cd-ripping"))]
-async fn read_cd_data(
-    _device: &str,
-    track: &CdTrack,
-    tx: &mpsc::Sender<String>,
-) -> Result<Vec<u8>> {
-    tx.send(format!(
-        "CD ripping feature is not enabled. Cannot read audio data for track: {}",
-        track.title
-    ))
-    .context("Failed to send message about disabled CD ripping feature")?;
-    Err(anyhow::anyhow!("CD ripping feature is not enabled."))
+    Ok((sectors_read, checksum))
 }
 
-/// Write audio data to FLAC file with proper error handling and optional cover art embedding
-fn write_flac_file(
-    path: &Path,
-    audio_data: &[u8],
-    _track: &CdTrack,
-    cover_art: Option<&Vec<u8>>,
-) -> Result<()> {
-    // Convert audio data to i32 samples (interleaved stereo)
-    let samples_i16: Vec<i16> = audio_data
-        .chunks_exact(2)
-        .map(|chunk| i16::from_le_bytes([chunk[0], chunk[1]]))
-        .collect();
-
-    // Convert to i32 samples as required by flacenc
-    let samples: Vec<i32> = samples_i16.iter().map(|&s| s as i32).collect();
-
-    let (channels, bits_per_sample, sample_rate) = (2, 16, 44100);
-
+/// Encode everything a sample source yields into a FLAC stream
+#[cfg(feature = "cd-ripping")]
+fn encode_flac<S: flacenc::source::Source>(source: S) -> Result<flacenc::component::Stream> {
     // Create encoder config
     let config = flacenc::config::Encoder::default()
         .into_verified()
         .map_err(|e| anyhow::anyhow!("Config verification failed: {:?}", e))?;
 
-    // Create memory source from samples
-    let source =
-        flacenc::source::MemSource::from_samples(&samples, channels, bits_per_sample, sample_rate);
-
     // Encode with fixed block size
-    let flac_stream = flacenc::encode_with_fixed_block_size(&config, source, config.block_size)
-        .map_err(|e| anyhow::anyhow!("FLAC encoding failed: {:?}", e))?;
+    flacenc::encode_with_fixed_block_size(&config, source, config.block_size)
+        .map_err(|e| anyhow::anyhow!("FLAC encoding failed: {:?}", e))
+}
 
+/// Write an encoded FLAC stream to a file, by way of a `.part` file so an interrupted write
+/// never leaves a truncated track
+#[cfg(feature = "cd-ripping")]
+fn write_flac_stream(path: &Path, flac_stream: &flacenc::component::Stream) -> Result<()> {
     // Write to byte sink
     let mut sink = flacenc::bitsink::ByteSink::new();
     flac_stream
@@ -594,51 +1129,365 @@ fn write_flac_file(
         .map_err(|e| anyhow::anyhow!("Failed to write FLAC stream to sink: {:?}", e))?;
 
     // Write to file
-    std::fs::write(path, sink.as_slice())
-        .with_context(|| format!("Failed to write FLAC data to file: {:?}", path))?;
-
-    // TODO: Embed cover art in FLAC file using lofty or other FLAC manipulation library
-    // For now, we'll save the cover art as a separate file if provided
-    if let Some(cover_art_data) = cover_art {
-        let cover_art_path = path.with_extension("jpg");
-        if let Err(e) = std::fs::write(&cover_art_path, cover_art_data) {
-            warn!("Failed to save cover art to {:?}: {}", cover_art_path, e);
-        }
-    }
-
-    Ok(())
+    crate::directory::write_via_part_file(path, sink.as_slice())
+        .with_context(|| format!("Failed to write FLAC data to file: {:?}", path))
 }
 
 /// Set metadata tags on audio file
+/// With `record_provenance` the file is also tagged as ripped by mfutil (see [`provenance`])
 fn set_audio_metadata(
     path: &Path,
     track: &CdTrack,
     album_title: &str,
     album_artist: &str,
     release_id: Option<&str>,
+    disc_number: Option<u32>,
+    record_provenance: bool,
 ) -> Result<()> {
     match lofty::read_from_path(path) {
         Ok(mut tagged_file) => {
-            if let Some(tag) = tagged_file.primary_tag_mut() {
+            // A freshly encoded FLAC file has no Vorbis comments yet, so one is created
+            metadata::update_file_tags(&mut tagged_file, |tag| {
                 tag.insert_text(ItemKey::TrackTitle, track.title.clone());
                 tag.insert_text(ItemKey::TrackArtist, track.artist.clone());
                 tag.insert_text(ItemKey::AlbumTitle, album_title.to_string());
                 tag.insert_text(ItemKey::AlbumArtist, album_artist.to_string());
                 tag.insert_text(ItemKey::TrackNumber, track.number.to_string());
+                if let Some(disc) = disc_number {
+                    tag.insert_text(ItemKey::DiscNumber, disc.to_string());
+                }
                 if let Some(id) = release_id {
-                    // Assuming lofty uses this key for MusicBrainz Release ID
                     tag.insert_text(ItemKey::MusicBrainzReleaseId, id.to_string());
                 }
-            }
-
-            // lofty::save() is the modern way to write tags
-            // For now, we'll continue to skip saving as per original logic.
-            // tagged_file.save_to(path)?;
+            })?;
+            gapless::save_tags(&tagged_file, path)?;
         }
         Err(_) => {
             warn!("Could not read file for metadata: {}", path.display());
+            return Ok(());
         }
     }
 
+    if record_provenance {
+        provenance::write_provenance(path, Source::CdRip)?;
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CD_INFO_OUTPUT: &str = "\
+CD-ROM Track List (1 - 2)
+  #: MSF       LSN    Type   Green? Copy?
+  1: 00:02:00  000000 audio  false  no
+  2: 03:12:45  014295 audio  false  no
+CD-TEXT for Disc:
+\tLanguage 0 'English':
+\tTITLE: Rare Album
+\tPERFORMER: Some Band
+CD-TEXT for Track  1:
+\tTITLE: Opening
+CD-TEXT for Track  2:
+\tTITLE: Guest Spot
+\tPERFORMER: Guest Singer
+Disc mode is listed as: CD-DA
+";
+
+    fn unknown_cd_info() -> CdInfo {
+        let tracks = (1..=3)
+            .map(|number| CdTrack {
+                number,
+                title: format!("Track {:02}", number),
+                artist: "Unknown Artist".to_string(),
+                duration: 180,
+                filename: format!("{:02} Track {:02}.flac", number, number),
+            })
+            .collect();
+        CdInfo {
+            disc_id: "test_disc_id".to_string(),
+            title: "Unknown Album".to_string(),
+            artist: "Unknown Artist".to_string(),
+            tracks,
+            total_duration: 540,
+            release_id: None,
+            disc_number: None,
+        }
+    }
+
+    #[test]
+    fn test_resolve_cd_device_keeps_explicit_path() -> Result<()> {
+        assert_eq!(resolve_cd_device("/dev/sr1")?, "/dev/sr1");
+        Ok(())
+    }
+
+    #[test]
+    fn test_optical_device_candidates() -> Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let dev_dir = temp_dir.path();
+        fs::write(dev_dir.join("sr1"), b"")?;
+        fs::write(dev_dir.join("sr0"), b"")?;
+        fs::write(dev_dir.join("sda"), b"")?;
+        std::os::unix::fs::symlink(dev_dir.join("sr0"), dev_dir.join("cdrom"))?;
+        let by_id = dev_dir.join("disk").join("by-id");
+        fs::create_dir_all(&by_id)?;
+        std::os::unix::fs::symlink(dev_dir.join("sr1"), by_id.join("ata-DVDRAM_1"))?;
+        std::os::unix::fs::symlink(dev_dir.join("sda"), by_id.join("ata-SSD_1"))?;
+
+        // cdrom and the by-id link point at drives that were already listed
+        assert_eq!(
+            optical_device_candidates(dev_dir),
+            vec![dev_dir.join("sr0"), dev_dir.join("sr1")]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_track_checksum_accuraterip_crc() {
+        let mut checksum = TrackChecksum::new(2, false, false);
+        checksum.update(&[1, 0, 0, 1]);
+
+        // Frame values are left | right << 16, weighted by their 1-based position
+        assert_eq!(checksum.accuraterip_crc, 1 + 2 * 0x1_0000);
+        assert_eq!(checksum.frames, 2);
+    }
+
+    #[test]
+    fn test_track_checksum_skips_disc_edges() {
+        let edge = (5 * FRAMES_PER_SECTOR) as usize;
+        let total_frames = 3 * edge;
+        let samples = vec![1i16; total_frames * 2];
+
+        let mut inner = TrackChecksum::new(total_frames as u64, false, false);
+        let mut first = TrackChecksum::new(total_frames as u64, true, false);
+        inner.update(&samples);
+        first.update(&samples);
+
+        assert_ne!(inner.accuraterip_crc, first.accuraterip_crc);
+        // Edges only affect the AccurateRip CRC, not the comparison hash
+        assert_eq!(inner.pcm_hash, first.pcm_hash);
+    }
+
+    #[test]
+    fn test_track_checksum_detects_differing_reads() {
+        let mut first_read = TrackChecksum::new(2, false, false);
+        let mut second_read = TrackChecksum::new(2, false, false);
+        first_read.update(&[10, 20, 30, 40]);
+        second_read.update(&[10, 20, 30, 41]);
+
+        assert_ne!(first_read, second_read);
+    }
+
+    #[test]
+    fn test_silence_trimmer_cuts_trailing_zeros() {
+        let trim = SilenceTrim {
+            threshold_db: -60.0,
+            keep: Duration::from_millis(10),
+        };
+        let keep_samples = 441 * 2;
+        let tone: Vec<i32> = (0..1000)
+            .map(|i| if i % 2 == 0 { 1000 } else { -1000 })
+            .collect();
+        let mut pcm = vec![0; 5000];
+        pcm.extend(&tone);
+        // A short dip below the threshold inside the track is kept
+        pcm.extend([3; 200]);
+        pcm.extend(&tone);
+        pcm.extend(vec![0; 44100 * 2]);
+
+        let mut trimmed = Vec::new();
+        let mut trimmer = SilenceTrimmer::new(trim);
+        for chunk in pcm.chunks(FRAMES_PER_SECTOR as usize * 2) {
+            trimmer.push(chunk, &mut trimmed);
+        }
+        trimmer.finish(&mut trimmed);
+
+        let mut expected = vec![0; keep_samples];
+        expected.extend(&tone);
+        expected.extend([3; 200]);
+        expected.extend(&tone);
+        expected.extend(vec![0; keep_samples]);
+        assert_eq!(trimmed, expected);
+
+        // Untrimmed silence shorter than the allowance is left alone
+        let mut short = vec![0; 100];
+        short.extend(&tone);
+        short.extend([0; 100]);
+        let mut out = Vec::new();
+        let mut trimmer = SilenceTrimmer::new(trim);
+        trimmer.push(&short, &mut out);
+        trimmer.finish(&mut out);
+        assert_eq!(out, short);
+    }
+
+    #[test]
+    fn test_parse_cd_text() {
+        let cd_text = parse_cd_text(CD_INFO_OUTPUT);
+
+        assert_eq!(cd_text.title.as_deref(), Some("Rare Album"));
+        assert_eq!(cd_text.artist.as_deref(), Some("Some Band"));
+        assert_eq!(cd_text.tracks[&1].title.as_deref(), Some("Opening"));
+        assert_eq!(cd_text.tracks[&1].artist, None);
+        assert_eq!(cd_text.tracks[&2].artist.as_deref(), Some("Guest Singer"));
+    }
+
+    #[test]
+    fn test_parse_cd_text_absent() {
+        let cd_text = parse_cd_text("CD-ROM Track List (1 - 2)\nDisc mode is listed as: CD-DA\n");
+        assert!(cd_text.is_empty());
+    }
+
+    #[test]
+    fn test_apply_cd_text() {
+        let mut cd_info = unknown_cd_info();
+        apply_cd_text(&mut cd_info, &parse_cd_text(CD_INFO_OUTPUT));
+
+        assert_eq!(cd_info.title, "Rare Album");
+        assert_eq!(cd_info.artist, "Some Band");
+        assert_eq!(cd_info.tracks[0].title, "Opening");
+        assert_eq!(cd_info.tracks[0].artist, "Some Band");
+        assert_eq!(cd_info.tracks[0].filename, "01 Opening.flac");
+        assert_eq!(cd_info.tracks[1].artist, "Guest Singer");
+        // Tracks without CD-TEXT keep their placeholder titles
+        assert_eq!(cd_info.tracks[2].title, "Track 03");
+    }
+
+    #[test]
+    fn test_cd_info_from_discid_response_picks_matching_medium() -> Result<()> {
+        let track = |number: u32, title: &str| {
+            let number = number.to_string();
+            serde_json::json!({"number": number, "title": title, "length": 200000})
+        };
+        let mut release = serde_json::json!({
+            "id": "release-id",
+            "title": "Double Album",
+            "artist-credit": [{"name": "Some Band"}],
+            "media": [
+                {
+                    "position": 1,
+                    "track-count": 2,
+                    "discs": [{"id": "other_disc_id"}],
+                    "tracks": [track(1, "One"), track(2, "Two")]
+                },
+                {
+                    "position": 2,
+                    "track-count": 3,
+                    "discs": [],
+                    "tracks": [track(1, "Three"), track(2, "Four"), track(3, "Five")]
+                }
+            ]
+        });
+
+        // The three-track TOC only fits the second disc
+        let parsed: DiscIdRelease = serde_json::from_value(release.clone())?;
+        let cd_info = cd_info_from_discid_response(&parsed, &unknown_cd_info())?;
+        assert_eq!(cd_info.disc_number, Some(2));
+        let titles: Vec<_> = cd_info.tracks.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(titles, ["Three", "Four", "Five"]);
+        assert_eq!(cd_info.tracks[0].filename, "01 Three.flac");
+        assert_eq!(cd_info.total_duration, 600);
+
+        // A medium listing the disc ID itself beats a matching track count
+        release["media"][0]["discs"] = serde_json::json!([{"id": "test_disc_id"}]);
+        let parsed: DiscIdRelease = serde_json::from_value(release)?;
+        let cd_info = cd_info_from_discid_response(&parsed, &unknown_cd_info())?;
+        assert_eq!(cd_info.disc_number, Some(1));
+        assert_eq!(cd_info.tracks.len(), 2);
+
+        Ok(())
+    }
+
+    /// Trimmed from a real `discid/<id>?inc=artists+release-groups+recordings` response
+    const DISCID_RESPONSE: &str = r#"{
+        "id": "test_disc_id",
+        "offset-count": 2,
+        "sectors": 40000,
+        "offsets": [150, 20150],
+        "releases": [{
+            "id": "6f2a3b1c-0000-4000-8000-000000000001",
+            "title": "Sample Album",
+            "status": "Official",
+            "date": "1999-05-01",
+            "artist-credit": [{
+                "name": "Sample Band",
+                "joinphrase": "",
+                "artist": {"id": "a1", "name": "Sample Band", "sort-name": "Band, Sample"}
+            }],
+            "release-group": {"id": "rg1", "primary-type": "Album", "title": "Sample Album"},
+            "media": [{
+                "position": 1,
+                "format": "CD",
+                "track-count": 2,
+                "discs": [{"id": "test_disc_id", "sectors": 40000}],
+                "tracks": [
+                    {"id": "t1", "number": "1", "position": 1, "title": "Intro", "length": 95000},
+                    {"id": "t2", "number": "2", "position": 2, "title": "Song/Two", "length": null}
+                ]
+            }]
+        }]
+    }"#;
+
+    #[test]
+    fn test_discid_response_deserializes() -> Result<()> {
+        let response: DiscIdResponse = serde_json::from_str(DISCID_RESPONSE)?;
+        let release = &response.releases[0];
+        assert_eq!(release.artist(), "Sample Band");
+        assert_eq!(release.title(), "Sample Album");
+
+        let cd_info = cd_info_from_discid_response(release, &unknown_cd_info())?;
+        assert_eq!(
+            cd_info.release_id.as_deref(),
+            Some("6f2a3b1c-0000-4000-8000-000000000001")
+        );
+        assert_eq!(cd_info.disc_number, Some(1));
+        assert_eq!(cd_info.tracks[0].duration, 95);
+        assert_eq!(cd_info.tracks[1].filename, "02 Song_Two.flac");
+        assert_eq!(cd_info.tracks[1].duration, 0);
+
+        // A release without an ID is schema drift, reported rather than guessed around
+        let drifted =
+            DISCID_RESPONSE.replace(r#""id": "6f2a3b1c-0000-4000-8000-000000000001","#, "");
+        assert!(serde_json::from_str::<DiscIdResponse>(&drifted).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ripped_track_carries_provenance_tag() -> Result<()> {
+        use lofty::file::TaggedFileExt;
+
+        let temp_dir = tempfile::tempdir()?;
+        let cd_info = unknown_cd_info();
+        let track = &cd_info.tracks[0];
+        let track_path = temp_dir.path().join(&track.filename);
+        // As the encoder leaves a rip: 44.1 kHz stereo STREAMINFO, padding, no Vorbis comments
+        let mut flac = b"fLaC".to_vec();
+        flac.extend_from_slice(&[0x00, 0x00, 0x00, 0x22]);
+        flac.extend_from_slice(&[0x10, 0x00, 0x10, 0x00, 0, 0, 0, 0, 0, 0]);
+        flac.extend_from_slice(&[0x0A, 0xC4, 0x42, 0xF0, 0x00, 0x00, 0x00, 0x00]);
+        flac.extend_from_slice(&[0u8; 16]);
+        flac.extend_from_slice(&[0x81, 0x00, 0x00, 0x08]);
+        flac.extend_from_slice(&[0u8; 8]);
+        fs::write(&track_path, flac)?;
+
+        set_audio_metadata(
+            &track_path,
+            track,
+            &cd_info.title,
+            &cd_info.artist,
+            None,
+            None,
+            true,
+        )?;
+
+        let recorded = provenance::read_provenance(&track_path).expect("provenance was written");
+        assert_eq!(provenance::provenance_source(&recorded), "cd-rip");
+        let tagged_file = lofty::read_from_path(&track_path)?;
+        let tag = tagged_file.primary_tag().expect("tags were written");
+        assert_eq!(tag.get_string(&ItemKey::TrackTitle), Some("Track 01"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,200 @@
+use crate::directory::FileFailure;
+use crate::utils;
+use anyhow::{Context, Result};
+use md5::Md5;
+use rayon::prelude::*;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Hash used for the checksum manifests written after an import or CD rip
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChecksumAlgorithm {
+    Sha256,
+    Md5,
+    #[default]
+    None,
+}
+
+impl ChecksumAlgorithm {
+    const ALL: [ChecksumAlgorithm; 2] = [ChecksumAlgorithm::Sha256, ChecksumAlgorithm::Md5];
+
+    /// Name of the manifest written in each album folder, e.g. `checksums.sha256`
+    pub fn manifest_name(self) -> Option<&'static str> {
+        match self {
+            ChecksumAlgorithm::Sha256 => Some("checksums.sha256"),
+            ChecksumAlgorithm::Md5 => Some("checksums.md5"),
+            ChecksumAlgorithm::None => None,
+        }
+    }
+
+    /// The algorithm a manifest was written with, judged by its file name
+    pub fn from_manifest_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.manifest_name() == Some(name))
+    }
+
+    /// Lowercase hex digest of the file at `path`
+    pub fn hash_file(self, path: &Path) -> Result<String> {
+        match self {
+            ChecksumAlgorithm::Sha256 => hash_file_with::<Sha256>(path),
+            ChecksumAlgorithm::Md5 => hash_file_with::<Md5>(path),
+            ChecksumAlgorithm::None => Err(anyhow::anyhow!("No checksum algorithm selected")),
+        }
+    }
+}
+
+impl FromStr for ChecksumAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "sha256" => Ok(ChecksumAlgorithm::Sha256),
+            "md5" => Ok(ChecksumAlgorithm::Md5),
+            "none" => Ok(ChecksumAlgorithm::None),
+            other => Err(format!(
+                "unknown checksum algorithm '{}' (expected sha256, md5 or none)",
+                other
+            )),
+        }
+    }
+}
+
+fn hash_file_with<D: Digest + io::Write>(path: &Path) -> Result<String> {
+    let mut file =
+        File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut hasher = D::new();
+    io::copy(&mut file, &mut hasher)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+/// Hash every audio file under `album_path` (disc folders included) in parallel and write
+/// `<hash>  <relative path>` lines to the album's manifest, replacing any earlier one
+/// The format is the one `sha256sum -c` and `md5sum -c` read. Returns None for
+/// [`ChecksumAlgorithm::None`].
+pub fn write_manifest(album_path: &Path, algorithm: ChecksumAlgorithm) -> Result<Option<PathBuf>> {
+    let Some(manifest_name) = algorithm.manifest_name() else {
+        return Ok(None);
+    };
+    let mut tracks = utils::album_track_paths(album_path);
+    tracks.sort();
+
+    let lines = tracks
+        .par_iter()
+        .map(|track| {
+            let hash = algorithm.hash_file(track)?;
+            let relative = track.strip_prefix(album_path)?;
+            Ok(format!("{}  {}\n", hash, relative.display()))
+        })
+        .collect::<Result<Vec<String>>>()?;
+
+    let manifest = album_path.join(manifest_name);
+    fs::write(&manifest, lines.concat())
+        .with_context(|| format!("Failed to write {}", manifest.display()))?;
+    Ok(Some(manifest))
+}
+
+/// Outcome of checking one manifest against the files it lists
+#[derive(Debug, Clone, Default)]
+pub struct ManifestCheck {
+    /// Files whose hash still matches the manifest
+    pub verified: usize,
+    /// Files that are missing, unreadable or no longer match
+    pub failures: Vec<FileFailure>,
+}
+
+/// Re-hash the files listed in `manifest` in parallel and compare them with the recorded hashes
+/// The algorithm is taken from the manifest's name, as written by [`write_manifest`]
+pub fn verify_manifest(manifest: &Path) -> Result<ManifestCheck> {
+    let algorithm = manifest
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(ChecksumAlgorithm::from_manifest_name)
+        .with_context(|| format!("Not a checksum manifest: {}", manifest.display()))?;
+    let album_path = manifest.parent().context("Manifest path has no parent")?;
+    let contents = fs::read_to_string(manifest)
+        .with_context(|| format!("Failed to read {}", manifest.display()))?;
+
+    let entries = contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            // `*` marks a binary-mode entry in the coreutils format
+            let (hash, relative) = line
+                .split_once("  ")
+                .or_else(|| line.split_once(" *"))
+                .with_context(|| format!("Invalid line in {}: {}", manifest.display(), line))?;
+            Ok((hash.to_ascii_lowercase(), album_path.join(relative)))
+        })
+        .collect::<Result<Vec<(String, PathBuf)>>>()?;
+
+    let failures: Vec<FileFailure> = entries
+        .par_iter()
+        .filter_map(|(expected, path)| {
+            if !path.is_file() {
+                return Some(FileFailure::new(path, "missing"));
+            }
+            match algorithm.hash_file(path) {
+                Ok(actual) if actual == *expected => None,
+                Ok(_) => Some(FileFailure::new(path, "checksum mismatch")),
+                Err(e) => Some(FileFailure::new(path, format!("{:#}", e))),
+            }
+        })
+        .collect();
+
+    Ok(ManifestCheck {
+        verified: entries.len() - failures.len(),
+        failures,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_write_and_verify_manifest() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let album = temp_dir.path().join("Album");
+        fs::create_dir_all(album.join("CD2"))?;
+        fs::write(album.join("01.flac"), b"first")?;
+        fs::write(album.join("CD2").join("02.flac"), b"second")?;
+        fs::write(album.join("cover.jpg"), b"not audio")?;
+
+        assert_eq!(write_manifest(&album, ChecksumAlgorithm::None)?, None);
+        let manifest = write_manifest(&album, ChecksumAlgorithm::Sha256)?.unwrap();
+        assert_eq!(manifest, album.join("checksums.sha256"));
+        let contents = fs::read_to_string(&manifest)?;
+        assert_eq!(contents.lines().count(), 2);
+        assert!(contents.starts_with(
+            "a7937b64b8caa58f03721bb6bacf5c78cb235febe0e70b1b84cd99541461a08e  01.flac\n"
+        ));
+
+        let check = verify_manifest(&manifest)?;
+        assert_eq!(check.verified, 2);
+        assert!(check.failures.is_empty());
+
+        // Bit rot in one file and a lost file in a disc folder
+        fs::write(album.join("01.flac"), b"firsT")?;
+        fs::remove_file(album.join("CD2").join("02.flac"))?;
+        let check = verify_manifest(&manifest)?;
+        assert_eq!(check.verified, 0);
+        let mut reasons: Vec<&str> = check.failures.iter().map(|f| f.reason.as_str()).collect();
+        reasons.sort();
+        assert_eq!(reasons, ["checksum mismatch", "missing"]);
+
+        let md5 = write_manifest(&album, ChecksumAlgorithm::Md5)?.unwrap();
+        assert_eq!(verify_manifest(&md5)?.verified, 1);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,657 @@
+use crate::checksums::ChecksumAlgorithm;
+use crate::commands::rename::read_symlinks;
+use crate::utils::{self, GroupBy, Naming};
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::os::unix::fs::symlink;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Link an album into `Albums/` as "Artist - Album", replacing a link that points elsewhere
+/// If another album would get the same name, a counter is appended instead
+/// With `dry_run`, the link that would be created, replaced or skipped is only logged
+/// With `check_target`, a link left pointing at a missing folder (the album was moved or
+/// deleted) is recreated and reported as repaired, instead of failing on the taken name
+/// Link names are made safe by `naming`, as the album folders were
+pub fn process_single_album_symlink(
+    album_path: &Path,
+    music_dir: &str,
+    naming: &Naming,
+    dry_run: bool,
+    check_target: bool,
+) -> Result<()> {
+    let music_path = Path::new(music_dir);
+    let artists_path = music_path.join("Artists");
+
+    // Validate that the album path is within the expected Artists directory structure
+    let album_path = PathBuf::from(album_path);
+
+    // Check if album_path is within music_dir/Artists/
+    if !album_path.starts_with(&artists_path) {
+        return Err(anyhow::anyhow!(
+            "Album path '{}' is not within the expected Artists directory '{}'",
+            album_path.display(),
+            artists_path.display()
+        ));
+    }
+
+    // Ensure the album is at the depth the library's layout puts albums, e.g. Artists/Artist/Album
+    let group_by = utils::group_by(music_path);
+    if album_path.ancestors().nth(group_by.depth()) != Some(artists_path.as_path()) {
+        return Err(anyhow::anyhow!(
+            "Album path '{}' is not in the expected structure (should be Artists/{})",
+            album_path.display(),
+            match group_by {
+                GroupBy::Artist => "Artist/Album",
+                GroupBy::ArtistYear => "Artist/Year - Album",
+                GroupBy::Flat => "Artist - Album",
+            }
+        ));
+    }
+
+    if !album_path.is_dir() {
+        return Err(anyhow::anyhow!(
+            "Album path '{}' is not a directory",
+            album_path.display()
+        ));
+    }
+
+    // Ensure the Albums directory exists
+    let albums_path = music_path.join("Albums");
+    if !albums_path.exists() {
+        if dry_run {
+            info!("Would create directory: {}", albums_path.display());
+        } else {
+            fs::create_dir(&albums_path)?;
+        }
+    }
+
+    // Names from the folders, without a year prefix the layout may have added
+    let (artist_name, album_name) = group_by.album_names(&album_path);
+    let base_name = album_base_name(&artist_name, &album_name, naming);
+    let link_name = album_link_name(&albums_path, &base_name, &album_path, group_by, naming);
+    if link_name != albums_path.join(&base_name) {
+        warn!(
+            "Album link '{}' already belongs to another album; linking {} as '{}'",
+            base_name,
+            album_path.display(),
+            link_name.display()
+        );
+    }
+
+    // `exists` follows the link, so a link whose target is gone only shows up as a symlink
+    let broken = check_target && link_name.is_symlink() && !link_name.is_dir();
+    let mut repaired_target = None;
+
+    // Check for existing symlink
+    if link_name.exists() || broken {
+        let current_target = if link_name.is_symlink() {
+            Some(fs::read_link(&link_name)?)
+        } else {
+            None
+        };
+        if current_target.as_ref() == Some(&album_path) && !broken {
+            // Already correctly linked, skip
+            if dry_run {
+                info!("Would skip (already linked): {}", link_name.display());
+            }
+            return Ok(());
+        }
+        if dry_run && broken {
+            info!(
+                "Would repair broken link: {} ({} -> {})",
+                link_name.display(),
+                current_target.unwrap_or_default().display(),
+                album_path.display()
+            );
+            return Ok(());
+        }
+        if dry_run {
+            info!(
+                "Would replace: {} ({} -> {})",
+                link_name.display(),
+                current_target.map_or_else(
+                    || "existing file".to_string(),
+                    |target| target.display().to_string()
+                ),
+                album_path.display()
+            );
+            return Ok(());
+        }
+        if broken {
+            repaired_target = current_target;
+        }
+        // Remove existing file/symlink and create new one
+        fs::remove_file(&link_name)?;
+    }
+
+    if dry_run {
+        info!(
+            "Would link: {} -> {}",
+            link_name.display(),
+            album_path.display()
+        );
+        return Ok(());
+    }
+
+    // Create the symlink
+    symlink(&album_path, &link_name).with_context(|| {
+        format!(
+            "Failed to create symlink from '{}' to '{}'",
+            link_name.display(),
+            album_path.display()
+        )
+    })?;
+    if let Some(old_target) = repaired_target {
+        info!(
+            "Repaired broken link: {} ({} -> {})",
+            link_name.display(),
+            old_target.display(),
+            album_path.display()
+        );
+    }
+
+    Ok(())
+}
+
+/// Pick the link for an album, passing over names held by a different album that would
+/// also be called `base_name` (e.g. "A - B"/"C" and "A"/"B - C"): "Album (2)", "Album (3)", ...
+fn album_link_name(
+    albums_path: &Path,
+    base_name: &str,
+    album_path: &Path,
+    group_by: GroupBy,
+    naming: &Naming,
+) -> PathBuf {
+    let mut link_name = albums_path.join(base_name);
+    let mut counter = 1;
+    while held_by_other_album(&link_name, base_name, album_path, group_by, naming) {
+        counter += 1;
+        link_name = albums_path.join(format!("{} ({})", base_name, counter));
+    }
+    link_name
+}
+
+/// "Artist - Album" link name for an album folder, with any configured aliases applied
+fn album_base_name(artist_name: &str, album_name: &str, naming: &Naming) -> String {
+    format!(
+        "{} - {}",
+        naming.sanitize(&naming.canonical_artist(artist_name)),
+        naming.sanitize(&naming.canonical_album(album_name))
+    )
+}
+
+/// Whether `link` points at another existing album whose own link name is `base_name`
+/// Links to missing folders or differently named albums are stale and may be replaced
+fn held_by_other_album(
+    link: &Path,
+    base_name: &str,
+    album_path: &Path,
+    group_by: GroupBy,
+    naming: &Naming,
+) -> bool {
+    let Ok(target) = fs::read_link(link) else {
+        return false;
+    };
+    let (artist_name, album_name) = group_by.album_names(&target);
+    target != album_path
+        && target.is_dir()
+        && album_base_name(&artist_name, &album_name, naming) == base_name
+}
+
+/// An `Albums/` link whose target holds the same tracks as the target of a link that is kept
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateLink {
+    /// The duplicate link
+    pub link: PathBuf,
+    /// The link kept for those tracks
+    pub kept: PathBuf,
+}
+
+/// Collapse `Albums/` links whose target folders hold identical tracks (the same file
+/// contents, however they are named), such as a compilation filed under two artists
+/// The first link by name is kept. Duplicates are only logged unless `apply` is set, in
+/// which case they are removed. Returns the duplicates found
+pub fn dedupe_album_links(music_dir: &str, apply: bool) -> Result<Vec<DuplicateLink>> {
+    let albums_path = Path::new(music_dir).join("Albums");
+    let mut links: Vec<(PathBuf, PathBuf)> = read_symlinks(&albums_path)?
+        .into_iter()
+        .map(|(link, target)| (link, albums_path.join(target)))
+        .filter(|(_, target)| target.is_dir())
+        .collect();
+    links.sort();
+
+    // Only albums with as many tracks of the same total size can match, so only those are hashed
+    let sizes: Vec<(usize, u64)> = links
+        .iter()
+        .map(|(_, target)| track_sizes(target))
+        .collect();
+    let mut size_counts: HashMap<(usize, u64), usize> = HashMap::new();
+    for size in &sizes {
+        *size_counts.entry(*size).or_default() += 1;
+    }
+
+    let mut kept: HashMap<Vec<String>, PathBuf> = HashMap::new();
+    let mut duplicates = Vec::new();
+    for ((link, target), size) in links.into_iter().zip(sizes) {
+        if size.0 == 0 || size_counts[&size] < 2 {
+            continue;
+        }
+        let mut hashes = utils::album_track_paths(&target)
+            .iter()
+            .map(|track| ChecksumAlgorithm::Sha256.hash_file(track))
+            .collect::<Result<Vec<String>>>()?;
+        hashes.sort();
+
+        match kept.get(&hashes) {
+            Some(kept_link) => duplicates.push(DuplicateLink {
+                link,
+                kept: kept_link.clone(),
+            }),
+            None => {
+                kept.insert(hashes, link);
+            }
+        }
+    }
+
+    for duplicate in &duplicates {
+        if apply {
+            fs::remove_file(&duplicate.link)?;
+            info!(
+                "Removed duplicate album link: {} (same tracks as {})",
+                duplicate.link.display(),
+                duplicate.kept.display()
+            );
+        } else {
+            info!(
+                "Would remove duplicate album link: {} (same tracks as {})",
+                duplicate.link.display(),
+                duplicate.kept.display()
+            );
+        }
+    }
+    Ok(duplicates)
+}
+
+/// Number and total size of the audio files in an album folder
+fn track_sizes(album_path: &Path) -> (usize, u64) {
+    let tracks = utils::album_track_paths(album_path);
+    let total = tracks
+        .iter()
+        .filter_map(|track| fs::metadata(track).ok())
+        .map(|metadata| metadata.len())
+        .sum();
+    (tracks.len(), total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::io::Write;
+    use std::os::unix::fs::symlink;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_process_single_album_symlink_valid_structure() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+        let artists_dir = music_root.join("Artists");
+        let albums_dir = music_root.join("Albums");
+
+        fs::create_dir_all(&artists_dir)?;
+        fs::create_dir_all(&albums_dir)?;
+
+        // Create proper structure: Music/Artists/Artist/Album
+        let artist_dir = artists_dir.join("TestArtist");
+        fs::create_dir(&artist_dir)?;
+        let album_dir = artist_dir.join("TestAlbum");
+        fs::create_dir(&album_dir)?;
+        fs::File::create(album_dir.join("track1.mp3"))?.write_all(b"test")?;
+
+        // Test the function
+        let result = process_single_album_symlink(
+            &album_dir,
+            music_root.to_str().unwrap(),
+            &Naming::default(),
+            false,
+            false,
+        );
+
+        assert!(result.is_ok());
+
+        // Check that symlink was created
+        let expected_link = albums_dir.join("TestArtist - TestAlbum");
+        assert!(expected_link.exists());
+        assert!(expected_link.is_symlink());
+
+        // Verify the symlink points to the correct target
+        let link_target = fs::read_link(&expected_link)?;
+        assert_eq!(link_target, album_dir);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_single_album_symlink_invalid_path() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+        let artists_dir = music_root.join("Artists");
+
+        fs::create_dir_all(&artists_dir)?;
+
+        // Create album path outside of expected structure
+        let invalid_album = temp_dir.path().join("InvalidAlbum");
+        fs::create_dir(&invalid_album)?;
+
+        // Test the function - should fail
+        let result = process_single_album_symlink(
+            &invalid_album,
+            music_root.to_str().unwrap(),
+            &Naming::default(),
+            false,
+            false,
+        );
+
+        assert!(result.is_err());
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("not within the expected Artists directory"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_single_album_symlink_already_exists_correct() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+        let artists_dir = music_root.join("Artists");
+        let albums_dir = music_root.join("Albums");
+
+        fs::create_dir_all(&artists_dir)?;
+        fs::create_dir_all(&albums_dir)?;
+
+        // Create proper structure
+        let artist_dir = artists_dir.join("TestArtist");
+        fs::create_dir(&artist_dir)?;
+        let album_dir = artist_dir.join("TestAlbum");
+        fs::create_dir(&album_dir)?;
+        fs::File::create(album_dir.join("track1.mp3"))?.write_all(b"test")?;
+
+        // Create the symlink manually first
+        let link_path = albums_dir.join("TestArtist - TestAlbum");
+        symlink(&album_dir, &link_path)?;
+
+        // Test the function - should succeed without recreating
+        let result = process_single_album_symlink(
+            &album_dir,
+            music_root.to_str().unwrap(),
+            &Naming::default(),
+            false,
+            false,
+        );
+
+        assert!(result.is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_single_album_symlink_already_exists_wrong_target() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+        let artists_dir = music_root.join("Artists");
+        let albums_dir = music_root.join("Albums");
+
+        fs::create_dir_all(&artists_dir)?;
+        fs::create_dir_all(&albums_dir)?;
+
+        // Create proper structure
+        let artist_dir = artists_dir.join("TestArtist");
+        fs::create_dir(&artist_dir)?;
+        let album_dir = artist_dir.join("TestAlbum");
+        fs::create_dir(&album_dir)?;
+        fs::File::create(album_dir.join("track1.mp3"))?.write_all(b"test")?;
+
+        // Create a different album to link to
+        let wrong_album = artist_dir.join("WrongAlbum");
+        fs::create_dir(&wrong_album)?;
+
+        // Create the symlink pointing to wrong target
+        let link_path = albums_dir.join("TestArtist - TestAlbum");
+        symlink(&wrong_album, &link_path)?;
+
+        // Test the function - should recreate the symlink
+        let result = process_single_album_symlink(
+            &album_dir,
+            music_root.to_str().unwrap(),
+            &Naming::default(),
+            false,
+            false,
+        );
+
+        assert!(result.is_ok());
+
+        // Verify the symlink now points to the correct target
+        let link_target = fs::read_link(&link_path)?;
+        assert_eq!(link_target, album_dir);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_single_album_symlink_missing_artists_dir() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+
+        // Don't create Artists directory
+        let artist_dir = music_root.join("Artists").join("TestArtist");
+        fs::create_dir_all(&artist_dir)?;
+        let album_dir = artist_dir.join("TestAlbum");
+        fs::create_dir(&album_dir)?;
+        fs::File::create(album_dir.join("track1.mp3"))?.write_all(b"test")?;
+
+        // Test the function - should create Albums directory
+        let result = process_single_album_symlink(
+            &album_dir,
+            music_root.to_str().unwrap(),
+            &Naming::default(),
+            false,
+            false,
+        );
+
+        assert!(result.is_ok());
+
+        // Check that Albums directory was created
+        let albums_dir = music_root.join("Albums");
+        assert!(albums_dir.exists());
+        assert!(albums_dir.is_dir());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_single_album_symlink_dry_run_changes_nothing() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+        let artist_dir = music_root.join("Artists").join("TestArtist");
+        let album_dir = artist_dir.join("TestAlbum");
+        fs::create_dir_all(&album_dir)?;
+        let wrong_album = artist_dir.join("WrongAlbum");
+        fs::create_dir(&wrong_album)?;
+
+        // Without an Albums directory, neither it nor the link is created
+        process_single_album_symlink(
+            &album_dir,
+            music_root.to_str().unwrap(),
+            &Naming::default(),
+            true,
+            false,
+        )?;
+        assert!(!music_root.join("Albums").exists());
+
+        // A link pointing elsewhere is left alone
+        let albums_dir = music_root.join("Albums");
+        fs::create_dir(&albums_dir)?;
+        let link_path = albums_dir.join("TestArtist - TestAlbum");
+        symlink(&wrong_album, &link_path)?;
+        process_single_album_symlink(
+            &album_dir,
+            music_root.to_str().unwrap(),
+            &Naming::default(),
+            true,
+            false,
+        )?;
+        assert_eq!(fs::read_link(&link_path)?, wrong_album);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_single_album_symlink_colliding_names_stay_reachable() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+        let artists_dir = music_root.join("Artists");
+
+        // "A - B" / "C" and "A" / "B - C" would both be linked as "A - B - C"
+        let first = artists_dir.join("A - B").join("C");
+        let second = artists_dir.join("A").join("B - C");
+        fs::create_dir_all(&first)?;
+        fs::create_dir_all(&second)?;
+
+        // Running twice must not add further links
+        for _ in 0..2 {
+            process_single_album_symlink(
+                &first,
+                music_root.to_str().unwrap(),
+                &Naming::default(),
+                false,
+                false,
+            )?;
+            process_single_album_symlink(
+                &second,
+                music_root.to_str().unwrap(),
+                &Naming::default(),
+                false,
+                false,
+            )?;
+        }
+
+        let albums_dir = music_root.join("Albums");
+        assert_eq!(fs::read_link(albums_dir.join("A - B - C"))?, first);
+        assert_eq!(fs::read_link(albums_dir.join("A - B - C (2)"))?, second);
+        assert_eq!(fs::read_dir(&albums_dir)?.count(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_single_album_symlink_invalid_unicode_names() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+        let artists_dir = music_root.join("Artists");
+
+        fs::create_dir_all(&artists_dir)?;
+
+        // Create directories with invalid unicode names (using filesystem-safe names)
+        let artist_dir = artists_dir.join("Test_Artist");
+        fs::create_dir(&artist_dir)?;
+        let album_dir = artist_dir.join("Test_Album");
+        fs::create_dir(&album_dir)?;
+        fs::File::create(album_dir.join("track1.mp3"))?.write_all(b"test")?;
+
+        // Test the function
+        let result = process_single_album_symlink(
+            &album_dir,
+            music_root.to_str().unwrap(),
+            &Naming::default(),
+            false,
+            false,
+        );
+
+        assert!(result.is_ok());
+
+        // Check that symlink was created with proper names
+        let albums_dir = music_root.join("Albums");
+        let expected_link = albums_dir.join("Test_Artist - Test_Album");
+        assert!(expected_link.exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_single_album_symlink_repairs_broken_link() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+        let album_dir = music_root.join("Artists").join("Artist").join("Album");
+        let albums_dir = music_root.join("Albums");
+        fs::create_dir_all(&album_dir)?;
+        fs::create_dir_all(&albums_dir)?;
+
+        // The album used to live somewhere that has since been moved away
+        let link_path = albums_dir.join("Artist - Album");
+        let gone = temp_dir.path().join("Old Library").join("Album");
+        symlink(&gone, &link_path)?;
+        let music_dir = music_root.to_str().unwrap();
+
+        process_single_album_symlink(&album_dir, music_dir, &Naming::default(), true, true)?;
+        assert_eq!(fs::read_link(&link_path)?, gone);
+
+        process_single_album_symlink(&album_dir, music_dir, &Naming::default(), false, true)?;
+        assert_eq!(fs::read_link(&link_path)?, album_dir);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_dedupe_album_links_collapses_identical_albums() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+        let artists_dir = music_root.join("Artists");
+
+        // A compilation filed under two artists, and an album of the same size that differs
+        let filed = artists_dir.join("Artist").join("Compilation");
+        let cross_filed = artists_dir.join("Various Artists").join("Compilation");
+        let different = artists_dir.join("Other").join("Lookalike");
+        for (album, contents) in [
+            (&filed, [b"one", b"two"]),
+            (&cross_filed, [b"one", b"two"]),
+            (&different, [b"one", b"twO"]),
+        ] {
+            fs::create_dir_all(album)?;
+            fs::write(album.join("01.mp3"), contents[0])?;
+            fs::write(album.join("02.mp3"), contents[1])?;
+        }
+        fs::rename(
+            cross_filed.join("02.mp3"),
+            cross_filed.join("02 Renamed.mp3"),
+        )?;
+
+        let music_dir = music_root.to_str().unwrap();
+        for album in [&filed, &cross_filed, &different] {
+            process_single_album_symlink(album, music_dir, &Naming::default(), false, false)?;
+        }
+
+        let albums_dir = music_root.join("Albums");
+        let expected = vec![DuplicateLink {
+            link: albums_dir.join("Various Artists - Compilation"),
+            kept: albums_dir.join("Artist - Compilation"),
+        }];
+        assert_eq!(dedupe_album_links(music_dir, false)?, expected);
+        assert!(albums_dir
+            .join("Various Artists - Compilation")
+            .is_symlink());
+
+        assert_eq!(dedupe_album_links(music_dir, true)?, expected);
+        assert!(!albums_dir
+            .join("Various Artists - Compilation")
+            .is_symlink());
+        assert!(albums_dir.join("Artist - Compilation").is_symlink());
+        assert!(albums_dir.join("Other - Lookalike").is_symlink());
+        assert!(dedupe_album_links(music_dir, true)?.is_empty());
+
+        Ok(())
+    }
+}
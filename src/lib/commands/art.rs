@@ -0,0 +1,1149 @@
+use crate::cover_art::{self, CoverFormat};
+use crate::musicbrainz::Network;
+use crate::{audio, utils};
+use anyhow::Result;
+use ffmpeg_next as ffmpeg;
+use ffmpeg_next::format::stream::Disposition;
+use gio::prelude::*;
+use lofty::{
+    self,
+    config::ParseOptions,
+    file::{TaggedFile, TaggedFileExt},
+    picture::{Picture, PictureInformation},
+    probe::Probe,
+    tag::ItemKey,
+};
+use rayon::prelude::*;
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime};
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+use tracing::{error, info, warn};
+use urlencoding;
+
+/// Environment variable with the minimum gap between Pexels searches, in milliseconds
+pub const PLACEHOLDER_DELAY_ENV: &str = "MFUTIL_PLACEHOLDER_DELAY_MS";
+const DEFAULT_PLACEHOLDER_DELAY: Duration = Duration::from_secs(1);
+
+/// Spaces out requests to one service so consecutive ones start at least `delay` apart
+struct RequestPacer {
+    delay: Duration,
+    last_request: Mutex<Option<Instant>>,
+}
+
+impl RequestPacer {
+    fn new(delay: Duration) -> Self {
+        RequestPacer {
+            delay,
+            last_request: Mutex::new(None),
+        }
+    }
+
+    /// Wait (without blocking the runtime) until the delay has passed since the previous
+    /// request, then record this one
+    async fn wait(&self) {
+        let mut last_request = self.last_request.lock().await;
+        if let Some(last) = *last_request {
+            tokio::time::sleep_until(last + self.delay).await;
+        }
+        *last_request = Some(Instant::now());
+    }
+}
+
+/// A directory extracted album art is collected in, mirroring the library's artist folders
+/// (`<dir>/<Artist>/<Album>.jpg`), as `--art-dir` sets up
+#[derive(Debug, Clone)]
+pub struct ArtDir {
+    pub path: PathBuf,
+    /// Also write `.folder.jpg` into the album folder, as without `--art-dir`
+    pub in_place: bool,
+}
+
+impl ArtDir {
+    /// Where the art extracted from `album_dir` (`.../<Artist>/<Album>`) goes
+    pub fn album_art_path(&self, album_dir: &Path) -> PathBuf {
+        let artist = album_dir
+            .parent()
+            .and_then(Path::file_name)
+            .unwrap_or_default();
+        let mut file_name = album_dir.file_name().unwrap_or_default().to_owned();
+        file_name.push(".jpg");
+        self.path.join(artist).join(file_name)
+    }
+}
+
+static FFMPEG_INIT: OnceLock<Result<(), String>> = OnceLock::new();
+
+#[cfg(test)]
+thread_local! {
+    /// Set by tests to make [`init_ffmpeg`] fail on their thread, as if ffmpeg were missing
+    pub(crate) static FFMPEG_UNAVAILABLE: std::cell::Cell<bool> =
+        const { std::cell::Cell::new(false) };
+}
+
+/// Initialize ffmpeg's global state exactly once, however many threads get here first
+/// Everything after this opens its own input context, so extraction threads share nothing
+/// Only album art extraction needs ffmpeg, so it is initialized there rather than at startup,
+/// and other commands run on systems where the libraries are missing or broken
+pub fn init_ffmpeg() -> Result<()> {
+    #[cfg(test)]
+    if FFMPEG_UNAVAILABLE.get() {
+        return Err(ffmpeg_unavailable("disabled by test"));
+    }
+    FFMPEG_INIT
+        .get_or_init(|| ffmpeg::init().map_err(|e| e.to_string()))
+        .clone()
+        .map_err(|e| ffmpeg_unavailable(&e))
+}
+
+fn ffmpeg_unavailable(reason: &str) -> anyhow::Error {
+    anyhow::anyhow!(
+        "Album art extraction needs the ffmpeg libraries, which failed to initialize: {}",
+        reason
+    )
+}
+
+/// Gap between Pexels searches for [`fetch_placeholders`]: `MFUTIL_PLACEHOLDER_DELAY_MS` if it
+/// is a number of milliseconds (0 turns the delay off), one second otherwise
+pub fn placeholder_delay() -> Duration {
+    env::var(PLACEHOLDER_DELAY_ENV)
+        .ok()
+        .and_then(|ms| ms.trim().parse::<u64>().ok())
+        .map_or(DEFAULT_PLACEHOLDER_DELAY, Duration::from_millis)
+}
+
+/// Validates that required API keys are present before making network requests
+pub fn validate_api_keys() -> Result<()> {
+    let pexels_key = env::var("PEXELS_API_KEY");
+    let audiodb_key = env::var("AUDIODB_API_KEY");
+
+    if pexels_key.is_err() {
+        warn!("PEXELS_API_KEY not set - placeholder image fetching will be disabled");
+    }
+
+    if audiodb_key.is_err() {
+        warn!("AUDIODB_API_KEY not set - artist image fetching will be disabled");
+    }
+
+    // Don't fail completely if keys are missing - just warn and continue with local fallbacks
+    Ok(())
+}
+
+fn pexels_api_key() -> Option<String> {
+    env::var("PEXELS_API_KEY").ok()
+}
+
+fn audiodb_api_key() -> Option<String> {
+    env::var("AUDIODB_API_KEY").ok()
+}
+
+fn extract_album_artist_from_directory(artist_path: &Path) -> Result<Option<String>> {
+    // For AlbumArtist metadata, look in the first directory's first music file
+    // This is more efficient and accurate than searching all files
+
+    // Get the first subdirectory (first album)
+    if let Some(first_album_dir) = fs::read_dir(artist_path)?
+        .filter_map(|e| e.ok())
+        .find(|e| e.path().is_dir())
+    {
+        let album_path = first_album_dir.path();
+
+        // Find the first music file in that album directory
+        if let Some(music_file) = fs::read_dir(&album_path)?.filter_map(|e| e.ok()).find(|e| {
+            let path = e.path();
+            if path.is_file() {
+                let ext = path
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .map(|e| e.to_lowercase())
+                    .unwrap_or_default();
+
+                // Check if it's an audio file
+                matches!(
+                    ext.as_str(),
+                    "mp3" | "flac" | "m4a" | "ogg" | "aac" | "wma" | "wav" | "aiff"
+                )
+            } else {
+                false
+            }
+        }) {
+            // Read the metadata from this single file
+            if let Ok(tagged_file) = lofty::read_from_path(music_file.path()) {
+                let tags = tagged_file.tags();
+                if let Some(tag) = tags.first() {
+                    // Try to get album artist first, fall back to track artist
+                    if let Some(album_artist) = tag.get_string(&ItemKey::AlbumArtist) {
+                        return Ok(Some(album_artist.to_string()));
+                    } else if let Some(track_artist) = tag.get_string(&ItemKey::TrackArtist) {
+                        return Ok(Some(track_artist.to_string()));
+                    }
+                }
+            }
+        }
+    }
+
+    // If no album artist found, return None
+    Ok(None)
+}
+
+#[derive(Deserialize, Debug)]
+struct PexelsPhotoSrc {
+    large: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct PexelsPhoto {
+    src: PexelsPhotoSrc,
+}
+
+#[derive(Deserialize, Debug)]
+struct PexelsSearchResponse {
+    photos: Vec<PexelsPhoto>,
+}
+
+/// Fetch each artist folder's `.folder.jpg` from AudioDB, falling back to a `folder.jpg`
+/// already in the folder
+/// With `square_art` the image is cropped to a centred square, as the top-level placeholders
+/// are, so artist thumbnails line up in file managers; otherwise it is kept as fetched
+/// AudioDB is reached over `network`
+pub fn extract_artist_art(music_dir: &str, square_art: bool, network: &Network) -> Result<()> {
+    // Validate API keys before starting
+    validate_api_keys()?;
+
+    let artists_path = Path::new(music_dir).join("Artists");
+    // One runtime for the whole run so the HTTP client's pooled connections stay usable
+    let rt = tokio::runtime::Runtime::new()?;
+
+    for artist_entry in fs::read_dir(&artists_path)?.filter_map(|e| e.ok()) {
+        let artist_path = artist_entry.path();
+        if artist_path.is_dir() {
+            let output_file = artist_path.join(".folder.jpg");
+            if cover_art::find_folder_icon(&artist_path).is_none() {
+                // Extract album artist from music files in this directory
+                let album_artist = extract_album_artist_from_directory(&artist_path)?;
+
+                if let Some(artist_name) = album_artist {
+                    let audiodb_fetch_successful = !network.is_offline() && rt.block_on(async {
+                        let client = network.http_client()?;
+                        let key = audiodb_api_key();
+                        if key.is_none() {
+                            warn!("AUDIODB_API_KEY not set, skipping AudioDB artist fetch for {}", artist_name);
+                            return Ok::<bool, anyhow::Error>(false);
+                        }
+                        let audiodb_url = format!("https://www.theaudiodb.com/api/v1/json/{}/search.php?s={}", key.unwrap(), urlencoding::encode(&artist_name));
+
+                        match client.get(&audiodb_url).send().await {
+                            Ok(response) => {
+                                if response.status().is_success() {
+                                    match response.json::<serde_json::Value>().await {
+                                        Ok(audiodb_json) => {
+                                            if let Some(artists) = audiodb_json["artists"].as_array() {
+                                                if let Some(artist) = artists.first() {
+                                                    if let Some(image_url) = artist["strArtistThumb"].as_str() {
+                                                        match client.get(image_url).send().await {
+                                                            Ok(image_response) => {
+                                                                match image_response.bytes().await {
+                                                                    Ok(image_content) => {
+                                                                        if fs::write(&output_file, &image_content).is_ok() {
+                                                                            info!("Artist image fetched from AudioDB for: {} (album artist)", artist_name);
+                                                                            return Ok(true);
+                                                                        }
+                                                                    }
+                                                                    Err(e) => error!("Failed to read image bytes: {}", e),
+                                                                }
+                                                            }
+                                                            Err(e) => error!("Failed to fetch image: {}", e),
+                                                        }
+                                                    }
+                                                }
+                                            }
+                                        }
+                                        Err(e) => error!("Failed to parse AudioDB JSON: {}", e),
+                                    }
+                                } else {
+                                    error!("Error searching AudioDB for artist {}: {}", artist_name, response.status());
+                                }
+                            }
+                            Err(e) => error!("Failed to send AudioDB request: {}", e),
+                        }
+                        Ok(false)
+                    })?;
+
+                    if !audiodb_fetch_successful {
+                        // If AudioDB failed, check for existing folder.jpg
+                        let folder_jpg_path = artist_path.join("folder.jpg");
+                        if folder_jpg_path.exists() {
+                            fs::copy(&folder_jpg_path, &output_file)?;
+                            info!(
+                                "Copied {} to {}",
+                                folder_jpg_path.display(),
+                                output_file.display()
+                            );
+                        }
+                    }
+
+                    if square_art {
+                        crop_image_to_square(&output_file, CoverFormat::Jpeg)?;
+                    }
+                } else {
+                    warn!(
+                        "No album artist metadata found in directory: {}",
+                        artist_path.display()
+                    );
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Extract an album's embedded art to `.folder.jpg`, and to `art_dir` (`--art-dir`) if given
+/// Every audio file is checked and the largest embedded picture wins, so a low-bitrate file
+/// carrying a thumbnail doesn't shadow full-size art elsewhere in the album
+/// Art already in a destination is left alone; the album is skipped once every destination
+/// has some. With `since`, albums none of whose files were modified after it are left alone
+pub fn process_single_album_art(
+    current_dir: &Path,
+    art_dir: Option<&ArtDir>,
+    since: Option<SystemTime>,
+) -> Result<()> {
+    let mut output_files = Vec::new();
+    if art_dir.is_none_or(|dir| dir.in_place) && cover_art::find_folder_icon(current_dir).is_none()
+    {
+        output_files.push(current_dir.join(".folder.jpg"));
+    }
+    if let Some(mirrored) = art_dir.map(|dir| dir.album_art_path(current_dir)) {
+        if !mirrored.exists() {
+            output_files.push(mirrored);
+        }
+    }
+    if output_files.is_empty() {
+        return Ok(());
+    }
+
+    let mut music_files: Vec<PathBuf> = fs::read_dir(current_dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|path| path.is_file() && audio::is_audio_file(path))
+        .collect();
+    music_files.sort();
+
+    // With --since, albums none of whose files changed are left alone
+    if !music_files
+        .iter()
+        .any(|path| utils::is_modified_since(path, since))
+    {
+        return Ok(());
+    }
+
+    if let Some(picture) = largest_embedded_picture(&music_files) {
+        return write_album_art(&output_files, picture.data());
+    }
+
+    // No tag carried a picture; let ffmpeg look for an attached picture stream instead. MP4
+    // files in particular (ALAC as well as AAC) can expose art to one reader and not the other
+    for music_file in &music_files {
+        if let Some(data) = ffmpeg_attached_picture(music_file)? {
+            return write_album_art(&output_files, &data);
+        }
+    }
+
+    Ok(())
+}
+
+fn write_album_art(output_files: &[PathBuf], data: &[u8]) -> Result<()> {
+    for output_file in output_files {
+        if let Some(parent) = output_file.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(output_file, data)?;
+        info!("Album art extracted to {}", output_file.display());
+    }
+    Ok(())
+}
+
+/// The first attached picture stream ffmpeg finds in `music_file`, if it can open the file
+fn ffmpeg_attached_picture(music_file: &Path) -> Result<Option<Vec<u8>>> {
+    init_ffmpeg()?;
+    let Ok(mut ictx) = ffmpeg::format::input(music_file) else {
+        return Ok(None);
+    };
+    let Some(index) = ictx
+        .streams()
+        .find(|s| s.disposition().contains(Disposition::ATTACHED_PIC))
+        .map(|s| s.index())
+    else {
+        return Ok(None);
+    };
+    Ok(ictx
+        .packets()
+        .find(|(s, _)| s.index() == index)
+        .and_then(|(_, packet)| packet.data().map(<[u8]>::to_vec)))
+}
+
+/// Extract album art for `album_paths` across the current rayon pool, so `--jobs` bounds it
+/// Each album is handled as [`process_single_album_art`] would and `on_done` is called as it
+/// finishes; once `keep_going` is cleared, albums not yet started are skipped
+pub fn extract_album_art_parallel<F>(
+    album_paths: &[PathBuf],
+    art_dir: Option<&ArtDir>,
+    since: Option<SystemTime>,
+    keep_going: &AtomicBool,
+    on_done: F,
+) -> Result<()>
+where
+    F: Fn(&Path) -> Result<()> + Sync,
+{
+    init_ffmpeg()?;
+    album_paths.par_iter().try_for_each(|album_path| {
+        if !keep_going.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        process_single_album_art(album_path, art_dir, since)?;
+        on_done(album_path)
+    })
+}
+
+/// Find the largest picture embedded in any of `files`, by pixel area and then by byte size
+fn largest_embedded_picture(files: &[PathBuf]) -> Option<Picture> {
+    files
+        .iter()
+        .filter_map(|path| read_tags(path))
+        .flat_map(|tagged_file| {
+            tagged_file
+                .tags()
+                .iter()
+                .flat_map(|tag| tag.pictures().to_vec())
+                .collect::<Vec<_>>()
+        })
+        .max_by_key(picture_size)
+}
+
+/// Read a file's tags without its audio properties, which art extraction has no use for
+/// An MP4 whose sample description lofty can't make sense of (some ALAC encoders write odd
+/// ones) then still yields its `covr` art
+fn read_tags(path: &Path) -> Option<TaggedFile> {
+    Probe::open(path)
+        .ok()?
+        .options(ParseOptions::new().read_properties(false))
+        .read()
+        .ok()
+}
+
+fn picture_size(picture: &Picture) -> (u64, usize) {
+    let area = PictureInformation::from_picture(picture)
+        .map(|info| u64::from(info.width) * u64::from(info.height))
+        .unwrap_or(0);
+    (area, picture.data().len())
+}
+
+pub fn set_folder_icons_callback(current_dir: &Path) -> Result<()> {
+    if let Some(icon_path) = cover_art::find_folder_icon(current_dir) {
+        let file = gio::File::for_path(current_dir);
+        let icon_uri = format!("file://{}", icon_path.display());
+        file.set_attribute_string(
+            "metadata::custom-icon",
+            &icon_uri,
+            gio::FileQueryInfoFlags::NONE,
+            None::<&gio::Cancellable>,
+        )?;
+
+        let directory_file = current_dir.join(".directory");
+        let icon_name = icon_path.file_name().unwrap_or_default().to_string_lossy();
+        fs::write(
+            directory_file,
+            format!("[Desktop Entry]\nIcon=./{}", icon_name),
+        )?;
+    }
+    Ok(())
+}
+
+async fn fetch_and_save_placeholder(
+    network: &Network,
+    pacer: &RequestPacer,
+    path: &Path,
+    name: &str,
+    category: &str,
+) -> Result<()> {
+    let placeholder_path = path.join(".folder.jpg");
+    if cover_art::find_folder_icon(path).is_none() {
+        if network.is_offline() {
+            info!("Offline, not fetching a placeholder for {}", path.display());
+            return Ok(());
+        }
+        info!("Fetching placeholder for {}: {}", name, path.display());
+
+        // Try to extract album artist from music files first
+        let search_name = if let Ok(album_artist) = extract_album_artist_from_directory(path) {
+            album_artist.unwrap_or_else(|| name.to_string())
+        } else {
+            name.to_string()
+        };
+
+        let client = network.http_client()?;
+        let query = format!("{} {}", category, search_name);
+        let url = format!(
+            "https://api.pexels.com/v1/search?query={}&per_page=1",
+            urlencoding::encode(&query)
+        );
+        let key = pexels_api_key();
+        if key.is_none() {
+            warn!(
+                "PEXELS_API_KEY not set, skipping placeholder fetch for {}",
+                name
+            );
+            return Ok(());
+        }
+
+        // Only searches that are actually sent wait their turn
+        pacer.wait().await;
+        match client
+            .get(&url)
+            .header("Authorization", key.unwrap())
+            .send()
+            .await
+        {
+            Ok(response) => {
+                if response.status().is_success() {
+                    match response.json::<PexelsSearchResponse>().await {
+                        Ok(search_result) => {
+                            if let Some(photo) = search_result.photos.first() {
+                                let image_url = &photo.src.large;
+                                match client.get(image_url).send().await {
+                                    Ok(image_response) => match image_response.bytes().await {
+                                        Ok(image_content) => {
+                                            if fs::write(&placeholder_path, &image_content).is_ok()
+                                            {
+                                                info!(
+                                                        "Placeholder fetched for {}: {} (searched by album artist)",
+                                                        name,
+                                                        path.display()
+                                                    );
+                                            }
+                                        }
+                                        Err(e) => error!("Failed to read image bytes: {}", e),
+                                    },
+                                    Err(e) => error!("Failed to fetch image: {}", e),
+                                }
+                            } else {
+                                warn!(
+                                    "No image found for {}: {} (searched by album artist)",
+                                    name,
+                                    path.display()
+                                );
+                            }
+                        }
+                        Err(e) => error!("Failed to parse Pexels JSON: {}", e),
+                    }
+                } else {
+                    error!(
+                        "Error searching Pexels for {}: {}: {}",
+                        name,
+                        path.display(),
+                        response.status()
+                    );
+                }
+            }
+            Err(e) => error!("Failed to send Pexels request: {}", e),
+        }
+    }
+    Ok(())
+}
+
+/// Fetch the Artists, Albums and Tracks placeholder icons over `network`, saved in
+/// `image_format`, with Pexels searches started at least `delay` apart (see
+/// [`placeholder_delay`])
+pub async fn fetch_placeholders(
+    music_dir: &str,
+    image_format: CoverFormat,
+    network: &Network,
+    delay: Duration,
+) -> Result<()> {
+    // Validate API keys before starting
+    validate_api_keys()?;
+
+    let artists_path = Path::new(music_dir).join("Artists");
+    let albums_path = Path::new(music_dir).join("Albums");
+    let tracks_path = Path::new(music_dir).join("Tracks");
+
+    // Fetch for root Artists, Albums, Tracks directories
+    let pacer = RequestPacer::new(delay);
+    for (path, name, category) in [
+        (&artists_path, "Artists", "Music Artists"),
+        (&albums_path, "Albums", "Music Albums"),
+        (&tracks_path, "Tracks", "Music Tracks"),
+    ] {
+        fetch_and_save_placeholder(network, &pacer, path, name, category).await?;
+        if let Some(icon_path) = cover_art::find_folder_icon(path) {
+            crop_image_to_square(&icon_path, image_format)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Crop an image to a centred square and save it in `image_format`
+/// The file is renamed to match the format (`.folder.jpg` becomes `.folder.png`, say);
+/// returns the path written, or `None` if there was no image
+pub fn crop_image_to_square(
+    image_path: &Path,
+    image_format: CoverFormat,
+) -> Result<Option<PathBuf>> {
+    if !image_path.exists() {
+        return Ok(None); // No image to crop
+    }
+
+    let image_content = fs::read(image_path)?;
+    let wand = cover_art::new_wand();
+    wand.read_image_blob(&image_content)?;
+
+    let width = wand.get_image_width();
+    let height = wand.get_image_height();
+    let size = std::cmp::min(width, height);
+    let x = (width - size) / 2;
+    let y = (height - size) / 2;
+
+    wand.crop_image(size, size, x as isize, y as isize)?;
+    wand.set_image_format(image_format.magick_name())?;
+
+    let output_path = image_path.with_extension(image_format.extension());
+    fs::write(
+        &output_path,
+        &wand.write_image_blob(image_format.magick_name())?,
+    )?;
+    if output_path != image_path {
+        fs::remove_file(image_path)?;
+    }
+    info!("Image cropped: {}", output_path.display());
+
+    Ok(Some(output_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{write_silent_wav, write_tagged_wav};
+    use lofty::config::WriteOptions;
+    use lofty::file::AudioFile;
+    use lofty::picture::PictureType;
+    use lofty::tag::Tag;
+    use magick_rust::PixelWand;
+    use std::env;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_validate_api_keys_missing_keys() {
+        // Save original values
+        let original_pexels = env::var("PEXELS_API_KEY");
+        let original_audiodb = env::var("AUDIODB_API_KEY");
+
+        // Remove API keys to simulate missing state
+        env::remove_var("PEXELS_API_KEY");
+        env::remove_var("AUDIODB_API_KEY");
+
+        // This should not fail, just warn
+        let result = validate_api_keys();
+        assert!(result.is_ok());
+
+        // Restore original values
+        if let Ok(pexels) = original_pexels {
+            env::set_var("PEXELS_API_KEY", pexels);
+        }
+        if let Ok(audiodb) = original_audiodb {
+            env::set_var("AUDIODB_API_KEY", audiodb);
+        }
+    }
+
+    #[test]
+    fn test_validate_api_keys_with_keys() {
+        // Save original values
+        let original_pexels = env::var("PEXELS_API_KEY");
+        let original_audiodb = env::var("AUDIODB_API_KEY");
+
+        // Set test keys
+        env::set_var("PEXELS_API_KEY", "test_pexels_key");
+        env::set_var("AUDIODB_API_KEY", "test_audiodb_key");
+
+        // This should succeed without warnings
+        let result = validate_api_keys();
+        assert!(result.is_ok());
+
+        // Restore original values
+        if let Ok(pexels) = original_pexels {
+            env::set_var("PEXELS_API_KEY", pexels);
+        } else {
+            env::remove_var("PEXELS_API_KEY");
+        }
+        if let Ok(audiodb) = original_audiodb {
+            env::set_var("AUDIODB_API_KEY", audiodb);
+        } else {
+            env::remove_var("AUDIODB_API_KEY");
+        }
+    }
+
+    #[test]
+    fn test_pexels_api_key_function() {
+        // Save original value
+        let original = env::var("PEXELS_API_KEY");
+
+        // Test with key set
+        env::set_var("PEXELS_API_KEY", "test_key");
+        assert_eq!(pexels_api_key(), Some("test_key".to_string()));
+
+        // Test without key
+        env::remove_var("PEXELS_API_KEY");
+        assert_eq!(pexels_api_key(), None);
+
+        // Restore original value
+        if let Ok(key) = original {
+            env::set_var("PEXELS_API_KEY", key);
+        }
+    }
+
+    #[test]
+    fn test_audiodb_api_key_function() {
+        // Save original value
+        let original = env::var("AUDIODB_API_KEY");
+
+        // Test with key set
+        env::set_var("AUDIODB_API_KEY", "test_key");
+        assert_eq!(audiodb_api_key(), Some("test_key".to_string()));
+
+        // Test without key
+        env::remove_var("AUDIODB_API_KEY");
+        assert_eq!(audiodb_api_key(), None);
+
+        // Restore original value
+        if let Ok(key) = original {
+            env::set_var("AUDIODB_API_KEY", key);
+        }
+    }
+
+    #[test]
+    fn test_crop_image_to_square_missing_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let missing_file = temp_dir.path().join("missing.jpg");
+
+        // Should not fail when file doesn't exist
+        let result = crop_image_to_square(&missing_file, CoverFormat::Jpeg);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_crop_image_to_square_converts_to_png() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let wand = cover_art::new_wand();
+        let mut background = PixelWand::new();
+        background.set_color("red")?;
+        wand.new_image(6, 4, &background)?;
+        let jpeg_path = temp_dir.path().join(".folder.jpg");
+        fs::write(&jpeg_path, wand.write_image_blob("jpeg")?)?;
+
+        let written = crop_image_to_square(&jpeg_path, CoverFormat::Png)?;
+
+        let png_path = temp_dir.path().join(".folder.png");
+        assert_eq!(written.as_ref(), Some(&png_path));
+        assert!(!jpeg_path.exists());
+        let data = fs::read(&png_path)?;
+        assert!(data.starts_with(PNG_SIGNATURE));
+        let cropped = cover_art::new_wand();
+        cropped.read_image_blob(&data)?;
+        assert_eq!(
+            (cropped.get_image_width(), cropped.get_image_height()),
+            (4, 4)
+        );
+        assert_eq!(cover_art::find_folder_icon(temp_dir.path()), Some(png_path));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_artist_art_square_art_crops_artist_image() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let artist_dir = temp_dir.path().join("Artists").join("Band");
+        let album_dir = artist_dir.join("Album");
+        fs::create_dir_all(&album_dir)?;
+        let track = album_dir.join("01.wav");
+        write_tagged_wav(&track, &[(ItemKey::AlbumArtist, "Band")])?;
+
+        // A wide artist photo left by another tool, which is used while AudioDB is out of reach
+        let wand = cover_art::new_wand();
+        let mut background = PixelWand::new();
+        background.set_color("red")?;
+        wand.new_image(6, 4, &background)?;
+        fs::write(
+            artist_dir.join("folder.jpg"),
+            wand.write_image_blob("jpeg")?,
+        )?;
+
+        let dimensions = |path: &Path| -> Result<(usize, usize)> {
+            let image = cover_art::new_wand();
+            image.read_image_blob(&fs::read(path)?)?;
+            Ok((image.get_image_width(), image.get_image_height()))
+        };
+        let music_dir = temp_dir.path().to_str().unwrap();
+        let icon = artist_dir.join(".folder.jpg");
+
+        let network = Network::offline();
+        let kept = extract_artist_art(music_dir, false, &network).and_then(|()| dimensions(&icon));
+        fs::remove_file(&icon)?;
+        let squared =
+            extract_artist_art(music_dir, true, &network).and_then(|()| dimensions(&icon));
+
+        assert_eq!(kept?, (6, 4));
+        assert_eq!(squared?, (4, 4));
+
+        Ok(())
+    }
+
+    const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+    // The same with `art` embedded as its front cover
+    fn write_wav_with_picture(path: &Path, art: &[u8]) -> Result<()> {
+        write_silent_wav(path)?;
+        let mut tagged_file = lofty::read_from_path(path)?;
+        let mut tag = Tag::new(tagged_file.primary_tag_type());
+        let mut picture = Picture::from_reader(&mut &art[..])?;
+        picture.set_pic_type(PictureType::CoverFront);
+        tag.push_picture(picture);
+        tagged_file.insert_tag(tag);
+        tagged_file.save_to_path(path, WriteOptions::default())?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_single_album_art_uses_largest_picture() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let album = temp_dir.path();
+        let thumbnail = [PNG_SIGNATURE, &[0u8; 8]].concat();
+        let full_size = [PNG_SIGNATURE, &[0u8; 64]].concat();
+
+        // The thumbnail sorts first, so picking the first file would get it wrong
+        write_wav_with_picture(&album.join("01 low bitrate.wav"), &thumbnail)?;
+        write_wav_with_picture(&album.join("02 high bitrate.wav"), &full_size)?;
+
+        process_single_album_art(album, None, None)?;
+        assert_eq!(fs::read(album.join(".folder.jpg"))?, full_size);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_album_art_lands_in_mirrored_art_dir() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let album = temp_dir
+            .path()
+            .join("Artists")
+            .join("Band")
+            .join("Live 1.0");
+        fs::create_dir_all(&album)?;
+        let art = [PNG_SIGNATURE, &[3u8; 16]].concat();
+        write_wav_with_picture(&album.join("01.wav"), &art)?;
+
+        let art_dir = ArtDir {
+            path: temp_dir.path().join("art"),
+            in_place: false,
+        };
+        let mirrored = temp_dir
+            .path()
+            .join("art")
+            .join("Band")
+            .join("Live 1.0.jpg");
+        assert_eq!(art_dir.album_art_path(&album), mirrored);
+
+        // Only the art directory gets the art
+        process_single_album_art(&album, Some(&art_dir), None)?;
+        assert_eq!(fs::read(&mirrored)?, art);
+        assert!(cover_art::find_folder_icon(&album).is_none());
+
+        // In addition to the album folder, which is filled in without touching the mirror
+        fs::write(&mirrored, b"kept")?;
+        let art_dir = ArtDir {
+            in_place: true,
+            ..art_dir
+        };
+        process_single_album_art(&album, Some(&art_dir), None)?;
+        assert_eq!(fs::read(album.join(".folder.jpg"))?, art);
+        assert_eq!(fs::read(&mirrored)?, b"kept");
+
+        Ok(())
+    }
+
+    fn mp4_atom(name: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        [&(8 + body.len() as u32).to_be_bytes()[..], name, body].concat()
+    }
+
+    // A bare ALAC .m4a as iTunes lays it out, with no audio samples and `art` as its `covr`
+    fn write_alac_m4a(path: &Path, art: &[u8]) -> Result<()> {
+        let full =
+            |version_flags: u32, body: &[u8]| [&version_flags.to_be_bytes()[..], body].concat();
+        let matrix: Vec<u8> = [0x10000u32, 0, 0, 0, 0x10000, 0, 0, 0, 0x4000_0000]
+            .iter()
+            .flat_map(|v| v.to_be_bytes())
+            .collect();
+
+        let mvhd = [
+            &[0u8; 8][..],             // creation and modification times
+            &44100u32.to_be_bytes(),   // timescale
+            &[0u8; 4],                 // duration
+            &0x10000u32.to_be_bytes(), // rate 1.0
+            &0x100u16.to_be_bytes(),   // volume 1.0
+            &[0u8; 10],
+            &matrix,
+            &[0u8; 24],
+            &2u32.to_be_bytes(), // next track ID
+        ]
+        .concat();
+        let tkhd = [
+            &[0u8; 8][..],
+            &1u32.to_be_bytes(), // track ID
+            &[0u8; 16],          // reserved, duration, reserved
+            &[0u8; 4],           // layer, alternate group
+            &0x100u16.to_be_bytes(),
+            &[0u8; 2],
+            &matrix,
+            &[0u8; 8], // width, height
+        ]
+        .concat();
+        let mdhd = [
+            &[0u8; 8][..],
+            &44100u32.to_be_bytes(),
+            &[0u8; 4],
+            &[0x55, 0xc4, 0, 0],
+        ]
+        .concat();
+        let hdlr = |kind: &[u8; 4]| [&[0u8; 4][..], kind, &[0u8; 13]].concat();
+        // The ALAC magic cookie: frame length, version, bit depth, rice parameters, channels,
+        // max run, max frame bytes, average bit rate, sample rate
+        let cookie = [
+            &4096u32.to_be_bytes()[..],
+            &[0, 16, 40, 10, 14, 2],
+            &255u16.to_be_bytes(),
+            &[0u8; 8],
+            &44100u32.to_be_bytes(),
+        ]
+        .concat();
+        let alac_entry = [
+            &[0u8; 6][..],
+            &1u16.to_be_bytes(), // data reference index
+            &[0u8; 8],           // version, revision, vendor
+            &2u16.to_be_bytes(), // channels
+            &16u16.to_be_bytes(),
+            &[0u8; 4],
+            &(44100u32 << 16).to_be_bytes(),
+            &mp4_atom(b"alac", &full(0, &cookie)),
+        ]
+        .concat();
+        let stbl = [
+            mp4_atom(
+                b"stsd",
+                &full(
+                    0,
+                    &[&1u32.to_be_bytes()[..], &mp4_atom(b"alac", &alac_entry)].concat(),
+                ),
+            ),
+            mp4_atom(b"stts", &full(0, &[0u8; 4])),
+            mp4_atom(b"stsc", &full(0, &[0u8; 4])),
+            mp4_atom(b"stsz", &full(0, &[0u8; 8])),
+            mp4_atom(b"stco", &full(0, &[0u8; 4])),
+        ]
+        .concat();
+        let dref = full(
+            0,
+            &[&1u32.to_be_bytes()[..], &mp4_atom(b"url ", &full(1, &[]))].concat(),
+        );
+        let minf = [
+            mp4_atom(b"smhd", &full(0, &[0u8; 4])),
+            mp4_atom(b"dinf", &mp4_atom(b"dref", &dref)),
+            mp4_atom(b"stbl", &stbl),
+        ]
+        .concat();
+        let mdia = [
+            mp4_atom(b"mdhd", &full(0, &mdhd)),
+            mp4_atom(b"hdlr", &hdlr(b"soun")),
+            mp4_atom(b"minf", &minf),
+        ]
+        .concat();
+        let trak = [mp4_atom(b"tkhd", &full(7, &tkhd)), mp4_atom(b"mdia", &mdia)].concat();
+
+        // Type 14 marks PNG data
+        let covr = mp4_atom(
+            b"covr",
+            &mp4_atom(
+                b"data",
+                &[&14u32.to_be_bytes()[..], &[0u8; 4], art].concat(),
+            ),
+        );
+        let meta = full(
+            0,
+            &[mp4_atom(b"hdlr", &hdlr(b"mdir")), mp4_atom(b"ilst", &covr)].concat(),
+        );
+        let moov = [
+            mp4_atom(b"mvhd", &full(0, &mvhd)),
+            mp4_atom(b"trak", &trak),
+            mp4_atom(b"udta", &mp4_atom(b"meta", &meta)),
+        ]
+        .concat();
+
+        let ftyp = mp4_atom(b"ftyp", b"M4A \0\0\0\0M4A mp42isom");
+        fs::write(
+            path,
+            [ftyp, mp4_atom(b"moov", &moov), mp4_atom(b"mdat", &[])].concat(),
+        )?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_single_album_art_reads_alac_m4a_cover() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let album = temp_dir.path();
+        let art = [PNG_SIGNATURE, &[7u8; 32]].concat();
+        write_alac_m4a(&album.join("01 Lossless.m4a"), &art)?;
+
+        process_single_album_art(album, None, None)?;
+        assert_eq!(fs::read(album.join(".folder.jpg"))?, art);
+
+        Ok(())
+    }
+
+    // Many small albums: half with art in their tags, half left to the ffmpeg fallback
+    fn make_art_library() -> Result<(TempDir, Vec<PathBuf>)> {
+        let temp_dir = TempDir::new()?;
+        let mut albums = Vec::new();
+        for i in 0..32 {
+            let album = temp_dir.path().join(format!("Album {:02}", i));
+            fs::create_dir(&album)?;
+            for track in 0..4 {
+                let path = album.join(format!("{:02}.wav", track));
+                if i % 2 == 0 {
+                    let art = [PNG_SIGNATURE, &[i as u8; 16]].concat();
+                    write_wav_with_picture(&path, &art)?;
+                } else {
+                    write_silent_wav(&path)?;
+                }
+            }
+            albums.push(album);
+        }
+        Ok((temp_dir, albums))
+    }
+
+    fn extracted_covers(albums: &[PathBuf]) -> Vec<Option<Vec<u8>>> {
+        albums
+            .iter()
+            .map(|album| fs::read(album.join(".folder.jpg")).ok())
+            .collect()
+    }
+
+    #[test]
+    fn test_parallel_album_art_matches_serial() -> Result<()> {
+        let (_serial_dir, serial_albums) = make_art_library()?;
+        for album in &serial_albums {
+            process_single_album_art(album, None, None)?;
+        }
+
+        let (_parallel_dir, parallel_albums) = make_art_library()?;
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(4).build()?;
+        let done = std::sync::Mutex::new(0);
+        pool.install(|| {
+            extract_album_art_parallel(&parallel_albums, None, None, &AtomicBool::new(true), |_| {
+                *done.lock().unwrap() += 1;
+                Ok(())
+            })
+        })?;
+
+        assert_eq!(*done.lock().unwrap(), parallel_albums.len());
+        let serial_covers = extracted_covers(&serial_albums);
+        assert_eq!(extracted_covers(&parallel_albums), serial_covers);
+        assert_eq!(
+            serial_covers[0].as_deref(),
+            Some([PNG_SIGNATURE, &[0u8; 16]].concat().as_slice())
+        );
+
+        // A cleared flag leaves every album untouched
+        let (_cancelled_dir, cancelled_albums) = make_art_library()?;
+        extract_album_art_parallel(
+            &cancelled_albums,
+            None,
+            None,
+            &AtomicBool::new(false),
+            |_| Ok(()),
+        )?;
+        assert!(extracted_covers(&cancelled_albums)
+            .iter()
+            .all(Option::is_none));
+
+        Ok(())
+    }
+
+    #[test]
+    #[ignore = "timing comparison; run with --ignored --nocapture"]
+    fn bench_parallel_album_art() -> Result<()> {
+        let (_serial_dir, serial_albums) = make_art_library()?;
+        let started = std::time::Instant::now();
+        for album in &serial_albums {
+            process_single_album_art(album, None, None)?;
+        }
+        let serial_time = started.elapsed();
+
+        let (_parallel_dir, parallel_albums) = make_art_library()?;
+        let started = std::time::Instant::now();
+        extract_album_art_parallel(&parallel_albums, None, None, &AtomicBool::new(true), |_| {
+            Ok(())
+        })?;
+        let parallel_time = started.elapsed();
+
+        println!(
+            "album art for {} albums: serial {:?}, parallel {:?}",
+            parallel_albums.len(),
+            serial_time,
+            parallel_time
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_album_artist_from_directory_empty_dir() {
+        let temp_dir = TempDir::new().unwrap();
+        let empty_dir = temp_dir.path();
+
+        // Should return None for empty directory
+        let result = extract_album_artist_from_directory(empty_dir);
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_placeholder_delay_only_paces_sent_requests() -> Result<()> {
+        let pacer = RequestPacer::new(Duration::from_millis(50));
+        let start = Instant::now();
+        pacer.wait().await;
+        pacer.wait().await;
+        assert!(start.elapsed() >= Duration::from_millis(50));
+
+        // A search was just sent, so another would have to wait a full minute
+        let temp_dir = TempDir::new()?;
+        fs::write(temp_dir.path().join(".folder.jpg"), b"existing")?;
+        let pacer = RequestPacer::new(Duration::from_secs(60));
+        pacer.wait().await;
+
+        // The folder already has an icon, so nothing is fetched and nothing waits
+        let start = Instant::now();
+        fetch_and_save_placeholder(
+            &Network::default(),
+            &pacer,
+            temp_dir.path(),
+            "Artists",
+            "Music Artists",
+        )
+        .await?;
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(fs::read(temp_dir.path().join(".folder.jpg"))?, b"existing");
+
+        Ok(())
+    }
+}
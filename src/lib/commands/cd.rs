@@ -1,12 +1,30 @@
+use crate::cd::{self, RipOptions};
+use crate::cover_art::CoverSettings;
+use crate::musicbrainz::Network;
+use crate::utils::Naming;
+use crate::{checksums, cover_art, directory};
 use anyhow::{Context, Result};
-use mfutil::{cd, cover_art};
 use std::fs;
 use std::path::Path;
 use std::sync::mpsc;
 
-/// Import a CD to the music library with real CD reading
+/// Import a CD to the music library with real CD reading, each track ripped as `options` say
+/// With `options.verify` each track is read twice and mismatches are reported
+/// The album folder and track files are named by `naming`, and downloaded cover art is
+/// saved once the tracks are ripped, as `cover` says; the CD is looked up and its art
+/// downloaded over `network`
 #[cfg(feature = "cd-ripping")]
-pub async fn import_cd(device: &str, music_dir: &str, tx: mpsc::Sender<String>) -> Result<()> {
+pub async fn import_cd(
+    device: &str,
+    music_dir: &str,
+    naming: &Naming,
+    cover: &CoverSettings,
+    network: &Network,
+    options: RipOptions,
+    tx: mpsc::Sender<String>,
+) -> Result<()> {
+    directory::clear_interrupt();
+    let device = &cd::resolve_cd_device(device)?;
     tx.send(format!("Reading CD from device: {}", device))
         .context("Failed to send CD reading message")?;
 
@@ -17,37 +35,50 @@ pub async fn import_cd(device: &str, music_dir: &str, tx: mpsc::Sender<String>)
         .context("Failed to send CD info message")?;
 
     // Look up CD information from MusicBrainz
-    let cd_info = cd::lookup_cd_info(&cd_info, tx.clone()).await?;
+    let mut cd_info = cd::lookup_cd_info(network, &cd_info, tx.clone()).await?;
+    // Track files are named by `naming`, whatever the lookup called them
+    for track in &mut cd_info.tracks {
+        track.filename = format!("{:02} {}.flac", track.number, naming.sanitize(&track.title));
+    }
 
     // Fetch cover art if we have a release ID
     let mut cover_art_data: Option<Vec<u8>> = None;
     if let Some(release_id) = &cd_info.release_id {
         // Try MusicBrainz first
-        if let Ok(Some(cover_art)) = cover_art::fetch_musicbrainz_cover_art(release_id, &tx).await {
-            cover_art_data = Some(cover_art);
-        } else {
-            // Fallback to AudioDB
+        if let Ok(Some(cover_art)) =
+            cover_art::fetch_musicbrainz_cover_art(network, release_id, &tx).await
+        {
+            cover_art_data = Some(cover_art)
+                .filter(|art| cover_art::accept_cover_art(art, "MusicBrainz", cover.min_size, &tx));
+        }
+        // Fallback to AudioDB
+        if cover_art_data.is_none() {
             if let Ok(Some(cover_art)) =
-                cover_art::fetch_audiodb_cover_art(&cd_info.artist, &cd_info.title, &tx).await
+                cover_art::fetch_audiodb_cover_art(network, &cd_info.artist, &cd_info.title, &tx)
+                    .await
             {
-                cover_art_data = Some(cover_art);
+                cover_art_data = Some(cover_art)
+                    .filter(|art| cover_art::accept_cover_art(art, "AudioDB", cover.min_size, &tx));
             }
         }
     }
 
     if cover_art_data.is_some() {
-        tx.send("Cover art fetched successfully - will be embedded in FLAC files".to_string())
+        tx.send("Cover art fetched successfully - will be saved after ripping".to_string())
             .context("Failed to send cover art success message")?;
     } else {
-        tx.send(
-            "No cover art found - FLAC files will be created without embedded artwork".to_string(),
-        )
-        .context("Failed to send no cover art message")?;
+        tx.send("No cover art found - the album will be ripped without artwork".to_string())
+            .context("Failed to send no cover art message")?;
     }
 
-    // Create directory structure
-    let artist_dir = Path::new(music_dir).join("Artists").join(&cd_info.artist);
-    let album_dir = artist_dir.join(&cd_info.title);
+    // Create directory structure, laid out like the rest of the library; nothing is ripped yet
+    // to read a year from
+    let album_dir = naming.layout(Path::new(music_dir)).album_dir(
+        &Path::new(music_dir).join("Artists"),
+        &naming.sanitize(&cd_info.artist),
+        &naming.sanitize(&cd_info.title),
+        [] as [&Path; 0],
+    );
     fs::create_dir_all(&album_dir)
         .with_context(|| format!("Failed to create album directory: {:?}", album_dir))?;
 
@@ -59,27 +90,40 @@ pub async fn import_cd(device: &str, music_dir: &str, tx: mpsc::Sender<String>)
     tx.send(format!("TOTAL_FILES:{}", total_tracks))
         .context("Failed to send total tracks count")?;
 
+    let mut mismatched_tracks = Vec::new();
+
     for (i, track) in cd_info.tracks.iter().enumerate() {
+        // Stop between tracks, never part way through one
+        if directory::interrupted() {
+            tx.send(format!(
+                "Interrupted: stopped after {} of {} tracks",
+                i, total_tracks
+            ))
+            .context("Failed to send interrupt message")?;
+            break;
+        }
         // Add timeout for individual tracks (5 minutes per track should be more than enough)
         match tokio::time::timeout(
             std::time::Duration::from_secs(300),
-            cd::import_cd_track(
-                device,
-                &cd_info,
-                track,
-                &album_dir,
-                tx.clone(),
-                cover_art_data.as_ref(),
-            ),
+            cd::import_cd_track(device, &cd_info, track, &album_dir, tx.clone(), options),
         )
         .await
         {
-            Ok(Ok(())) => {
+            Ok(Ok(rip)) => {
+                let verification = match rip.verified {
+                    Some(true) => " [verified]",
+                    Some(false) => {
+                        mismatched_tracks.push(track.number);
+                        " [verify mismatch]"
+                    }
+                    None => "",
+                };
                 tx.send(format!(
-                    "COMPLETED: Imported track {}/{}: {}",
+                    "COMPLETED: Imported track {}/{}: {}{}",
                     i + 1,
                     total_tracks,
-                    track.title
+                    track.title,
+                    verification
                 ))
                 .context("Failed to send track completion message")?;
             }
@@ -102,6 +146,42 @@ pub async fn import_cd(device: &str, music_dir: &str, tx: mpsc::Sender<String>)
         }
     }
 
+    if options.verify {
+        if mismatched_tracks.is_empty() {
+            tx.send("Verification passed: every ripped track matched a second read".to_string())
+                .context("Failed to send verification summary")?;
+        } else {
+            let numbers: Vec<String> = mismatched_tracks.iter().map(|n| n.to_string()).collect();
+            tx.send(format!(
+                "ERROR: Verification failed for tracks: {}",
+                numbers.join(", ")
+            ))
+            .context("Failed to send verification summary")?;
+        }
+    }
+
+    // Embedded art changes the files, so it goes in before they are checksummed
+    if let Some(data) = &cover_art_data {
+        match cover_art::store_cover_art(&album_dir, data, cover.format, cover.mode) {
+            Ok(stored) => tx
+                .send(format!("Saved cover art to: {}", stored))
+                .context("Failed to send cover art message")?,
+            Err(e) => tx
+                .send(format!("ERROR: Failed to save cover art: {:#}", e))
+                .context("Failed to send cover art error message")?,
+        }
+    }
+
+    match checksums::write_manifest(&album_dir, options.checksums) {
+        Ok(Some(manifest)) => tx
+            .send(format!("Wrote checksums to {}", manifest.display()))
+            .context("Failed to send checksum message")?,
+        Ok(None) => {}
+        Err(e) => tx
+            .send(format!("ERROR: Failed to write checksums: {:#}", e))
+            .context("Failed to send checksum error message")?,
+    }
+
     tx.send(format!(
         "Successfully imported CD: {} - {}",
         cd_info.artist, cd_info.title
@@ -112,7 +192,15 @@ pub async fn import_cd(device: &str, music_dir: &str, tx: mpsc::Sender<String>)
 }
 
 #[cfg(not(feature = "cd-ripping"))]
-pub async fn import_cd(_device: &str, _music_dir: &str, tx: mpsc::Sender<String>) -> Result<()> {
+pub async fn import_cd(
+    _device: &str,
+    _music_dir: &str,
+    _naming: &Naming,
+    _cover: &CoverSettings,
+    _network: &Network,
+    _options: RipOptions,
+    tx: mpsc::Sender<String>,
+) -> Result<()> {
     tx.send("CD ripping feature is not enabled. Cannot import CD.".to_string())
         .context("Failed to send message about disabled CD ripping feature")?;
     Err(anyhow::anyhow!("CD ripping feature is not enabled. Please enable the 'cd-ripping' feature in Cargo.toml to use this command."))
@@ -121,8 +209,7 @@ pub async fn import_cd(_device: &str, _music_dir: &str, tx: mpsc::Sender<String>
 #[cfg(test)]
 mod tests {
     use super::*;
-    use mfutil;
-    use mfutil::cd::{CdInfo, CdTrack};
+    use crate::cd::{CdInfo, CdTrack};
     use std::fs;
     use std::path::Path;
     use std::sync::mpsc;
@@ -152,6 +239,7 @@ mod tests {
             ],
             total_duration: 4,
             release_id: None,
+            disc_number: None,
         })
     }
 
@@ -218,25 +306,25 @@ mod tests {
     #[test]
     fn test_sanitize_filename_basic() {
         assert_eq!(
-            mfutil::utils::sanitize_filename("normal_name"),
+            crate::utils::sanitize_filename("normal_name"),
             "normal_name"
         );
         assert_eq!(
-            mfutil::utils::sanitize_filename("file with spaces"),
+            crate::utils::sanitize_filename("file with spaces"),
             "file with spaces"
         );
         assert_eq!(
-            mfutil::utils::sanitize_filename("file/with\\bad:chars*"),
+            crate::utils::sanitize_filename("file/with\\bad:chars*"),
             "file_with_bad_chars_"
         );
     }
 
     #[test]
     fn test_sanitize_filename_edge_cases() {
-        assert_eq!(mfutil::utils::sanitize_filename(""), "");
-        assert_eq!(mfutil::utils::sanitize_filename("   "), "");
+        assert_eq!(crate::utils::sanitize_filename(""), "");
+        assert_eq!(crate::utils::sanitize_filename("   "), "");
         assert_eq!(
-            mfutil::utils::sanitize_filename("file\x00with\x01control\x02chars"),
+            crate::utils::sanitize_filename("file\x00with\x01control\x02chars"),
             "file_with_control_chars"
         );
     }
@@ -249,11 +337,14 @@ mod tests {
 
         // Test that the functions can be called (even if they return None for test data)
         let rt = tokio::runtime::Runtime::new().unwrap();
+        let network = Network::default();
         let result1 = rt.block_on(cover_art::fetch_musicbrainz_cover_art(
+            &network,
             "test_release_id",
             &tx,
         ));
         let result2 = rt.block_on(cover_art::fetch_audiodb_cover_art(
+            &network,
             "Test Artist",
             "Test Album",
             &tx,
@@ -0,0 +1,170 @@
+use crate::metadata::{self, ArtistAlbum};
+use crate::utils::{self, Naming};
+use anyhow::Result;
+use serde::Serialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// A file whose tags put it in a different album folder from the one it is in
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Mismatch {
+    /// The file
+    pub path: PathBuf,
+    /// The album folder it is in
+    pub current: PathBuf,
+    /// The album folder its tags call for
+    pub expected: PathBuf,
+}
+
+/// Summary of a check run
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CheckSummary {
+    /// Files looked at, tagged or not
+    pub checked: usize,
+    /// Files without readable artist/album tags, which have nothing to compare
+    pub untagged: usize,
+    /// Files that are filed somewhere their tags disagree with
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl fmt::Display for CheckSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} files checked, {} misfiled ({} untagged)",
+            self.checked,
+            self.mismatches.len(),
+            self.untagged
+        )
+    }
+}
+
+/// Compare each track's tag-derived artist/album with the album folder it is in (laid out by
+/// the library's [`utils::GroupBy`] scheme), listing the files that are misfiled without
+/// moving anything
+/// Folder names are derived as organize and reorganize derive them, so `ascii_punctuation`
+/// and `naming` should match what they are run with. Tracks in disc folders belong to the
+/// album above; files without artist and album tags are counted but not compared
+pub fn check_tag_folder_mismatches(
+    music_dir: &str,
+    ascii_punctuation: bool,
+    naming: &Naming,
+) -> Result<CheckSummary> {
+    let artists_path = Path::new(music_dir).join("Artists");
+    if !artists_path.is_dir() {
+        return Err(anyhow::anyhow!(
+            "Artists directory '{}' does not exist. Run organize first to create the directory structure.",
+            artists_path.display()
+        ));
+    }
+
+    let group_by = naming.layout(Path::new(music_dir));
+    let mut summary = CheckSummary::default();
+    for album in utils::iter_albums(music_dir) {
+        for track in utils::iter_album_tracks(&album.path) {
+            summary.checked += 1;
+            let Some((artist, album_name)) = metadata::read_artist_album_tags(&track) else {
+                summary.untagged += 1;
+                continue;
+            };
+            let names = metadata::file_under_composer(
+                &track,
+                ArtistAlbum {
+                    album_artist: artist.clone(),
+                    track_artist: artist,
+                    album: album_name,
+                },
+                naming,
+            );
+            let artist = naming.sanitize(&metadata::normalize_folder_text(
+                &names.album_artist,
+                ascii_punctuation,
+                metadata::UNKNOWN_ARTIST,
+            ));
+            let album_name = naming.sanitize(&metadata::normalize_folder_text(
+                &names.album,
+                ascii_punctuation,
+                metadata::UNKNOWN_ALBUM,
+            ));
+
+            let expected = group_by.album_dir(&artists_path, &artist, &album_name, [&track]);
+            if expected != album.path {
+                info!(
+                    "Misfiled: {} is in {} but its tags say {}",
+                    track.display(),
+                    album.path.display(),
+                    expected.display()
+                );
+                summary.mismatches.push(Mismatch {
+                    path: track,
+                    current: album.path.clone(),
+                    expected,
+                });
+            }
+        }
+    }
+
+    summary.mismatches.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::write_tagged_wav;
+    use lofty::tag::ItemKey;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_check_lists_misfiled_tracks() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let artists = temp_dir.path().join("Artists");
+        let album = artists.join("Artist").join("Album");
+        fs::create_dir_all(album.join("CD2"))?;
+        write_tagged_wav(
+            &album.join("01.wav"),
+            &[
+                (ItemKey::AlbumArtist, "Artist"),
+                (ItemKey::AlbumTitle, "Album"),
+            ],
+        )?;
+        write_tagged_wav(
+            &album.join("CD2").join("02.wav"),
+            &[
+                (ItemKey::AlbumArtist, "Artist"),
+                (ItemKey::AlbumTitle, "Album"),
+            ],
+        )?;
+        // Filed under the wrong album, and a file with no tags at all
+        write_tagged_wav(
+            &album.join("03.wav"),
+            &[
+                (ItemKey::AlbumArtist, "Artist"),
+                (ItemKey::AlbumTitle, "Other Album"),
+            ],
+        )?;
+        fs::write(album.join("04.mp3"), b"not really audio")?;
+
+        let summary = check_tag_folder_mismatches(
+            temp_dir.path().to_str().unwrap(),
+            false,
+            &Naming::default(),
+        )?;
+        assert_eq!(
+            summary.mismatches,
+            [Mismatch {
+                path: album.join("03.wav"),
+                current: album.clone(),
+                expected: artists.join("Artist").join("Other Album"),
+            }]
+        );
+        assert_eq!(summary.checked, 4);
+        assert_eq!(summary.untagged, 1);
+        // Nothing was moved
+        assert!(album.join("03.wav").exists());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,351 @@
+use crate::directory::FileFailure;
+use crate::{audio, cover_art, gapless, utils};
+use anyhow::{Context, Result};
+use lofty::file::TaggedFileExt;
+use lofty::picture::{Picture, PictureType};
+use lofty::tag::Tag;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tracing::{info, warn};
+
+/// Cover file names looked for in an album folder, in order of preference
+const COVER_FILE_NAMES: &[&str] = &[
+    "cover.jpg",
+    "cover.jpeg",
+    "cover.png",
+    "folder.jpg",
+    "folder.png",
+    "front.jpg",
+    "front.png",
+    ".folder.jpg",
+];
+
+/// What to do with a file that already carries embedded art
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ArtClobber {
+    /// Leave the file's art alone
+    #[default]
+    Skip,
+    /// Replace its front cover with the new art
+    Replace,
+    /// Replace it only if the new art has more pixels than the largest picture it has
+    KeepLarger,
+}
+
+impl FromStr for ArtClobber {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "skip" => Ok(ArtClobber::Skip),
+            "replace" => Ok(ArtClobber::Replace),
+            "keep-larger" => Ok(ArtClobber::KeepLarger),
+            other => Err(format!(
+                "unknown art clobber policy '{}' (expected skip, replace or keep-larger)",
+                other
+            )),
+        }
+    }
+}
+
+/// Result of embedding an album's cover file into its tracks
+#[derive(Debug, Clone, Default)]
+pub struct EmbedArtSummary {
+    /// Whether this summary describes a dry run (no files were written)
+    pub dry_run: bool,
+    /// The cover file that was embedded, or `None` if the album has none
+    pub cover: Option<PathBuf>,
+    /// Files that received (or would receive, in a dry run) the cover
+    pub embedded: usize,
+    /// Files left alone because of the art they already carry
+    pub skipped: usize,
+    /// Files that could not be read or written
+    pub failures: Vec<FileFailure>,
+}
+
+impl std::fmt::Display for EmbedArtSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Some(cover) = &self.cover else {
+            return write!(f, "no cover file found");
+        };
+        let cover_name = cover.file_name().unwrap_or_default().to_string_lossy();
+        let action = if self.dry_run {
+            "Would embed"
+        } else {
+            "Embedded"
+        };
+        write!(
+            f,
+            "{} {} into {} files ({} already had art, {} failed)",
+            action,
+            cover_name,
+            self.embedded,
+            self.skipped,
+            self.failures.len()
+        )
+    }
+}
+
+/// Find the cover image sidecar in an album folder
+pub fn find_cover_file(album_path: &Path) -> Option<PathBuf> {
+    COVER_FILE_NAMES
+        .iter()
+        .map(|name| album_path.join(name))
+        .find(|path| path.is_file())
+}
+
+/// Embed an album's cover file as the front-cover picture of each of its audio files
+/// Files that already carry embedded art are handled as `clobber` says
+/// Disc folders (`CD1`, `Disc 2`, ...) are included; the cover is taken from the album folder
+pub fn embed_album_cover_art(
+    album_path: &Path,
+    dry_run: bool,
+    clobber: ArtClobber,
+) -> Result<EmbedArtSummary> {
+    let mut summary = EmbedArtSummary {
+        dry_run,
+        ..EmbedArtSummary::default()
+    };
+
+    let Some(cover_path) = find_cover_file(album_path) else {
+        return Ok(summary);
+    };
+    let cover_data = fs::read(&cover_path)
+        .with_context(|| format!("Failed to read cover file: {}", cover_path.display()))?;
+    let picture = front_cover_picture(&cover_data)
+        .with_context(|| format!("Unsupported cover image: {}", cover_path.display()))?;
+    summary.cover = Some(cover_path);
+
+    embed_picture_in_album(album_path, &picture, clobber, &mut summary);
+    Ok(summary)
+}
+
+/// Embed cover image data, such as freshly downloaded art, into each of an album's audio files
+/// Any existing front cover is replaced, as writing a new `cover.<ext>` would replace that file
+pub fn embed_cover_data(album_path: &Path, cover_data: &[u8]) -> Result<EmbedArtSummary> {
+    let picture = front_cover_picture(cover_data).context("Unsupported cover image")?;
+    let mut summary = EmbedArtSummary::default();
+    embed_picture_in_album(album_path, &picture, ArtClobber::Replace, &mut summary);
+    Ok(summary)
+}
+
+fn front_cover_picture(cover_data: &[u8]) -> Result<Picture> {
+    let mut picture = Picture::from_reader(&mut &cover_data[..])?;
+    picture.set_pic_type(PictureType::CoverFront);
+    Ok(picture)
+}
+
+/// Embed `picture` into every audio file of an album, including its disc folders
+fn embed_picture_in_album(
+    album_path: &Path,
+    picture: &Picture,
+    clobber: ArtClobber,
+    summary: &mut EmbedArtSummary,
+) {
+    let mut audio_files: Vec<PathBuf> = utils::album_content_dirs(album_path)
+        .iter()
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()))
+        .filter(|path| path.is_file() && audio::is_audio_file(path))
+        .collect();
+    audio_files.sort();
+
+    for file_path in audio_files {
+        match embed_picture_in_file(&file_path, picture, summary.dry_run, clobber) {
+            Ok(true) => summary.embedded += 1,
+            Ok(false) => summary.skipped += 1,
+            Err(e) => {
+                warn!(
+                    "Failed to embed cover art in {}: {}",
+                    file_path.display(),
+                    e
+                );
+                summary.failures.push(FileFailure::new(&file_path, e));
+            }
+        }
+    }
+}
+
+/// Embed `picture` into a single file, returning `false` if it was skipped for existing art
+fn embed_picture_in_file(
+    file_path: &Path,
+    picture: &Picture,
+    dry_run: bool,
+    clobber: ArtClobber,
+) -> Result<bool> {
+    let mut tagged_file = lofty::read_from_path(file_path)
+        .with_context(|| format!("Failed to read tags from {}", file_path.display()))?;
+
+    let existing: Vec<&Picture> = tagged_file
+        .tags()
+        .iter()
+        .flat_map(|tag| tag.pictures())
+        .collect();
+    let replace = existing.is_empty()
+        || match clobber {
+            ArtClobber::Skip => false,
+            ArtClobber::Replace => true,
+            // Pictures MagickWand can't decode count as smaller than anything it can
+            ArtClobber::KeepLarger => {
+                let largest = existing
+                    .iter()
+                    .map(|existing| cover_art::image_area(existing.data()).unwrap_or(0))
+                    .max()
+                    .unwrap_or(0);
+                cover_art::image_area(picture.data()).is_some_and(|area| area > largest)
+            }
+        };
+    if !replace {
+        return Ok(false);
+    }
+
+    if dry_run {
+        info!("Would embed cover art in {}", file_path.display());
+        return Ok(true);
+    }
+
+    if tagged_file.primary_tag().is_none() {
+        let tag_type = tagged_file.primary_tag_type();
+        tagged_file.insert_tag(Tag::new(tag_type));
+    }
+    let tag = tagged_file
+        .primary_tag_mut()
+        .context("File format does not support tags")?;
+    tag.remove_picture_type(PictureType::CoverFront);
+    tag.push_picture(picture.clone());
+
+    gapless::save_tags(&tagged_file, file_path)?;
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::write_silent_wav;
+    use magick_rust::PixelWand;
+    use tempfile::TempDir;
+
+    // Eight-byte PNG signature followed by padding, enough for lofty to recognise the format
+    const PNG_BYTES: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\0\0\0\0\0";
+
+    /// A square PNG `size` pixels wide
+    fn png_image(size: usize) -> Result<Vec<u8>> {
+        let wand = cover_art::new_wand();
+        let mut background = PixelWand::new();
+        background.set_color("red")?;
+        wand.new_image(size, size, &background)?;
+        Ok(wand.write_image_blob("png")?)
+    }
+
+    /// An album whose one track already has `embedded` as its front cover, with `cover` as
+    /// its cover file
+    fn album_with_embedded_art(album: &Path, embedded: &[u8], cover: &[u8]) -> Result<PathBuf> {
+        let track = album.join("01.wav");
+        write_silent_wav(&track)?;
+        fs::write(album.join("cover.png"), embedded)?;
+        embed_album_cover_art(album, false, ArtClobber::Replace)?;
+        fs::write(album.join("cover.png"), cover)?;
+        Ok(track)
+    }
+
+    fn embedded_art(track: &Path) -> Result<Vec<u8>> {
+        let tagged_file = lofty::read_from_path(track)?;
+        let tag = tagged_file.primary_tag().expect("tag was written");
+        assert_eq!(tag.pictures().len(), 1);
+        Ok(tag.pictures()[0].data().to_vec())
+    }
+
+    #[test]
+    fn test_find_cover_file_prefers_cover_over_folder() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        assert_eq!(find_cover_file(temp_dir.path()), None);
+
+        fs::write(temp_dir.path().join("folder.jpg"), PNG_BYTES)?;
+        fs::write(temp_dir.path().join("cover.png"), PNG_BYTES)?;
+        assert_eq!(
+            find_cover_file(temp_dir.path()),
+            Some(temp_dir.path().join("cover.png"))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_embed_album_cover_art_skips_existing_unless_forced() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let album = temp_dir.path();
+        let track = album.join("01.wav");
+        write_silent_wav(&track)?;
+        fs::write(album.join("cover.png"), PNG_BYTES)?;
+
+        let summary = embed_album_cover_art(album, true, ArtClobber::Skip)?;
+        assert_eq!(summary.embedded, 1);
+        let tagged_file = lofty::read_from_path(&track)?;
+        assert!(tagged_file.tags().iter().all(|t| t.pictures().is_empty()));
+
+        let summary = embed_album_cover_art(album, false, ArtClobber::Skip)?;
+        assert_eq!(summary.embedded, 1);
+        assert!(summary.failures.is_empty());
+        let tagged_file = lofty::read_from_path(&track)?;
+        let tag = tagged_file.primary_tag().expect("tag was written");
+        assert_eq!(tag.pictures().len(), 1);
+        assert_eq!(tag.pictures()[0].pic_type(), PictureType::CoverFront);
+
+        let summary = embed_album_cover_art(album, false, ArtClobber::Skip)?;
+        assert_eq!((summary.embedded, summary.skipped), (0, 1));
+
+        let summary = embed_album_cover_art(album, false, ArtClobber::Replace)?;
+        assert_eq!((summary.embedded, summary.skipped), (1, 0));
+        let tagged_file = lofty::read_from_path(&track)?;
+        assert_eq!(tagged_file.primary_tag().unwrap().pictures().len(), 1);
+
+        Ok(())
+    }
+    #[test]
+    fn test_art_clobber_skip_leaves_existing_art() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let (small, large) = (png_image(4)?, png_image(16)?);
+        let track = album_with_embedded_art(temp_dir.path(), &small, &large)?;
+
+        let summary = embed_album_cover_art(temp_dir.path(), false, ArtClobber::Skip)?;
+        assert_eq!((summary.embedded, summary.skipped), (0, 1));
+        assert_eq!(embedded_art(&track)?, small);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_art_clobber_replace_swaps_in_smaller_art() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let (small, large) = (png_image(4)?, png_image(16)?);
+        let track = album_with_embedded_art(temp_dir.path(), &large, &small)?;
+
+        let summary = embed_album_cover_art(temp_dir.path(), false, ArtClobber::Replace)?;
+        assert_eq!((summary.embedded, summary.skipped), (1, 0));
+        assert_eq!(embedded_art(&track)?, small);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_art_clobber_keep_larger_compares_dimensions() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let (small, medium, large) = (png_image(4)?, png_image(8)?, png_image(16)?);
+        let track = album_with_embedded_art(temp_dir.path(), &medium, &small)?;
+
+        // Smaller art is kept out...
+        let summary = embed_album_cover_art(temp_dir.path(), false, ArtClobber::KeepLarger)?;
+        assert_eq!((summary.embedded, summary.skipped), (0, 1));
+        assert_eq!(embedded_art(&track)?, medium);
+
+        // ...and larger art goes in
+        fs::write(temp_dir.path().join("cover.png"), &large)?;
+        let summary = embed_album_cover_art(temp_dir.path(), false, ArtClobber::KeepLarger)?;
+        assert_eq!((summary.embedded, summary.skipped), (1, 0));
+        assert_eq!(embedded_art(&track)?, large);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,105 @@
+use crate::directory::{self, FileFailure};
+use crate::{metadata, utils};
+use anyhow::Result;
+use rayon::prelude::*;
+use std::fmt;
+use std::path::PathBuf;
+use tracing::info;
+
+/// Summary of a fix-encoding pass
+#[derive(Debug, Clone, Default)]
+pub struct FixEncodingSummary {
+    /// Files whose tags were repaired
+    pub fixed: Vec<PathBuf>,
+    /// Files whose tags could not be read or written
+    pub failures: Vec<FileFailure>,
+}
+
+impl fmt::Display for FixEncodingSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} files with mis-encoded tags repaired, {} failed",
+            self.fixed.len(),
+            self.failures.len()
+        )
+    }
+}
+
+/// Repair mojibake in the artist, album and title tags of every track under `music_dir`
+/// (see [`metadata::repair_mojibake`]), in parallel on the current rayon pool
+/// With `backup_tags` each file's tags are backed up before they are repaired
+pub fn fix_tag_encoding(music_dir: &str, backup_tags: bool) -> Result<FixEncodingSummary> {
+    let tracks: Vec<PathBuf> = utils::iter_tracks(music_dir).collect();
+    let results: Vec<(PathBuf, Result<bool>)> = tracks
+        .into_par_iter()
+        .map(|track| {
+            let result = metadata::fix_tag_encoding(&track, backup_tags);
+            (track, result)
+        })
+        .collect();
+
+    let mut summary = FixEncodingSummary::default();
+    for (track, result) in results {
+        match result {
+            Ok(true) => {
+                info!("Repaired tag encoding of {}", track.display());
+                summary.fixed.push(track);
+            }
+            Ok(false) => {}
+            Err(e) => summary
+                .failures
+                .push(FileFailure::new(&track, format!("{:#}", e))),
+        }
+    }
+
+    directory::report_failures(&summary.failures, "repaired");
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::write_silent_wav;
+    use lofty::config::WriteOptions;
+    use lofty::file::{AudioFile, TaggedFileExt};
+    use lofty::tag::{Accessor, Tag, TagType};
+    use std::fs;
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    fn write_artist(path: &Path, artist: &str) -> Result<()> {
+        write_silent_wav(path)?;
+        let mut tagged_file = lofty::read_from_path(path)?;
+        let mut tag = Tag::new(TagType::Id3v2);
+        tag.set_artist(artist.to_string());
+        tagged_file.insert_tag(tag);
+        tagged_file.save_to_path(path, WriteOptions::default())?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_fix_tag_encoding_repairs_only_mis_encoded_files() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let album_dir = temp_dir
+            .path()
+            .join("Artists")
+            .join("Motörhead")
+            .join("Ace of Spades");
+        fs::create_dir_all(&album_dir)?;
+        let broken = album_dir.join("01.wav");
+        let intact = album_dir.join("02.wav");
+        write_artist(&broken, "MotÃ¶rhead")?;
+        write_artist(&intact, "Motörhead")?;
+
+        let summary = fix_tag_encoding(temp_dir.path().to_str().unwrap(), false)?;
+        assert_eq!(summary.fixed, std::slice::from_ref(&broken));
+        assert!(summary.failures.is_empty());
+        let artist = lofty::read_from_path(&broken)?
+            .primary_tag()
+            .and_then(|tag| tag.artist().map(|a| a.to_string()));
+        assert_eq!(artist.as_deref(), Some("Motörhead"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,2385 @@
+use crate::audio::QualityFloor;
+use crate::checksums::ChecksumAlgorithm;
+use crate::cover_art::CoverSettings;
+use crate::cue::{self, CueSheet};
+use crate::directory::{self, FileFailure, OrganizeSummary};
+use crate::musicbrainz::Network;
+use crate::provenance::{self, Source};
+use crate::utils::Naming;
+use crate::{audio, checksums, cover_art, gapless, metadata, musicbrainz, tag_backup, utils};
+use anyhow::{Context, Result};
+use lofty::{self, tag::ItemKey};
+use musicbrainz_rs::{entity::release::Release, prelude::*};
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde_json;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use tracing::{info, warn};
+use urlencoding;
+use walkdir::WalkDir;
+/// Type alias for file grouping by artist, album, and release ID
+/// Each file carries its MusicBrainz artist credit, if a release matched
+type FileGroupsByMetadata =
+    FxHashMap<(String, String, Option<String>), Vec<(PathBuf, Option<String>)>>;
+
+/// How [`import_and_organize_files`] imports; the defaults copy every tagged file, regrouped
+/// file by file, and leave the sources alone
+#[derive(Debug, Clone, Default)]
+pub struct ImportOptions {
+    /// Report what would be imported without copying anything
+    pub dry_run: bool,
+    pub quiet: bool,
+    /// Convert smart quotes and dashes in tags to ASCII
+    pub ascii_punctuation: bool,
+    /// Give imported copies the source files' access and modification times
+    pub preserve_mtime: bool,
+    /// Delete sources whose copy is verified afterwards
+    pub prune: bool,
+    /// With `prune`, also remove the folders under the import paths the deletions left empty
+    pub prune_empty_dirs: bool,
+    /// Import files without usable artist/album tags under the Unknown placeholders instead of
+    /// listing them in the summary's `excluded`
+    pub import_unknown: bool,
+    /// Split a single-file album with a cue sheet into one tagged file per track; its source
+    /// is then never pruned
+    pub split_cue: bool,
+    /// Copy the album folders of import paths that are themselves libraries whole (see
+    /// [`whole_albums`])
+    pub preserve_structure: bool,
+    /// File whole albums by their folder names rather than their tags
+    pub path_only: bool,
+    /// Lossy files below this floor are excluded, even with `import_unknown`
+    pub quality: QualityFloor,
+    /// How artist and album folders and split track files are named
+    pub naming: Naming,
+    /// How cover art fetched by [`import_and_organize_files_with_musicbrainz`] is saved
+    pub cover: CoverSettings,
+    /// Tag each imported copy with its provenance (see [`provenance::write_provenance`])
+    pub provenance: bool,
+    /// Write a checksum manifest in each album folder imported into, once it is complete
+    pub checksums: ChecksumAlgorithm,
+    /// Back up the tags of each copy (see [`tag_backup`]) before
+    /// [`import_and_organize_files_with_musicbrainz`] rewrites them
+    pub backup_tags: bool,
+    /// Leading articles moved to the end of the sort tags
+    /// [`import_and_organize_files_with_musicbrainz`] derives, as `--sort-articles` gives them
+    pub sort_articles: Option<Vec<String>>,
+    /// How [`import_and_organize_files_with_musicbrainz`] reaches MusicBrainz and the cover
+    /// art services
+    pub network: Network,
+}
+
+/// Import files from one or more external directories into the music library
+/// This function copies files from the specified import paths, one after another, and
+/// organizes them under `dest_root/Artists`, which need not be the library that is later
+/// organized. Every path must be an existing directory; files reached through more than one
+/// of them are imported once, and a file bound for a destination another source's file has
+/// already taken is skipped
+/// A single-file album with a cue sheet (see [`cue::find_cue_sheet`]) takes its artist and
+/// album from the sheet and is copied along with it, or is split as `options` say
+/// Returns a summary of the files that were (or would be, in a dry run) imported
+pub fn import_and_organize_files(
+    import_paths: &[&str],
+    dest_root: &str,
+    options: &ImportOptions,
+) -> Result<OrganizeSummary> {
+    let &ImportOptions {
+        dry_run,
+        quiet,
+        ascii_punctuation,
+        preserve_mtime,
+        prune,
+        prune_empty_dirs,
+        import_unknown,
+        split_cue,
+        preserve_structure,
+        path_only,
+        quality,
+        ref naming,
+        cover: _,
+        provenance,
+        checksums,
+        backup_tags: _,
+        sort_articles: _,
+        network: _,
+    } = options;
+    directory::clear_interrupt();
+    let root_path = Path::new(dest_root);
+    let artists_path = root_path.join("Artists");
+    let import_paths = validate_import_paths(import_paths)?;
+
+    // Ensure Artists directory exists
+    if !artists_path.exists() {
+        if dry_run {
+            if !quiet {
+                info!("Would create Artists directory: {}", artists_path.display());
+            }
+        } else {
+            fs::create_dir_all(&artists_path).map_err(|e| {
+                anyhow::anyhow!(
+                    "Failed to create Artists directory '{}': {}",
+                    artists_path.display(),
+                    e
+                )
+            })?;
+        }
+    }
+
+    let group_by = naming.layout(root_path);
+    if !dry_run {
+        utils::record_group_by(root_path, group_by)?;
+    }
+
+    if !quiet {
+        for import_path in &import_paths {
+            info!("Scanning import directory: {}", import_path.display());
+        }
+    }
+
+    // Albums of library-shaped sources are copied folder by folder, not regrouped file by file
+    let mut seen_albums: FxHashSet<PathBuf> = FxHashSet::default();
+    let whole: Vec<WholeAlbum> = import_paths
+        .iter()
+        .filter(|_| preserve_structure)
+        .flat_map(|import_path| {
+            whole_albums(import_path, ascii_punctuation, path_only, quality, naming)
+        })
+        .filter(|album| {
+            seen_albums
+                .insert(fs::canonicalize(&album.source).unwrap_or_else(|_| album.source.clone()))
+        })
+        .collect();
+    let whole_tracks: FxHashSet<&Path> = whole
+        .iter()
+        .flat_map(|album| album.tracks.iter().map(PathBuf::as_path))
+        .collect();
+
+    let mut files_to_import = Vec::new();
+    let mut excluded = Vec::new();
+    let mut unknown_placeholders = 0;
+    let mut cue_albums: FxHashMap<PathBuf, (PathBuf, CueSheet)> = FxHashMap::default();
+
+    // Find all audio files in the import directories
+    for path in source_files(&import_paths) {
+        let path = path.as_path();
+        if whole_tracks.contains(path) {
+            continue;
+        }
+
+        // Only process audio files
+        if audio::is_audio_file(path) {
+            if let Some(reason) = quality.shortfall(path) {
+                if !quiet {
+                    info!("Excluding file with {}: {}", reason, path.display());
+                }
+                excluded.push(FileFailure::new(path, reason));
+                continue;
+            }
+
+            let mut cue_sheet = cue::find_cue_sheet(path);
+            let names =
+                source_artist_album(path, cue_sheet.as_mut().map(|(_, sheet)| sheet), naming);
+            if let Some(found) = cue_sheet {
+                if !quiet {
+                    info!(
+                        "Found cue sheet for {}: {}",
+                        path.display(),
+                        found.0.display()
+                    );
+                }
+                cue_albums.insert(path.to_path_buf(), found);
+            }
+
+            // Check if file has proper metadata before including it
+            let (artist, album, reason) = match names {
+                Ok(metadata::ArtistAlbum {
+                    album_artist: artist,
+                    album,
+                    ..
+                }) => {
+                    // Only include files with meaningful metadata
+                    if !artist.is_empty()
+                        && !album.is_empty()
+                        && !metadata::is_unknown_placeholder(&artist, &album)
+                    {
+                        files_to_import.push((path.to_path_buf(), artist, album));
+                        continue;
+                    }
+                    let reason = format!(
+                        "no proper metadata (Artist: '{}', Album: '{}')",
+                        artist, album
+                    );
+                    (artist, album, reason)
+                }
+                Err(e) => (
+                    String::new(),
+                    String::new(),
+                    format!("unreadable metadata ({})", e),
+                ),
+            };
+
+            if import_unknown {
+                if !quiet {
+                    info!(
+                        "Importing file with {} under the Unknown placeholders: {}",
+                        reason,
+                        path.display()
+                    );
+                }
+                let artist = if artist.is_empty() {
+                    metadata::UNKNOWN_ARTIST.to_string()
+                } else {
+                    artist
+                };
+                let album = if album.is_empty() {
+                    metadata::UNKNOWN_ALBUM.to_string()
+                } else {
+                    album
+                };
+                unknown_placeholders += 1;
+                files_to_import.push((path.to_path_buf(), artist, album));
+            } else {
+                if !quiet {
+                    info!("Excluding file with {}: {}", reason, path.display());
+                }
+                excluded.push(FileFailure::new(path, reason));
+            }
+        }
+    }
+    let files_excluded = excluded.len();
+
+    if files_to_import.is_empty() && whole.is_empty() {
+        if !quiet {
+            if files_excluded > 0 {
+                info!("No files with proper metadata found. {} files excluded due to insufficient metadata.", files_excluded);
+            } else {
+                info!("No audio files found in import directory. Nothing to import.");
+            }
+        }
+        return Ok(OrganizeSummary {
+            excluded,
+            ..OrganizeSummary::new(dry_run)
+        });
+    }
+
+    let import_count = files_to_import.len() + whole_tracks.len();
+    if !quiet {
+        info!(
+            "Found {} files with proper metadata to import ({} excluded)",
+            import_count, files_excluded
+        );
+        for album in &whole {
+            info!(
+                "Copying album folder whole: {} -> {} / {}",
+                album.source.display(),
+                album.artist,
+                album.album
+            );
+        }
+    }
+
+    // Group files by their correct artist/album based on metadata
+    let mut file_groups: FxHashMap<(String, String), Vec<PathBuf>> = FxHashMap::default();
+
+    for (file_path, artist, album) in files_to_import {
+        // Create clean names for directory creation
+        let (clean_artist, clean_album) = folder_names(&artist, &album, ascii_punctuation, naming);
+
+        file_groups
+            .entry((clean_artist.clone(), clean_album.clone()))
+            .or_default()
+            .push(file_path.clone());
+
+        if dry_run && !quiet {
+            info!(
+                "Would import: {} -> {} / {}",
+                file_path.display(),
+                clean_artist,
+                clean_album
+            );
+        }
+    }
+
+    if !quiet && dry_run {
+        info!(
+            "Found {} unique artist/album combinations for {} files",
+            file_groups.len(),
+            import_count
+        );
+    }
+
+    // Import files to their correct locations
+    let mut all_groups = file_groups.clone();
+    for album in &whole {
+        all_groups
+            .entry((album.artist.clone(), album.album.clone()))
+            .or_default()
+            .extend(album.tracks.iter().cloned());
+    }
+    let total_groups = all_groups.len();
+    let mut summary = OrganizeSummary {
+        excluded,
+        unknown_placeholders,
+        tree: directory::AlbumTree::from_groups(&all_groups),
+        ..OrganizeSummary::new(dry_run)
+    };
+    let mut imported_albums: FxHashSet<PathBuf> = FxHashSet::default();
+    // Destinations taken by this run, so a file that several sources share is imported once
+    let mut claimed: FxHashSet<PathBuf> = FxHashSet::default();
+    // Tracks split from cue albums; recorded after pruning, which must not touch their sources
+    let mut split_moves: Vec<(PathBuf, PathBuf)> = Vec::new();
+    // Set once an interrupt is seen; the file in hand is always finished first
+    let mut stopped = false;
+
+    for ((artist, album), files) in file_groups {
+        if directory::interrupted() {
+            stopped = true;
+            break;
+        }
+        let album_path = group_by.album_dir(&artists_path, &artist, &album, &files);
+
+        if !album_path.exists() {
+            summary.dirs_created += 1;
+        }
+
+        if dry_run {
+            if !quiet {
+                info!("Would create directory: {}", album_path.display());
+            }
+            for file in &files {
+                if let Some((_, sheet)) = cue_albums.get(file).filter(|_| split_cue) {
+                    let extension = cue::split_extension(file);
+                    for track in &sheet.tracks {
+                        let dest_path =
+                            album_path.join(cue::track_file_name(track, &extension, naming));
+                        if !quiet {
+                            info!(
+                                "  Would split track {}: {} -> {}",
+                                track.number,
+                                file.display(),
+                                dest_path.display()
+                            );
+                        }
+                        split_moves.push((file.clone(), dest_path));
+                    }
+                    continue;
+                }
+                if !quiet {
+                    info!(
+                        "  Would copy: {} -> {}",
+                        file.display(),
+                        album_path.display()
+                    );
+                }
+                match directory::destination_path(file, &album_path, naming) {
+                    Ok(dest_path) if claimed.insert(dest_path.clone()) => {
+                        summary.record_move(file, &dest_path)
+                    }
+                    Ok(_) => summary.files_skipped += 1,
+                    Err(e) => summary
+                        .failures
+                        .push(FileFailure::new(file, format!("{:#}", e))),
+                }
+            }
+        } else {
+            // Create directories if they don't exist
+            fs::create_dir_all(&album_path).map_err(|e| {
+                anyhow::anyhow!(
+                    "Failed to create album directory '{}': {}",
+                    album_path.display(),
+                    e
+                )
+            })?;
+
+            // Copy each file
+            for file_path in files {
+                if directory::interrupted() {
+                    stopped = true;
+                    break;
+                }
+                if let Some((_, sheet)) = cue_albums.get(&file_path).filter(|_| split_cue) {
+                    split_cue_album(
+                        &file_path,
+                        sheet,
+                        &album_path,
+                        &mut summary,
+                        &mut split_moves,
+                        options,
+                    );
+                    imported_albums.insert(album_path.clone());
+                    continue;
+                }
+                let dest_path = match directory::destination_path(&file_path, &album_path, naming) {
+                    Ok(dest_path) => dest_path,
+                    Err(e) => {
+                        summary
+                            .failures
+                            .push(FileFailure::new(&file_path, format!("{:#}", e)));
+                        continue;
+                    }
+                };
+
+                if !claimed.insert(dest_path.clone()) {
+                    if !quiet {
+                        info!(
+                            "Skipping {}: a file from another import path already goes to {}",
+                            file_path.display(),
+                            dest_path.display()
+                        );
+                    }
+                    summary.files_skipped += 1;
+                    continue;
+                }
+
+                // Only copy if the destination doesn't already exist
+                if dest_path.exists() {
+                    if !quiet {
+                        info!(
+                            "Warning: File already exists at destination, skipping: {} -> {}",
+                            file_path.display(),
+                            dest_path.display()
+                        );
+                    }
+                    summary.files_skipped += 1;
+                    continue;
+                }
+
+                // Copy the file
+                if let Err(e) = directory::copy_via_part_file(&file_path, &dest_path) {
+                    summary.failures.push(FileFailure::new(
+                        &file_path,
+                        format!("Failed to copy to '{}': {}", dest_path.display(), e),
+                    ));
+                    continue;
+                }
+                record_import_provenance(&dest_path, provenance);
+                if preserve_mtime {
+                    if let Err(e) = directory::copy_file_times(&file_path, &dest_path) {
+                        warn!("{:?}", e);
+                    }
+                }
+                // Keep the sheet with its unsplit album so players can still find the tracks
+                if let Some((cue_path, _)) = cue_albums.get(&file_path) {
+                    copy_cue_sheet(cue_path, &album_path, &mut summary.failures);
+                }
+
+                if !quiet {
+                    info!(
+                        "Imported: {} -> {}",
+                        file_path.display(),
+                        dest_path.display()
+                    );
+                }
+                summary.record_move(&file_path, &dest_path);
+                imported_albums.insert(album_path.clone());
+            }
+            if stopped {
+                break;
+            }
+        }
+    }
+
+    for album in &whole {
+        if stopped || directory::interrupted() {
+            stopped = true;
+            break;
+        }
+        let album_path =
+            group_by.album_dir(&artists_path, &album.artist, &album.album, &album.tracks);
+        if !album_path.exists() {
+            summary.dirs_created += 1;
+        }
+        let copied = copy_whole_album(album, &album_path, &mut claimed, &mut summary, options);
+        if copied && !dry_run {
+            imported_albums.insert(album_path);
+        }
+    }
+
+    if stopped && !quiet {
+        info!("Interrupted: stopped after the last complete file");
+    }
+
+    for album_path in &imported_albums {
+        write_checksum_manifest(album_path, checksums, &mut summary.failures, quiet);
+    }
+
+    if prune && dry_run {
+        if !quiet {
+            info!(
+                "Would remove {} source files once their copies are verified",
+                summary.moves.len()
+            );
+        }
+    } else if prune {
+        let pruned = prune_imported_sources(&mut summary, &import_paths, prune_empty_dirs, quiet);
+        if !quiet {
+            info!("Removed {} imported source files", pruned);
+        }
+    }
+    for (source, dest_path) in split_moves {
+        summary.record_move(&source, &dest_path);
+    }
+
+    directory::report_failures(&summary.failures, "imported");
+
+    if dry_run && !quiet {
+        info!("\nThis was a dry run. No files were actually imported.");
+        info!("Run without --dry-run to perform the actual import.");
+    } else if !quiet {
+        info!("\nFile import completed successfully!");
+        info!(
+            "   Imported {} files into {} artist/album combinations ({} files excluded)",
+            import_count, total_groups, files_excluded
+        );
+    }
+
+    Ok(summary)
+}
+
+/// Folder names for an artist and album from the tags: normalized, aliased and sanitized
+fn folder_names(
+    artist: &str,
+    album: &str,
+    ascii_punctuation: bool,
+    naming: &Naming,
+) -> (String, String) {
+    let artist =
+        metadata::normalize_folder_text(artist, ascii_punctuation, metadata::UNKNOWN_ARTIST);
+    let album = metadata::normalize_folder_text(album, ascii_punctuation, metadata::UNKNOWN_ALBUM);
+    let artist = naming.canonical_artist(&artist);
+    let album = naming.canonical_album(&album);
+    (naming.sanitize(&artist), naming.sanitize(&album))
+}
+
+/// An album folder of a library-shaped import source, copied whole rather than file by file
+#[derive(Debug)]
+struct WholeAlbum {
+    /// The album folder in the import source
+    source: PathBuf,
+    /// Artist and album folder names it is filed under
+    artist: String,
+    album: String,
+    /// Its audio files, disc folders included
+    tracks: Vec<PathBuf>,
+}
+
+/// The album folders of `import_path` that can be copied whole, when it is itself a library
+/// (an `Artists/<artist>/<album>` tree laid out by its own [`utils::GroupBy`] scheme)
+/// Each album is filed under the artist and album all its tracks' tags agree on, or with
+/// `path_only` under its folder names. Albums whose tracks lack tags or disagree, that have a
+/// cue sheet, or that hold files below `quality` are left to the file-by-file import
+fn whole_albums(
+    import_path: &Path,
+    ascii_punctuation: bool,
+    path_only: bool,
+    quality: QualityFloor,
+    naming: &Naming,
+) -> Vec<WholeAlbum> {
+    if !import_path.join("Artists").is_dir() {
+        return Vec::new();
+    }
+    let Some(import_dir) = import_path.to_str() else {
+        return Vec::new();
+    };
+
+    let mut albums = Vec::new();
+    for album in utils::iter_albums(import_dir) {
+        let tracks = utils::album_track_paths(&album.path);
+        if tracks.is_empty()
+            || tracks.iter().any(|track| {
+                quality.shortfall(track).is_some() || cue::find_cue_sheet(track).is_some()
+            })
+        {
+            continue;
+        }
+
+        let names = if path_only {
+            Some((album.artist_folder.clone(), album.album_folder.clone()))
+        } else {
+            let names: Option<FxHashSet<(String, String)>> = tracks
+                .iter()
+                .map(|track| {
+                    let names = source_artist_album(track, None, naming).ok()?;
+                    let usable = !names.album_artist.is_empty()
+                        && !names.album.is_empty()
+                        && !metadata::is_unknown_placeholder(&names.album_artist, &names.album);
+                    usable.then_some((names.album_artist, names.album))
+                })
+                .collect();
+            names
+                .filter(|names| names.len() == 1)
+                .and_then(|names| names.into_iter().next())
+        };
+        let Some((artist, album_name)) = names else {
+            continue;
+        };
+
+        let (artist, album_name) = folder_names(&artist, &album_name, ascii_punctuation, naming);
+        albums.push(WholeAlbum {
+            source: album.path,
+            artist,
+            album: album_name,
+            tracks,
+        });
+    }
+    albums
+}
+
+/// Copy an album folder into `album_path` as it is, disc folders, cover art, logs and other
+/// sidecars included; files whose destination is taken are skipped
+/// Only the tracks are recorded in `summary`, so pruning leaves the sidecars in the source
+/// Returns whether any track was (or would be) imported
+fn copy_whole_album(
+    album: &WholeAlbum,
+    album_path: &Path,
+    claimed: &mut FxHashSet<PathBuf>,
+    summary: &mut OrganizeSummary,
+    options: &ImportOptions,
+) -> bool {
+    let &ImportOptions {
+        dry_run,
+        quiet,
+        preserve_mtime,
+        ref naming,
+        provenance,
+        ..
+    } = options;
+    let mut imported = false;
+    for entry in WalkDir::new(&album.source)
+        .into_iter()
+        .filter_map(|e| e.ok())
+    {
+        if directory::interrupted() {
+            break;
+        }
+        let file_path = entry.path();
+        let Ok(relative) = file_path.strip_prefix(&album.source) else {
+            continue;
+        };
+        if !file_path.is_file() {
+            continue;
+        }
+        let dest_dir = album_path.join(relative.parent().unwrap_or(Path::new("")));
+        let is_track = audio::is_audio_file(file_path);
+        let dest_path = if is_track {
+            match directory::destination_path(file_path, &dest_dir, naming) {
+                Ok(dest_path) => dest_path,
+                Err(e) => {
+                    summary
+                        .failures
+                        .push(FileFailure::new(file_path, format!("{:#}", e)));
+                    continue;
+                }
+            }
+        } else {
+            album_path.join(relative)
+        };
+
+        if dest_path.exists() || !claimed.insert(dest_path.clone()) {
+            if is_track {
+                summary.files_skipped += 1;
+            }
+            continue;
+        }
+        if dry_run {
+            if !quiet {
+                info!(
+                    "  Would copy: {} -> {}",
+                    file_path.display(),
+                    dest_path.display()
+                );
+            }
+            if is_track {
+                summary.record_move(file_path, &dest_path);
+                imported = true;
+            }
+            continue;
+        }
+
+        if let Err(e) = fs::create_dir_all(&dest_dir)
+            .and_then(|()| directory::copy_via_part_file(file_path, &dest_path))
+        {
+            summary.failures.push(FileFailure::new(
+                file_path,
+                format!("Failed to copy to '{}': {}", dest_path.display(), e),
+            ));
+            continue;
+        }
+        if preserve_mtime {
+            if let Err(e) = directory::copy_file_times(file_path, &dest_path) {
+                warn!("{:?}", e);
+            }
+        }
+        if is_track {
+            record_import_provenance(&dest_path, provenance);
+            if !quiet {
+                info!(
+                    "Imported: {} -> {}",
+                    file_path.display(),
+                    dest_path.display()
+                );
+            }
+            summary.record_move(file_path, &dest_path);
+            imported = true;
+        }
+    }
+    imported
+}
+
+/// Artist and album of an import source, preferring its cue sheet's PERFORMER and TITLE to
+/// the tags; values the sheet lacks are filled in from the tags, so split tracks get them too
+fn source_artist_album(
+    path: &Path,
+    cue_sheet: Option<&mut CueSheet>,
+    naming: &Naming,
+) -> Result<metadata::ArtistAlbum> {
+    let Some(sheet) = cue_sheet else {
+        return metadata::extract_artist_album_from_file(path)
+            .map(|names| metadata::file_under_composer(path, names, naming));
+    };
+    if let (Some(performer), Some(title)) = (&sheet.performer, &sheet.title) {
+        return Ok(metadata::ArtistAlbum {
+            album_artist: performer.clone(),
+            track_artist: performer.clone(),
+            album: title.clone(),
+        });
+    }
+
+    let names = metadata::extract_artist_album_from_file(path)?;
+    let performer = sheet.performer.get_or_insert(names.album_artist);
+    let title = sheet.title.get_or_insert(names.album);
+    Ok(metadata::ArtistAlbum {
+        album_artist: performer.clone(),
+        track_artist: performer.clone(),
+        album: title.clone(),
+    })
+}
+
+/// Split a single-file album into its cue sheet's tracks inside `album_path`, tagging each one
+/// Tracks already in the album are skipped; each split track is added to `split_moves`
+fn split_cue_album(
+    source: &Path,
+    sheet: &CueSheet,
+    album_path: &Path,
+    summary: &mut OrganizeSummary,
+    split_moves: &mut Vec<(PathBuf, PathBuf)>,
+    options: &ImportOptions,
+) {
+    let &ImportOptions {
+        quiet,
+        ref naming,
+        provenance,
+        ..
+    } = options;
+    let extension = cue::split_extension(source);
+    let artist = sheet
+        .performer
+        .as_deref()
+        .unwrap_or(metadata::UNKNOWN_ARTIST);
+    let album = sheet.title.as_deref().unwrap_or(metadata::UNKNOWN_ALBUM);
+    let track_total = sheet.tracks.len() as u32;
+
+    for track in &sheet.tracks {
+        if directory::interrupted() {
+            break;
+        }
+        let dest_path = album_path.join(cue::track_file_name(track, &extension, naming));
+        if dest_path.exists() {
+            summary.files_skipped += 1;
+            continue;
+        }
+        let result = cue::split_track(source, track, &dest_path)
+            .and_then(|_| cue::tag_split_track(&dest_path, artist, album, track, track_total));
+        match result {
+            Ok(()) => {
+                record_import_provenance(&dest_path, provenance);
+                if !quiet {
+                    info!(
+                        "Split track {}: {} -> {}",
+                        track.number,
+                        source.display(),
+                        dest_path.display()
+                    );
+                }
+                split_moves.push((source.to_path_buf(), dest_path));
+            }
+            Err(e) => {
+                // Don't leave a half-written or untagged track behind
+                let _ = fs::remove_file(&dest_path);
+                summary.failures.push(FileFailure::new(
+                    source,
+                    format!("track {}: {:#}", track.number, e),
+                ));
+            }
+        }
+    }
+}
+
+/// Copy a cue sheet into `album_path` unless one of that name is already there
+fn copy_cue_sheet(cue_path: &Path, album_path: &Path, failures: &mut Vec<FileFailure>) {
+    let Some(file_name) = cue_path.file_name() else {
+        return;
+    };
+    let dest_path = album_path.join(file_name);
+    if dest_path.exists() {
+        return;
+    }
+    if let Err(e) = fs::copy(cue_path, &dest_path) {
+        failures.push(FileFailure::new(
+            cue_path,
+            format!("Failed to copy to '{}': {}", dest_path.display(), e),
+        ));
+    }
+}
+
+/// Tag an imported copy with its provenance if `enabled` (`--provenance`)
+/// A failure is only logged, since the copy itself is fine
+fn record_import_provenance(dest_path: &Path, enabled: bool) {
+    if !enabled {
+        return;
+    }
+    if let Err(e) = provenance::write_provenance(dest_path, Source::Import) {
+        warn!("{:#}", e);
+    }
+}
+
+/// Write the album's checksum manifest unless `algorithm` (`--checksums`) is
+/// [`ChecksumAlgorithm::None`], recording a failure if it can't be written
+fn write_checksum_manifest(
+    album_path: &Path,
+    algorithm: ChecksumAlgorithm,
+    failures: &mut Vec<FileFailure>,
+    quiet: bool,
+) {
+    match checksums::write_manifest(album_path, algorithm) {
+        Ok(Some(manifest)) if !quiet => info!("Wrote checksums to {}", manifest.display()),
+        Ok(_) => {}
+        Err(e) => failures.push(FileFailure::new(album_path, format!("{:#}", e))),
+    }
+}
+
+/// Check that each import path is an existing directory
+fn validate_import_paths<'a>(import_paths: &[&'a str]) -> Result<Vec<&'a Path>> {
+    if import_paths.is_empty() {
+        return Err(anyhow::anyhow!("No import path given"));
+    }
+    import_paths
+        .iter()
+        .map(|&import_path| {
+            let import_path = Path::new(import_path);
+            if !import_path.exists() {
+                return Err(anyhow::anyhow!(
+                    "Import path '{}' does not exist",
+                    import_path.display()
+                ));
+            }
+            if !import_path.is_dir() {
+                return Err(anyhow::anyhow!(
+                    "Import path '{}' is not a directory",
+                    import_path.display()
+                ));
+            }
+            Ok(import_path)
+        })
+        .collect()
+}
+
+/// The files under each import path in turn, each file once even when the paths overlap
+/// (the same folder given twice, or one inside another)
+fn source_files(import_paths: &[&Path]) -> Vec<PathBuf> {
+    let mut seen: FxHashSet<PathBuf> = FxHashSet::default();
+    let mut files = Vec::new();
+    for import_path in import_paths {
+        for entry in WalkDir::new(import_path).into_iter().filter_map(|e| e.ok()) {
+            let path = entry.into_path();
+            if !path.is_file() {
+                continue;
+            }
+            let identity = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+            if seen.insert(identity) {
+                files.push(path);
+            }
+        }
+    }
+    files
+}
+
+/// Delete the sources of imported files once each copy exists with the same size as its source,
+/// and with `prune_empty_dirs` remove folders under the import root each came from that the
+/// deletions left empty
+/// Skipped and failed files are never in `summary.moves`, so they are always left in place
+/// Returns the number of source files removed
+fn prune_imported_sources(
+    summary: &mut OrganizeSummary,
+    import_roots: &[&Path],
+    prune_empty_dirs: bool,
+    quiet: bool,
+) -> usize {
+    let mut pruned = 0;
+    let mut failures = Vec::new();
+
+    for file_move in &summary.moves {
+        let verified = match (fs::metadata(&file_move.from), fs::metadata(&file_move.to)) {
+            (Ok(source), Ok(copy)) => copy.is_file() && copy.len() == source.len(),
+            _ => false,
+        };
+        if !verified {
+            failures.push(FileFailure::new(
+                &file_move.from,
+                format!(
+                    "Not removed: imported copy '{}' is missing or differs in size",
+                    file_move.to.display()
+                ),
+            ));
+            continue;
+        }
+
+        if let Err(e) = fs::remove_file(&file_move.from) {
+            failures.push(FileFailure::new(
+                &file_move.from,
+                format!("Imported but failed to remove the source: {}", e),
+            ));
+            continue;
+        }
+        pruned += 1;
+        if !quiet {
+            info!("Removed imported source: {}", file_move.from.display());
+        }
+
+        if !prune_empty_dirs {
+            continue;
+        }
+        // remove_dir fails on non-empty folders, which ends the walk up
+        let Some(&import_root) = import_roots
+            .iter()
+            .find(|root| file_move.from.starts_with(root))
+        else {
+            continue;
+        };
+        let mut dir = file_move.from.parent();
+        while let Some(parent) = dir.filter(|d| *d != import_root && d.starts_with(import_root)) {
+            if fs::remove_dir(parent).is_err() {
+                break;
+            }
+            dir = parent.parent();
+        }
+    }
+
+    summary.failures.extend(failures);
+    pruned
+}
+
+/// Enhanced import with MusicBrainz integration and cover art fetching, from one or more
+/// import paths as [`import_and_organize_files`] takes them
+/// Of `options` it honours `dry_run`, `quiet`, `preserve_mtime`, `naming`, `cover`,
+/// `provenance`, `checksums`, `backup_tags` and `sort_articles`; with `preserve_mtime`,
+/// imported copies keep the source files' times even after retagging
+/// With `all_art`, matched albums also get the release's back cover, booklet and other artwork
+pub async fn import_and_organize_files_with_musicbrainz(
+    import_paths: &[&str],
+    music_dir: &str,
+    options: &ImportOptions,
+    all_art: bool,
+    tx: mpsc::Sender<String>,
+) -> Result<()> {
+    let &ImportOptions {
+        dry_run,
+        quiet,
+        preserve_mtime,
+        ref naming,
+        cover,
+        provenance,
+        checksums,
+        backup_tags,
+        ref sort_articles,
+        ref network,
+        ..
+    } = options;
+    directory::clear_interrupt();
+    let music_path = Path::new(music_dir);
+    let artists_path = music_path.join("Artists");
+    let import_paths = validate_import_paths(import_paths)?;
+
+    // Ensure Artists directory exists
+    if !artists_path.exists() {
+        if dry_run {
+            if !quiet {
+                info!("Would create Artists directory: {}", artists_path.display());
+            }
+        } else {
+            fs::create_dir_all(&artists_path).map_err(|e| {
+                anyhow::anyhow!(
+                    "Failed to create Artists directory '{}': {}",
+                    artists_path.display(),
+                    e
+                )
+            })?;
+        }
+    }
+
+    let group_by = naming.layout(music_path);
+    if !dry_run {
+        utils::record_group_by(music_path, group_by)?;
+    }
+
+    tx.send("Scanning import directory for audio files...".to_string())
+        .context("Failed to send scan message")?;
+
+    let mut files_to_import = Vec::new();
+    let mut files_excluded = 0;
+
+    // Find all audio files in the import directories
+    for path in source_files(&import_paths) {
+        let path = path.as_path();
+
+        // Only process audio files
+        if audio::is_audio_file(path) {
+            // Enhanced metadata extraction with MusicBrainz lookup
+            match extract_and_enhance_metadata(network, path, &tx).await {
+                Ok((artist, album, release)) => {
+                    // Only include files with meaningful metadata
+                    if !artist.is_empty()
+                        && !album.is_empty()
+                        && !metadata::is_unknown_placeholder(&artist, &album)
+                    {
+                        files_to_import.push((path.to_path_buf(), artist, album, release));
+                    } else {
+                        files_excluded += 1;
+                        tx.send(format!("Excluding file without proper metadata: {} (Artist: '{}', Album: '{}')",
+                                           path.display(), artist, album))
+                                .context("Failed to send exclusion message")?;
+                    }
+                }
+                Err(e) => {
+                    files_excluded += 1;
+                    tx.send(format!(
+                        "Excluding file with unreadable metadata: {} ({})",
+                        path.display(),
+                        e
+                    ))
+                    .context("Failed to send metadata error message")?;
+                }
+            }
+        }
+    }
+
+    if files_to_import.is_empty() {
+        tx.send(format!(
+            "No files with proper metadata found. {} files excluded due to insufficient metadata.",
+            files_excluded
+        ))
+        .context("Failed to send completion message")?;
+        return Ok(());
+    }
+
+    tx.send(format!("TOTAL_FILES:{}", files_to_import.len()))
+        .context("Failed to send total files count")?;
+
+    // Group files by their correct artist/album based on enhanced metadata
+    let mut file_groups: FileGroupsByMetadata = FxHashMap::default();
+    let import_count = files_to_import.len();
+
+    for (file_path, artist, album, release) in files_to_import {
+        let (release_id, artist_credit) = release.unzip();
+        // Create clean names for directory creation
+        let artist = metadata::normalize_folder_text(&artist, false, metadata::UNKNOWN_ARTIST);
+        let album = metadata::normalize_folder_text(&album, false, metadata::UNKNOWN_ALBUM);
+        let artist = naming.canonical_artist(&artist);
+        let album = naming.canonical_album(&album);
+        let clean_artist = naming.sanitize(&artist);
+        let clean_album = naming.sanitize(&album);
+
+        file_groups
+            .entry((
+                clean_artist.clone(),
+                clean_album.clone(),
+                release_id.clone(),
+            ))
+            .or_default()
+            .push((file_path.clone(), artist_credit));
+
+        if dry_run && !quiet {
+            tx.send(format!(
+                "Would import: {} -> {} / {} (Release ID: {:?})",
+                file_path.display(),
+                clean_artist,
+                clean_album,
+                release_id
+            ))
+            .context("Failed to send dry run message")?;
+        }
+    }
+
+    tx.send(format!(
+        "Found {} unique artist/album combinations for {} files",
+        file_groups.len(),
+        import_count
+    ))
+    .context("Failed to send group count message")?;
+
+    // Import files to their correct locations with cover art fetching
+    let total_groups = file_groups.len();
+    // Groups with and without a release ID can share an album folder; art is saved once per folder
+    let mut albums_with_art: FxHashSet<PathBuf> = FxHashSet::default();
+    let mut albums_with_all_art: FxHashSet<PathBuf> = FxHashSet::default();
+    let mut imported_albums: FxHashSet<PathBuf> = FxHashSet::default();
+    let mut stopped = false;
+
+    for ((artist, album, release_id), files) in file_groups {
+        if stopped || directory::interrupted() {
+            stopped = true;
+            break;
+        }
+        let album_path = group_by.album_dir(
+            &artists_path,
+            &artist,
+            &album,
+            files.iter().map(|(path, _)| path),
+        );
+
+        // Fetch cover art: MusicBrainz needs a release ID, AudioDB only needs artist/album,
+        // so loosely matched albums without an ID still get artwork
+        let mut cover_art_data: Option<Vec<u8>> = None;
+        if !albums_with_art.contains(&album_path) {
+            if let Some(ref id) = release_id {
+                if let Ok(Some(cover_art)) = fetch_musicbrainz_cover_art(network, id, &tx).await {
+                    cover_art_data = Some(cover_art).filter(|art| {
+                        cover_art::accept_cover_art(art, "MusicBrainz", cover.min_size, &tx)
+                    });
+                }
+            }
+            if cover_art_data.is_none() {
+                if let Ok(Some(cover_art)) =
+                    fetch_audiodb_cover_art(network, &artist, &album, &tx).await
+                {
+                    cover_art_data = Some(cover_art).filter(|art| {
+                        cover_art::accept_cover_art(art, "AudioDB", cover.min_size, &tx)
+                    });
+                }
+            }
+        }
+
+        if dry_run {
+            tx.send(format!("Would create directory: {}", album_path.display()))
+                .context("Failed to send dry run directory message")?;
+            for (file, _) in &files {
+                tx.send(format!(
+                    "  Would copy: {} -> {}",
+                    file.display(),
+                    album_path.display()
+                ))
+                .context("Failed to send dry run file message")?;
+            }
+        } else {
+            // Create directories if they don't exist
+            fs::create_dir_all(&album_path).map_err(|e| {
+                anyhow::anyhow!(
+                    "Failed to create album directory '{}': {}",
+                    album_path.display(),
+                    e
+                )
+            })?;
+
+            // Copy each file
+            for (file_path, artist_credit) in files {
+                if directory::interrupted() {
+                    stopped = true;
+                    break;
+                }
+                let dest_path = match directory::destination_path(&file_path, &album_path, naming) {
+                    Ok(dest_path) => dest_path,
+                    Err(e) => {
+                        warn!("Not importing {}: {:#}", file_path.display(), e);
+                        continue;
+                    }
+                };
+
+                // Only copy if the destination doesn't already exist
+                if dest_path.exists() {
+                    tx.send(format!(
+                        "File already exists at destination, skipping: {} -> {}",
+                        file_path.display(),
+                        dest_path.display()
+                    ))
+                    .context("Failed to send skip message")?;
+                    continue;
+                }
+
+                // Copy the file
+                directory::copy_via_part_file(&file_path, &dest_path).map_err(|e| {
+                    anyhow::anyhow!(
+                        "Failed to copy '{}' to '{}': {}",
+                        file_path.display(),
+                        dest_path.display(),
+                        e
+                    )
+                })?;
+
+                // Set enhanced metadata with MusicBrainz release ID
+                if let Some(ref release_id) = release_id {
+                    if backup_tags {
+                        tag_backup::backup_file_tags(&dest_path)?;
+                    }
+                    let track_artist = artist_credit.as_deref().unwrap_or(&artist);
+                    set_enhanced_metadata(
+                        &dest_path,
+                        &artist,
+                        track_artist,
+                        &album,
+                        release_id,
+                        sort_articles.as_deref(),
+                    )
+                    .with_context(|| format!("Failed to set metadata for: {:?}", dest_path))?;
+                }
+                record_import_provenance(&dest_path, provenance);
+
+                // Restore times last, since retagging rewrites the copy
+                if preserve_mtime {
+                    if let Err(e) = directory::copy_file_times(&file_path, &dest_path) {
+                        warn!("{:?}", e);
+                    }
+                }
+
+                tx.send(format!(
+                    "COMPLETED: Imported {} -> {}",
+                    file_path.display(),
+                    dest_path.display()
+                ))
+                .context("Failed to send completion message")?;
+                imported_albums.insert(album_path.clone());
+            }
+
+            // Save cover art if we fetched it
+            if let Some(data) = cover_art_data {
+                match cover_art::store_cover_art(&album_path, &data, cover.format, cover.mode) {
+                    Ok(stored) => {
+                        albums_with_art.insert(album_path.clone());
+                        tx.send(format!("Saved cover art to: {}", stored))
+                            .context("Failed to send cover art save message")?;
+                    }
+                    Err(e) => warn!(
+                        "Failed to save cover art to {}: {}",
+                        album_path.display(),
+                        e
+                    ),
+                }
+            }
+
+            // The rest of the release's artwork goes alongside the cover, once per folder
+            if let Some(id) = release_id.as_ref().filter(|_| all_art) {
+                if albums_with_all_art.insert(album_path.clone()) {
+                    match cover_art::save_additional_artwork(
+                        network,
+                        &album_path,
+                        id,
+                        cover.format,
+                        &tx,
+                    )
+                    .await
+                    {
+                        Ok(saved) if !saved.is_empty() => tx
+                            .send(format!(
+                                "Saved {} more artwork images to: {}",
+                                saved.len(),
+                                album_path.display()
+                            ))
+                            .context("Failed to send artwork save message")?,
+                        Ok(_) => {}
+                        Err(e) => warn!(
+                            "Failed to fetch more artwork for {}: {:#}",
+                            album_path.display(),
+                            e
+                        ),
+                    }
+                }
+            }
+        }
+    }
+
+    if stopped && !quiet {
+        info!("Interrupted: stopped after the last complete file");
+    }
+
+    // Hash last, after every group sharing a folder is copied and any cover art embedded
+    for album_path in &imported_albums {
+        match checksums::write_manifest(album_path, checksums) {
+            Ok(Some(manifest)) => tx
+                .send(format!("Wrote checksums to {}", manifest.display()))
+                .context("Failed to send checksum message")?,
+            Ok(None) => {}
+            Err(e) => warn!(
+                "Failed to write checksums for {}: {:#}",
+                album_path.display(),
+                e
+            ),
+        }
+    }
+
+    tx.send(format!(
+        "Successfully imported {} files into {} artist/album combinations",
+        import_count, total_groups
+    ))
+    .context("Failed to send final completion message")?;
+
+    Ok(())
+}
+
+/// Enhanced metadata extraction with MusicBrainz lookup
+/// Returns the artist folder name, album, and the matched release ID and track artist, if any
+async fn extract_and_enhance_metadata(
+    network: &Network,
+    file_path: &Path,
+    tx: &mpsc::Sender<String>,
+) -> Result<(String, String, Option<(String, String)>)> {
+    // First try to extract from file metadata
+    let names = metadata::extract_artist_album_from_file(file_path)?;
+    let (artist, album) = (names.album_artist.as_str(), names.album.as_str());
+    let mut matched = None;
+
+    // If we have basic metadata, try to enhance it with MusicBrainz
+    if !metadata::is_unknown_placeholder(artist, album) {
+        match lookup_musicbrainz_release(network, artist, album, tx).await {
+            Ok(Some((artist_credit, title, release_id))) => {
+                tx.send(format!(
+                    "Enhanced metadata for {}: '{}' -> '{}' (credited to '{}')",
+                    file_path.display(),
+                    album,
+                    title,
+                    artist_credit
+                ))
+                .context("Failed to send enhancement message")?;
+                matched = Some((artist_credit, title, release_id));
+            }
+            Ok(None) => {
+                // No enhancement available, use original metadata
+                tx.send(format!(
+                    "No MusicBrainz match found for {} - {} (using original metadata)",
+                    artist, album
+                ))
+                .context("Failed to send no match message")?;
+            }
+            Err(e) => {
+                warn!(
+                    "MusicBrainz lookup failed for {} - {}: {}",
+                    artist, album, e
+                );
+            }
+        }
+    }
+
+    Ok(enhanced_import_target(names, matched))
+}
+
+/// Decide where an enhanced import files a track
+/// The artist folder always comes from the file's own album artist, the same name
+/// `organize` and the plain import use; a MusicBrainz match only supplies the album title,
+/// the release ID and the artist credit, which is written to the TrackArtist tag unless the
+/// file names a track artist of its own (a guest or compilation track), which is kept
+fn enhanced_import_target(
+    names: metadata::ArtistAlbum,
+    matched: Option<(String, String, String)>,
+) -> (String, String, Option<(String, String)>) {
+    let metadata::ArtistAlbum {
+        album_artist,
+        track_artist,
+        album,
+    } = names;
+    match matched {
+        Some((artist_credit, title, release_id)) => {
+            let track_artist = if track_artist != album_artist {
+                track_artist
+            } else {
+                artist_credit
+            };
+            (album_artist, title, Some((release_id, track_artist)))
+        }
+        None => (album_artist, album, None),
+    }
+}
+
+/// Look up release information from MusicBrainz
+async fn lookup_musicbrainz_release(
+    network: &Network,
+    artist: &str,
+    album: &str,
+    tx: &mpsc::Sender<String>,
+) -> Result<Option<(String, String, String)>> {
+    if network.is_offline() {
+        return Ok(None);
+    }
+
+    tx.send(format!(
+        "Looking up MusicBrainz release: {} - {}",
+        artist, album
+    ))
+    .context("Failed to send MusicBrainz lookup message")?;
+
+    let client = network.musicbrainz_client()?;
+
+    // Search for releases by artist and album
+    let query = musicbrainz_rs::entity::release::ReleaseSearchQuery::query_builder()
+        .release(album)
+        .and()
+        .artist(artist)
+        .build();
+
+    match Release::search(query).execute_with_client(&client).await {
+        Ok(search_result) => {
+            if let Some(release) = search_result.entities.into_iter().next() {
+                let artist_credit = release
+                    .artist_credit
+                    .as_ref()
+                    .map(|credits| {
+                        credits
+                            .iter()
+                            .map(|c| c.name.clone())
+                            .collect::<Vec<_>>()
+                            .join(" & ")
+                    })
+                    .unwrap_or_else(|| artist.to_string());
+
+                tx.send(format!(
+                    "Found MusicBrainz release: {} - {} ({})",
+                    artist_credit, release.title, release.id
+                ))
+                .context("Failed to send release found message")?;
+
+                Ok(Some((artist_credit, release.title, release.id)))
+            } else {
+                tx.send(format!(
+                    "No MusicBrainz release found for {} - {}",
+                    artist, album
+                ))
+                .context("Failed to send no release message")?;
+                Ok(None)
+            }
+        }
+        Err(e) => {
+            warn!("MusicBrainz search failed: {}", e);
+            Err(anyhow::anyhow!("MusicBrainz search failed: {}", e))
+        }
+    }
+}
+
+/// Fetch cover art from MusicBrainz Cover Art Archive
+async fn fetch_musicbrainz_cover_art(
+    network: &Network,
+    release_id: &str,
+    tx: &mpsc::Sender<String>,
+) -> Result<Option<Vec<u8>>> {
+    if network.is_offline() {
+        return Ok(None);
+    }
+
+    tx.send(format!(
+        "Fetching cover art from MusicBrainz for release: {}",
+        release_id
+    ))
+    .context("Failed to send cover art fetch message")?;
+
+    let cover_art_url = format!(
+        "{}/release/{}/front",
+        musicbrainz::cover_art_archive_base(),
+        release_id
+    );
+    let client = network.http_client()?;
+
+    match client.get(&cover_art_url).send().await {
+        Ok(response) => {
+            if response.status().is_success() {
+                match response.bytes().await {
+                    Ok(image_data) => {
+                        tx.send("Successfully fetched cover art from MusicBrainz".to_string())
+                            .context("Failed to send cover art success message")?;
+                        Ok(Some(image_data.to_vec()))
+                    }
+                    Err(e) => {
+                        tx.send(format!("Failed to read cover art data: {}", e))
+                            .context("Failed to send cover art data error")?;
+                        Ok(None)
+                    }
+                }
+            } else {
+                tx.send(format!(
+                    "Cover art not available from MusicBrainz (status: {})",
+                    response.status()
+                ))
+                .context("Failed to send cover art unavailable message")?;
+                Ok(None)
+            }
+        }
+        Err(e) => {
+            tx.send(format!("Failed to fetch cover art from MusicBrainz: {}", e))
+                .context("Failed to send cover art fetch error")?;
+            Ok(None)
+        }
+    }
+}
+
+/// Fetch cover art from AudioDB as fallback
+async fn fetch_audiodb_cover_art(
+    network: &Network,
+    artist: &str,
+    album: &str,
+    tx: &mpsc::Sender<String>,
+) -> Result<Option<Vec<u8>>> {
+    if network.is_offline() {
+        return Ok(None);
+    }
+
+    tx.send(format!(
+        "Trying AudioDB for cover art: {} - {}",
+        artist, album
+    ))
+    .context("Failed to send AudioDB cover art message")?;
+
+    let encoded_artist = urlencoding::encode(artist);
+    let encoded_album = urlencoding::encode(album);
+    let audiodb_url = format!(
+        "https://www.theaudiodb.com/api/v1/json/2/searchalbum.php?s={}&a={}",
+        encoded_artist, encoded_album
+    );
+
+    let client = network.http_client()?;
+
+    match client.get(&audiodb_url).send().await {
+        Ok(response) => {
+            if response.status().is_success() {
+                match response.json::<serde_json::Value>().await {
+                    Ok(json_data) => {
+                        if let Some(albums) = json_data.get("album") {
+                            if let Some(albums_array) = albums.as_array() {
+                                if let Some(first_album) = albums_array.first() {
+                                    if let Some(thumbnail_url) = first_album.get("strAlbumThumb") {
+                                        if let Some(url_str) = thumbnail_url.as_str() {
+                                            if !url_str.is_empty() && url_str != "null" {
+                                                match client.get(url_str).send().await {
+                                                    Ok(image_response) => {
+                                                        if image_response.status().is_success() {
+                                                            match image_response.bytes().await {
+                                                                Ok(image_data) => {
+                                                                    tx.send("Successfully fetched cover art from AudioDB".to_string())
+                                                                        .context("Failed to send AudioDB success message")?;
+                                                                    Ok(Some(image_data.to_vec()))
+                                                                }
+                                                                Err(e) => {
+                                                                    tx.send(format!("Failed to download AudioDB cover art: {}", e))
+                                                                        .context("Failed to send AudioDB download error")?;
+                                                                    Ok(None)
+                                                                }
+                                                            }
+                                                        } else {
+                                                            tx.send("AudioDB cover art download failed".to_string())
+                                                                .context("Failed to send AudioDB download failed")?;
+                                                            Ok(None)
+                                                        }
+                                                    }
+                                                    Err(e) => {
+                                                        tx.send(format!(
+                                                            "Failed to fetch from AudioDB URL: {}",
+                                                            e
+                                                        ))
+                                                        .context(
+                                                            "Failed to send AudioDB URL error",
+                                                        )?;
+                                                        Ok(None)
+                                                    }
+                                                }
+                                            } else {
+                                                tx.send(
+                                                    "No cover art URL found in AudioDB response"
+                                                        .to_string(),
+                                                )
+                                                .context("Failed to send no AudioDB URL message")?;
+                                                Ok(None)
+                                            }
+                                        } else {
+                                            tx.send(
+                                                "No cover art URL found in AudioDB response"
+                                                    .to_string(),
+                                            )
+                                            .context("Failed to send no AudioDB URL message")?;
+                                            Ok(None)
+                                        }
+                                    } else {
+                                        tx.send(
+                                            "No cover art found in AudioDB response".to_string(),
+                                        )
+                                        .context("Failed to send no AudioDB cover art")?;
+                                        Ok(None)
+                                    }
+                                } else {
+                                    tx.send("No albums found in AudioDB response".to_string())
+                                        .context("Failed to send no AudioDB albums")?;
+                                    Ok(None)
+                                }
+                            } else {
+                                tx.send("Invalid AudioDB response format".to_string())
+                                    .context("Failed to send invalid AudioDB format")?;
+                                Ok(None)
+                            }
+                        } else {
+                            tx.send("No album data in AudioDB response".to_string())
+                                .context("Failed to send no AudioDB album data")?;
+                            Ok(None)
+                        }
+                    }
+                    Err(e) => {
+                        tx.send(format!("Failed to parse AudioDB response: {}", e))
+                            .context("Failed to send AudioDB parse error")?;
+                        Ok(None)
+                    }
+                }
+            } else {
+                tx.send(format!(
+                    "AudioDB request failed (status: {})",
+                    response.status()
+                ))
+                .context("Failed to send AudioDB request failed")?;
+                Ok(None)
+            }
+        }
+        Err(e) => {
+            tx.send(format!("Failed to fetch from AudioDB: {}", e))
+                .context("Failed to send AudioDB fetch error")?;
+            Ok(None)
+        }
+    }
+}
+
+/// Set enhanced metadata with MusicBrainz release ID
+/// `album_artist` matches the artist folder; the release's artist credit goes in `track_artist`
+/// Derived sort tags move `sort_articles` (see [`metadata::artist_sort_name`])
+fn set_enhanced_metadata(
+    file_path: &Path,
+    album_artist: &str,
+    track_artist: &str,
+    album: &str,
+    release_id: &str,
+    sort_articles: Option<&[String]>,
+) -> Result<()> {
+    match lofty::read_from_path(file_path) {
+        Ok(mut tagged_file) => {
+            metadata::update_file_tags(&mut tagged_file, |tag| {
+                // Sort tags for a different artist would be stale; they are derived afresh below
+                if tag.get_string(&ItemKey::AlbumArtist) != Some(album_artist) {
+                    tag.remove_key(&ItemKey::AlbumArtistSortOrder);
+                    tag.remove_key(&ItemKey::TrackArtistSortOrder);
+                }
+
+                // Set standard metadata
+                tag.insert_text(ItemKey::TrackArtist, track_artist.to_string());
+                tag.insert_text(ItemKey::AlbumArtist, album_artist.to_string());
+                tag.insert_text(ItemKey::AlbumTitle, album.to_string());
+
+                // Add MusicBrainz release ID
+                tag.insert_text(ItemKey::MusicBrainzReleaseId, release_id.to_string());
+
+                metadata::fill_sort_tags(tag, None, sort_articles);
+            })?;
+
+            // Try to save the enhanced metadata
+            if let Err(e) = gapless::save_tags(&tagged_file, file_path) {
+                warn!(
+                    "Failed to save enhanced metadata for {}: {}",
+                    file_path.display(),
+                    e
+                );
+            }
+        }
+        Err(e) => {
+            warn!(
+                "Could not read file for metadata enhancement: {} ({})",
+                file_path.display(),
+                e
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{write_silent_wav, write_tagged_wav};
+    use std::fs;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_import_and_organize_files_nonexistent_import_path() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+        let nonexistent_import = temp_dir.path().join("NonexistentImport");
+
+        // Test that it fails with nonexistent import path
+        let result = import_and_organize_files(
+            &[nonexistent_import.to_str().unwrap()],
+            music_root.to_str().unwrap(),
+            &ImportOptions {
+                quiet: true,
+                ..Default::default()
+            },
+        );
+
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("does not exist"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_and_organize_files_import_path_not_directory() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+        let import_file = temp_dir.path().join("import.txt");
+
+        // Create a file instead of directory
+        fs::File::create(&import_file)?.write_all(b"not a directory")?;
+
+        // Test that it fails when import path is not a directory
+        let result = import_and_organize_files(
+            &[import_file.to_str().unwrap()],
+            music_root.to_str().unwrap(),
+            &ImportOptions {
+                quiet: true,
+                ..Default::default()
+            },
+        );
+
+        assert!(result.is_err());
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("is not a directory"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_and_organize_files_empty_import_directory() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+        let empty_import = temp_dir.path().join("EmptyImport");
+
+        // Create empty import directory
+        fs::create_dir(&empty_import)?;
+
+        // Test that it succeeds with empty import directory
+        let result = import_and_organize_files(
+            &[empty_import.to_str().unwrap()],
+            music_root.to_str().unwrap(),
+            &ImportOptions {
+                quiet: true,
+                ..Default::default()
+            },
+        );
+
+        assert!(result.is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_and_organize_files_dry_run() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+        let import_dir = temp_dir.path().join("Import");
+
+        // Create import directory with audio file
+        fs::create_dir(&import_dir)?;
+        fs::File::create(import_dir.join("test.mp3"))?.write_all(b"audio")?;
+
+        // Test dry run - should not actually import files
+        let summary = import_and_organize_files(
+            &[import_dir.to_str().unwrap()],
+            music_root.to_str().unwrap(),
+            &ImportOptions {
+                dry_run: true,
+                quiet: true,
+                ..Default::default()
+            },
+        )?;
+
+        assert!(summary.dry_run);
+        assert_eq!(summary.files_moved, 0);
+        assert_eq!(summary.moves.len() + summary.excluded.len(), 1);
+
+        // Check that no files were actually moved
+        let artists_dir = music_root.join("Artists");
+        assert!(!artists_dir.exists()); // Should not create directories in dry run
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_from_several_paths_into_one_library() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+        let first = temp_dir.path().join("First");
+        let second = temp_dir.path().join("Second");
+        let first_album = first.join("Trio").join("Record");
+        let second_album = second.join("Trio").join("Record");
+        let other_album = second.join("Other Trio").join("Other Record");
+        for dir in [&first_album, &second_album, &other_album] {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(first_album.join("01.mp3"), b"first 01")?;
+        fs::write(first_album.join("02.mp3"), b"first 02")?;
+        // Bound for the same file as the first source's 02.mp3
+        fs::write(second_album.join("02.mp3"), b"second 02")?;
+        fs::write(other_album.join("03.mp3"), b"second 03")?;
+
+        // The first path given twice is only scanned once
+        let summary = import_and_organize_files(
+            &[
+                first.to_str().unwrap(),
+                second.to_str().unwrap(),
+                first.to_str().unwrap(),
+            ],
+            music_root.to_str().unwrap(),
+            &ImportOptions {
+                quiet: true,
+                ..Default::default()
+            },
+        )?;
+
+        assert_eq!(summary.files_moved, 3);
+        assert_eq!(summary.files_skipped, 1);
+        let record = music_root.join("Artists").join("Trio").join("Record");
+        assert_eq!(fs::read(record.join("01.mp3"))?, b"first 01");
+        assert_eq!(fs::read(record.join("02.mp3"))?, b"first 02");
+        assert!(music_root
+            .join("Artists")
+            .join("Other Trio")
+            .join("Other Record")
+            .join("03.mp3")
+            .exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_interrupted_import_leaves_no_partial_files() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+        let import_dir = temp_dir.path().join("Import");
+        let source_album = import_dir.join("Trio").join("Record");
+        fs::create_dir_all(&source_album)?;
+        for n in 1..=4 {
+            fs::write(
+                source_album.join(format!("0{}.mp3", n)),
+                format!("track {}", n).repeat(1000),
+            )?;
+        }
+
+        // Interrupted after the album and its first two files have been started
+        directory::INTERRUPT_AFTER.set(Some(3));
+        let summary = import_and_organize_files(
+            &[import_dir.to_str().unwrap()],
+            music_root.to_str().unwrap(),
+            &ImportOptions {
+                quiet: true,
+                ..Default::default()
+            },
+        );
+        directory::INTERRUPT_AFTER.set(None);
+        let summary = summary?;
+
+        assert_eq!(summary.files_moved, 2);
+        let record = music_root.join("Artists").join("Trio").join("Record");
+        let mut copied = Vec::new();
+        for entry in fs::read_dir(&record)? {
+            let dest_path = entry?.path();
+            let file_name = dest_path.file_name().unwrap().to_owned();
+            assert_eq!(
+                fs::read(&dest_path)?,
+                fs::read(source_album.join(&file_name))?
+            );
+            copied.push(file_name);
+        }
+        // Every file there is a whole copy, with no part files left over
+        assert_eq!(copied.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_copies_library_albums_whole() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let source = temp_dir.path().join("Other Library");
+        // Filed under names its tags disagree with, with a disc folder and sidecars
+        let source_album = source
+            .join("Artists")
+            .join("Wrong Name")
+            .join("Some Folder");
+        fs::create_dir_all(source_album.join("CD2"))?;
+        write_tagged_wav(
+            &source_album.join("01.wav"),
+            &[
+                (ItemKey::TrackArtist, "Band"),
+                (ItemKey::AlbumArtist, "Band"),
+                (ItemKey::AlbumTitle, "Record"),
+            ],
+        )?;
+        write_tagged_wav(
+            &source_album.join("CD2").join("02.wav"),
+            &[
+                (ItemKey::TrackArtist, "Band"),
+                (ItemKey::AlbumArtist, "Band"),
+                (ItemKey::AlbumTitle, "Record"),
+            ],
+        )?;
+        fs::write(source_album.join("cover.jpg"), b"art")?;
+        fs::write(source_album.join("CD2").join("rip.log"), b"log")?;
+
+        let import = |music_root: &Path, preserve_structure, path_only| {
+            import_and_organize_files(
+                &[source.to_str().unwrap()],
+                music_root.to_str().unwrap(),
+                &ImportOptions {
+                    quiet: true,
+                    preserve_structure,
+                    path_only,
+                    ..Default::default()
+                },
+            )
+        };
+
+        // The folder is copied as it is, but the tags decide where it goes
+        let music_root = temp_dir.path().join("Music");
+        let summary = import(&music_root, true, false)?;
+        let record = music_root.join("Artists").join("Band").join("Record");
+        assert_eq!(summary.files_moved, 2);
+        assert!(record.join("01.wav").exists());
+        assert!(record.join("CD2").join("02.wav").exists());
+        assert_eq!(fs::read(record.join("cover.jpg"))?, b"art");
+        assert_eq!(fs::read(record.join("CD2").join("rip.log"))?, b"log");
+
+        // Trusting the folders keeps the source's names
+        let trusted_root = temp_dir.path().join("Trusted");
+        import(&trusted_root, true, true)?;
+        let trusted = trusted_root
+            .join("Artists")
+            .join("Wrong Name")
+            .join("Some Folder");
+        assert!(trusted.join("CD2").join("02.wav").exists());
+        assert!(trusted.join("cover.jpg").exists());
+
+        // Without the option, tracks are regrouped one by one and the sidecars stay behind
+        let flat_root = temp_dir.path().join("Flat");
+        let summary = import(&flat_root, false, false)?;
+        let flat = flat_root.join("Artists").join("Band").join("Record");
+        assert_eq!(summary.files_moved, 2);
+        assert!(flat.join("02.wav").exists());
+        assert!(!flat.join("cover.jpg").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_lists_excluded_files_with_reasons() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+        let import_dir = temp_dir.path().join("Import");
+
+        // "Songs" is not an album name, so this untagged file has no usable album
+        let songs_dir = import_dir.join("Some Artist").join("Songs");
+        fs::create_dir_all(&songs_dir)?;
+        let track = songs_dir.join("track.mp3");
+        fs::write(&track, b"audio")?;
+
+        let summary = import_and_organize_files(
+            &[import_dir.to_str().unwrap()],
+            music_root.to_str().unwrap(),
+            &ImportOptions {
+                quiet: true,
+                ..Default::default()
+            },
+        )?;
+
+        assert_eq!(summary.files_moved, 0);
+        assert_eq!(summary.excluded.len(), 1);
+        assert_eq!(summary.excluded[0].path, track);
+        assert!(summary.excluded[0].reason.contains("Unknown Album"));
+
+        let list_path = temp_dir.path().join("excluded.txt");
+        directory::write_file_list(&list_path, &summary.excluded)?;
+        let list = fs::read_to_string(&list_path)?;
+        assert!(list.starts_with(&format!("{}\t", track.display())));
+        assert_eq!(list.lines().count(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_unknown_files_under_placeholders() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+        let import_dir = temp_dir.path().join("Import");
+        let songs_dir = import_dir.join("Some Singer").join("Songs");
+        fs::create_dir_all(&songs_dir)?;
+        fs::write(songs_dir.join("track.mp3"), b"audio")?;
+
+        let summary = import_and_organize_files(
+            &[import_dir.to_str().unwrap()],
+            music_root.to_str().unwrap(),
+            &ImportOptions {
+                quiet: true,
+                import_unknown: true,
+                ..Default::default()
+            },
+        )?;
+
+        assert!(summary.excluded.is_empty());
+        assert_eq!(summary.files_moved, 1);
+        assert_eq!(summary.unknown_placeholders, 1);
+        assert!(music_root
+            .join("Artists")
+            .join("Some Singer")
+            .join(metadata::UNKNOWN_ALBUM)
+            .join("track.mp3")
+            .exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_excludes_files_below_quality_floor() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+        let album_dir = temp_dir.path().join("Import").join("Trio").join("Record");
+        fs::create_dir_all(&album_dir)?;
+
+        // Twenty 32 kbps, 44.1 kHz mono MPEG-1 Layer III frames of silence
+        let low_bitrate = album_dir.join("low.mp3");
+        let mut frame = vec![0xFF, 0xFB, 0x10, 0xC0];
+        frame.resize(104, 0);
+        fs::write(&low_bitrate, frame.repeat(20))?;
+
+        // An 8 kHz FLAC: lossless, but still held to the sample rate floor
+        let low_sample_rate = album_dir.join("low.flac");
+        let mut flac = b"fLaC".to_vec();
+        flac.extend_from_slice(&[0x80, 0x00, 0x00, 0x22]);
+        flac.extend_from_slice(&[0x10, 0x00, 0x10, 0x00, 0, 0, 0, 0, 0, 0]);
+        flac.extend_from_slice(&[0x01, 0xF4, 0x02, 0xF0, 0x00, 0x00, 0x00, 0x00]);
+        flac.extend_from_slice(&[0u8; 16]);
+        fs::write(&low_sample_rate, flac)?;
+        // A 44.1 kHz WAV, which passes
+        write_silent_wav(&album_dir.join("lossless.wav"))?;
+
+        let summary = import_and_organize_files(
+            &[temp_dir.path().join("Import").to_str().unwrap()],
+            music_root.to_str().unwrap(),
+            &ImportOptions {
+                quiet: true,
+                import_unknown: true,
+                quality: QualityFloor {
+                    min_bitrate: Some(128),
+                    min_sample_rate: Some(44100),
+                },
+                ..Default::default()
+            },
+        )?;
+
+        assert_eq!(summary.files_moved, 1);
+        let mut excluded: Vec<_> = summary
+            .excluded
+            .iter()
+            .map(|failure| (failure.path.clone(), failure.reason.as_str()))
+            .collect();
+        excluded.sort();
+        assert_eq!(
+            excluded,
+            [
+                (low_sample_rate, "sample rate 8000 Hz below 44100 Hz"),
+                (low_bitrate, "bitrate 32 kbps below 128 kbps"),
+            ]
+        );
+        let dest_album = music_root.join("Artists").join("Trio").join("Record");
+        assert!(dest_album.join("lossless.wav").exists());
+        assert!(!dest_album.join("low.mp3").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_names_and_splits_cue_albums() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+        let import_dir = temp_dir.path().join("Import").join("rip");
+        fs::create_dir_all(&import_dir)?;
+        fs::write(import_dir.join("CDImage.flac"), b"audio")?;
+        fs::write(
+            import_dir.join("CDImage.cue"),
+            "PERFORMER \"Cue Artist\"\nTITLE \"Cue Album\"\nFILE \"CDImage.wav\" WAVE\n\
+             TRACK 01 AUDIO\nTITLE \"First\"\nINDEX 01 00:00:00\n\
+             TRACK 02 AUDIO\nTITLE \"Second\"\nINDEX 01 04:10:00\n",
+        )?;
+        let album_dir = music_root
+            .join("Artists")
+            .join("Cue Artist")
+            .join("Cue Album");
+
+        let import = |split_cue| {
+            import_and_organize_files(
+                &[import_dir.to_str().unwrap()],
+                music_root.to_str().unwrap(),
+                &ImportOptions {
+                    dry_run: true,
+                    quiet: true,
+                    split_cue,
+                    ..Default::default()
+                },
+            )
+        };
+        let destinations = |summary: &OrganizeSummary| -> Vec<PathBuf> {
+            summary.moves.iter().map(|m| m.to.clone()).collect()
+        };
+
+        // The whole file is filed under the sheet's names, even though it has no tags
+        let summary = import(false)?;
+        assert_eq!(destinations(&summary), [album_dir.join("CDImage.flac")]);
+
+        // With --split-cue, one file per track is planned instead
+        let summary = import(true)?;
+        assert_eq!(
+            destinations(&summary),
+            [
+                album_dir.join("01 - First.flac"),
+                album_dir.join("02 - Second.flac")
+            ]
+        );
+        assert!(!music_root.join("Artists").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_normalizes_extensions() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+        let import_dir = temp_dir.path().join("Import");
+        let songs_dir = import_dir.join("Some Singer").join("Songs");
+        fs::create_dir_all(&songs_dir)?;
+        fs::write(songs_dir.join("Track.MP3"), b"audio")?;
+        fs::write(songs_dir.join("Other.Flac"), b"audio")?;
+
+        // A different file already in the album whose name differs only in case
+        let album_dir = music_root
+            .join("Artists")
+            .join("Some Singer")
+            .join(metadata::UNKNOWN_ALBUM);
+        fs::create_dir_all(&album_dir)?;
+        fs::write(album_dir.join("OTHER.flac"), b"existing")?;
+
+        let summary = import_and_organize_files(
+            &[import_dir.to_str().unwrap()],
+            music_root.to_str().unwrap(),
+            &ImportOptions {
+                quiet: true,
+                import_unknown: true,
+                naming: Naming {
+                    normalize_extensions: true,
+                    ..Naming::default()
+                },
+                ..Default::default()
+            },
+        )?;
+
+        assert_eq!(summary.files_moved, 1);
+        assert_eq!(summary.moves[0].to, album_dir.join("Track.mp3"));
+        assert!(album_dir.join("Track.mp3").exists());
+        assert_eq!(summary.failures.len(), 1);
+        assert_eq!(summary.failures[0].path, songs_dir.join("Other.Flac"));
+        assert_eq!(fs::read(album_dir.join("OTHER.flac"))?, b"existing");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_records_provenance_only_when_asked() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let import_dir = temp_dir.path().join("Import");
+        fs::create_dir_all(&import_dir)?;
+        write_tagged_wav(
+            &import_dir.join("01.wav"),
+            &[
+                (ItemKey::TrackArtist, "Band"),
+                (ItemKey::AlbumArtist, "Band"),
+                (ItemKey::AlbumTitle, "Record"),
+            ],
+        )?;
+
+        for provenance in [false, true] {
+            let music_root = temp_dir.path().join(format!("Music {}", provenance));
+            import_and_organize_files(
+                &[import_dir.to_str().unwrap()],
+                music_root.to_str().unwrap(),
+                &ImportOptions {
+                    quiet: true,
+                    provenance,
+                    ..Default::default()
+                },
+            )?;
+            let copy = music_root
+                .join("Artists")
+                .join("Band")
+                .join("Record")
+                .join("01.wav");
+            let recorded = provenance::read_provenance(&copy);
+            assert_eq!(
+                recorded.as_deref().map(provenance::provenance_source),
+                provenance.then_some("import")
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_enhanced_import_uses_same_artist_folder_as_plain_import() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+        let import_dir = temp_dir.path().join("Import");
+        let album_dir = import_dir.join("Massive Attack").join("Heligoland");
+        fs::create_dir_all(&album_dir)?;
+        let track = album_dir.join("track.mp3");
+        fs::File::create(&track)?.write_all(b"audio")?;
+
+        let summary = import_and_organize_files(
+            &[import_dir.to_str().unwrap()],
+            music_root.to_str().unwrap(),
+            &ImportOptions {
+                dry_run: true,
+                quiet: true,
+                ..Default::default()
+            },
+        )?;
+        let plain_artist_dir = summary.moves[0].to.parent().and_then(Path::parent);
+
+        // The enhanced import matched a release credited to more than one artist
+        let names = metadata::extract_artist_album_from_file(&track)?;
+        let matched = Some((
+            "Massive Attack & Hope Sandoval".to_string(),
+            "Heligoland".to_string(),
+            "release-id".to_string(),
+        ));
+        let (folder_artist, folder_album, release) = enhanced_import_target(names, matched);
+
+        let enhanced_artist_dir = music_root.join("Artists").join(&folder_artist);
+        assert_eq!(plain_artist_dir, Some(enhanced_artist_dir.as_path()));
+        assert_eq!(folder_album, "Heligoland");
+        assert_eq!(
+            release,
+            Some((
+                "release-id".to_string(),
+                "Massive Attack & Hope Sandoval".to_string()
+            ))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_prune_removes_only_imported_sources() -> Result<()> {
+        for prune_empty_dirs in [true, false] {
+            let temp_dir = TempDir::new()?;
+            let music_root = temp_dir.path().join("Music");
+            let import_dir = temp_dir.path().join("Import");
+
+            // Untagged files fall back to their Artist/Album folders
+            let album_dir = import_dir.join("SomeArtist").join("SomeAlbum");
+            fs::create_dir_all(&album_dir)?;
+            fs::write(album_dir.join("imported.mp3"), b"audio")?;
+            fs::write(album_dir.join("skipped.mp3"), b"audio")?;
+            let other_album_dir = import_dir.join("SomeArtist").join("OtherAlbum");
+            fs::create_dir_all(&other_album_dir)?;
+            fs::write(other_album_dir.join("track.mp3"), b"audio")?;
+
+            // A file already at the destination is skipped, so its source must survive
+            let dest_album = music_root
+                .join("Artists")
+                .join("SomeArtist")
+                .join("SomeAlbum");
+            fs::create_dir_all(&dest_album)?;
+            fs::write(dest_album.join("skipped.mp3"), b"existing")?;
+
+            let summary = import_and_organize_files(
+                &[import_dir.to_str().unwrap()],
+                music_root.to_str().unwrap(),
+                &ImportOptions {
+                    quiet: true,
+                    prune: true,
+                    prune_empty_dirs,
+                    ..Default::default()
+                },
+            )?;
+
+            assert_eq!(summary.files_moved, 2);
+            assert_eq!(summary.files_skipped, 1);
+            assert!(summary.failures.is_empty());
+            assert!(dest_album.join("imported.mp3").exists());
+            assert!(!album_dir.join("imported.mp3").exists());
+            assert!(!other_album_dir.join("track.mp3").exists());
+            assert!(album_dir.join("skipped.mp3").exists());
+            // The emptied album folder is removed only when asked, and never the import root
+            assert_eq!(other_album_dir.exists(), !prune_empty_dirs);
+            assert!(import_dir.exists());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sanitize_filename_basic() -> Result<()> {
+        // Test basic sanitization
+        assert_eq!(utils::sanitize_filename("normal_name"), "normal_name");
+        assert_eq!(
+            utils::sanitize_filename("file with spaces"),
+            "file with spaces"
+        );
+        assert_eq!(
+            utils::sanitize_filename("file/with\\bad:chars*"),
+            "file_with_bad_chars_"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sanitize_filename_edge_cases() -> Result<()> {
+        // Test edge cases
+        assert_eq!(utils::sanitize_filename(""), "");
+        assert_eq!(utils::sanitize_filename("   "), "");
+        assert_eq!(
+            utils::sanitize_filename("file\x00with\x01control\x02chars"),
+            "file_with_control_chars"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_from_path_valid_structure() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+        let artists_dir = music_root.join("Artists");
+        let artist_dir = artists_dir.join("TestArtist");
+        let album_dir = artist_dir.join("TestAlbum");
+        let file_path = album_dir.join("track.mp3");
+
+        // Create the directory structure
+        fs::create_dir_all(&album_dir)?;
+
+        // Test extraction from path
+        let (artist, album) = metadata::extract_from_path(&file_path)?;
+
+        assert_eq!(artist, "TestArtist");
+        assert_eq!(album, "TestAlbum");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,184 @@
+use crate::commands::{albums, tracks};
+use crate::utils::{self, Naming};
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// Symlink views rebuilt by the `links` command, relative to the music directory
+pub const LINK_VIEWS: &[&str] = &["Albums", "Tracks"];
+
+/// Which of the flat symlink views to build, as `--no-album-links`/`--no-track-links` choose
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinkViews {
+    /// Link each album into `Albums/`
+    pub albums: bool,
+    /// Link each track into `Tracks/`
+    pub tracks: bool,
+}
+
+impl Default for LinkViews {
+    fn default() -> Self {
+        Self {
+            albums: true,
+            tracks: true,
+        }
+    }
+}
+
+impl LinkViews {
+    /// The enabled views' directories, relative to the music directory
+    pub fn dirs(self) -> impl Iterator<Item = &'static str> {
+        LINK_VIEWS
+            .iter()
+            .copied()
+            .zip([self.albums, self.tracks])
+            .filter_map(|(view, enabled)| enabled.then_some(view))
+    }
+}
+
+/// Link an album into `Albums/` and each of its tracks (disc folders included) into `Tracks/`,
+/// leaving out whichever view `views` turns off
+/// This is one step of a full rebuild: callers walk the albums once and call this for each
+pub fn process_single_album_links(
+    album_path: &Path,
+    music_dir: &str,
+    naming: &Naming,
+    views: LinkViews,
+    dry_run: bool,
+) -> Result<()> {
+    if views.albums {
+        albums::process_single_album_symlink(album_path, music_dir, naming, dry_run, true)?;
+    }
+    if views.tracks {
+        for track_path in utils::album_track_paths(album_path) {
+            tracks::process_single_track_symlink(&track_path, music_dir, dry_run)?;
+        }
+    }
+    Ok(())
+}
+
+/// Remove links in the enabled symlink views whose target no longer exists, returning the
+/// links removed; views that are turned off are left untouched
+/// With `dry_run`, the stale links are only logged
+pub fn prune_stale_links(music_dir: &str, views: LinkViews, dry_run: bool) -> Result<Vec<PathBuf>> {
+    let mut stale = Vec::new();
+    for view in views.dirs() {
+        let view_path = Path::new(music_dir).join(view);
+        let Ok(entries) = fs::read_dir(&view_path) else {
+            continue;
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            let link = entry.path();
+            // `exists` follows the link, so it is false exactly when the target is gone
+            if link.is_symlink() && !link.exists() {
+                stale.push(link);
+            }
+        }
+    }
+    stale.sort();
+
+    for link in &stale {
+        if dry_run {
+            info!("Would remove stale link: {}", link.display());
+        } else {
+            fs::remove_file(link)?;
+            info!("Removed stale link: {}", link.display());
+        }
+    }
+    Ok(stale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_rebuild_links_replaces_stale_views() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+        let album = music_root.join("Artists").join("Artist").join("Album");
+        fs::create_dir_all(album.join("CD2"))?;
+        fs::write(album.join("01.mp3"), b"audio")?;
+        fs::write(album.join("CD2").join("02.mp3"), b"audio")?;
+
+        // Links left behind by an album that has since been moved away
+        let albums_dir = music_root.join("Albums");
+        let tracks_dir = music_root.join("Tracks");
+        fs::create_dir_all(&albums_dir)?;
+        fs::create_dir_all(&tracks_dir)?;
+        let gone = music_root.join("Artists").join("Old").join("Gone");
+        symlink(&gone, albums_dir.join("Old - Gone"))?;
+        symlink(gone.join("old.mp3"), tracks_dir.join("old.mp3"))?;
+
+        let music_dir = music_root.to_str().unwrap();
+        let stale = prune_stale_links(music_dir, LinkViews::default(), true)?;
+        assert_eq!(stale.len(), 2);
+        assert!(albums_dir.join("Old - Gone").is_symlink());
+
+        assert_eq!(
+            prune_stale_links(music_dir, LinkViews::default(), false)?,
+            stale
+        );
+        for album_path in utils::get_all_album_paths(music_dir)? {
+            process_single_album_links(
+                &album_path,
+                music_dir,
+                &Naming::default(),
+                LinkViews::default(),
+                false,
+            )?;
+        }
+
+        assert!(!albums_dir.join("Old - Gone").is_symlink());
+        assert!(!tracks_dir.join("old.mp3").is_symlink());
+        assert_eq!(fs::read_link(albums_dir.join("Artist - Album"))?, album);
+        assert_eq!(
+            fs::read_link(tracks_dir.join("02.mp3"))?,
+            album.join("CD2").join("02.mp3")
+        );
+        assert!(tracks_dir.join("01.mp3").is_symlink());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_links_build_only_the_requested_view() -> Result<()> {
+        for views in [
+            LinkViews {
+                albums: true,
+                tracks: false,
+            },
+            LinkViews {
+                albums: false,
+                tracks: true,
+            },
+        ] {
+            let temp_dir = TempDir::new()?;
+            let music_root = temp_dir.path();
+            let album = music_root.join("Artists").join("Artist").join("Album");
+            fs::create_dir_all(&album)?;
+            fs::write(album.join("01.mp3"), b"audio")?;
+            // A stale link in each view: only the enabled view is pruned
+            for view in LINK_VIEWS {
+                fs::create_dir_all(music_root.join(view))?;
+                symlink(music_root.join("gone"), music_root.join(view).join("gone"))?;
+            }
+
+            let music_dir = music_root.to_str().unwrap();
+            assert_eq!(prune_stale_links(music_dir, views, false)?.len(), 1);
+            process_single_album_links(&album, music_dir, &Naming::default(), views, false)?;
+
+            let albums_dir = music_root.join("Albums");
+            let tracks_dir = music_root.join("Tracks");
+            assert_eq!(albums_dir.join("Artist - Album").is_symlink(), views.albums);
+            assert_eq!(tracks_dir.join("01.mp3").is_symlink(), views.tracks);
+            assert_eq!(albums_dir.join("gone").is_symlink(), !views.albums);
+            assert_eq!(tracks_dir.join("gone").is_symlink(), !views.tracks);
+        }
+
+        Ok(())
+    }
+}
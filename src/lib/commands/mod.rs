@@ -0,0 +1,18 @@
+pub mod albums;
+pub mod art;
+pub mod cd;
+pub mod check;
+pub mod embed_art;
+pub mod fix_encoding;
+pub mod import;
+pub mod links;
+pub mod organize;
+pub mod provenance;
+pub mod rename;
+pub mod rename_files;
+pub mod reorganize;
+pub mod restore_tags;
+pub mod scan;
+pub mod sync;
+pub mod tracks;
+pub mod verify;
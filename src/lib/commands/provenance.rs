@@ -0,0 +1,85 @@
+use crate::provenance;
+use crate::utils;
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+/// How the library's audio files came into it, from their provenance tags
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ProvenanceSummary {
+    /// Files per recorded operation (`cd-rip`, `import`)
+    pub sources: BTreeMap<String, usize>,
+    /// Files without a provenance tag: sourced outside mfutil, or added without `--provenance`
+    pub external: usize,
+}
+
+impl fmt::Display for ProvenanceSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (source, count) in &self.sources {
+            write!(f, "{} {}, ", count, source)?;
+        }
+        write!(f, "{} external", self.external)
+    }
+}
+
+/// Count the library's audio files by the operation their provenance tag records (see
+/// [`provenance::write_provenance`]), and those without one
+pub fn provenance_report(music_dir: &str) -> Result<ProvenanceSummary> {
+    let artists_path = Path::new(music_dir).join("Artists");
+    if !artists_path.is_dir() {
+        return Err(anyhow::anyhow!(
+            "Artists directory '{}' does not exist. Run organize first to create the directory structure.",
+            artists_path.display()
+        ));
+    }
+
+    let mut summary = ProvenanceSummary::default();
+    for track in utils::iter_tracks(music_dir) {
+        match provenance::read_provenance(&track) {
+            Some(recorded) => {
+                let source = provenance::provenance_source(&recorded).to_string();
+                *summary.sources.entry(source).or_default() += 1;
+            }
+            None => summary.external += 1,
+        }
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provenance::Source;
+    use crate::test_support::write_silent_wav;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_provenance_report_counts_sources() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let album = temp_dir.path().join("Artists").join("Artist").join("Album");
+        fs::create_dir_all(&album)?;
+        for (file, source) in [
+            ("01.wav", Some(Source::CdRip)),
+            ("02.wav", Some(Source::CdRip)),
+            ("03.wav", Some(Source::Import)),
+            ("04.wav", None),
+        ] {
+            let track = album.join(file);
+            write_silent_wav(&track)?;
+            if let Some(source) = source {
+                provenance::write_provenance(&track, source)?;
+            }
+        }
+
+        let summary = provenance_report(temp_dir.path().to_str().unwrap())?;
+        assert_eq!(summary.sources.get("cd-rip"), Some(&2));
+        assert_eq!(summary.sources.get("import"), Some(&1));
+        assert_eq!(summary.external, 1);
+        assert_eq!(summary.to_string(), "2 cd-rip, 1 import, 1 external");
+
+        Ok(())
+    }
+}
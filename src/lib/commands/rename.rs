@@ -0,0 +1,596 @@
+use crate::directory::{self, FileFailure, FileMove};
+use crate::utils::{self, GroupBy, Naming};
+use crate::{audio, metadata};
+use anyhow::{Context, Result};
+use rustc_hash::FxHashMap;
+use std::fs;
+use std::os::unix::fs::symlink;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+use walkdir::WalkDir;
+
+/// Rename album folders so they match the artist/album stored in their tags
+/// Albums without usable tags are left alone, and symlinks under `Albums/` and
+/// `Tracks/` pointing into a renamed folder are updated to follow it
+/// Folder names are made from the tags by `naming`
+/// Returns the renames that were (or would be, in a dry run) made
+pub fn rename_folders_to_match_tags(
+    music_dir: &str,
+    naming: &Naming,
+    dry_run: bool,
+    quiet: bool,
+) -> Result<Vec<FileMove>> {
+    let music_path = Path::new(music_dir);
+    let artists_path = music_path.join("Artists");
+
+    if !artists_path.is_dir() {
+        return Err(anyhow::anyhow!(
+            "Artists directory '{}' does not exist. Run organize first to create the directory structure.",
+            artists_path.display()
+        ));
+    }
+
+    let group_by = utils::group_by(music_path);
+    rename_album_folders(music_dir, group_by, dry_run, quiet, |album_path| {
+        let Some((artist, album)) = canonical_artist_album(album_path, naming) else {
+            if !quiet {
+                info!(
+                    "Skipping {}: no usable artist/album tags",
+                    album_path.display()
+                );
+            }
+            return None;
+        };
+
+        Some(group_by.album_dir(
+            &artists_path,
+            &artist,
+            &album,
+            utils::iter_album_tracks(album_path),
+        ))
+    })
+}
+
+/// Rename album folders whose names carry a year (`Abbey Road (1969)`, `[1969] Abbey Road`,
+/// ...) to the `<year> - <album>` form `--group-by artist-year` uses, so a library that mixes
+/// placements reads consistently; in a flat layout the folder becomes `<artist> - <year> - <album>`
+/// The year is taken from the tracks' tags (which sync fills from MusicBrainz) when they have
+/// one, and otherwise from the folder name. Folders without a year in their name are left
+/// alone, and `Albums/` and `Tracks/` symlinks follow renamed folders
+/// Returns the renames that were (or would be, in a dry run) made
+pub fn normalize_album_folder_years(
+    music_dir: &str,
+    dry_run: bool,
+    quiet: bool,
+) -> Result<Vec<FileMove>> {
+    let music_path = Path::new(music_dir);
+    let artists_path = music_path.join("Artists");
+
+    if !artists_path.is_dir() {
+        return Err(anyhow::anyhow!(
+            "Artists directory '{}' does not exist. Run organize first to create the directory structure.",
+            artists_path.display()
+        ));
+    }
+
+    let group_by = utils::group_by(music_path);
+    rename_album_folders(music_dir, group_by, dry_run, quiet, |album_path| {
+        year_normalized_path(album_path, group_by, quiet)
+    })
+}
+
+/// Rename each album folder to the path `target_for` gives it, skipping albums it has no
+/// target for and targets that are already taken
+fn rename_album_folders<F>(
+    music_dir: &str,
+    group_by: GroupBy,
+    dry_run: bool,
+    quiet: bool,
+    mut target_for: F,
+) -> Result<Vec<FileMove>>
+where
+    F: FnMut(&Path) -> Option<PathBuf>,
+{
+    let music_path = Path::new(music_dir);
+    let mut renames = Vec::new();
+    let mut failures = Vec::new();
+
+    for album_path in utils::get_all_album_paths(music_dir)? {
+        let Some(target_path) = target_for(&album_path) else {
+            continue;
+        };
+        if target_path == album_path {
+            continue;
+        }
+
+        if target_path.exists() {
+            warn!(
+                "Not renaming {} -> {}: destination already exists",
+                album_path.display(),
+                target_path.display()
+            );
+            continue;
+        }
+
+        if dry_run {
+            if !quiet {
+                info!(
+                    "Would rename: {} -> {}",
+                    album_path.display(),
+                    target_path.display()
+                );
+            }
+        } else if let Err(e) = rename_album_folder(music_path, &album_path, &target_path, group_by)
+        {
+            failures.push(FileFailure::new(&album_path, e));
+            continue;
+        } else if !quiet {
+            info!(
+                "Renamed: {} -> {}",
+                album_path.display(),
+                target_path.display()
+            );
+        }
+
+        renames.push(FileMove {
+            from: album_path,
+            to: target_path,
+        });
+    }
+
+    directory::report_failures(&failures, "renamed");
+
+    if dry_run && !quiet {
+        info!("\nThis was a dry run. No folders were actually renamed.");
+        info!("Run without --dry-run to perform the actual renames.");
+    }
+
+    Ok(renames)
+}
+
+/// Where `album_path` goes with the year in its name normalized, or None if it has no year
+fn year_normalized_path(album_path: &Path, group_by: GroupBy, quiet: bool) -> Option<PathBuf> {
+    let folder = album_path.file_name()?.to_str()?;
+    let (artist, album_folder) = match group_by {
+        GroupBy::Flat => {
+            let (artist, album_folder) = folder.split_once(" - ")?;
+            (Some(artist), album_folder)
+        }
+        GroupBy::Artist | GroupBy::ArtistYear => (None, folder),
+    };
+    let (folder_year, album) = split_folder_year(album_folder)?;
+
+    let year = match album_tag_year(album_path) {
+        Some(tag_year) if tag_year != folder_year => {
+            if !quiet {
+                info!(
+                    "{}: folder says {} but the tags say {}; using {}",
+                    album_path.display(),
+                    folder_year,
+                    tag_year,
+                    tag_year
+                );
+            }
+            tag_year
+        }
+        _ => folder_year,
+    };
+    let name = match artist {
+        Some(artist) => format!("{} - {} - {}", artist, year, album),
+        None => format!("{} - {}", year, album),
+    };
+    Some(album_path.with_file_name(name))
+}
+
+/// Split a year out of an album folder name, wherever the common layouts put it:
+/// `1969 - Abbey Road`, `[1969] Abbey Road`, `(1969) Abbey Road`, `Abbey Road (1969)`,
+/// `Abbey Road [1969]` or `Abbey Road - 1969`
+fn split_folder_year(name: &str) -> Option<(u32, &str)> {
+    let year = |text: &str| -> Option<u32> {
+        let valid = text.len() == 4
+            && text.bytes().all(|b| b.is_ascii_digit())
+            && matches!(text.as_bytes()[0], b'1' | b'2');
+        valid.then(|| text.parse().ok()).flatten()
+    };
+
+    let prefixed = [("", " - "), ("[", "] "), ("(", ") ")]
+        .iter()
+        .find_map(|&(open, close)| {
+            let rest = name.strip_prefix(open)?;
+            let year = year(rest.get(..4)?)?;
+            Some((year, rest.get(4..)?.strip_prefix(close)?))
+        });
+    let suffixed = || {
+        [(" (", ")"), (" [", "]"), (" - ", "")]
+            .iter()
+            .find_map(|&(open, close)| {
+                let rest = name.strip_suffix(close)?;
+                let split = rest.len().checked_sub(4)?;
+                let year = year(rest.get(split..)?)?;
+                Some((year, rest.get(..split)?.strip_suffix(open)?))
+            })
+    };
+    prefixed
+        .or_else(suffixed)
+        .filter(|(_, album)| !album.trim().is_empty())
+}
+
+/// The release year most of an album's tagged tracks agree on
+fn album_tag_year(album_path: &Path) -> Option<u32> {
+    let mut counts: FxHashMap<u32, usize> = FxHashMap::default();
+    for track in utils::iter_album_tracks(album_path) {
+        if let Some(year) = metadata::read_year(&track) {
+            *counts.entry(year).or_default() += 1;
+        }
+    }
+    counts
+        .into_iter()
+        .max_by(|(a_year, a_count), (b_year, b_count)| {
+            a_count.cmp(b_count).then_with(|| b_year.cmp(a_year))
+        })
+        .map(|(year, _)| year)
+}
+
+/// Pick the artist/album most of an album's tagged files agree on, sanitized for use as folder names
+fn canonical_artist_album(album_path: &Path, naming: &Naming) -> Option<(String, String)> {
+    let mut counts: FxHashMap<(String, String), usize> = FxHashMap::default();
+
+    for entry in WalkDir::new(album_path).into_iter().filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if !path.is_file() || !audio::is_audio_file(path) {
+            continue;
+        }
+
+        let Some((artist, album)) = metadata::read_artist_album_tags(path) else {
+            continue;
+        };
+        let artist = naming.sanitize(&metadata::normalize_tag_text(&artist, false));
+        let album = naming.sanitize(&metadata::normalize_tag_text(&album, false));
+
+        if is_usable_folder_name(&artist, "Unknown Artist")
+            && is_usable_folder_name(&album, "Unknown Album")
+        {
+            *counts.entry((artist, album)).or_default() += 1;
+        }
+    }
+
+    counts
+        .into_iter()
+        .max_by(|(a_key, a_count), (b_key, b_count)| {
+            a_count.cmp(b_count).then_with(|| b_key.cmp(a_key))
+        })
+        .map(|(key, _)| key)
+}
+
+/// Reject empty, placeholder and path-traversing names
+fn is_usable_folder_name(name: &str, placeholder: &str) -> bool {
+    !name.is_empty() && name != placeholder && name != "." && name != ".."
+}
+
+/// Move an album folder to its new location and point existing symlinks at it
+fn rename_album_folder(
+    music_path: &Path,
+    album_path: &Path,
+    target_path: &Path,
+    group_by: GroupBy,
+) -> Result<()> {
+    if let Some(artist_path) = target_path.parent() {
+        fs::create_dir_all(artist_path).with_context(|| {
+            format!(
+                "Failed to create artist directory '{}'",
+                artist_path.display()
+            )
+        })?;
+    }
+
+    fs::rename(album_path, target_path).with_context(|| {
+        format!(
+            "Failed to rename '{}' to '{}'",
+            album_path.display(),
+            target_path.display()
+        )
+    })?;
+
+    // Drop the old artist folder if this was its last album; a flat layout has none
+    if let Some(old_artist_path) = album_path.parent() {
+        if group_by.depth() > 1 && fs::read_dir(old_artist_path)?.next().is_none() {
+            fs::remove_dir(old_artist_path)?;
+        }
+    }
+
+    update_album_symlinks(
+        &music_path.join("Albums"),
+        album_path,
+        target_path,
+        group_by,
+    )?;
+    update_track_symlinks(&music_path.join("Tracks"), album_path, target_path)?;
+
+    Ok(())
+}
+
+/// Collect the symlinks directly inside `dir` along with their targets
+pub(crate) fn read_symlinks(dir: &Path) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let mut links = Vec::new();
+    if !dir.is_dir() {
+        return Ok(links);
+    }
+
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_symlink() {
+            let target = fs::read_link(entry.path())?;
+            links.push((entry.path(), target));
+        }
+    }
+
+    Ok(links)
+}
+
+/// Replace `Albums/` links to the old folder with a link named after the new artist/album
+fn update_album_symlinks(
+    albums_path: &Path,
+    old_path: &Path,
+    new_path: &Path,
+    group_by: GroupBy,
+) -> Result<()> {
+    let (artist_name, album_name) = group_by.album_names(new_path);
+    let new_link = albums_path.join(format!("{} - {}", artist_name, album_name));
+
+    for (link, target) in read_symlinks(albums_path)? {
+        if target != old_path {
+            continue;
+        }
+
+        fs::remove_file(&link)?;
+        if new_link.is_symlink() {
+            fs::remove_file(&new_link)?;
+        }
+        symlink(new_path, &new_link).with_context(|| {
+            format!(
+                "Failed to create symlink from '{}' to '{}'",
+                new_link.display(),
+                new_path.display()
+            )
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Re-point `Tracks/` links into the old folder at the same file in the new folder
+fn update_track_symlinks(tracks_path: &Path, old_path: &Path, new_path: &Path) -> Result<()> {
+    for (link, target) in read_symlinks(tracks_path)? {
+        let Ok(relative) = target.strip_prefix(old_path) else {
+            continue;
+        };
+
+        fs::remove_file(&link)?;
+        symlink(new_path.join(relative), &link)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::write_tagged_wav;
+    use lofty::tag::ItemKey;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_rename_folders_to_match_tags() -> Result<()> {
+        for dry_run in [true, false] {
+            let temp_dir = TempDir::new()?;
+            let music_root = temp_dir.path().join("Music");
+            let artist_dir = music_root.join("Artists").join("Band");
+            let misnamed = artist_dir.join("Recrod");
+            let renamed = artist_dir.join("Record");
+            fs::create_dir_all(&misnamed)?;
+            write_tagged_wav(
+                &misnamed.join("01.wav"),
+                &[
+                    (ItemKey::AlbumArtist, "Band"),
+                    (ItemKey::AlbumTitle, "Record"),
+                ],
+            )?;
+            write_tagged_wav(
+                &misnamed.join("02.wav"),
+                &[
+                    (ItemKey::AlbumArtist, "Band"),
+                    (ItemKey::AlbumTitle, "Record"),
+                ],
+            )?;
+            let albums_dir = music_root.join("Albums");
+            fs::create_dir_all(&albums_dir)?;
+            symlink(&misnamed, albums_dir.join("Band - Recrod"))?;
+
+            let renames = rename_folders_to_match_tags(
+                music_root.to_str().unwrap(),
+                &Naming::default(),
+                dry_run,
+                true,
+            )?;
+
+            assert_eq!(
+                renames,
+                [FileMove {
+                    from: misnamed.clone(),
+                    to: renamed.clone(),
+                }],
+                "dry run: {}",
+                dry_run
+            );
+            if dry_run {
+                assert!(misnamed.join("01.wav").exists());
+                assert!(!renamed.exists());
+                assert_eq!(fs::read_link(albums_dir.join("Band - Recrod"))?, misnamed);
+                assert!(!albums_dir.join("Band - Record").is_symlink());
+            } else {
+                assert!(!misnamed.exists());
+                assert!(renamed.join("01.wav").exists());
+                assert!(renamed.join("02.wav").exists());
+                assert!(!albums_dir.join("Band - Recrod").is_symlink());
+                assert_eq!(fs::read_link(albums_dir.join("Band - Record"))?, renamed);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename_folders_skips_untagged_albums() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+        let artist_dir = music_root.join("Artists").join("SomeArtist");
+        let album_dir = artist_dir.join("Typo Album");
+        fs::create_dir_all(&album_dir)?;
+        fs::File::create(album_dir.join("track.mp3"))?.write_all(b"untagged audio")?;
+
+        let renames = rename_folders_to_match_tags(
+            music_root.to_str().unwrap(),
+            &Naming::default(),
+            false,
+            true,
+        )?;
+
+        assert!(renames.is_empty());
+        assert!(album_dir.exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename_folders_requires_artists_directory() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+
+        let result = rename_folders_to_match_tags(
+            music_root.to_str().unwrap(),
+            &Naming::default(),
+            true,
+            true,
+        );
+
+        assert!(result.is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename_album_folder_updates_symlinks() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+        let artists_dir = music_root.join("Artists");
+        let old_album = artists_dir.join("Artist").join("Albmu");
+        let new_album = artists_dir.join("Artist").join("Album");
+        fs::create_dir_all(&old_album)?;
+        fs::File::create(old_album.join("track.mp3"))?.write_all(b"audio")?;
+
+        let albums_dir = music_root.join("Albums");
+        let tracks_dir = music_root.join("Tracks");
+        fs::create_dir_all(&albums_dir)?;
+        fs::create_dir_all(&tracks_dir)?;
+        symlink(&old_album, albums_dir.join("Artist - Albmu"))?;
+        symlink(old_album.join("track.mp3"), tracks_dir.join("track.mp3"))?;
+
+        rename_album_folder(&music_root, &old_album, &new_album, GroupBy::Artist)?;
+
+        assert!(!old_album.exists());
+        assert!(new_album.join("track.mp3").exists());
+        assert!(!albums_dir.join("Artist - Albmu").is_symlink());
+        assert_eq!(fs::read_link(albums_dir.join("Artist - Album"))?, new_album);
+        assert_eq!(
+            fs::read_link(tracks_dir.join("track.mp3"))?,
+            new_album.join("track.mp3")
+        );
+
+        Ok(())
+    }
+    #[test]
+    fn test_split_folder_year_handles_common_placements() {
+        for name in [
+            "1969 - Abbey Road",
+            "[1969] Abbey Road",
+            "(1969) Abbey Road",
+            "Abbey Road (1969)",
+            "Abbey Road [1969]",
+            "Abbey Road - 1969",
+        ] {
+            assert_eq!(
+                split_folder_year(name),
+                Some((1969, "Abbey Road")),
+                "{}",
+                name
+            );
+        }
+        // A title that is only a number is not a year with nothing after it
+        assert_eq!(split_folder_year("1984"), None);
+        assert_eq!(split_folder_year("1976 - 2112"), Some((1976, "2112")));
+        assert_eq!(split_folder_year("Abbey Road"), None);
+        assert_eq!(split_folder_year("Live 0042"), None);
+    }
+
+    #[test]
+    fn test_normalize_album_folder_years() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+        let artist_dir = music_root.join("Artists").join("The Beatles");
+        let albums_dir = music_root.join("Albums");
+        fs::create_dir_all(&albums_dir)?;
+
+        // Untagged, so the folder's year stands
+        let abbey_road = artist_dir.join("Abbey Road (1969)");
+        fs::create_dir_all(&abbey_road)?;
+        fs::File::create(abbey_road.join("01.mp3"))?.write_all(b"audio")?;
+        symlink(
+            &abbey_road,
+            albums_dir.join("The Beatles - Abbey Road (1969)"),
+        )?;
+        // The tags disagree with the folder and win
+        let help = artist_dir.join("[1964] Help!");
+        fs::create_dir_all(&help)?;
+        write_tagged_wav(&help.join("01.wav"), &[(ItemKey::RecordingDate, "1965")])?;
+        // Already normalized, and no year at all
+        let revolver = artist_dir.join("1966 - Revolver");
+        let let_it_be = artist_dir.join("Let It Be");
+        for album in [&revolver, &let_it_be] {
+            fs::create_dir_all(album)?;
+            fs::File::create(album.join("01.mp3"))?.write_all(b"audio")?;
+        }
+
+        let mut renames = normalize_album_folder_years(music_root.to_str().unwrap(), false, true)?;
+        renames.sort_by(|a, b| a.from.cmp(&b.from));
+
+        let normalized_abbey_road = artist_dir.join("1969 - Abbey Road");
+        let normalized_help = artist_dir.join("1965 - Help!");
+        assert_eq!(
+            renames,
+            [
+                FileMove {
+                    from: abbey_road,
+                    to: normalized_abbey_road.clone(),
+                },
+                FileMove {
+                    from: help,
+                    to: normalized_help.clone(),
+                },
+            ]
+        );
+        assert!(normalized_abbey_road.join("01.mp3").exists());
+        assert!(normalized_help.join("01.wav").exists());
+        assert!(revolver.exists());
+        assert!(let_it_be.exists());
+        assert_eq!(
+            fs::read_link(albums_dir.join("The Beatles - 1969 - Abbey Road"))?,
+            normalized_abbey_road
+        );
+        assert!(!albums_dir
+            .join("The Beatles - Abbey Road (1969)")
+            .is_symlink());
+
+        Ok(())
+    }
+}
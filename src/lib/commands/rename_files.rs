@@ -0,0 +1,349 @@
+use crate::commands::rename::read_symlinks;
+use crate::directory::{self, FileFailure, FileMove};
+use crate::metadata::{self, TrackFields};
+use crate::tag_backup;
+use crate::utils::{self, Naming};
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::fs;
+use std::os::unix::fs::symlink;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tracing::{info, warn};
+
+/// File name template used when `--file-template` is not given
+pub const DEFAULT_FILE_TEMPLATE: &str = "{track:02} - {title}";
+
+/// A parsed file name template such as `{track:02} - {title}`
+/// Fields are `track`, `disc`, `title`, `artist`, `albumartist`, `album` and `year`; a number
+/// after `:` zero-pads `track` and `disc` to that width
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileTemplate {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Field(Field, usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Track,
+    Disc,
+    Title,
+    Artist,
+    AlbumArtist,
+    Album,
+    Year,
+}
+
+impl FileTemplate {
+    /// Build the file name (without extension) for a track with `fields`, made safe by `naming`
+    /// Returns None if a field the template uses is missing, or the result is not a usable name
+    pub fn render(&self, fields: &TrackFields, naming: &Naming) -> Option<String> {
+        let text = |value: &Option<String>| {
+            value
+                .as_deref()
+                .map(|v| metadata::normalize_tag_text(v, false))
+        };
+        let mut name = String::new();
+        for segment in &self.segments {
+            let value = match segment {
+                Segment::Literal(literal) => literal.clone(),
+                Segment::Field(field, width) => match field {
+                    Field::Track => format!("{:0width$}", fields.track?, width = *width),
+                    Field::Disc => format!("{:0width$}", fields.disc?, width = *width),
+                    Field::Title => text(&fields.title)?,
+                    Field::Artist => text(&fields.artist)?,
+                    Field::AlbumArtist => text(&fields.album_artist)?,
+                    Field::Album => text(&fields.album)?,
+                    Field::Year => format!("{:0width$}", fields.year?, width = *width),
+                },
+            };
+            name.push_str(&value);
+        }
+
+        let name = naming.sanitize(&name);
+        (!name.is_empty() && name != "." && name != "..").then_some(name)
+    }
+}
+
+impl FromStr for FileTemplate {
+    type Err = String;
+
+    fn from_str(template: &str) -> std::result::Result<Self, Self::Err> {
+        let mut segments = Vec::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                segments.push(Segment::Literal(rest[..start].to_string()));
+            }
+            let end = rest[start..]
+                .find('}')
+                .map(|i| start + i)
+                .ok_or_else(|| format!("unclosed '{{' in file template '{}'", template))?;
+            let placeholder = &rest[start + 1..end];
+            let (name, width) = match placeholder.split_once(':') {
+                Some((name, width)) => {
+                    let width = width
+                        .parse()
+                        .map_err(|_| format!("invalid width in {{{}}}", placeholder))?;
+                    (name, width)
+                }
+                None => (placeholder, 0),
+            };
+            let field = match name {
+                "track" => Field::Track,
+                "disc" => Field::Disc,
+                "title" => Field::Title,
+                "artist" => Field::Artist,
+                "albumartist" => Field::AlbumArtist,
+                "album" => Field::Album,
+                "year" => Field::Year,
+                other => {
+                    return Err(format!(
+                        "unknown field {{{}}} (expected track, disc, title, artist, albumartist, album or year)",
+                        other
+                    ))
+                }
+            };
+            segments.push(Segment::Field(field, width));
+            rest = &rest[end + 1..];
+        }
+        if !rest.is_empty() {
+            segments.push(Segment::Literal(rest.to_string()));
+        }
+
+        if !segments.iter().any(|s| matches!(s, Segment::Field(..))) {
+            return Err(format!("file template '{}' uses no tag fields", template));
+        }
+        Ok(FileTemplate { segments })
+    }
+}
+
+/// Rename every track under `music_dir` to `template` filled in from its tags, keeping its
+/// folder and extension
+/// Files missing a field the template uses are skipped, as are renames onto an existing
+/// file. `Tracks/` links to a renamed file are replaced by a link under its new name.
+/// Returns the renames that were (or would be, in a dry run) made
+pub fn rename_files_to_template(
+    music_dir: &str,
+    template: &FileTemplate,
+    naming: &Naming,
+    dry_run: bool,
+    quiet: bool,
+) -> Result<Vec<FileMove>> {
+    let music_path = Path::new(music_dir);
+    let artists_path = music_path.join("Artists");
+
+    if !artists_path.is_dir() {
+        return Err(anyhow::anyhow!(
+            "Artists directory '{}' does not exist. Run organize first to create the directory structure.",
+            artists_path.display()
+        ));
+    }
+
+    // Collected up front so renamed files are not visited again
+    let tracks: Vec<PathBuf> = utils::iter_tracks(music_dir).collect();
+    let mut renames = Vec::new();
+    let mut failures = Vec::new();
+    let mut claimed: HashSet<PathBuf> = HashSet::new();
+
+    for track in tracks {
+        let Some(stem) =
+            metadata::read_track_fields(&track).and_then(|f| template.render(&f, naming))
+        else {
+            if !quiet {
+                info!(
+                    "Skipping {}: tags lack a field the template needs",
+                    track.display()
+                );
+            }
+            continue;
+        };
+        let file_name = match track.extension().and_then(|e| e.to_str()) {
+            Some(extension) => format!("{}.{}", stem, extension),
+            None => stem,
+        };
+        let target = track.with_file_name(file_name);
+        if target == track {
+            continue;
+        }
+
+        if target.exists() || claimed.contains(&target) {
+            warn!(
+                "Not renaming {} -> {}: destination already exists",
+                track.display(),
+                target.display()
+            );
+            continue;
+        }
+
+        if dry_run {
+            if !quiet {
+                info!("Would rename: {} -> {}", track.display(), target.display());
+            }
+        } else if let Err(e) = rename_track_file(music_path, &track, &target) {
+            failures.push(FileFailure::new(&track, format!("{:#}", e)));
+            continue;
+        } else if !quiet {
+            info!("Renamed: {} -> {}", track.display(), target.display());
+        }
+
+        claimed.insert(target.clone());
+        renames.push(FileMove {
+            from: track,
+            to: target,
+        });
+    }
+
+    directory::report_failures(&failures, "renamed");
+
+    if dry_run && !quiet {
+        info!("\nThis was a dry run. No files were actually renamed.");
+        info!("Run without --dry-run to perform the actual renames.");
+    }
+
+    Ok(renames)
+}
+
+/// Rename a track, taking its tag backup along and moving `Tracks/` links to the new name
+fn rename_track_file(music_path: &Path, track: &Path, target: &Path) -> Result<()> {
+    fs::rename(track, target).with_context(|| {
+        format!(
+            "Failed to rename '{}' to '{}'",
+            track.display(),
+            target.display()
+        )
+    })?;
+
+    // Backups are keyed by file name, so one left behind would no longer be found
+    if let (Some(old_backup), Some(new_backup)) = (
+        tag_backup::backup_path(track),
+        tag_backup::backup_path(target),
+    ) {
+        if old_backup.exists() && !new_backup.exists() {
+            fs::rename(&old_backup, &new_backup)?;
+        }
+    }
+
+    let tracks_path = music_path.join("Tracks");
+    let new_link = tracks_path.join(target.file_name().context("Target has no file name")?);
+    for (link, link_target) in read_symlinks(&tracks_path)? {
+        if link_target != track {
+            continue;
+        }
+
+        fs::remove_file(&link)?;
+        if new_link.is_symlink() {
+            fs::remove_file(&new_link)?;
+        }
+        symlink(target, &new_link).with_context(|| {
+            format!(
+                "Failed to create symlink from '{}' to '{}'",
+                new_link.display(),
+                target.display()
+            )
+        })?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::write_silent_wav;
+    use lofty::config::WriteOptions;
+    use lofty::file::{AudioFile, TaggedFileExt};
+    use lofty::tag::{Accessor, Tag, TagType};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_file_template_rendering() {
+        let fields = TrackFields {
+            track: Some(3),
+            disc: Some(2),
+            title: Some("  Who Are You? ".to_string()),
+            artist: Some("The Who".to_string()),
+            ..TrackFields::default()
+        };
+        let naming = Naming::default();
+
+        let template: FileTemplate = DEFAULT_FILE_TEMPLATE.parse().unwrap();
+        assert_eq!(
+            template.render(&fields, &naming).as_deref(),
+            Some("03 - Who Are You_")
+        );
+        let deleting = Naming {
+            sanitize_replacement: String::new(),
+            ..Naming::default()
+        };
+        assert_eq!(
+            template.render(&fields, &deleting).as_deref(),
+            Some("03 - Who Are You")
+        );
+        let template: FileTemplate = "{disc}-{track:03} {artist} - {title}".parse().unwrap();
+        assert_eq!(
+            template.render(&fields, &naming).as_deref(),
+            Some("2-003 The Who - Who Are You_")
+        );
+
+        // A field the template needs is missing
+        let template: FileTemplate = "{track} - {album}".parse().unwrap();
+        assert_eq!(template.render(&fields, &naming), None);
+
+        assert!("{track} - {name}".parse::<FileTemplate>().is_err());
+        assert!("{track:xx}".parse::<FileTemplate>().is_err());
+        assert!("{track".parse::<FileTemplate>().is_err());
+        assert!("no fields".parse::<FileTemplate>().is_err());
+    }
+
+    #[test]
+    fn test_rename_files_updates_track_symlinks() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+        let album_dir = music_root.join("Artists").join("Artist").join("Album");
+        fs::create_dir_all(&album_dir)?;
+        let tagged = album_dir.join("track01.wav");
+        write_silent_wav(&tagged)?;
+        let mut tagged_file = lofty::read_from_path(&tagged)?;
+        let mut tag = Tag::new(TagType::Id3v2);
+        tag.set_track(1);
+        tag.set_title("Opening".to_string());
+        tagged_file.insert_tag(tag);
+        tagged_file.save_to_path(&tagged, WriteOptions::default())?;
+        let untagged = album_dir.join("track02.wav");
+        write_silent_wav(&untagged)?;
+
+        let tracks_dir = music_root.join("Tracks");
+        fs::create_dir_all(&tracks_dir)?;
+        symlink(&tagged, tracks_dir.join("track01.wav"))?;
+
+        let music_dir = music_root.to_str().unwrap();
+        let template: FileTemplate = DEFAULT_FILE_TEMPLATE.parse().unwrap();
+        let naming = Naming::default();
+        let renamed = album_dir.join("01 - Opening.wav");
+
+        let preview = rename_files_to_template(music_dir, &template, &naming, true, true)?;
+        assert_eq!(preview.len(), 1);
+        assert_eq!(preview[0].to, renamed);
+        assert!(tagged.exists());
+
+        let renames = rename_files_to_template(music_dir, &template, &naming, false, true)?;
+        assert_eq!(renames.len(), 1);
+        assert!(!tagged.exists());
+        assert!(renamed.exists());
+        // The untagged file has no title to build a name from
+        assert!(untagged.exists());
+        assert!(!tracks_dir.join("track01.wav").is_symlink());
+        assert_eq!(fs::read_link(tracks_dir.join("01 - Opening.wav"))?, renamed);
+
+        // Already named after the template
+        assert!(rename_files_to_template(music_dir, &template, &naming, false, true)?.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,775 @@
+use crate::audio;
+use crate::commands::organize::OrganizeOptions;
+use crate::directory::{self, FileFailure, OrganizeSummary};
+use crate::metadata;
+use crate::musicbrainz;
+use crate::utils::{self, GroupBy};
+use anyhow::Result;
+use rayon::prelude::*;
+use rustc_hash::FxHashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::info;
+use walkdir::WalkDir;
+
+/// Reorganize files that are not in their correct artist/album structure
+/// This function finds files that are misplaced and moves them to their proper locations,
+/// naming folders as `options` say, the same way
+/// [`organize_music_library`](crate::organize_music_library) does
+/// When `copy` is set, files are copied into place and the originals are left untouched
+/// When `quarantine` is given, each file is first kept there at its path relative to
+/// `music_dir` (see [`directory::quarantine_file`]), and is not moved if that fails
+/// Returns a summary of the files that were (or would be, in a dry run) reorganized
+pub fn reorganize_misplaced_files(
+    music_dir: &str,
+    options: &OrganizeOptions,
+    copy: bool,
+    quarantine: Option<&Path>,
+) -> Result<OrganizeSummary> {
+    let &OrganizeOptions {
+        dry_run,
+        quiet,
+        ascii_punctuation,
+        strict,
+        mb_folder_names,
+        path_only,
+        ref naming,
+        since,
+        ref network,
+    } = options;
+    let music_path = Path::new(music_dir);
+    let artists_path = music_path.join("Artists");
+
+    if !artists_path.exists() || !artists_path.is_dir() {
+        return Err(anyhow::anyhow!(
+            "Artists directory '{}' does not exist. Run organize first to create the directory structure.",
+            artists_path.display()
+        ));
+    }
+
+    let group_by = naming.layout(music_path);
+    if !dry_run {
+        utils::record_group_by(music_path, group_by)?;
+    }
+
+    if !quiet {
+        info!("Scanning for misplaced files to reorganize...");
+    }
+
+    let mut files_to_move = Vec::new();
+
+    // Walk through the music directory and find audio files
+    for entry in WalkDir::new(music_path).into_iter().filter_map(|e| e.ok()) {
+        let path = entry.path();
+
+        // Skip the Artists directory and its contents - these are already organized - except
+        // tracks lying loose in an artist folder, which still need an album folder
+        if path.starts_with(&artists_path) && !is_loose_artist_track(path, &artists_path, group_by)
+        {
+            continue;
+        }
+        // Quarantined originals are kept as they were, not reorganized again
+        if quarantine.is_some_and(|quarantine| path.starts_with(quarantine)) {
+            continue;
+        }
+
+        // Only process audio files, and with --since only recently modified ones
+        if path.is_file() && audio::is_audio_file(path) && utils::is_modified_since(path, since) {
+            files_to_move.push(path.to_path_buf());
+        }
+    }
+
+    if files_to_move.is_empty() {
+        if !quiet {
+            info!("No misplaced files found. All files are already properly organized.");
+        }
+        return Ok(OrganizeSummary::new(dry_run));
+    }
+
+    if !quiet {
+        info!(
+            "Found {} misplaced files to reorganize",
+            files_to_move.len()
+        );
+    }
+
+    // Group files by their correct artist/album based on metadata, setting aside unreadable ones
+    let extracted: Vec<_> = files_to_move
+        .into_par_iter()
+        .map(|file_path| -> Result<_, FileFailure> {
+            // Files are grouped by album artist; each keeps its own track artist tag
+            let names = if is_loose_artist_track(&file_path, &artists_path, group_by) {
+                metadata::loose_track_artist_album(&file_path, path_only)
+            } else if path_only {
+                metadata::artist_album_from_path(&file_path)
+                    .map_err(|e| FileFailure::new(&file_path, e))?
+            } else {
+                metadata::extract_artist_album_from_file(&file_path)
+                    .map_err(|e| FileFailure::new(&file_path, e))?
+            };
+            let names = if path_only {
+                names
+            } else {
+                metadata::file_under_composer(&file_path, names, naming)
+            };
+            let metadata::ArtistAlbum {
+                album_artist: artist,
+                album,
+                ..
+            } = names;
+            let artist = metadata::normalize_folder_text(
+                &artist,
+                ascii_punctuation,
+                metadata::UNKNOWN_ARTIST,
+            );
+            let album =
+                metadata::normalize_folder_text(&album, ascii_punctuation, metadata::UNKNOWN_ALBUM);
+            let clean_artist = naming.sanitize(&artist);
+            let clean_album = naming.sanitize(&album);
+            Ok((file_path, clean_artist, clean_album))
+        })
+        .collect();
+
+    let mut summary = OrganizeSummary::new(dry_run);
+    let mut processed_files = Vec::with_capacity(extracted.len());
+    for result in extracted {
+        match result {
+            Ok(file) => processed_files.push(file),
+            Err(failure) => summary.failures.push(failure),
+        }
+    }
+
+    let mut file_groups: FxHashMap<(String, String), Vec<PathBuf>> = FxHashMap::default();
+    for (file_path, clean_artist, clean_album) in &processed_files {
+        if metadata::is_unknown_placeholder(clean_artist, clean_album) {
+            summary.unknown_placeholders += 1;
+            if strict {
+                if !quiet {
+                    info!(
+                        "Leaving in place (unknown artist/album): {}",
+                        file_path.display()
+                    );
+                }
+                continue;
+            }
+        }
+        file_groups.entry((clean_artist.clone(), clean_album.clone())).or_default().push(file_path.clone());
+        if dry_run && !quiet {
+            info!(
+                "Would reorganize: {} -> {} / {}",
+                file_path.display(),
+                clean_artist,
+                clean_album
+            );
+        }
+    }
+    if mb_folder_names {
+        file_groups = musicbrainz::apply_musicbrainz_folder_names(
+            file_groups,
+            ascii_punctuation,
+            naming,
+            network,
+            quiet,
+        )?;
+    }
+    summary.tree = directory::AlbumTree::from_groups(&file_groups);
+    let file_groups: directory::DiscGroups = if naming.split_boxsets && !path_only {
+        directory::split_boxset_groups(file_groups, naming, quiet)
+    } else {
+        file_groups
+            .into_iter()
+            .map(|((artist, album), files)| ((artist, album, None), files))
+            .collect()
+    };
+    let total_processed = processed_files.len();
+
+    if !quiet && dry_run {
+        info!(
+            "Found {} unique artist/album combinations for {} files",
+            file_groups.len(),
+            total_processed
+        );
+    }
+
+    // Move files to their correct locations
+    let total_groups = file_groups.len();
+
+    for ((artist, album, disc_folder), files) in file_groups {
+        let tagged_files: &[PathBuf] = if path_only { &[] } else { &files };
+        let album_path = group_by.album_dir(&artists_path, &artist, &album, tagged_files);
+        let album_path = match disc_folder {
+            Some(disc_folder) => album_path.join(disc_folder),
+            None => album_path,
+        };
+
+        if !album_path.exists() {
+            summary.dirs_created += 1;
+        }
+
+        if dry_run {
+            if !quiet {
+                info!("Would create directory: {}", album_path.display());
+            }
+            let action = if copy { "copy" } else { "move" };
+            for file in &files {
+                if !quiet {
+                    info!(
+                        "  Would {}: {} -> {}",
+                        action,
+                        file.display(),
+                        album_path.display()
+                    );
+                }
+                match directory::destination_path(file, &album_path, naming) {
+                    Ok(dest_path) => summary.record_move(file, &dest_path),
+                    Err(e) => summary
+                        .failures
+                        .push(FileFailure::new(file, format!("{:#}", e))),
+                }
+            }
+        } else {
+            // Create directories if they don't exist
+            if let Err(e) = fs::create_dir_all(&album_path) {
+                let reason = format!(
+                    "Failed to create album directory '{}': {}",
+                    album_path.display(),
+                    e
+                );
+                summary
+                    .failures
+                    .extend(files.iter().map(|file| FileFailure::new(file, &reason)));
+                continue;
+            }
+
+            // Move each file
+            for file_path in files {
+                let dest_path = match directory::destination_path(&file_path, &album_path, naming) {
+                    Ok(dest_path) => dest_path,
+                    Err(e) => {
+                        summary
+                            .failures
+                            .push(FileFailure::new(&file_path, format!("{:#}", e)));
+                        continue;
+                    }
+                };
+
+                // Only move if the destination doesn't already exist
+                if dest_path.exists() {
+                    if !quiet {
+                        info!(
+                            "Warning: File already exists at destination, skipping: {} -> {}",
+                            file_path.display(),
+                            dest_path.display()
+                        );
+                    }
+                    summary.files_skipped += 1;
+                    continue;
+                }
+
+                // Keep the original where it can be restored from before moving it
+                if let Some(quarantine) = quarantine.filter(|_| !copy) {
+                    if let Err(e) = directory::quarantine_file(&file_path, music_path, quarantine) {
+                        summary
+                            .failures
+                            .push(FileFailure::new(&file_path, format!("{:#}", e)));
+                        continue;
+                    }
+                }
+
+                // Move (or copy) the file
+                let method = match directory::transfer_file(&file_path, &dest_path, copy) {
+                    Ok(method) => method,
+                    Err(e) => {
+                        summary.failures.push(FileFailure::new(&file_path, e));
+                        continue;
+                    }
+                };
+
+                if !quiet {
+                    info!(
+                        "Reorganized ({}): {} -> {}",
+                        method,
+                        file_path.display(),
+                        dest_path.display()
+                    );
+                }
+                summary.record_move(&file_path, &dest_path);
+            }
+        }
+    }
+
+    directory::report_unknown_placeholders(summary.unknown_placeholders, strict);
+    directory::report_failures(&summary.failures, "reorganized");
+
+    if dry_run && !quiet {
+        info!("\nThis was a dry run. No files were actually moved.");
+        info!("Run without --dry-run to perform the actual reorganization.");
+    } else if !quiet {
+        info!("\nFile reorganization completed successfully!");
+        info!(
+            "   Reorganized {} files into {} artist/album combinations",
+            total_processed, total_groups
+        );
+    }
+
+    Ok(summary)
+}
+
+/// Whether `path` sits directly in an artist folder, `Artists/<artist>/<file>`, with no album
+/// A flat layout has no artist folders, so a file one level down is already in its album
+fn is_loose_artist_track(path: &Path, artists_path: &Path, group_by: GroupBy) -> bool {
+    group_by.depth() > 1 && path.parent().and_then(Path::parent) == Some(artists_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::write_silent_wav;
+    use lofty::config::WriteOptions;
+    use lofty::file::{AudioFile, TaggedFileExt};
+    use lofty::tag::{Accessor, Tag, TagType};
+    use std::fs;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_reorganize_misplaced_files_no_artists_dir() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+
+        // Test that it fails when Artists directory doesn't exist
+        let result = reorganize_misplaced_files(
+            music_root.to_str().unwrap(),
+            &OrganizeOptions {
+                quiet: true,
+                ..Default::default()
+            },
+            false,
+            None,
+        );
+
+        assert!(result.is_err());
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("Artists directory"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reorganize_misplaced_files_with_no_misplaced_files() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+        let artists_dir = music_root.join("Artists");
+
+        // Create proper structure with no misplaced files
+        fs::create_dir_all(&artists_dir)?;
+        let artist_dir = artists_dir.join("TestArtist");
+        fs::create_dir(&artist_dir)?;
+        let album_dir = artist_dir.join("TestAlbum");
+        fs::create_dir(&album_dir)?;
+        fs::File::create(album_dir.join("track.mp3"))?.write_all(b"audio")?;
+
+        // Test that it succeeds with no misplaced files
+        let result = reorganize_misplaced_files(
+            music_root.to_str().unwrap(),
+            &OrganizeOptions {
+                quiet: true,
+                ..Default::default()
+            },
+            false,
+            None,
+        );
+
+        assert!(result.is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reorganize_misplaced_files_dry_run() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+        let artists_dir = music_root.join("Artists");
+
+        // Create proper structure
+        fs::create_dir_all(&artists_dir)?;
+
+        // Create a misplaced file
+        let misplaced_file = music_root.join("misplaced.mp3");
+        fs::File::create(&misplaced_file)?.write_all(b"audio")?;
+
+        // Test dry run - should not actually move files
+        let summary = reorganize_misplaced_files(
+            music_root.to_str().unwrap(),
+            &OrganizeOptions {
+                dry_run: true,
+                quiet: true,
+                ..Default::default()
+            },
+            false,
+            None,
+        )?;
+
+        assert!(misplaced_file.exists()); // File should still be in original location
+        assert_eq!(summary.files_moved, 0);
+        assert_eq!(summary.moves.len(), 1);
+        assert_eq!(summary.moves[0].from, misplaced_file);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reorganize_misplaced_files_continues_past_failed_file() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+        let artists_dir = music_root.join("Artists");
+        fs::create_dir_all(&artists_dir)?;
+
+        // Two misplaced files that should be reorganized
+        let downloads_dir = music_root.join("Downloads");
+        let good_dir = downloads_dir.join("GoodArtist").join("GoodAlbum");
+        fs::create_dir_all(&good_dir)?;
+        fs::File::create(good_dir.join("track1.mp3"))?.write_all(b"audio")?;
+        fs::File::create(good_dir.join("track2.mp3"))?.write_all(b"audio")?;
+
+        // One misplaced file whose destination is blocked by a regular file
+        let bad_dir = downloads_dir.join("BadArtist").join("BadAlbum");
+        fs::create_dir_all(&bad_dir)?;
+        fs::File::create(bad_dir.join("track.mp3"))?.write_all(b"audio")?;
+        fs::create_dir(artists_dir.join("BadArtist"))?;
+        fs::File::create(artists_dir.join("BadArtist").join("BadAlbum"))?.write_all(b"blocker")?;
+
+        let summary = reorganize_misplaced_files(
+            music_root.to_str().unwrap(),
+            &OrganizeOptions {
+                quiet: true,
+                ..Default::default()
+            },
+            false,
+            None,
+        )?;
+        assert_eq!(summary.files_moved, 2);
+        assert_eq!(summary.failures.len(), 1);
+
+        let reorganized = artists_dir.join("GoodArtist").join("GoodAlbum");
+        assert!(reorganized.join("track1.mp3").exists());
+        assert!(reorganized.join("track2.mp3").exists());
+        assert!(bad_dir.join("track.mp3").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reorganize_misplaced_files_copy_keeps_originals() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+        let artists_dir = music_root.join("Artists");
+        fs::create_dir_all(&artists_dir)?;
+
+        let mounted_dir = music_root.join("Mounted");
+        let source_dir = mounted_dir.join("CopyArtist").join("CopyAlbum");
+        fs::create_dir_all(&source_dir)?;
+        let source_file = source_dir.join("track.mp3");
+        fs::File::create(&source_file)?.write_all(b"audio")?;
+
+        let result = reorganize_misplaced_files(
+            music_root.to_str().unwrap(),
+            &OrganizeOptions {
+                quiet: true,
+                ..Default::default()
+            },
+            true,
+            None,
+        );
+        assert!(result.is_ok());
+
+        assert!(source_file.exists());
+        assert!(artists_dir
+            .join("CopyArtist")
+            .join("CopyAlbum")
+            .join("track.mp3")
+            .exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reorganize_quarantine_mirrors_original_layout() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+        let artists_dir = music_root.join("Artists");
+        fs::create_dir_all(&artists_dir)?;
+        let quarantine = music_root.join("Quarantine");
+
+        let source_dir = music_root.join("Downloads").join("Singer").join("Record");
+        fs::create_dir_all(&source_dir)?;
+        fs::File::create(source_dir.join("01.mp3"))?.write_all(b"first")?;
+        fs::File::create(source_dir.join("02.mp3"))?.write_all(b"second")?;
+
+        let summary = reorganize_misplaced_files(
+            music_root.to_str().unwrap(),
+            &OrganizeOptions {
+                quiet: true,
+                ..Default::default()
+            },
+            false,
+            Some(&quarantine),
+        )?;
+        assert_eq!(summary.files_moved, 2);
+        assert!(!source_dir.join("01.mp3").exists());
+
+        let kept_dir = quarantine.join("Downloads").join("Singer").join("Record");
+        assert_eq!(fs::read(kept_dir.join("01.mp3"))?, b"first");
+        assert_eq!(fs::read(kept_dir.join("02.mp3"))?, b"second");
+        let album_dir = artists_dir.join("Singer").join("Record");
+        assert!(album_dir.join("01.mp3").exists());
+        assert!(album_dir.join("02.mp3").exists());
+
+        // The quarantine is not mistaken for misplaced files on the next run
+        let summary = reorganize_misplaced_files(
+            music_root.to_str().unwrap(),
+            &OrganizeOptions {
+                quiet: true,
+                ..Default::default()
+            },
+            false,
+            Some(&quarantine),
+        )?;
+        assert!(summary.moves.is_empty());
+        assert!(kept_dir.join("01.mp3").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reorganize_strict_leaves_unknown_album_in_place() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+        let artists_dir = music_root.join("Artists");
+        fs::create_dir_all(&artists_dir)?;
+
+        // A folder named "Music" is filtered out of the album name, so this falls back to Unknown Album
+        let untagged_dir = music_root.join("Downloads").join("Music");
+        fs::create_dir_all(&untagged_dir)?;
+        let untagged_file = untagged_dir.join("track.mp3");
+        fs::File::create(&untagged_file)?.write_all(b"audio")?;
+
+        let good_dir = music_root
+            .join("Downloads")
+            .join("GoodArtist")
+            .join("GoodAlbum");
+        fs::create_dir_all(&good_dir)?;
+        fs::File::create(good_dir.join("track.mp3"))?.write_all(b"audio")?;
+
+        let summary = reorganize_misplaced_files(
+            music_root.to_str().unwrap(),
+            &OrganizeOptions {
+                quiet: true,
+                strict: true,
+                ..Default::default()
+            },
+            false,
+            None,
+        )?;
+        assert_eq!(summary.unknown_placeholders, 1);
+        assert_eq!(summary.files_moved, 1);
+        assert!(untagged_file.exists());
+        assert!(!artists_dir.join("Downloads").exists());
+
+        // Without --strict the file is filed under the placeholder but still counted
+        let summary = reorganize_misplaced_files(
+            music_root.to_str().unwrap(),
+            &OrganizeOptions {
+                quiet: true,
+                ..Default::default()
+            },
+            false,
+            None,
+        )?;
+        assert_eq!(summary.unknown_placeholders, 1);
+        assert!(artists_dir
+            .join("Downloads")
+            .join(metadata::UNKNOWN_ALBUM)
+            .join("track.mp3")
+            .exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reorganize_groups_tracks_loose_in_artist_folder() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+        let artist_dir = music_root.join("Artists").join("Band");
+        let album_dir = artist_dir.join("Live");
+        fs::create_dir_all(&album_dir)?;
+        fs::File::create(album_dir.join("organized.mp3"))?.write_all(b"audio")?;
+
+        // Loose tracks: two tagged with their album, one with no tags at all
+        for name in ["01 Intro.wav", "02 Song.wav"] {
+            let track = artist_dir.join(name);
+            write_silent_wav(&track)?;
+            let mut tagged_file = lofty::read_from_path(&track)?;
+            let mut tag = Tag::new(TagType::Id3v2);
+            tag.set_artist("Band".to_string());
+            tag.set_album("Debut".to_string());
+            tagged_file.insert_tag(tag);
+            tagged_file.save_to_path(&track, WriteOptions::default())?;
+        }
+        fs::File::create(artist_dir.join("demo.mp3"))?.write_all(b"audio")?;
+
+        let summary = reorganize_misplaced_files(
+            music_root.to_str().unwrap(),
+            &OrganizeOptions {
+                quiet: true,
+                ..Default::default()
+            },
+            false,
+            None,
+        )?;
+
+        assert_eq!(summary.files_moved, 3);
+        assert!(artist_dir.join("Debut").join("01 Intro.wav").exists());
+        assert!(artist_dir.join("Debut").join("02 Song.wav").exists());
+        assert!(artist_dir
+            .join(metadata::SINGLES_ALBUM)
+            .join("demo.mp3")
+            .exists());
+        // Tracks already in an album folder are left alone
+        assert!(album_dir.join("organized.mp3").exists());
+        let album_paths = utils::get_all_album_paths(music_root.to_str().unwrap())?;
+        assert_eq!(album_paths.len(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reorganize_runs_without_ffmpeg() -> Result<()> {
+        use crate::commands::art;
+
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+        let artist_dir = music_root.join("Artists").join("Band");
+        fs::create_dir_all(&artist_dir)?;
+        let track = artist_dir.join("01 Intro.wav");
+        write_silent_wav(&track)?;
+        let mut tagged_file = lofty::read_from_path(&track)?;
+        let mut tag = Tag::new(TagType::Id3v2);
+        tag.set_artist("Band".to_string());
+        tag.set_album("Debut".to_string());
+        tagged_file.insert_tag(tag);
+        tagged_file.save_to_path(&track, WriteOptions::default())?;
+
+        // As on a system whose ffmpeg libraries are missing: only art extraction should care
+        art::FFMPEG_UNAVAILABLE.set(true);
+        let summary = reorganize_misplaced_files(
+            music_root.to_str().unwrap(),
+            &OrganizeOptions {
+                quiet: true,
+                ..Default::default()
+            },
+            false,
+            None,
+        );
+        let art_error = art::extract_album_art_parallel(
+            &[artist_dir.join("Debut")],
+            None,
+            None,
+            &std::sync::atomic::AtomicBool::new(true),
+            |_| Ok(()),
+        );
+        art::FFMPEG_UNAVAILABLE.set(false);
+
+        assert_eq!(summary?.files_moved, 1);
+        assert!(artist_dir.join("Debut").join("01 Intro.wav").exists());
+        assert!(art_error
+            .unwrap_err()
+            .to_string()
+            .contains("needs the ffmpeg libraries"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sanitize_filename_basic() -> Result<()> {
+        // Test basic sanitization
+        assert_eq!(utils::sanitize_filename("normal_name"), "normal_name");
+        assert_eq!(
+            utils::sanitize_filename("file with spaces"),
+            "file with spaces"
+        );
+        assert_eq!(
+            utils::sanitize_filename("file/with\\bad:chars*"),
+            "file_with_bad_chars_"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sanitize_filename_edge_cases() -> Result<()> {
+        // Test edge cases
+        assert_eq!(utils::sanitize_filename(""), "");
+        assert_eq!(utils::sanitize_filename("   "), "");
+        assert_eq!(
+            utils::sanitize_filename("file\x00with\x01control\x02chars"),
+            "file_with_control_chars"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_from_path_valid_structure() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let artist_dir = temp_dir.path().join("Test Artist");
+        let album_dir = artist_dir.join("Test Album");
+        let file_path = album_dir.join("track.mp3");
+
+        // Create the directory structure
+        fs::create_dir_all(&album_dir)?;
+
+        let (artist, album) = metadata::extract_from_path(&file_path)?;
+
+        // The function filters out common words like "artist" and "album"
+        assert_eq!(artist, "Test"); // "Test Artist" -> "Test" (removes "artist")
+        assert_eq!(album, "Test"); // "Test Album" -> "Test" (removes "album")
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_from_path_with_problematic_names() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let artist_dir = temp_dir.path().join("Metallica");
+        let album_dir = artist_dir.join("Master of Puppets");
+        let file_path = album_dir.join("track.mp3");
+
+        // Create the directory structure
+        fs::create_dir_all(&album_dir)?;
+
+        let (artist, album) = metadata::extract_from_path(&file_path)?;
+
+        // These names should not be filtered out
+        assert_eq!(artist, "Metallica");
+        assert_eq!(album, "Master of Puppets");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_from_path_missing_parent() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let file_path = temp_dir.path().join("track.mp3");
+
+        // File with no parent directory - this should actually work
+        let result = metadata::extract_from_path(&file_path);
+
+        // The function should handle this case gracefully
+        assert!(result.is_ok());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,150 @@
+use crate::directory::{self, FileFailure};
+use crate::tag_backup;
+use anyhow::Result;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use tracing::info;
+use walkdir::WalkDir;
+
+/// Summary of a restore-tags run
+#[derive(Debug, Clone, Default)]
+pub struct RestoreTagsSummary {
+    /// Whether this summary describes a dry run (nothing was touched)
+    pub dry_run: bool,
+    /// Files whose tags were (or would be) restored
+    pub restored: Vec<PathBuf>,
+    /// Backups that could not be restored, e.g. because their file has since moved
+    pub failures: Vec<FileFailure>,
+}
+
+impl fmt::Display for RestoreTagsSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verb = if self.dry_run {
+            "would be restored"
+        } else {
+            "restored"
+        };
+        write!(
+            f,
+            "{} files {}, {} failed",
+            self.restored.len(),
+            verb,
+            self.failures.len()
+        )
+    }
+}
+
+/// Roll every file under `music_dir` with a tag backup back to its backed-up tags
+/// Backups are looked up next to where each file was when it was backed up, so run this
+/// before moving files; a backup whose file is gone is kept and reported as a failure
+pub fn restore_tags(music_dir: &str, dry_run: bool) -> Result<RestoreTagsSummary> {
+    let mut summary = RestoreTagsSummary {
+        dry_run,
+        ..RestoreTagsSummary::default()
+    };
+
+    let mut backups: Vec<PathBuf> = WalkDir::new(music_dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| {
+            e.file_type().is_file()
+                && e.path()
+                    .parent()
+                    .and_then(Path::file_name)
+                    .is_some_and(|dir| dir == tag_backup::BACKUP_DIR)
+        })
+        .map(|e| e.into_path())
+        .collect();
+    backups.sort();
+
+    for backup in backups {
+        let Some(file_path) = tag_backup::backed_up_file(&backup) else {
+            continue;
+        };
+        if !file_path.is_file() {
+            summary
+                .failures
+                .push(FileFailure::new(&file_path, "file no longer exists"));
+            continue;
+        }
+
+        if dry_run {
+            info!("Would restore tags of {}", file_path.display());
+            summary.restored.push(file_path);
+            continue;
+        }
+        match tag_backup::restore_file_tags(&file_path) {
+            Ok(_) => {
+                info!("Restored tags of {}", file_path.display());
+                summary.restored.push(file_path);
+            }
+            Err(e) => summary
+                .failures
+                .push(FileFailure::new(&file_path, format!("{:#}", e))),
+        }
+    }
+
+    directory::report_failures(&summary.failures, "restored");
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::write_silent_wav;
+    use lofty::config::WriteOptions;
+    use lofty::file::{AudioFile, TaggedFileExt};
+    use lofty::tag::{Accessor, Tag, TagType};
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn album_title(path: &Path) -> Option<String> {
+        let tagged_file = lofty::read_from_path(path).ok()?;
+        tagged_file.primary_tag()?.album().map(|a| a.to_string())
+    }
+
+    #[test]
+    fn test_restore_tags_undoes_enhanced_metadata() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let album_dir = temp_dir.path().join("Artists").join("Artist").join("Album");
+        fs::create_dir_all(&album_dir)?;
+        let track = album_dir.join("01.wav");
+        write_silent_wav(&track)?;
+        let mut tagged_file = lofty::read_from_path(&track)?;
+        let mut tag = Tag::new(TagType::Id3v2);
+        tag.set_album("Curated Album".to_string());
+        tagged_file.insert_tag(tag);
+        tagged_file.save_to_path(&track, WriteOptions::default())?;
+
+        // What a sync does with backups on: back up, then overwrite the tags
+        assert!(tag_backup::backup_file_tags(&track)?);
+        crate::metadata::set_enhanced_metadata(
+            &track,
+            "Wrong",
+            "Wrong Album",
+            "release-id",
+            None,
+            false,
+            None,
+        )?;
+        assert_eq!(album_title(&track).as_deref(), Some("Wrong Album"));
+
+        // A backup left behind by a file that has since been moved away
+        let gone_dir = album_dir.join(tag_backup::BACKUP_DIR);
+        fs::write(gone_dir.join("02.wav.json"), r#"{"tags":[]}"#)?;
+
+        let music_dir = temp_dir.path().to_str().unwrap();
+        let preview = restore_tags(music_dir, true)?;
+        assert_eq!(preview.restored, std::slice::from_ref(&track));
+        assert_eq!(album_title(&track).as_deref(), Some("Wrong Album"));
+
+        let summary = restore_tags(music_dir, false)?;
+        assert_eq!(summary.restored, std::slice::from_ref(&track));
+        assert_eq!(summary.failures.len(), 1);
+        assert_eq!(summary.failures[0].path, album_dir.join("02.wav"));
+        assert_eq!(album_title(&track).as_deref(), Some("Curated Album"));
+        assert!(!tag_backup::backup_path(&track).unwrap().exists());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,126 @@
+use crate::{metadata, utils};
+use anyhow::Result;
+use rayon::prelude::*;
+use serde::Serialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// How many of the slowest album folders a scan lists
+pub const SLOWEST_DIRS: usize = 10;
+
+/// Time taken to read the tags of one album folder's files
+#[derive(Debug, Clone, Serialize)]
+pub struct DirTiming {
+    /// The album folder
+    pub path: PathBuf,
+    /// Audio files in it, disc folders included
+    pub files: usize,
+    /// Wall time to list and read them, in seconds
+    pub secs: f64,
+}
+
+/// Counts and timings from a scan
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ScanSummary {
+    /// Album folders walked
+    pub albums: usize,
+    /// Audio files found
+    pub files: usize,
+    /// Files whose tags could not be read
+    pub unreadable: usize,
+    /// Wall time for the whole scan, in seconds
+    pub total_secs: f64,
+    /// Files read per second over the whole scan
+    pub files_per_sec: f64,
+    /// The [`SLOWEST_DIRS`] album folders that took longest, slowest first
+    pub slowest: Vec<DirTiming>,
+}
+
+impl fmt::Display for ScanSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} files in {} albums read in {:.2}s ({:.0} files/s, {} unreadable)",
+            self.files, self.albums, self.total_secs, self.files_per_sec, self.unreadable
+        )
+    }
+}
+
+/// Walk the library and read every track's tags as organize and sync would, timing the
+/// whole run and each album folder, without writing anything or using the network
+/// Albums are streamed one at a time (see [`utils::iter_albums`]) and each album's files are
+/// read in parallel on the current rayon pool, so `--jobs` applies
+pub fn scan_library(music_dir: &str) -> Result<ScanSummary> {
+    let artists_path = Path::new(music_dir).join("Artists");
+    if !artists_path.is_dir() {
+        return Err(anyhow::anyhow!(
+            "Artists directory '{}' does not exist. Run organize first to create the directory structure.",
+            artists_path.display()
+        ));
+    }
+
+    let started = Instant::now();
+    let mut summary = ScanSummary::default();
+    let mut timings = Vec::new();
+    for album in utils::iter_albums(music_dir) {
+        let album_started = Instant::now();
+        let tracks: Vec<PathBuf> = utils::iter_album_tracks(&album.path).collect();
+        let unreadable = tracks
+            .par_iter()
+            .filter(|track| metadata::read_track_fields(track).is_none())
+            .count();
+
+        summary.albums += 1;
+        summary.files += tracks.len();
+        summary.unreadable += unreadable;
+        timings.push(DirTiming {
+            path: album.path,
+            files: tracks.len(),
+            secs: album_started.elapsed().as_secs_f64(),
+        });
+    }
+
+    summary.total_secs = started.elapsed().as_secs_f64();
+    if summary.total_secs > 0.0 {
+        summary.files_per_sec = summary.files as f64 / summary.total_secs;
+    }
+    timings.sort_by(|a, b| b.secs.total_cmp(&a.secs));
+    timings.truncate(SLOWEST_DIRS);
+    summary.slowest = timings;
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_scan_counts_files_without_changing_them() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let artists = temp_dir.path().join("Artists");
+        let first = artists.join("Artist").join("First");
+        let second = artists.join("Artist").join("Second");
+        fs::create_dir_all(first.join("CD2"))?;
+        fs::create_dir_all(&second)?;
+        fs::write(first.join("01.mp3"), b"not really audio")?;
+        fs::write(first.join("CD2").join("02.mp3"), b"not really audio")?;
+        fs::write(second.join("01.mp3"), b"not really audio")?;
+        fs::write(second.join("cover.jpg"), b"not audio")?;
+        let modified = fs::metadata(second.join("01.mp3"))?.modified()?;
+
+        let summary = scan_library(temp_dir.path().to_str().unwrap())?;
+        assert_eq!(summary.albums, 2);
+        assert_eq!(summary.files, 3);
+        assert_eq!(summary.unreadable, 3);
+        assert_eq!(summary.slowest.len(), 2);
+        assert!(summary.slowest[0].secs >= summary.slowest[1].secs);
+        let files: usize = summary.slowest.iter().map(|dir| dir.files).sum();
+        assert_eq!(files, 3);
+        assert_eq!(fs::metadata(second.join("01.mp3"))?.modified()?, modified);
+
+        Ok(())
+    }
+}
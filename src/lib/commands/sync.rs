@@ -0,0 +1,1114 @@
+use crate::cover_art::CoverSettings;
+use crate::musicbrainz::{Network, ReleaseChooser};
+use crate::progress::{self, LabelledSink, ProgressMessage, ProgressSink};
+use crate::{cover_art, featuring, lyrics, metadata, musicbrainz, tag_backup, tagging, utils};
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+use rayon::ThreadPool;
+use rustc_hash::FxHashMap;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::SystemTime;
+use tracing::{error, warn};
+
+/// Result of syncing a single album
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncOutcome {
+    /// The album was looked up on MusicBrainz and its files processed; groups of its files
+    /// that matched no release were left as they were and are listed in `unmatched`
+    Synced { unmatched: Vec<UnmatchedAlbum> },
+    /// Every file already carried a MusicBrainz release ID, so the album was skipped
+    AlreadySynced,
+}
+
+/// An artist/album that MusicBrainz had no release for, so sync left its files untagged
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnmatchedAlbum {
+    /// The artist and album the search was for
+    pub artist: String,
+    pub album: String,
+    /// The album folder the files are in
+    pub path: PathBuf,
+}
+
+/// Write one `artist<TAB>album<TAB>folder` line per album MusicBrainz couldn't match, so the
+/// tags can be fixed by hand or the release added to MusicBrainz
+pub fn write_unmatched_list(path: &Path, unmatched: &[UnmatchedAlbum]) -> Result<()> {
+    let list: String = unmatched
+        .iter()
+        .map(|album| {
+            format!(
+                "{}\t{}\t{}\n",
+                album.artist,
+                album.album,
+                album.path.display()
+            )
+        })
+        .collect();
+    fs::write(path, list).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// How [`process_single_album_sync_tags`] syncs; the defaults skip albums already synced and
+/// keep artist credits as they are written
+#[derive(Debug, Clone, Default)]
+pub struct SyncOptions {
+    /// Sync albums whose files all carry a MusicBrainz release ID too
+    pub force: bool,
+    /// Search MusicBrainz by the folder names before the tags (see [`lookup_names`])
+    pub match_by_folder: bool,
+    /// Write featured artists one way in the artist tags, as `--normalize-featuring` does
+    pub normalize_featuring: bool,
+    /// With `normalize_featuring`, leave featured artists out of the album artist altogether,
+    /// as `--strip-featured` does
+    pub strip_featured_album_artist: bool,
+    /// How the cover art fetched for matched albums is saved
+    pub cover: CoverSettings,
+    /// Back up each file's tags (see [`tag_backup`]) before sync first rewrites them
+    pub backup_tags: bool,
+    /// Fetch each tagged track's lyrics from LRCLIB and embed them, as `--embed-lyrics` does
+    pub embed_lyrics: bool,
+    /// Leading articles moved to the end of derived artist sort tags, in place of
+    /// [`metadata::DEFAULT_SORT_ARTICLES`], as `--sort-articles` gives them
+    pub sort_articles: Option<Vec<String>>,
+    /// Only files modified after this are synced, as `--since` says
+    pub since: Option<SystemTime>,
+    /// How MusicBrainz, LRCLIB and the cover art services are reached
+    pub network: Network,
+    /// Albums [`sync_albums`] syncs at once, as `--parallel-albums` gives it; 0 and 1 both
+    /// sync one at a time
+    pub parallel_albums: usize,
+}
+
+/// Comprehensive function to update all tags on a file using MusicBrainz data
+/// Progress is reported through `progress`; pass the TUI channel or any other sink. Where a
+/// search matches several releases, `chooser` picks one (see
+/// [`musicbrainz::lookup_musicbrainz_release`])
+pub async fn process_single_album_sync_tags(
+    album_path: &Path,
+    options: &SyncOptions,
+    chooser: Option<&dyn ReleaseChooser>,
+    progress: &dyn ProgressSink,
+) -> Result<SyncOutcome> {
+    let &SyncOptions {
+        force,
+        match_by_folder,
+        normalize_featuring,
+        strip_featured_album_artist,
+        cover,
+        backup_tags,
+        embed_lyrics,
+        ref sort_articles,
+        since,
+        ref network,
+        ..
+    } = options;
+    let artist_path = album_path.parent().context("Album path has no parent")?;
+    let folder_artist = artist_path
+        .file_name()
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+    let folder_album = album_path
+        .file_name()
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+
+    progress.custom(format!("Scanning album folder: {}", folder_album));
+
+    // First, collect all audio files and count them for progress tracking
+    let scan_result = utils::scan_directory_for_audio_files_since(album_path, since)
+        .context("Failed to scan directory for audio files")?;
+
+    let audio_files = scan_result.audio_files;
+    let files_scanned = scan_result.files_scanned;
+    let files_skipped = scan_result.files_skipped;
+
+    // Send progress for file discovery phase
+    progress::send_scan_complete(progress, files_scanned, audio_files.len(), files_skipped);
+
+    if !force && album_already_synced(&audio_files) {
+        progress::send_album_already_synced(progress, &folder_album);
+        return Ok(SyncOutcome::AlreadySynced);
+    }
+
+    let audio_files_count = audio_files.len();
+
+    // Group files by their tags using parallel processing
+    let album_groups: FxHashMap<(String, String), Vec<PathBuf>> = audio_files
+        .into_par_iter()
+        .fold(
+            FxHashMap::default,
+            |mut groups: FxHashMap<(String, String), Vec<PathBuf>>, path: PathBuf| {
+                let (artist, album) =
+                    lookup_names(&path, &folder_artist, &folder_album, match_by_folder);
+                groups.entry((artist, album)).or_default().push(path);
+                groups
+            },
+        )
+        .reduce(
+            FxHashMap::default,
+            |mut a: FxHashMap<(String, String), Vec<PathBuf>>, b| {
+                for (key, paths) in b {
+                    a.entry(key).or_default().extend(paths);
+                }
+                a
+            },
+        );
+
+    // Until the searches are done, assume every group matches and all its files get tagged
+    progress::send_total_files(
+        progress,
+        sync_task_count(album_groups.len(), audio_files_count),
+    );
+
+    // Send progress for file grouping phase
+    progress::send_grouping_complete(progress, audio_files_count, album_groups.len());
+
+    // Batch MusicBrainz searches for better performance
+    let mut release_cache: FxHashMap<(String, String), Option<String>> = FxHashMap::default();
+
+    // Pre-fetch all MusicBrainz release data for album groups
+    for (artist, album) in album_groups.keys() {
+        if let std::collections::hash_map::Entry::Vacant(e) =
+            release_cache.entry((artist.clone(), album.clone()))
+        {
+            // Use library function for MusicBrainz lookup
+            match musicbrainz::lookup_musicbrainz_release(network, chooser, artist, album, progress)
+                .await
+            {
+                Ok(Some((_, _, release_id))) => {
+                    e.insert(Some(release_id));
+                    // Send progress for completed MusicBrainz search
+                    progress::send_musicbrainz_search_complete(progress, artist, album, true);
+                }
+                Ok(None) => {
+                    warn!(
+                        "MusicBrainz search failed for {} - {}: No release found",
+                        artist, album
+                    );
+                    release_cache.insert((artist.clone(), album.clone()), None);
+                    // Still count as completed task even if failed
+                    progress::send_musicbrainz_search_complete(progress, artist, album, false);
+                }
+                Err(e) => {
+                    warn!(
+                        "MusicBrainz search failed for {} - {}: {}",
+                        artist, album, e
+                    );
+                    release_cache.insert((artist.clone(), album.clone()), None);
+                    // Still count as completed task even if failed
+                    progress::send_musicbrainz_search_complete(progress, artist, album, false);
+                }
+            }
+        }
+    }
+
+    // Files in groups without a match are never tagged, which settles the total
+    let tagged_files = album_groups
+        .iter()
+        .filter(|(key, _)| matches!(release_cache.get(*key), Some(Some(_))))
+        .map(|(_, paths)| paths.len())
+        .sum();
+    progress::send_total_files(progress, sync_task_count(album_groups.len(), tagged_files));
+
+    // Process each group
+    let mut cover_art_jobs = Vec::new();
+    let mut unmatched = Vec::new();
+    for ((artist, album), paths) in album_groups.into_iter() {
+        let artist = artist.as_str();
+        let album = album.as_str();
+        progress::send_processing_group(progress, artist, album);
+
+        // Get release data from cache
+        if let Some(Some(release_id)) = release_cache.get(&(artist.to_string(), album.to_string()))
+        {
+            progress.custom(format!("Found cached release: {}", release_id));
+
+            // The tracklist is compared after tagging; the sort name and compilation flag are
+            // written while tagging
+            let details = match musicbrainz::fetch_release_details(network, release_id).await {
+                Ok(details) => details,
+                Err(e) => {
+                    warn!(
+                        "Failed to fetch release details for {} - {}: {}",
+                        artist, album, e
+                    );
+                    musicbrainz::ReleaseDetails::default()
+                }
+            };
+            let artist_sort = details.artist_sort_name.as_deref();
+
+            // Process files in parallel within this group
+            let album_path = album_path.to_path_buf();
+
+            paths.par_iter().for_each(|path| {
+                // A file whose tags can't be backed up is reported like a failed write and
+                // left alone
+                if let Err(e) = back_up_tags(path, backup_tags) {
+                    progress.custom(format!(
+                        "COMPLETED: {} - Failed to back up tags: {:#}",
+                        path.display(),
+                        e
+                    ));
+                    return;
+                }
+                let result = {
+                    // Calculate relative path from album directory
+                    let relative_path = path
+                        .strip_prefix(&album_path)
+                        .unwrap_or(path)
+                        .to_string_lossy()
+                        .to_string();
+                    tagging::process_music_file_with_musicbrainz(
+                        path,
+                        release_id,
+                        artist_sort,
+                        details.compilation,
+                        sort_articles.as_deref(),
+                        &relative_path,
+                        progress,
+                    )
+                };
+                if let Err(e) = result {
+                    error!("Error processing {}: {}", path.display(), e);
+                }
+
+                // Tracks are matched to the release's credits by the positions just tagged
+                if normalize_featuring {
+                    let track_credit = metadata::read_track_position(path)
+                        .and_then(|position| details.track_credit(position));
+                    if let Err(e) = featuring::normalize_featuring_tags(
+                        path,
+                        details.artist_credit.as_ref(),
+                        track_credit,
+                        strip_featured_album_artist,
+                    ) {
+                        warn!(
+                            "Failed to normalize featured artists in {}: {:#}",
+                            path.display(),
+                            e
+                        );
+                    }
+                }
+            });
+
+            // Lyrics are looked up by the names just tagged, one track at a time
+            if embed_lyrics {
+                for path in &paths {
+                    if back_up_tags(path, backup_tags).is_err() {
+                        continue;
+                    }
+                    if let Err(e) = lyrics::save_track_lyrics(network, path, force, progress).await
+                    {
+                        warn!("Failed to save lyrics for {}: {:#}", path.display(), e);
+                    }
+                }
+            }
+
+            // Send summary for this album group
+            progress::send_album_processing_complete(progress, artist, album, paths.len());
+
+            // Cover art is fetched for every matched group at once after the loop
+            cover_art_jobs.push(cover_art::CoverArtJob {
+                album_path: album_path.clone(),
+                release_id: release_id.clone(),
+                artist: artist.to_string(),
+                album: album.to_string(),
+            });
+
+            // Informational only: point out tracks missing from (or extra to) the release,
+            // using the track numbers as they stand after tagging
+            let local_positions: Vec<_> = paths
+                .par_iter()
+                .map(|path| metadata::read_track_position(path))
+                .collect();
+            if !details.tracklist.is_empty() {
+                report_tracklist_mismatch(progress, album, &details.tracklist, &local_positions);
+            }
+        } else {
+            progress::send_album_skipped(progress, artist, album);
+            unmatched.push(UnmatchedAlbum {
+                artist: artist.to_string(),
+                album: album.to_string(),
+                path: album_path.to_path_buf(),
+            });
+        }
+    }
+
+    if let Err(e) = cover_art::save_cover_art_batch(
+        network,
+        cover_art_jobs,
+        cover_art::DEFAULT_COVER_ART_CONCURRENCY,
+        cover,
+        progress,
+    )
+    .await
+    {
+        warn!("Failed to fetch cover art for {}: {}", folder_album, e);
+    }
+
+    progress::send_final_complete(progress, &folder_album);
+
+    unmatched.sort_by(|a, b| (&a.artist, &a.album).cmp(&(&b.artist, &b.album)));
+    Ok(SyncOutcome::Synced { unmatched })
+}
+
+/// Sync `album_paths` as [`process_single_album_sync_tags`] does, up to
+/// `options.parallel_albums` at once, with each album's file work on `pool` so `--jobs` bounds it
+/// Must be called within a Tokio runtime, which drives the lookups. The MusicBrainz rate limiter
+/// and the cover art download permits are process-wide, so they hold however many albums run
+/// together. Each album's messages are labelled with its folder name, its own totals dropped
+/// and its completion reported as it finishes, so the progress bar counts whole albums against
+/// the album count the caller sends
+/// Once `keep_going` is cleared, albums not yet started are skipped; `on_done` is called as
+/// each album finishes. Returns the albums MusicBrainz had no match for, in folder order
+pub fn sync_albums<F>(
+    album_paths: Vec<PathBuf>,
+    options: &SyncOptions,
+    chooser: Option<&dyn ReleaseChooser>,
+    pool: &ThreadPool,
+    progress: &dyn ProgressSink,
+    keep_going: &AtomicBool,
+    on_done: F,
+) -> Result<Vec<UnmatchedAlbum>>
+where
+    F: Fn(&Path) + Sync,
+{
+    let rt = tokio::runtime::Handle::try_current().context("Syncing needs a Tokio runtime")?;
+    let parallel_albums = options.parallel_albums.max(1);
+    let sync_album = |album_path: &Path| -> Result<SyncOutcome> {
+        let name = album_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+        let album_progress = LabelledSink::new(progress, name.as_str());
+        let outcome = pool.install(|| {
+            rt.block_on(process_single_album_sync_tags(
+                album_path,
+                options,
+                chooser,
+                &album_progress,
+            ))
+        })?;
+        progress.send(ProgressMessage::Custom {
+            message: format!("COMPLETED: Finished {}", name),
+        });
+        Ok(outcome)
+    };
+
+    // Workers take albums off one queue until it is empty, or one of them fails
+    let albums = Mutex::new(album_paths.into_iter());
+    let already_synced = AtomicUsize::new(0);
+    let unmatched = Mutex::new(Vec::new());
+    let failed = AtomicBool::new(false);
+    thread::scope(|scope| {
+        let mut workers = Vec::new();
+        for _ in 0..parallel_albums {
+            workers.push(scope.spawn(|| -> Result<()> {
+                while keep_going.load(Ordering::SeqCst) && !failed.load(Ordering::SeqCst) {
+                    let Some(album_path) = albums.lock().unwrap().next() else {
+                        break;
+                    };
+                    let outcome = sync_album(&album_path).inspect_err(|_| {
+                        failed.store(true, Ordering::SeqCst);
+                    })?;
+                    match outcome {
+                        SyncOutcome::AlreadySynced => {
+                            already_synced.fetch_add(1, Ordering::SeqCst);
+                        }
+                        SyncOutcome::Synced { unmatched: albums } => {
+                            unmatched.lock().unwrap().extend(albums);
+                        }
+                    }
+                    on_done(&album_path);
+                }
+                Ok(())
+            }));
+        }
+        workers
+            .into_iter()
+            .try_for_each(|worker| worker.join().unwrap())
+    })?;
+
+    let already_synced = already_synced.into_inner();
+    if already_synced > 0 {
+        progress.send(ProgressMessage::Custom {
+            message: format!(
+                "Skipped {} already-synced albums (use --force to re-sync)",
+                already_synced
+            ),
+        });
+    }
+    let mut unmatched = unmatched.into_inner().unwrap();
+    unmatched.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(unmatched)
+}
+
+/// Save `path`'s tags before sync first rewrites them, if `backup_tags`; a backup already
+/// there is kept, so it still holds the tags from before any sync
+fn back_up_tags(path: &Path, backup_tags: bool) -> Result<()> {
+    if backup_tags {
+        tag_backup::backup_file_tags(path)?;
+    }
+    Ok(())
+}
+
+/// Progress tasks in syncing an album, one per `COMPLETED:` message it sends: the scan and
+/// the grouping, each group's search and its finish (tagged or skipped), and each file tagged
+/// Tagged files report from parallel workers in any order, so only the count is relied on
+fn sync_task_count(groups: usize, tagged_files: usize) -> usize {
+    2 + 2 * groups + tagged_files
+}
+
+/// The artist and album a file is looked up on MusicBrainz under
+/// Tags come first, then the album's folder names; `match_by_folder` inverts this for
+/// libraries whose `Artists/<artist>/<album>` folders are more reliable than their tags
+pub fn lookup_names(
+    file_path: &Path,
+    folder_artist: &str,
+    folder_album: &str,
+    match_by_folder: bool,
+) -> (String, String) {
+    if match_by_folder {
+        if let Ok((artist, album)) = metadata::extract_from_path(file_path) {
+            if !metadata::is_unknown_placeholder(&artist, &album) {
+                return (artist, album);
+            }
+        }
+    }
+    tagging::extract_artist_album_from_path_with_fallback(file_path, folder_artist, folder_album)
+}
+
+/// Report which cover art sources have art for an album, without tagging or saving anything
+/// The release ID is read from the files' tags, or else looked up on MusicBrainz through the
+/// rate-limited client; returns `None` for folders without audio files, or none modified after
+/// `since`. Where the search matches several releases, `chooser` picks one
+pub async fn probe_album_cover_art(
+    network: &Network,
+    album_path: &Path,
+    match_by_folder: bool,
+    since: Option<SystemTime>,
+    chooser: Option<&dyn ReleaseChooser>,
+) -> Result<Option<cover_art::CoverArtAvailability>> {
+    let folder_artist = album_path
+        .parent()
+        .and_then(Path::file_name)
+        .context("Album path has no parent")?
+        .to_string_lossy()
+        .to_string();
+    let folder_album = album_path
+        .file_name()
+        .context("Album path has no folder name")?
+        .to_string_lossy()
+        .to_string();
+
+    let audio_files = utils::scan_directory_for_audio_files_since(album_path, since)
+        .context("Failed to scan directory for audio files")?
+        .audio_files;
+    let Some(first_file) = audio_files.first() else {
+        return Ok(None);
+    };
+    let (artist, album) = lookup_names(first_file, &folder_artist, &folder_album, match_by_folder);
+
+    let release_id = match audio_files
+        .iter()
+        .find_map(|path| metadata::read_musicbrainz_release_id(path))
+    {
+        Some(release_id) => Some(release_id),
+        None => match musicbrainz::lookup_musicbrainz_release(
+            network,
+            chooser,
+            &artist,
+            &album,
+            &progress::NoopSink,
+        )
+        .await
+        {
+            Ok(found) => found.map(|(_, _, release_id)| release_id),
+            Err(e) => {
+                warn!(
+                    "MusicBrainz search failed for {} - {}: {}",
+                    artist, album, e
+                );
+                None
+            }
+        },
+    };
+
+    cover_art::probe_cover_art_sources(network, &artist, &album, release_id.as_deref())
+        .await
+        .map(Some)
+}
+
+/// Release positions (disc, track) an album lacks or has beyond the release's tracklist
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct TracklistComparison {
+    pub missing: Vec<(u32, u32)>,
+    /// Positions not on the release, or present more than once locally
+    pub extra: Vec<(u32, u32)>,
+    /// Local files without a track number, which can't be matched
+    pub unnumbered: usize,
+}
+
+/// Compare the release's tracklist against the positions read from local files
+pub fn compare_tracklist(
+    tracklist: &[(u32, u32)],
+    local_positions: &[Option<(u32, u32)>],
+) -> TracklistComparison {
+    let mut local_counts: BTreeMap<(u32, u32), usize> = BTreeMap::new();
+    for position in local_positions.iter().flatten() {
+        *local_counts.entry(*position).or_default() += 1;
+    }
+
+    let mut comparison = TracklistComparison {
+        unnumbered: local_positions.iter().filter(|p| p.is_none()).count(),
+        ..TracklistComparison::default()
+    };
+    for position in tracklist {
+        match local_counts.get_mut(position) {
+            Some(count) if *count > 0 => *count -= 1,
+            _ => comparison.missing.push(*position),
+        }
+    }
+    for (position, count) in local_counts {
+        comparison
+            .extra
+            .extend(std::iter::repeat_n(position, count));
+    }
+    comparison.missing.sort_unstable();
+    comparison
+}
+
+/// Send a tracklist mismatch message if the album doesn't match its release
+/// Positions are shown as plain track numbers, or "disc-track" for multi-disc releases
+fn report_tracklist_mismatch(
+    progress: &dyn ProgressSink,
+    album: &str,
+    tracklist: &[(u32, u32)],
+    local_positions: &[Option<(u32, u32)>],
+) {
+    let comparison = compare_tracklist(tracklist, local_positions);
+    if comparison == TracklistComparison::default() {
+        return;
+    }
+
+    let multi_disc = tracklist.iter().any(|(disc, _)| *disc != 1);
+    let format_positions = |positions: &[(u32, u32)]| {
+        positions
+            .iter()
+            .map(|(disc, track)| {
+                if multi_disc {
+                    format!("{}-{}", disc, track)
+                } else {
+                    track.to_string()
+                }
+            })
+            .collect()
+    };
+    progress::send_tracklist_mismatch(
+        progress,
+        album,
+        format_positions(&comparison.missing),
+        format_positions(&comparison.extra),
+        comparison.unnumbered,
+    );
+}
+
+/// Whether every audio file in an album already carries a MusicBrainz release ID
+fn album_already_synced(audio_files: &[PathBuf]) -> bool {
+    !audio_files.is_empty()
+        && audio_files
+            .par_iter()
+            .all(|path| metadata::read_musicbrainz_release_id(path).is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::{CollectingSink, NoopSink, ProgressMessage};
+    use crate::test_support::write_tagged_wav;
+    use lofty::tag::ItemKey;
+    use std::fs;
+    use std::io::Write;
+    use std::sync::mpsc;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_process_single_album_sync_tags_with_valid_album() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+        let artists_dir = music_root.join("Artists");
+        fs::create_dir_all(&artists_dir)?;
+
+        // Create test structure
+        let artist_dir = artists_dir.join("TestArtist");
+        fs::create_dir(&artist_dir)?;
+        let album_dir = artist_dir.join("TestAlbum");
+        fs::create_dir(&album_dir)?;
+
+        // Create a test audio file
+        let track_file = album_dir.join("test_track.mp3");
+        fs::File::create(&track_file)?.write_all(b"fake audio content")?;
+
+        // Collect progress messages instead of feeding a TUI
+        let sink = CollectingSink::new();
+
+        // Mock the MusicBrainz response by setting up a minimal test
+        // Since we can't easily mock the MusicBrainz API, we'll test the file scanning part
+        let result =
+            process_single_album_sync_tags(&album_dir, &SyncOptions::default(), None, &sink).await;
+
+        // The function should complete (even if MusicBrainz search fails in test environment)
+        // The untagged file means the album isn't treated as already synced
+        assert!(matches!(result?, SyncOutcome::Synced { .. }));
+
+        // We should receive some progress messages, starting with the scan results
+        let messages = sink.messages();
+        assert!(
+            !messages.is_empty(),
+            "Should receive at least one progress message"
+        );
+        assert!(messages.iter().any(|m| matches!(
+            m,
+            ProgressMessage::ScanComplete {
+                audio_files_found: 1,
+                ..
+            }
+        )));
+        assert!(matches!(
+            messages.last(),
+            Some(ProgressMessage::FinalComplete { folder_name }) if folder_name == "TestAlbum"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_single_album_sync_tags_with_nonexistent_album() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let nonexistent_album = temp_dir.path().join("NonexistentAlbum");
+
+        let (tx, _rx) = mpsc::channel::<String>();
+
+        // This should fail gracefully
+        let _result = std::panic::AssertUnwindSafe(async {
+            process_single_album_sync_tags(&nonexistent_album, &SyncOptions::default(), None, &tx)
+                .await
+        });
+
+        // The function should handle the error gracefully
+        // Note: This is a runtime test that might need adjustment based on actual behavior
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_single_album_sync_tags_with_empty_album() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+        let artists_dir = music_root.join("Artists");
+        fs::create_dir_all(&artists_dir)?;
+
+        // Create test structure
+        let artist_dir = artists_dir.join("TestArtist");
+        fs::create_dir(&artist_dir)?;
+        let album_dir = artist_dir.join("EmptyAlbum");
+        fs::create_dir(&album_dir)?;
+
+        // No audio files in the album
+        let (tx, _rx) = mpsc::channel::<String>();
+
+        // This should complete without processing any files
+        let _result = std::panic::AssertUnwindSafe(async {
+            process_single_album_sync_tags(&album_dir, &SyncOptions::default(), None, &tx).await
+        });
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_single_album_sync_tags_with_mixed_files() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+        let artists_dir = music_root.join("Artists");
+        fs::create_dir_all(&artists_dir)?;
+
+        // Create test structure
+        let artist_dir = artists_dir.join("TestArtist");
+        fs::create_dir(&artist_dir)?;
+        let album_dir = artist_dir.join("MixedAlbum");
+        fs::create_dir(&album_dir)?;
+
+        // Create mix of audio and non-audio files
+        fs::File::create(album_dir.join("track1.mp3"))?.write_all(b"audio")?;
+        fs::File::create(album_dir.join("track2.flac"))?.write_all(b"audio")?;
+        fs::File::create(album_dir.join("cover.jpg"))?.write_all(b"image")?;
+        fs::File::create(album_dir.join("lyrics.txt"))?.write_all(b"text")?;
+
+        let (tx, _rx) = mpsc::channel::<String>();
+
+        // Should process only audio files
+        let _result = std::panic::AssertUnwindSafe(async {
+            process_single_album_sync_tags(&album_dir, &SyncOptions::default(), None, &tx).await
+        });
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_single_album_sync_tags_with_unsupported_formats() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+        let artists_dir = music_root.join("Artists");
+        fs::create_dir_all(&artists_dir)?;
+
+        // Create test structure
+        let artist_dir = artists_dir.join("TestArtist");
+        fs::create_dir(&artist_dir)?;
+        let album_dir = artist_dir.join("UnsupportedAlbum");
+        fs::create_dir(&album_dir)?;
+
+        // Create files with unsupported extensions
+        fs::File::create(album_dir.join("file.m3u"))?.write_all(b"playlist")?;
+        fs::File::create(album_dir.join("file.exe"))?.write_all(b"binary")?;
+        fs::File::create(album_dir.join("file.doc"))?.write_all(b"document")?;
+
+        let (tx, _rx) = mpsc::channel::<String>();
+
+        // Should skip all unsupported files
+        let _result = std::panic::AssertUnwindSafe(async {
+            process_single_album_sync_tags(&album_dir, &SyncOptions::default(), None, &tx).await
+        });
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_single_album_sync_tags_with_no_artist_parent() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let album_dir = temp_dir.path().join("OrphanedAlbum");
+        fs::create_dir(&album_dir)?;
+
+        let (tx, _rx) = mpsc::channel::<String>();
+
+        // This should fail because album has no artist parent
+        let _result = std::panic::AssertUnwindSafe(async {
+            process_single_album_sync_tags(&album_dir, &SyncOptions::default(), None, &tx).await
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_process_single_album_sync_tags_groups_disc_folders_as_one_album() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let album_dir = temp_dir
+            .path()
+            .join("Artists")
+            .join("Pink Floyd")
+            .join("The Wall");
+        for disc in ["CD1", "CD2"] {
+            let disc_dir = album_dir.join(disc);
+            fs::create_dir_all(&disc_dir)?;
+            fs::File::create(disc_dir.join("01.mp3"))?.write_all(b"fake audio content")?;
+        }
+
+        let sink = CollectingSink::new();
+        process_single_album_sync_tags(&album_dir, &SyncOptions::default(), None, &sink).await?;
+
+        // Untagged files in CD1 and CD2 both fall back to the album folder, not the disc folder
+        assert!(sink.messages().iter().any(|m| matches!(
+            m,
+            ProgressMessage::GroupingComplete {
+                audio_files_count: 2,
+                album_groups_count: 1,
+            }
+        )));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unmatched_album_is_reported() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let album_dir = temp_dir
+            .path()
+            .join("Artists")
+            .join("Obscure Trio")
+            .join("Demo Tape");
+        fs::create_dir_all(&album_dir)?;
+        fs::File::create(album_dir.join("01.mp3"))?.write_all(b"fake audio content")?;
+
+        // Offline, no search finds anything
+        let options = SyncOptions {
+            network: Network::offline(),
+            ..Default::default()
+        };
+        let result = process_single_album_sync_tags(&album_dir, &options, None, &NoopSink).await;
+
+        let unmatched = match result? {
+            SyncOutcome::Synced { unmatched } => unmatched,
+            other => panic!("unexpected outcome: {:?}", other),
+        };
+        assert_eq!(
+            unmatched,
+            [UnmatchedAlbum {
+                artist: "Obscure Trio".to_string(),
+                album: "Demo Tape".to_string(),
+                path: album_dir.clone(),
+            }]
+        );
+
+        let report = temp_dir.path().join("unmatched.tsv");
+        write_unmatched_list(&report, &unmatched)?;
+        assert_eq!(
+            fs::read_to_string(&report)?,
+            format!("Obscure Trio\tDemo Tape\t{}\n", album_dir.display())
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sync_progress_total_matches_completions() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let album_dir = temp_dir.path().join("Artists").join("Band").join("Album");
+        fs::create_dir_all(album_dir.join("CD2"))?;
+        for track in ["01.mp3", "02.mp3", "CD2/01.mp3"] {
+            fs::File::create(album_dir.join(track))?.write_all(b"fake audio content")?;
+        }
+
+        let options = SyncOptions {
+            network: Network::offline(),
+            ..Default::default()
+        };
+        let sink = CollectingSink::new();
+        process_single_album_sync_tags(&album_dir, &options, None, &sink).await?;
+
+        // The last total sent is exactly the number of completions, so the bar ends at 100%
+        let messages: Vec<String> = sink.messages().iter().map(|m| m.format()).collect();
+        let completed = messages
+            .iter()
+            .filter(|m| m.starts_with("COMPLETED:"))
+            .count();
+        let total = messages
+            .iter()
+            .rev()
+            .find_map(|m| m.strip_prefix("TOTAL_FILES:"))
+            .map(str::parse::<usize>)
+            .transpose()?;
+        assert_eq!(total, Some(completed));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sync_albums_runs_several_albums_at_once() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let artist_dir = temp_dir.path().join("Artists").join("Band");
+        let album_paths: Vec<PathBuf> = ["Debut", "Live", "Demos"]
+            .iter()
+            .map(|album| artist_dir.join(album))
+            .collect();
+        for album_dir in &album_paths {
+            fs::create_dir_all(album_dir)?;
+            fs::File::create(album_dir.join("01.mp3"))?.write_all(b"fake audio content")?;
+        }
+
+        let rt = tokio::runtime::Runtime::new()?;
+        let _rt = rt.enter();
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(2).build()?;
+        let options = SyncOptions {
+            network: Network::offline(),
+            parallel_albums: 2,
+            ..Default::default()
+        };
+        let sink = CollectingSink::new();
+        let done = Mutex::new(Vec::new());
+        let unmatched = sync_albums(
+            album_paths.clone(),
+            &options,
+            None,
+            &pool,
+            &sink,
+            &AtomicBool::new(true),
+            |album_path| done.lock().unwrap().push(album_path.to_path_buf()),
+        )?;
+
+        // Offline, none of them match; they come back in folder order however they finished
+        let mut sorted_paths = album_paths.clone();
+        sorted_paths.sort();
+        let unmatched_paths: Vec<PathBuf> = unmatched.into_iter().map(|a| a.path).collect();
+        assert_eq!(unmatched_paths, sorted_paths);
+        let mut done = done.into_inner().unwrap();
+        done.sort();
+        assert_eq!(done, sorted_paths);
+
+        // Each album finishes with one completion, and its own messages are labelled
+        let messages: Vec<String> = sink.messages().iter().map(|m| m.format()).collect();
+        let finished: Vec<&String> = messages
+            .iter()
+            .filter(|m| m.starts_with("COMPLETED:"))
+            .collect();
+        assert_eq!(finished.len(), album_paths.len());
+        assert!(messages.iter().any(|m| m.starts_with("[Live] ")));
+        assert!(!messages.iter().any(|m| m.starts_with("TOTAL_FILES:")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sync_albums_one_at_a_time_counts_whole_albums() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let artist_dir = temp_dir.path().join("Artists").join("Band");
+        let mut album_paths = Vec::new();
+        for (album, tracks) in [("Debut", 3), ("Live", 1)] {
+            let album_dir = artist_dir.join(album);
+            fs::create_dir_all(&album_dir)?;
+            for track in 0..tracks {
+                fs::File::create(album_dir.join(format!("{:02}.mp3", track + 1)))?
+                    .write_all(b"fake audio content")?;
+            }
+            album_paths.push(album_dir);
+        }
+
+        let rt = tokio::runtime::Runtime::new()?;
+        let _rt = rt.enter();
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(1).build()?;
+        let options = SyncOptions {
+            network: Network::offline(),
+            ..Default::default()
+        };
+        let sink = CollectingSink::new();
+        sync_albums(
+            album_paths.clone(),
+            &options,
+            None,
+            &pool,
+            &sink,
+            &AtomicBool::new(true),
+            |_| {},
+        )?;
+
+        // Against the album count the caller sends, the bar only fills once the last album is done
+        let mut tally = progress::ProgressTally::default();
+        tally.record(&format!("TOTAL_FILES:{}", album_paths.len()));
+        let messages: Vec<String> = sink.messages().iter().map(|m| m.format()).collect();
+        for (i, message) in messages.iter().enumerate() {
+            tally.record(message);
+            if i + 1 < messages.len() {
+                assert!(tally.fraction() < 1.0, "full early at {:?}", message);
+            }
+        }
+        assert_eq!(tally.total, album_paths.len());
+        assert_eq!(tally.completed, album_paths.len());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compare_tracklist_reports_missing_and_extra_tracks() {
+        let tracklist: Vec<_> = (1..=8).map(|track| (1, track)).collect();
+        let local = [
+            Some((1, 1)),
+            Some((1, 2)),
+            Some((1, 3)),
+            Some((1, 5)),
+            Some((1, 6)),
+            Some((1, 8)),
+            Some((1, 8)),
+            Some((1, 12)),
+            None,
+        ];
+
+        let comparison = compare_tracklist(&tracklist, &local);
+        assert_eq!(comparison.missing, vec![(1, 4), (1, 7)]);
+        assert_eq!(comparison.extra, vec![(1, 8), (1, 12)]);
+        assert_eq!(comparison.unnumbered, 1);
+
+        let complete: Vec<_> = tracklist.iter().copied().map(Some).collect();
+        assert_eq!(
+            compare_tracklist(&tracklist, &complete),
+            TracklistComparison::default()
+        );
+    }
+
+    #[test]
+    fn test_report_tracklist_mismatch_formats_disc_positions() {
+        let sink = CollectingSink::new();
+        let tracklist = [(1, 1), (1, 2), (2, 1), (2, 2)];
+        report_tracklist_mismatch(
+            &sink,
+            "The Wall",
+            &tracklist,
+            &[Some((1, 1)), Some((1, 2)), Some((2, 1))],
+        );
+        report_tracklist_mismatch(&sink, "The Wall", &tracklist, &tracklist.map(Some));
+
+        let messages = sink.messages();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].format(), "Album The Wall: missing tracks 2-2");
+    }
+
+    #[test]
+    fn test_album_already_synced_requires_tagged_files() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let untagged = temp_dir.path().join("track.mp3");
+        fs::File::create(&untagged)?.write_all(b"fake audio content")?;
+
+        assert!(!album_already_synced(&[]));
+        assert!(!album_already_synced(&[untagged]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lookup_names_match_by_folder_prefers_folder_names() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let album_dir = temp_dir
+            .path()
+            .join("Artists")
+            .join("Boards of Canada")
+            .join("Geogaddi");
+        fs::create_dir_all(album_dir.join("CD2"))?;
+
+        // Messy tags, as left by a ripper, on a track in each of the album and a disc folder
+        let tracks = [
+            album_dir.join("01.wav"),
+            album_dir.join("CD2").join("02.wav"),
+        ];
+        for track in &tracks {
+            write_tagged_wav(
+                track,
+                &[
+                    (ItemKey::AlbumArtist, "boards of canada (ripped)"),
+                    (ItemKey::AlbumTitle, "geogaddi [320]"),
+                ],
+            )?;
+        }
+
+        for track in &tracks {
+            assert_eq!(
+                lookup_names(track, "Boards of Canada", "Geogaddi", true),
+                ("Boards of Canada".to_string(), "Geogaddi".to_string())
+            );
+            // Without the option the tags still take precedence
+            assert_eq!(
+                lookup_names(track, "Boards of Canada", "Geogaddi", false),
+                (
+                    "boards of canada (ripped)".to_string(),
+                    "geogaddi [320]".to_string()
+                )
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -2,10 +2,16 @@ use anyhow::Result;
 use std::fs;
 use std::os::unix::fs::symlink;
 use std::path::Path;
+use tracing::info;
 
-pub fn process_single_track_symlink(track_path: &Path, music_dir: &str) -> Result<()> {
-    let music_dir = shellexpand::tilde(music_dir);
-    let tracks_path = Path::new(music_dir.as_ref()).join("Tracks");
+/// Link a track into `Tracks/` under its file name, replacing a link that points elsewhere
+/// With `dry_run`, the link that would be created, replaced or skipped is only logged
+pub fn process_single_track_symlink(
+    track_path: &Path,
+    music_dir: &str,
+    dry_run: bool,
+) -> Result<()> {
+    let tracks_path = Path::new(music_dir).join("Tracks");
 
     // Check if the source file exists
     if !track_path.exists() {
@@ -16,24 +22,54 @@ pub fn process_single_track_symlink(track_path: &Path, music_dir: &str) -> Resul
     }
 
     if !tracks_path.exists() {
-        fs::create_dir(&tracks_path)?;
+        if dry_run {
+            info!("Would create directory: {}", tracks_path.display());
+        } else {
+            fs::create_dir(&tracks_path)?;
+        }
     }
 
     let link_name = tracks_path.join(track_path.file_name().unwrap());
 
     if link_name.exists() {
         // Check if it's already a symlink to the correct target
-        if link_name.is_symlink() {
-            let current_target = fs::read_link(&link_name)?;
-            if current_target == track_path {
-                // Already correctly linked, skip
-                return Ok(());
+        let current_target = if link_name.is_symlink() {
+            Some(fs::read_link(&link_name)?)
+        } else {
+            None
+        };
+        if current_target.as_deref() == Some(track_path) {
+            // Already correctly linked, skip
+            if dry_run {
+                info!("Would skip (already linked): {}", link_name.display());
             }
+            return Ok(());
+        }
+        if dry_run {
+            info!(
+                "Would replace: {} ({} -> {})",
+                link_name.display(),
+                current_target.map_or_else(
+                    || "existing file".to_string(),
+                    |target| target.display().to_string()
+                ),
+                track_path.display()
+            );
+            return Ok(());
         }
         // Remove existing file/symlink and create new one
         fs::remove_file(&link_name)?;
     }
 
+    if dry_run {
+        info!(
+            "Would link: {} -> {}",
+            link_name.display(),
+            track_path.display()
+        );
+        return Ok(());
+    }
+
     symlink(track_path, &link_name)?;
     Ok(())
 }
@@ -63,7 +99,7 @@ mod tests {
         fs::File::create(&track_file)?.write_all(b"test audio content")?;
 
         // Test the function
-        let result = process_single_track_symlink(&track_file, music_root.to_str().unwrap());
+        let result = process_single_track_symlink(&track_file, music_root.to_str().unwrap(), false);
 
         assert!(result.is_ok());
 
@@ -93,7 +129,7 @@ mod tests {
         fs::File::create(&track_file)?.write_all(b"test audio content")?;
 
         // Test the function - should create Tracks directory
-        let result = process_single_track_symlink(&track_file, music_root.to_str().unwrap());
+        let result = process_single_track_symlink(&track_file, music_root.to_str().unwrap(), false);
 
         assert!(result.is_ok());
 
@@ -130,7 +166,7 @@ mod tests {
         symlink(&track_file, &link_path)?;
 
         // Test the function - should succeed without recreating
-        let result = process_single_track_symlink(&track_file, music_root.to_str().unwrap());
+        let result = process_single_track_symlink(&track_file, music_root.to_str().unwrap(), false);
 
         assert!(result.is_ok());
 
@@ -161,7 +197,8 @@ mod tests {
         symlink(&wrong_track, &link_path)?;
 
         // Test the function - should recreate the symlink
-        let result = process_single_track_symlink(&correct_track, music_root.to_str().unwrap());
+        let result =
+            process_single_track_symlink(&correct_track, music_root.to_str().unwrap(), false);
 
         assert!(result.is_ok());
 
@@ -172,6 +209,35 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_process_single_track_symlink_dry_run_changes_nothing() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_root = temp_dir.path().join("Music");
+        let album_dir = music_root
+            .join("Artists")
+            .join("TestArtist")
+            .join("TestAlbum");
+        fs::create_dir_all(&album_dir)?;
+        let track_file = album_dir.join("test_track.mp3");
+        fs::File::create(&track_file)?.write_all(b"test audio content")?;
+        let wrong_track = album_dir.join("wrong_track.mp3");
+        fs::File::create(&wrong_track)?.write_all(b"wrong content")?;
+
+        // Without a Tracks directory, neither it nor the link is created
+        process_single_track_symlink(&track_file, music_root.to_str().unwrap(), true)?;
+        assert!(!music_root.join("Tracks").exists());
+
+        // A link pointing elsewhere is left alone
+        let tracks_dir = music_root.join("Tracks");
+        fs::create_dir(&tracks_dir)?;
+        let link_path = tracks_dir.join("test_track.mp3");
+        symlink(&wrong_track, &link_path)?;
+        process_single_track_symlink(&track_file, music_root.to_str().unwrap(), true)?;
+        assert_eq!(fs::read_link(&link_path)?, wrong_track);
+
+        Ok(())
+    }
+
     #[test]
     fn test_process_single_track_symlink_nonexistent_file() -> Result<()> {
         let temp_dir = TempDir::new()?;
@@ -184,7 +250,8 @@ mod tests {
         let nonexistent_file = temp_dir.path().join("nonexistent.mp3");
 
         // Test the function - should fail
-        let result = process_single_track_symlink(&nonexistent_file, music_root.to_str().unwrap());
+        let result =
+            process_single_track_symlink(&nonexistent_file, music_root.to_str().unwrap(), false);
 
         assert!(result.is_err());
 
@@ -192,7 +259,7 @@ mod tests {
     }
 
     #[test]
-    fn test_process_single_track_symlink_with_tilde_expansion() -> Result<()> {
+    fn test_process_single_track_symlink_with_expanded_home_path() -> Result<()> {
         let temp_dir = TempDir::new()?;
         let home_dir = temp_dir.path().join("home").join("user");
         fs::create_dir_all(&home_dir)?;
@@ -212,9 +279,9 @@ mod tests {
         let track_file = album_dir.join("test_track.mp3");
         fs::File::create(&track_file)?.write_all(b"test audio content")?;
 
-        // Test with tilde path
-        let tilde_path = "~/Music";
-        let result = process_single_track_symlink(&track_file, tilde_path);
+        // Callers expand `~` before handing the music directory over, as the CLI does
+        let expanded_path = shellexpand::tilde("~/Music").into_owned();
+        let result = process_single_track_symlink(&track_file, &expanded_path, false);
 
         assert!(result.is_ok());
 
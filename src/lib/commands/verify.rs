@@ -0,0 +1,104 @@
+use crate::checksums::{self, ChecksumAlgorithm};
+use crate::directory::{self, FileFailure};
+use anyhow::Result;
+use std::fmt;
+use std::path::PathBuf;
+use tracing::info;
+use walkdir::WalkDir;
+
+/// Summary of a verify run
+#[derive(Debug, Clone, Default)]
+pub struct VerifySummary {
+    /// Checksum manifests that were checked
+    pub manifests: usize,
+    /// Files whose hash still matches their manifest
+    pub verified: usize,
+    /// Files that are missing, unreadable or no longer match, plus manifests that could not be read
+    pub failures: Vec<FileFailure>,
+}
+
+impl fmt::Display for VerifySummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} files verified against {} manifests, {} failed",
+            self.verified,
+            self.manifests,
+            self.failures.len()
+        )
+    }
+}
+
+/// Check every file listed in a checksum manifest (`checksums.sha256` or `checksums.md5`)
+/// under `music_dir` against its recorded hash, to catch bit rot since the manifest was written
+pub fn verify_checksums(music_dir: &str) -> Result<VerifySummary> {
+    let mut manifests: Vec<PathBuf> = WalkDir::new(music_dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| {
+            e.file_type().is_file()
+                && e.file_name()
+                    .to_str()
+                    .and_then(ChecksumAlgorithm::from_manifest_name)
+                    .is_some()
+        })
+        .map(|e| e.into_path())
+        .collect();
+    manifests.sort();
+
+    let mut summary = VerifySummary {
+        manifests: manifests.len(),
+        ..VerifySummary::default()
+    };
+    for manifest in manifests {
+        match checksums::verify_manifest(&manifest) {
+            Ok(check) => {
+                info!(
+                    "Verified {} files against {} ({} failed)",
+                    check.verified,
+                    manifest.display(),
+                    check.failures.len()
+                );
+                summary.verified += check.verified;
+                summary.failures.extend(check.failures);
+            }
+            Err(e) => summary
+                .failures
+                .push(FileFailure::new(&manifest, format!("{:#}", e))),
+        }
+    }
+
+    directory::report_failures(&summary.failures, "verified");
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_verify_checksums_reports_mismatches() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let artist = temp_dir.path().join("Artists").join("Artist");
+        let intact = artist.join("Intact");
+        let rotted = artist.join("Rotted");
+        fs::create_dir_all(&intact)?;
+        fs::create_dir_all(&rotted)?;
+        fs::write(intact.join("01.flac"), b"audio")?;
+        fs::write(rotted.join("01.flac"), b"audio")?;
+        checksums::write_manifest(&intact, ChecksumAlgorithm::Sha256)?;
+        checksums::write_manifest(&rotted, ChecksumAlgorithm::Md5)?;
+        fs::write(rotted.join("01.flac"), b"audiO")?;
+
+        let summary = verify_checksums(temp_dir.path().to_str().unwrap())?;
+        assert_eq!(summary.manifests, 2);
+        assert_eq!(summary.verified, 1);
+        assert_eq!(summary.failures.len(), 1);
+        assert_eq!(summary.failures[0].path, rotted.join("01.flac"));
+        assert_eq!(summary.failures[0].reason, "checksum mismatch");
+
+        Ok(())
+    }
+}
@@ -1,259 +1,1421 @@
-use anyhow::{Context, Result};
-use reqwest;
-use serde_json;
-use std::path::Path;
-use std::sync::mpsc;
+use crate::commands::embed_art;
+use crate::directory::write_via_part_file;
+use crate::musicbrainz::{self, Network};
+use crate::progress::{NoopSink, ProgressSink};
+use anyhow::Result;
+use magick_rust::{magick_wand_genesis, magick_wand_terminus, MagickWand};
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Once, OnceLock};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tracing::warn;
 use urlencoding;
 
+/// Base URL of the AudioDB JSON API
+const AUDIODB_API_URL: &str = "https://www.theaudiodb.com/api/v1/json/2";
+
+/// Tries at a cover image download before giving up on a failed or truncated transfer
+const DOWNLOAD_ATTEMPTS: u32 = 3;
+
+/// Albums [`save_cover_art_batch`] fetches at once unless told otherwise
+pub const DEFAULT_COVER_ART_CONCURRENCY: usize = 4;
+
+static DOWNLOAD_PERMITS: OnceLock<Semaphore> = OnceLock::new();
+
+/// Process-wide cap on cover art downloads in flight, shared by every batch
+/// Albums synced side by side each run their own batch; this keeps their total to what a
+/// single album would use, so the cover art hosts see no more load
+pub fn download_permits() -> &'static Semaphore {
+    DOWNLOAD_PERMITS.get_or_init(|| Semaphore::new(DEFAULT_COVER_ART_CONCURRENCY))
+}
+
+/// Image format that cover art and folder icons are saved in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CoverFormat {
+    #[default]
+    Jpeg,
+    Png,
+    WebP,
+}
+
+impl CoverFormat {
+    const ALL: [CoverFormat; 3] = [CoverFormat::Jpeg, CoverFormat::Png, CoverFormat::WebP];
+
+    /// File extension used for images in this format
+    pub fn extension(self) -> &'static str {
+        match self {
+            CoverFormat::Jpeg => "jpg",
+            CoverFormat::Png => "png",
+            CoverFormat::WebP => "webp",
+        }
+    }
+
+    /// Format name as understood by ImageMagick
+    pub fn magick_name(self) -> &'static str {
+        match self {
+            CoverFormat::Jpeg => "jpeg",
+            CoverFormat::Png => "png",
+            CoverFormat::WebP => "webp",
+        }
+    }
+}
+
+impl FromStr for CoverFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "jpeg" | "jpg" => Ok(CoverFormat::Jpeg),
+            "png" => Ok(CoverFormat::Png),
+            "webp" => Ok(CoverFormat::WebP),
+            other => Err(format!(
+                "unknown cover format '{}' (expected jpeg, png or webp)",
+                other
+            )),
+        }
+    }
+}
+
+/// Whether the smaller side of the image in `data` is at least `min_size` pixels
+/// Data MagickWand can't read passes, and fails later when it is stored
+pub fn meets_min_size(data: &[u8], min_size: u32) -> bool {
+    let wand = new_wand();
+    if wand.read_image_blob(data).is_err() {
+        return true;
+    }
+    wand.get_image_width().min(wand.get_image_height()) >= min_size as usize
+}
+
+/// The pixel area of the image in `data`, or None if MagickWand can't read it
+pub fn image_area(data: &[u8]) -> Option<u64> {
+    let wand = new_wand();
+    wand.read_image_blob(data).ok()?;
+    Some(wand.get_image_width() as u64 * wand.get_image_height() as u64)
+}
+
+/// Whether cover art downloaded from `source` is at least `min_size` pixels (see
+/// [`meets_min_size`]); any size is accepted without one
+/// A rejection is reported to `progress`, and the caller should try its next source
+pub fn accept_cover_art(
+    data: &[u8],
+    source: &str,
+    min_size: Option<u32>,
+    progress: &dyn ProgressSink,
+) -> bool {
+    let Some(min_size) = min_size else {
+        return true;
+    };
+    let accepted = meets_min_size(data, min_size);
+    if !accepted {
+        progress.custom(format!(
+            "Rejected {} cover art: smaller than {} px",
+            source, min_size
+        ));
+    }
+    accepted
+}
+
+/// Where downloaded cover art is stored: a `cover.<ext>` sidecar, the audio files' tags, or both
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CoverMode {
+    #[default]
+    Sidecar,
+    Embed,
+    Both,
+}
+
+impl CoverMode {
+    /// Whether art is written as a `cover.<ext>` file in the album folder
+    pub fn writes_sidecar(self) -> bool {
+        self != CoverMode::Embed
+    }
+
+    /// Whether art is embedded into the album's audio files
+    pub fn embeds(self) -> bool {
+        self != CoverMode::Sidecar
+    }
+}
+
+impl FromStr for CoverMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "sidecar" => Ok(CoverMode::Sidecar),
+            "embed" => Ok(CoverMode::Embed),
+            "both" => Ok(CoverMode::Both),
+            other => Err(format!(
+                "unknown cover mode '{}' (expected sidecar, embed or both)",
+                other
+            )),
+        }
+    }
+}
+
+/// How downloaded cover art is saved; the defaults save art of any size as a JPEG sidecar
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CoverSettings {
+    /// Format new cover art and folder icons are saved in, as `--cover-format` chooses
+    pub format: CoverFormat,
+    /// Where new cover art is stored, as `--cover-mode` chooses
+    pub mode: CoverMode,
+    /// Reject downloaded cover art whose smaller side is under this many pixels, so the next
+    /// source is tried instead, as `--cover-min-size` asks
+    pub min_size: Option<u32>,
+}
+
+/// The folder icon (`.folder.jpg`, `.folder.png` or `.folder.webp`) in `dir`, if there is one
+pub fn find_folder_icon(dir: &Path) -> Option<PathBuf> {
+    CoverFormat::ALL
+        .iter()
+        .map(|format| dir.join(format!(".folder.{}", format.extension())))
+        .find(|path| path.is_file())
+}
+
+static MAGICK_GENESIS: Once = Once::new();
+
+/// Initialize MagickWand on first use; safe to call repeatedly, even after `magick_wand_genesis`
+pub fn init_magick() {
+    MAGICK_GENESIS.call_once(magick_wand_genesis);
+}
+
+/// A new MagickWand, initializing the library first if nothing has yet
+pub fn new_wand() -> MagickWand {
+    init_magick();
+    MagickWand::new()
+}
+
+/// Release MagickWand's global state at shutdown, if [`init_magick`] ever ran
+/// No wand may be created afterwards
+pub fn shutdown_magick() {
+    if MAGICK_GENESIS.is_completed() {
+        magick_wand_terminus();
+    }
+}
+
+/// Re-encode image data as `image_format`, returning it untouched if it already is
+pub fn convert_image(data: &[u8], image_format: CoverFormat) -> Result<Vec<u8>> {
+    let wand = new_wand();
+    wand.read_image_blob(data)?;
+    if wand
+        .get_image_format()?
+        .eq_ignore_ascii_case(image_format.magick_name())
+    {
+        return Ok(data.to_vec());
+    }
+    wand.set_image_format(image_format.magick_name())?;
+    Ok(wand.write_image_blob(image_format.magick_name())?)
+}
+
+/// Write album cover art as `cover.<ext>` in `image_format`, returning the path written
+pub fn write_cover_art(
+    album_path: &Path,
+    data: &[u8],
+    image_format: CoverFormat,
+) -> Result<PathBuf> {
+    let data = convert_image(data, image_format)?;
+    let cover_art_path = album_path.join(format!("cover.{}", image_format.extension()));
+    write_via_part_file(&cover_art_path, &data)?;
+    Ok(cover_art_path)
+}
+
+/// What [`store_cover_art`] wrote for an album
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StoredCoverArt {
+    /// The `cover.<ext>` file written, if the mode writes one
+    pub sidecar: Option<PathBuf>,
+    /// Audio files the art was embedded into
+    pub embedded: usize,
+}
+
+impl std::fmt::Display for StoredCoverArt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.sidecar {
+            Some(path) if self.embedded > 0 => {
+                write!(f, "{} and {} audio files", path.display(), self.embedded)
+            }
+            Some(path) => write!(f, "{}", path.display()),
+            None => write!(f, "{} audio files", self.embedded),
+        }
+    }
+}
+
+/// Store downloaded album cover art in `image_format` wherever `mode` asks for it
+/// The image is converted once and the same bytes go to the sidecar and the embedded pictures
+pub fn store_cover_art(
+    album_path: &Path,
+    data: &[u8],
+    image_format: CoverFormat,
+    mode: CoverMode,
+) -> Result<StoredCoverArt> {
+    let data = convert_image(data, image_format)?;
+    let mut stored = StoredCoverArt::default();
+    if mode.writes_sidecar() {
+        let cover_art_path = album_path.join(format!("cover.{}", image_format.extension()));
+        write_via_part_file(&cover_art_path, &data)?;
+        stored.sidecar = Some(cover_art_path);
+    }
+    if mode.embeds() {
+        stored.embedded = embed_art::embed_cover_data(album_path, &data)?.embedded;
+    }
+    Ok(stored)
+}
+
+/// Read an image response in full, failing if the body is shorter than its Content-Length
+/// or is not an image MagickWand can read
+async fn read_image_body(response: reqwest::Response) -> Result<Vec<u8>> {
+    let expected = response.content_length();
+    let data = response.bytes().await?;
+    if let Some(expected) = expected.filter(|&expected| expected != data.len() as u64) {
+        return Err(anyhow::anyhow!(
+            "download truncated at {} of {} bytes",
+            data.len(),
+            expected
+        ));
+    }
+    if new_wand().read_image_blob(&data).is_err() {
+        return Err(anyhow::anyhow!(
+            "downloaded {} bytes that are not a readable image",
+            data.len()
+        ));
+    }
+    Ok(data.to_vec())
+}
+
+/// Download the cover image at `url` from `source`, retrying transfers that fail, come back
+/// truncated or aren't an image, up to [`DOWNLOAD_ATTEMPTS`] tries in all
+/// An error status from the server is final and gives None
+async fn download_image(
+    client: &reqwest::Client,
+    url: &str,
+    source: &str,
+    progress: &dyn ProgressSink,
+) -> Result<Option<Vec<u8>>> {
+    let mut last_error = None;
+    for attempt in 1..=DOWNLOAD_ATTEMPTS {
+        if attempt > 1 {
+            tokio::time::sleep(Duration::from_millis(250 * u64::from(attempt - 1))).await;
+        }
+        let result = match client.get(url).send().await {
+            Ok(response) if response.status().is_success() => read_image_body(response).await,
+            Ok(response) => {
+                progress.custom(format!(
+                    "Cover art not available from {} (status: {})",
+                    source,
+                    response.status()
+                ));
+                return Ok(None);
+            }
+            Err(e) => Err(e.into()),
+        };
+        match result {
+            Ok(data) => return Ok(Some(data)),
+            Err(e) => {
+                progress.custom(format!(
+                    "Cover art download from {} failed (attempt {}/{}): {}",
+                    source, attempt, DOWNLOAD_ATTEMPTS, e
+                ));
+                last_error = Some(e);
+            }
+        }
+    }
+    Err(last_error
+        .unwrap_or_else(|| anyhow::anyhow!("no download attempts made"))
+        .context(format!(
+            "Failed to download cover art from {} after {} attempts",
+            source, DOWNLOAD_ATTEMPTS
+        )))
+}
+
 /// Fetch cover art from MusicBrainz Cover Art Archive
 pub async fn fetch_musicbrainz_cover_art(
+    network: &Network,
+    release_id: &str,
+    progress: &dyn ProgressSink,
+) -> Result<Option<Vec<u8>>> {
+    fetch_cover_art_archive(
+        network,
+        &musicbrainz::cover_art_archive_base(),
+        release_id,
+        progress,
+    )
+    .await
+}
+
+async fn fetch_cover_art_archive(
+    network: &Network,
+    cover_art_archive: &str,
     release_id: &str,
-    tx: &mpsc::Sender<String>,
+    progress: &dyn ProgressSink,
 ) -> Result<Option<Vec<u8>>> {
-    tx.send(format!(
+    if network.is_offline() {
+        return Ok(None);
+    }
+
+    progress.custom(format!(
         "Fetching cover art from MusicBrainz for release: {}",
         release_id
-    ))
-    .context("Failed to send cover art fetch message")?;
+    ));
 
-    let cover_art_url = format!("https://coverartarchive.org/release/{}/front", release_id);
-    let client = reqwest::Client::new();
+    let cover_art_url = format!("{}/release/{}/front", cover_art_archive, release_id);
+    let client = network.http_client()?;
 
-    match client
-        .get(&cover_art_url)
-        .header(
-            "User-Agent",
-            "mfutil/0.1.1 (https://github.com/anoraktrend/music-folder-utils)",
-        )
-        .send()
-        .await
-    {
-        Ok(response) => {
-            if response.status().is_success() {
-                match response.bytes().await {
-                    Ok(image_data) => {
-                        tx.send("Successfully fetched cover art from MusicBrainz".to_string())
-                            .context("Failed to send cover art success message")?;
-                        Ok(Some(image_data.to_vec()))
-                    }
-                    Err(e) => {
-                        tx.send(format!("Failed to read cover art data: {}", e))
-                            .context("Failed to send cover art data error")?;
-                        Ok(None)
-                    }
-                }
-            } else {
-                tx.send(format!(
-                    "Cover art not available from MusicBrainz (status: {})",
-                    response.status()
-                ))
-                .context("Failed to send cover art unavailable message")?;
-                Ok(None)
-            }
+    match download_image(client, &cover_art_url, "MusicBrainz", progress).await {
+        Ok(Some(image_data)) => {
+            progress.custom("Successfully fetched cover art from MusicBrainz");
+            Ok(Some(image_data))
         }
+        Ok(None) => Ok(None),
         Err(e) => {
-            tx.send(format!("Failed to fetch cover art from MusicBrainz: {}", e))
-                .context("Failed to send cover art fetch error")?;
+            progress.custom(format!("{:#}", e));
             Ok(None)
         }
     }
 }
 
+/// Cover Art Archive `release/<id>` listing, limited to the fields artwork lookups use
+#[derive(Debug, Deserialize)]
+struct CaaListing {
+    #[serde(default)]
+    images: Vec<CaaImage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CaaImage {
+    /// What the image shows (`Back`, `Booklet`, ...); an image can have several types
+    #[serde(default)]
+    types: Vec<String>,
+    /// Whether this is the release's main front cover
+    #[serde(default)]
+    front: bool,
+    /// URL of the full-size image
+    image: String,
+}
+
+/// File names (without extension) and URLs of the artwork besides the main front cover
+/// Each image is named after its first type in lower case (`back`, `booklet`,
+/// `raw-unedited`), numbered from the second of a type on (`booklet-2`); untyped ones are `other`
+fn additional_artwork(listing: &CaaListing) -> Vec<(String, &str)> {
+    let mut seen: FxHashMap<String, usize> = FxHashMap::default();
+    listing
+        .images
+        .iter()
+        .filter(|image| !image.front)
+        .map(|image| {
+            let kind = image.types.first().map_or_else(
+                || "other".to_string(),
+                |kind| {
+                    kind.to_lowercase()
+                        .split(|c: char| !c.is_alphanumeric())
+                        .filter(|word| !word.is_empty())
+                        .collect::<Vec<_>>()
+                        .join("-")
+                },
+            );
+            let count = seen.entry(kind.clone()).or_default();
+            *count += 1;
+            let name = match *count {
+                1 => kind,
+                n => format!("{}-{}", kind, n),
+            };
+            (name, image.image.as_str())
+        })
+        .collect()
+}
+
+/// Save a release's Cover Art Archive artwork other than the main front cover (back, booklet
+/// pages and so on) in `image_format` next to the album's cover, returning the files written
+/// The front cover is left to the usual single download per album, and only it is embedded;
+/// images that fail to download are reported and skipped
+pub async fn save_additional_artwork(
+    network: &Network,
+    album_path: &Path,
+    release_id: &str,
+    image_format: CoverFormat,
+    progress: &dyn ProgressSink,
+) -> Result<Vec<PathBuf>> {
+    save_additional_artwork_from(
+        network,
+        &musicbrainz::cover_art_archive_base(),
+        album_path,
+        release_id,
+        image_format,
+        progress,
+    )
+    .await
+}
+
+async fn save_additional_artwork_from(
+    network: &Network,
+    cover_art_archive: &str,
+    album_path: &Path,
+    release_id: &str,
+    image_format: CoverFormat,
+    progress: &dyn ProgressSink,
+) -> Result<Vec<PathBuf>> {
+    if network.is_offline() {
+        return Ok(Vec::new());
+    }
+
+    let client = network.http_client()?;
+    let listing_url = format!("{}/release/{}", cover_art_archive, release_id);
+    let response = client.get(&listing_url).send().await?;
+    if !response.status().is_success() {
+        progress.custom(format!(
+            "No artwork listed on MusicBrainz for release {} (status: {})",
+            release_id,
+            response.status()
+        ));
+        return Ok(Vec::new());
+    }
+    let listing: CaaListing = response.json().await?;
+
+    let mut saved = Vec::new();
+    for (name, url) in additional_artwork(&listing) {
+        let data = match download_image(client, url, "MusicBrainz", progress).await {
+            Ok(Some(data)) => data,
+            Ok(None) => continue,
+            Err(e) => {
+                progress.custom(format!("{:#}", e));
+                continue;
+            }
+        };
+        let path = album_path.join(format!("{}.{}", name, image_format.extension()));
+        match convert_image(&data, image_format).and_then(|data| write_via_part_file(&path, &data))
+        {
+            Ok(()) => saved.push(path),
+            Err(e) => warn!("Failed to save {}: {:#}", path.display(), e),
+        }
+    }
+    Ok(saved)
+}
+
+/// AudioDB `searchalbum.php` response, limited to the fields cover art lookups use
+#[derive(Debug, Deserialize)]
+struct AudioDbAlbumResponse {
+    /// Matching albums; AudioDB sends `null` rather than an empty list when there are none
+    album: Option<Vec<AudioDbAlbum>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AudioDbAlbum {
+    #[serde(rename = "strAlbumThumb")]
+    album_thumb: Option<String>,
+}
+
+impl AudioDbAlbum {
+    /// The album's cover image URL, unless AudioDB has none (empty, `null` or `"null"`)
+    fn thumb_url(&self) -> Option<&str> {
+        self.album_thumb
+            .as_deref()
+            .filter(|url| !url.is_empty() && *url != "null")
+    }
+}
+
 /// Fetch cover art from AudioDB as fallback
 pub async fn fetch_audiodb_cover_art(
+    network: &Network,
     artist: &str,
     album: &str,
-    tx: &mpsc::Sender<String>,
+    progress: &dyn ProgressSink,
 ) -> Result<Option<Vec<u8>>> {
-    tx.send(format!(
+    if network.is_offline() {
+        return Ok(None);
+    }
+
+    progress.custom(format!(
         "Trying AudioDB for cover art: {} - {}",
         artist, album
-    ))
-    .context("Failed to send AudioDB cover art message")?;
+    ));
 
     let encoded_artist = urlencoding::encode(artist);
     let encoded_album = urlencoding::encode(album);
     let audiodb_url = format!(
-        "https://www.theaudiodb.com/api/v1/json/2/searchalbum.php?s={}&a={}",
-        encoded_artist, encoded_album
+        "{}/searchalbum.php?s={}&a={}",
+        AUDIODB_API_URL, encoded_artist, encoded_album
     );
 
-    let client = reqwest::Client::new();
+    let client = network.http_client()?;
 
-    match client
-        .get(&audiodb_url)
-        .header(
-            "User-Agent",
-            "mfutil/0.1.1 (https://github.com/anoraktrend/music-folder-utils)",
-        )
-        .send()
-        .await
-    {
+    let response = match client.get(&audiodb_url).send().await {
+        Ok(response) if response.status().is_success() => response,
         Ok(response) => {
-            if response.status().is_success() {
-                match response.json::<serde_json::Value>().await {
-                    Ok(json_data) => {
-                        if let Some(albums) = json_data.get("album") {
-                            if let Some(albums_array) = albums.as_array() {
-                                if let Some(first_album) = albums_array.first() {
-                                    if let Some(thumbnail_url) = first_album.get("strAlbumThumb") {
-                                        if let Some(url_str) = thumbnail_url.as_str() {
-                                            if !url_str.is_empty() && url_str != "null" {
-                                                match client.get(url_str)
-                                                    .header("User-Agent", "mfutil/0.1.1 (https://github.com/anoraktrend/music-folder-utils)")
-                                                    .send()
-                                                    .await
-                                                {
-                                                    Ok(image_response) => {
-                                                        if image_response.status().is_success() {
-                                                            match image_response.bytes().await {
-                                                                Ok(image_data) => {
-                                                                    tx.send("Successfully fetched cover art from AudioDB".to_string())
-                                                                        .context("Failed to send AudioDB success message")?;
-                                                                    Ok(Some(image_data.to_vec()))
-                                                                }
-                                                                Err(e) => {
-                                                                    tx.send(format!("Failed to download AudioDB cover art: {}", e))
-                                                                        .context("Failed to send AudioDB download error")?;
-                                                                    Ok(None)
-                                                                }
-                                                            }
-                                                        } else {
-                                                            tx.send("AudioDB cover art download failed".to_string())
-                                                                .context("Failed to send AudioDB download failed")?;
-                                                            Ok(None)
-                                                        }
-                                                    }
-                                                    Err(e) => {
-                                                        tx.send(format!("Failed to fetch from AudioDB URL: {}", e))
-                                                            .context("Failed to send AudioDB URL error")?;
-                                                        Ok(None)
-                                                    }
-                                                }
-                                            } else {
-                                                tx.send(
-                                                    "No cover art URL found in AudioDB response"
-                                                        .to_string(),
-                                                )
-                                                .context("Failed to send no AudioDB URL message")?;
-                                                Ok(None)
-                                            }
-                                        } else {
-                                            tx.send(
-                                                "No cover art URL found in AudioDB response"
-                                                    .to_string(),
-                                            )
-                                            .context("Failed to send no AudioDB URL message")?;
-                                            Ok(None)
-                                        }
-                                    } else {
-                                        tx.send(
-                                            "No cover art found in AudioDB response".to_string(),
-                                        )
-                                        .context("Failed to send no AudioDB cover art")?;
-                                        Ok(None)
-                                    }
-                                } else {
-                                    tx.send("No albums found in AudioDB response".to_string())
-                                        .context("Failed to send no AudioDB albums")?;
-                                    Ok(None)
-                                }
-                            } else {
-                                tx.send("Invalid AudioDB response format".to_string())
-                                    .context("Failed to send invalid AudioDB format")?;
-                                Ok(None)
-                            }
-                        } else {
-                            tx.send("No album data in AudioDB response".to_string())
-                                .context("Failed to send no AudioDB album data")?;
-                            Ok(None)
-                        }
-                    }
-                    Err(e) => {
-                        tx.send(format!("Failed to parse AudioDB response: {}", e))
-                            .context("Failed to send AudioDB parse error")?;
-                        Ok(None)
-                    }
-                }
-            } else {
-                tx.send(format!(
-                    "AudioDB request failed (status: {})",
-                    response.status()
-                ))
-                .context("Failed to send AudioDB request failed")?;
-                Ok(None)
-            }
+            progress.custom(format!(
+                "AudioDB request failed (status: {})",
+                response.status()
+            ));
+            return Ok(None);
         }
         Err(e) => {
-            tx.send(format!("Failed to fetch from AudioDB: {}", e))
-                .context("Failed to send AudioDB fetch error")?;
+            progress.custom(format!("Failed to fetch from AudioDB: {}", e));
+            return Ok(None);
+        }
+    };
+
+    let albums = match response.json::<AudioDbAlbumResponse>().await {
+        Ok(parsed) => parsed.album.unwrap_or_default(),
+        Err(e) => {
+            progress.custom(format!("Failed to parse AudioDB response: {}", e));
+            return Ok(None);
+        }
+    };
+    let Some(first_album) = albums.first() else {
+        progress.custom("No albums found in AudioDB response");
+        return Ok(None);
+    };
+    let Some(url_str) = first_album.thumb_url() else {
+        progress.custom("No cover art URL found in AudioDB response");
+        return Ok(None);
+    };
+
+    match download_image(client, url_str, "AudioDB", progress).await {
+        Ok(Some(image_data)) => {
+            progress.custom("Successfully fetched cover art from AudioDB");
+            Ok(Some(image_data))
+        }
+        Ok(None) => Ok(None),
+        Err(e) => {
+            progress.custom(format!("{:#}", e));
             Ok(None)
         }
     }
 }
 
-/// Save cover art to album directory
+/// Which cover art sources have art for an album, as found by [`probe_cover_art_sources`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CoverArtAvailability {
+    pub artist: String,
+    pub album: String,
+    /// The MusicBrainz release the Cover Art Archive was asked about, if one was known
+    pub release_id: Option<String>,
+    pub cover_art_archive: bool,
+    pub audiodb: bool,
+}
+
+impl CoverArtAvailability {
+    /// Whether any source has art for the album
+    pub fn any(&self) -> bool {
+        self.cover_art_archive || self.audiodb
+    }
+}
+
+impl std::fmt::Display for CoverArtAvailability {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let yes_no = |available: bool| if available { "yes" } else { "no" };
+        write!(f, "{} - {}: Cover Art Archive: ", self.artist, self.album)?;
+        if self.release_id.is_some() {
+            write!(f, "{}", yes_no(self.cover_art_archive))?;
+        } else {
+            write!(f, "no release ID")?;
+        }
+        write!(f, ", AudioDB: {}", yes_no(self.audiodb))
+    }
+}
+
+/// Check which sources have cover art for an album without downloading any images
+/// The Cover Art Archive gets a HEAD request for `release_id`'s front image and AudioDB a
+/// single album search; requests that fail count as unavailable
+pub async fn probe_cover_art_sources(
+    network: &Network,
+    artist: &str,
+    album: &str,
+    release_id: Option<&str>,
+) -> Result<CoverArtAvailability> {
+    probe_cover_art_sources_from(
+        network,
+        &musicbrainz::cover_art_archive_base(),
+        AUDIODB_API_URL,
+        artist,
+        album,
+        release_id,
+    )
+    .await
+}
+
+async fn probe_cover_art_sources_from(
+    network: &Network,
+    cover_art_archive: &str,
+    audiodb: &str,
+    artist: &str,
+    album: &str,
+    release_id: Option<&str>,
+) -> Result<CoverArtAvailability> {
+    if network.is_offline() {
+        anyhow::bail!("Cannot probe cover art sources while offline");
+    }
+    let client = network.http_client()?;
+
+    let cover_art_archive = match release_id {
+        Some(release_id) => {
+            let url = format!("{}/release/{}/front", cover_art_archive, release_id);
+            match client.head(&url).send().await {
+                Ok(response) => response.status().is_success(),
+                Err(e) => {
+                    warn!("Cover Art Archive probe failed for {}: {}", release_id, e);
+                    false
+                }
+            }
+        }
+        None => false,
+    };
+
+    let url = format!(
+        "{}/searchalbum.php?s={}&a={}",
+        audiodb,
+        urlencoding::encode(artist),
+        urlencoding::encode(album)
+    );
+    let audiodb = match client.get(&url).send().await {
+        Ok(response) if response.status().is_success() => response
+            .json::<AudioDbAlbumResponse>()
+            .await
+            .is_ok_and(|parsed| audiodb_album_thumb(&parsed).is_some()),
+        Ok(_) => false,
+        Err(e) => {
+            warn!("AudioDB probe failed for {} - {}: {}", artist, album, e);
+            false
+        }
+    };
+
+    Ok(CoverArtAvailability {
+        artist: artist.to_string(),
+        album: album.to_string(),
+        release_id: release_id.map(str::to_string),
+        cover_art_archive,
+        audiodb,
+    })
+}
+
+/// The cover art URL of the first album in an AudioDB search response, if it has one
+fn audiodb_album_thumb(response: &AudioDbAlbumResponse) -> Option<&str> {
+    response.album.as_deref()?.first()?.thumb_url()
+}
+
+/// Fetch an album's cover art and store it as `cover` says
 pub async fn save_cover_art_to_album(
+    network: &Network,
     album_path: &Path,
     release_id: &str,
     artist: &str,
     album: &str,
-    tx: &mpsc::Sender<String>,
+    cover: CoverSettings,
+    progress: &dyn ProgressSink,
 ) -> Result<()> {
+    save_cover_art_from(
+        network,
+        &musicbrainz::cover_art_archive_base(),
+        &CoverArtJob {
+            album_path: album_path.to_path_buf(),
+            release_id: release_id.to_string(),
+            artist: artist.to_string(),
+            album: album.to_string(),
+        },
+        cover,
+        progress,
+    )
+    .await
+}
+
+async fn save_cover_art_from(
+    network: &Network,
+    cover_art_archive: &str,
+    job: &CoverArtJob,
+    cover: CoverSettings,
+    progress: &dyn ProgressSink,
+) -> Result<()> {
+    let CoverArtJob {
+        album_path,
+        release_id,
+        artist,
+        album,
+    } = job;
+
     // Try MusicBrainz first
-    if let Ok(Some(cover_art)) = fetch_musicbrainz_cover_art(release_id, tx).await {
-        let cover_art_path = album_path.join("cover.jpg");
-        if let Err(e) = std::fs::write(&cover_art_path, &cover_art) {
-            tracing::warn!(
-                "Failed to save MusicBrainz cover art to {:?}: {}",
-                cover_art_path,
-                e
-            );
-            // Try AudioDB as fallback
-            if let Ok(Some(audiodb_cover_art)) = fetch_audiodb_cover_art(artist, album, tx).await {
-                if let Err(e) = std::fs::write(&cover_art_path, &audiodb_cover_art) {
-                    tracing::warn!(
-                        "Failed to save AudioDB cover art to {:?}: {}",
-                        cover_art_path,
-                        e
-                    );
-                } else {
-                    tx.send(format!(
-                        "Saved AudioDB cover art to: {}",
-                        cover_art_path.display()
-                    ))
-                    .context("Failed to send AudioDB cover art save message")?;
-                }
+    if let Some(cover_art) =
+        fetch_cover_art_archive(network, cover_art_archive, release_id, progress)
+            .await
+            .ok()
+            .flatten()
+            .filter(|art| accept_cover_art(art, "MusicBrainz", cover.min_size, progress))
+    {
+        match store_cover_art(album_path, &cover_art, cover.format, cover.mode) {
+            Ok(stored) => {
+                progress.custom(format!("Saved MusicBrainz cover art to: {}", stored));
+                return Ok(());
             }
-        } else {
-            tx.send(format!(
-                "Saved MusicBrainz cover art to: {}",
-                cover_art_path.display()
-            ))
-            .context("Failed to send MusicBrainz cover art save message")?;
+            Err(e) => tracing::warn!(
+                "Failed to save MusicBrainz cover art to {}: {}",
+                album_path.display(),
+                e
+            ),
+        }
+    }
+
+    // Try AudioDB as fallback
+    if let Some(cover_art) = fetch_audiodb_cover_art(network, artist, album, progress)
+        .await
+        .ok()
+        .flatten()
+        .filter(|art| accept_cover_art(art, "AudioDB", cover.min_size, progress))
+    {
+        match store_cover_art(album_path, &cover_art, cover.format, cover.mode) {
+            Ok(stored) => progress.custom(format!("Saved AudioDB cover art to: {}", stored)),
+            Err(e) => tracing::warn!(
+                "Failed to save AudioDB cover art to {}: {}",
+                album_path.display(),
+                e
+            ),
         }
     } else {
-        // Try AudioDB as fallback
-        if let Ok(Some(cover_art)) = fetch_audiodb_cover_art(artist, album, tx).await {
-            let cover_art_path = album_path.join("cover.jpg");
-            if let Err(e) = std::fs::write(&cover_art_path, &cover_art) {
-                tracing::warn!(
-                    "Failed to save AudioDB cover art to {:?}: {}",
-                    cover_art_path,
-                    e
+        progress.custom("No cover art found from any source");
+    }
+
+    Ok(())
+}
+
+/// An album to fetch cover art for with [`save_cover_art_batch`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoverArtJob {
+    pub album_path: PathBuf,
+    pub release_id: String,
+    pub artist: String,
+    pub album: String,
+}
+
+/// Fetch and save cover art for many albums, at most `max_concurrent` at a time (and no more
+/// than [`download_permits`] allows across all batches running at once)
+/// Each album tries the same sources as [`save_cover_art_to_album`]; a progress line is sent
+/// as each one finishes. Only the first job for a folder is fetched, since later ones
+/// would overwrite the same cover file
+pub async fn save_cover_art_batch(
+    network: &Network,
+    jobs: Vec<CoverArtJob>,
+    max_concurrent: usize,
+    cover: CoverSettings,
+    progress: &dyn ProgressSink,
+) -> Result<()> {
+    save_cover_art_batch_from(
+        network,
+        &musicbrainz::cover_art_archive_base(),
+        jobs,
+        max_concurrent,
+        cover,
+        progress,
+    )
+    .await
+}
+
+async fn save_cover_art_batch_from(
+    network: &Network,
+    cover_art_archive: &str,
+    jobs: Vec<CoverArtJob>,
+    max_concurrent: usize,
+    cover: CoverSettings,
+    progress: &dyn ProgressSink,
+) -> Result<()> {
+    let mut seen_folders = FxHashSet::default();
+    let jobs: Vec<_> = jobs
+        .into_iter()
+        .filter(|job| seen_folders.insert(job.album_path.clone()))
+        .collect();
+    let total = jobs.len();
+
+    let permits = Arc::new(Semaphore::new(max_concurrent.max(1)));
+    let mut tasks = JoinSet::new();
+    for job in jobs {
+        let permits = Arc::clone(&permits);
+        let network = network.clone();
+        let cover_art_archive = cover_art_archive.to_string();
+        tasks.spawn(async move {
+            let _permit = permits.acquire_owned().await?;
+            let _download = download_permits().acquire().await?;
+            let result =
+                save_cover_art_from(&network, &cover_art_archive, &job, cover, &NoopSink).await;
+            Ok::<_, anyhow::Error>((job, result))
+        });
+    }
+
+    let mut done = 0;
+    while let Some(joined) = tasks.join_next().await {
+        let (job, result) = joined??;
+        done += 1;
+        match result {
+            Ok(()) => progress.custom(format!(
+                "Cover art {}/{}: {} - {}",
+                done, total, job.artist, job.album
+            )),
+            Err(e) => warn!(
+                "Failed to fetch cover art for {} - {}: {}",
+                job.artist, job.album, e
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::CollectingSink;
+    use crate::test_support::write_silent_wav;
+    use lofty::file::TaggedFileExt;
+    use magick_rust::PixelWand;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use tempfile::TempDir;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Serve `/release/<id>/front` from `images`, tracking the most requests in flight at once
+    async fn serve_cover_art(
+        listener: TcpListener,
+        images: Arc<FxHashMap<String, Vec<u8>>>,
+        in_flight: Arc<AtomicUsize>,
+        peak: Arc<AtomicUsize>,
+    ) {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let (images, in_flight, peak) = (images.clone(), in_flight.clone(), peak.clone());
+            tokio::spawn(async move {
+                let mut request = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match socket.read(&mut buf).await {
+                        Ok(0) | Err(_) => return,
+                        Ok(n) => request.extend_from_slice(&buf[..n]),
+                    }
+                }
+                let request = String::from_utf8_lossy(&request);
+                let release_id = request
+                    .split_whitespace()
+                    .nth(1)
+                    .and_then(|path| path.split('/').nth(2))
+                    .unwrap_or_default()
+                    .to_string();
+
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+
+                let image = &images[&release_id];
+                let header = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    image.len()
                 );
+                let _ = socket.write_all(header.as_bytes()).await;
+                let _ = socket.write_all(image).await;
+            });
+        }
+    }
+
+    #[tokio::test]
+    async fn test_save_cover_art_batch_bounds_concurrency() -> Result<()> {
+        let network = Network::default();
+        let temp_dir = TempDir::new()?;
+
+        // A differently sized JPEG per release, so each album's file can be told apart
+        let mut images = FxHashMap::default();
+        let mut jobs = Vec::new();
+        for i in 0..6 {
+            let wand = new_wand();
+            let mut background = PixelWand::new();
+            background.set_color("blue")?;
+            wand.new_image(i + 1, 2, &background)?;
+            let release_id = format!("release-{}", i);
+            images.insert(release_id.clone(), wand.write_image_blob("jpeg")?);
+
+            let album_path = temp_dir.path().join(format!("Album {}", i));
+            std::fs::create_dir(&album_path)?;
+            jobs.push(CoverArtJob {
+                album_path,
+                release_id,
+                artist: "Artist".to_string(),
+                album: format!("Album {}", i),
+            });
+        }
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let base_url = format!("http://{}", listener.local_addr()?);
+        let images = Arc::new(images);
+        let peak = Arc::new(AtomicUsize::new(0));
+        tokio::spawn(serve_cover_art(
+            listener,
+            images.clone(),
+            Arc::new(AtomicUsize::new(0)),
+            peak.clone(),
+        ));
+
+        let sink = CollectingSink::new();
+        save_cover_art_batch_from(
+            &network,
+            &base_url,
+            jobs.clone(),
+            2,
+            CoverSettings::default(),
+            &sink,
+        )
+        .await?;
+
+        assert!(peak.load(Ordering::SeqCst) <= 2);
+        for job in &jobs {
+            let cover = std::fs::read(job.album_path.join("cover.jpg"))?;
+            assert_eq!(cover, images[&job.release_id]);
+        }
+        assert_eq!(sink.messages().len(), jobs.len());
+
+        Ok(())
+    }
+
+    /// Serve `image` for every request, but cut the first `truncated` responses short of
+    /// their Content-Length, as a dropped connection would; counts the requests made
+    async fn serve_truncated_then_full(
+        listener: TcpListener,
+        image: Vec<u8>,
+        truncated: usize,
+        requests: Arc<AtomicUsize>,
+    ) {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let mut request = Vec::new();
+            let mut buf = [0u8; 1024];
+            while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                match socket.read(&mut buf).await {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => request.extend_from_slice(&buf[..n]),
+                }
+            }
+            let header = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                image.len()
+            );
+            let body = if requests.fetch_add(1, Ordering::SeqCst) < truncated {
+                &image[..image.len() / 2]
             } else {
-                tx.send(format!(
-                    "Saved AudioDB cover art to: {}",
-                    cover_art_path.display()
-                ))
-                .context("Failed to send AudioDB cover art save message")?;
+                &image[..]
+            };
+            let _ = socket.write_all(header.as_bytes()).await;
+            let _ = socket.write_all(body).await;
+            let _ = socket.shutdown().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_truncated_cover_art_download_is_retried_and_never_stored() -> Result<()> {
+        let network = Network::default();
+        let temp_dir = TempDir::new()?;
+        let wand = new_wand();
+        let mut background = PixelWand::new();
+        background.set_color("blue")?;
+        wand.new_image(64, 64, &background)?;
+        let image = wand.write_image_blob("jpeg")?;
+        let album_files = |album: &Path| -> Result<Vec<String>> {
+            let mut names: Vec<String> = std::fs::read_dir(album)?
+                .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+                .collect::<Result<_>>()?;
+            names.sort();
+            Ok(names)
+        };
+
+        // One short read, then a full one: the retry gets the whole image
+        let album_path = temp_dir.path().join("Retried");
+        std::fs::create_dir(&album_path)?;
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let base_url = format!("http://{}", listener.local_addr()?);
+        let requests = Arc::new(AtomicUsize::new(0));
+        tokio::spawn(serve_truncated_then_full(
+            listener,
+            image.clone(),
+            1,
+            requests.clone(),
+        ));
+        let job = CoverArtJob {
+            album_path: album_path.clone(),
+            release_id: "release".to_string(),
+            artist: "Artist".to_string(),
+            album: "Retried".to_string(),
+        };
+        save_cover_art_from(
+            &network,
+            &base_url,
+            &job,
+            CoverSettings::default(),
+            &NoopSink,
+        )
+        .await?;
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+        assert_eq!(std::fs::read(album_path.join("cover.jpg"))?, image);
+        assert_eq!(album_files(&album_path)?, ["cover.jpg"]);
+
+        // Every read cut short: nothing comes back, so nothing can be stored
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let base_url = format!("http://{}", listener.local_addr()?);
+        let requests = Arc::new(AtomicUsize::new(0));
+        tokio::spawn(serve_truncated_then_full(
+            listener,
+            image.clone(),
+            usize::MAX,
+            requests.clone(),
+        ));
+        let fetched = fetch_cover_art_archive(&network, &base_url, "release", &NoopSink).await?;
+        assert_eq!(fetched, None);
+        assert_eq!(requests.load(Ordering::SeqCst), DOWNLOAD_ATTEMPTS as usize);
+
+        // A write that fails part way leaves neither the cover nor its partial file
+        let missing = temp_dir.path().join("Missing");
+        assert!(write_cover_art(&missing, &image, CoverFormat::Jpeg).is_err());
+        assert!(!missing.exists());
+        let blocked = temp_dir.path().join("Blocked");
+        std::fs::create_dir(&blocked)?;
+        std::fs::create_dir(blocked.join("cover.jpg"))?;
+        assert!(write_cover_art(&blocked, &image, CoverFormat::Jpeg).is_err());
+        assert_eq!(album_files(&blocked)?, ["cover.jpg"]);
+
+        Ok(())
+    }
+
+    /// Serve a Cover Art Archive listing for `release` with a front cover, a back cover and
+    /// two booklet pages, and each of their images; every request path is recorded
+    async fn serve_artwork(
+        listener: TcpListener,
+        image: Vec<u8>,
+        requests: Arc<std::sync::Mutex<Vec<String>>>,
+    ) {
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let (image, requests, base_url) = (image.clone(), requests.clone(), base_url.clone());
+            tokio::spawn(async move {
+                let mut request = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match socket.read(&mut buf).await {
+                        Ok(0) | Err(_) => return,
+                        Ok(n) => request.extend_from_slice(&buf[..n]),
+                    }
+                }
+                let request = String::from_utf8_lossy(&request);
+                let path = request.split_whitespace().nth(1).unwrap_or_default();
+                requests.lock().unwrap().push(path.to_string());
+
+                let (content_type, body) = match path {
+                    "/release/release" => (
+                        "application/json",
+                        format!(
+                            r#"{{"images":[
+                                {{"types":["Front"],"front":true,"back":false,"image":"{0}/img/front.jpg"}},
+                                {{"types":["Back"],"front":false,"back":true,"image":"{0}/img/back.jpg"}},
+                                {{"types":["Booklet"],"front":false,"back":false,"image":"{0}/img/booklet1.jpg"}},
+                                {{"types":["Booklet","Tray"],"front":false,"back":false,"image":"{0}/img/booklet2.jpg"}}
+                            ],"release":"{0}/release/release"}}"#,
+                            base_url
+                        )
+                        .into_bytes(),
+                    ),
+                    _ => ("image/jpeg", image),
+                };
+                let header = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    content_type,
+                    body.len()
+                );
+                let _ = socket.write_all(header.as_bytes()).await;
+                let _ = socket.write_all(&body).await;
+            });
+        }
+    }
+
+    #[tokio::test]
+    async fn test_save_additional_artwork_names_each_type() -> Result<()> {
+        let network = Network::default();
+        let temp_dir = TempDir::new()?;
+        let wand = new_wand();
+        let mut background = PixelWand::new();
+        background.set_color("blue")?;
+        wand.new_image(8, 8, &background)?;
+        let image = wand.write_image_blob("jpeg")?;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let base_url = format!("http://{}", listener.local_addr()?);
+        let requests = Arc::new(std::sync::Mutex::new(Vec::new()));
+        tokio::spawn(serve_artwork(listener, image, requests.clone()));
+
+        let saved = save_additional_artwork_from(
+            &network,
+            &base_url,
+            temp_dir.path(),
+            "release",
+            CoverFormat::Png,
+            &NoopSink,
+        )
+        .await?;
+        let names: Vec<_> = saved
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, ["back.png", "booklet.png", "booklet-2.png"]);
+        for path in &saved {
+            assert!(new_wand().read_image(path.to_str().unwrap()).is_ok());
+        }
+
+        // The front cover is fetched once per album elsewhere, never again here
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 4);
+        assert!(!requests.iter().any(|path| path.contains("front")));
+        assert!(!temp_dir.path().join("cover.png").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cover_format_from_str() {
+        assert_eq!("JPG".parse(), Ok(CoverFormat::Jpeg));
+        assert_eq!("png".parse(), Ok(CoverFormat::Png));
+        assert_eq!("WebP".parse(), Ok(CoverFormat::WebP));
+        assert!("gif".parse::<CoverFormat>().is_err());
+        assert_eq!(CoverFormat::default().extension(), "jpg");
+    }
+
+    #[test]
+    fn test_store_cover_art_modes() -> Result<()> {
+        let wand = new_wand();
+        let mut background = PixelWand::new();
+        background.set_color("red")?;
+        wand.new_image(2, 2, &background)?;
+        let png = wand.write_image_blob("png")?;
+
+        for (mode, sidecar, embedded) in [
+            (CoverMode::Sidecar, true, 0),
+            (CoverMode::Embed, false, 1),
+            (CoverMode::Both, true, 1),
+        ] {
+            let temp_dir = TempDir::new()?;
+            let album = temp_dir.path();
+            let track = album.join("01.wav");
+            write_silent_wav(&track)?;
+
+            let stored = store_cover_art(album, &png, CoverFormat::Jpeg, mode)?;
+
+            let cover_path = album.join("cover.jpg");
+            assert_eq!(cover_path.is_file(), sidecar, "{:?}", mode);
+            assert_eq!(stored.sidecar.is_some(), sidecar, "{:?}", mode);
+            assert_eq!(stored.embedded, embedded, "{:?}", mode);
+
+            let tagged_file = lofty::read_from_path(&track)?;
+            let pictures: Vec<_> = tagged_file
+                .tags()
+                .iter()
+                .flat_map(|tag| tag.pictures())
+                .collect();
+            assert_eq!(pictures.len(), embedded, "{:?}", mode);
+            if let Some(picture) = pictures.first() {
+                assert!(picture.data().starts_with(b"\xFF\xD8"), "{:?}", mode);
+            }
+            // Both mode embeds exactly the bytes written to the sidecar
+            if mode == CoverMode::Both {
+                assert_eq!(pictures[0].data(), std::fs::read(&cover_path)?);
             }
-        } else {
-            tx.send("No cover art found from any source".to_string())
-                .context("Failed to send no cover art message")?;
         }
+
+        Ok(())
     }
 
-    Ok(())
+    #[test]
+    fn test_cover_mode_from_str() {
+        assert_eq!("Sidecar".parse(), Ok(CoverMode::Sidecar));
+        assert_eq!("embed".parse(), Ok(CoverMode::Embed));
+        assert_eq!("BOTH".parse(), Ok(CoverMode::Both));
+        assert!("tags".parse::<CoverMode>().is_err());
+        assert_eq!(CoverMode::default(), CoverMode::Sidecar);
+    }
+
+    #[test]
+    fn test_init_magick_is_idempotent() -> Result<()> {
+        // Repeated and mixed initialization must leave a usable library behind
+        init_magick();
+        magick_wand_genesis();
+        init_magick();
+
+        let wand = new_wand();
+        let mut background = PixelWand::new();
+        background.set_color("green")?;
+        wand.new_image(3, 3, &background)?;
+        let png = convert_image(&wand.write_image_blob("jpeg")?, CoverFormat::Png)?;
+        assert!(png.starts_with(b"\x89PNG"));
+
+        Ok(())
+    }
+
+    /// Answer cover art probes: HEAD requests for `with-art` succeed, AudioDB only knows "Found"
+    /// Every request is recorded so the test can check that no image was downloaded
+    async fn serve_probes(listener: TcpListener, requests: Arc<std::sync::Mutex<Vec<String>>>) {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let requests = requests.clone();
+            tokio::spawn(async move {
+                let mut request = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match socket.read(&mut buf).await {
+                        Ok(0) | Err(_) => return,
+                        Ok(n) => request.extend_from_slice(&buf[..n]),
+                    }
+                }
+                let request = String::from_utf8_lossy(&request);
+                let line: Vec<&str> = request.split_whitespace().take(2).collect();
+                requests.lock().unwrap().push(line.join(" "));
+
+                let (status, body) = match line.as_slice() {
+                    ["HEAD", "/release/with-art/front"] => ("200 OK", String::new()),
+                    ["GET", path] if path.starts_with("/searchalbum.php") => {
+                        let thumb = if path.ends_with("a=Found") {
+                            "\"https://example.com/thumb.jpg\""
+                        } else {
+                            "null"
+                        };
+                        (
+                            "200 OK",
+                            format!("{{\"album\":[{{\"strAlbumThumb\":{}}}]}}", thumb),
+                        )
+                    }
+                    _ => ("404 Not Found", String::new()),
+                };
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            });
+        }
+    }
+
+    #[test]
+    fn test_meets_min_size_rejects_thumbnails() -> Result<()> {
+        let image = |width, height| -> Result<Vec<u8>> {
+            let wand = new_wand();
+            let mut background = PixelWand::new();
+            background.set_color("green")?;
+            wand.new_image(width, height, &background)?;
+            Ok(wand.write_image_blob("jpeg")?)
+        };
+
+        // A 150px thumbnail, and a full-size cover that is only wide enough one way
+        assert!(!meets_min_size(&image(150, 150)?, 300));
+        assert!(!meets_min_size(&image(600, 150)?, 300));
+        assert!(meets_min_size(&image(600, 400)?, 300));
+        assert!(meets_min_size(b"not an image", 300));
+
+        // A rejection says why, and without a minimum every size is taken
+        let sink = CollectingSink::new();
+        assert!(!accept_cover_art(
+            &image(150, 150)?,
+            "AudioDB",
+            Some(300),
+            &sink
+        ));
+        let messages: Vec<String> = sink.messages().iter().map(|m| m.format()).collect();
+        assert_eq!(
+            messages,
+            ["Rejected AudioDB cover art: smaller than 300 px"]
+        );
+        assert!(accept_cover_art(&image(150, 150)?, "AudioDB", None, &sink));
+
+        Ok(())
+    }
+
+    /// Trimmed from a real `searchalbum.php` response
+    const AUDIODB_RESPONSE: &str = r#"{"album":[{
+        "idAlbum": "2115888",
+        "idArtist": "111239",
+        "strAlbum": "Sample Album",
+        "strArtist": "Sample Band",
+        "intYearReleased": "1999",
+        "strGenre": "Rock",
+        "strAlbumThumb": "https://www.theaudiodb.com/images/media/album/thumb/sample.jpg",
+        "strAlbumThumbHQ": null,
+        "strAlbumCDart": "",
+        "intScore": null
+    }]}"#;
+
+    #[test]
+    fn test_audiodb_response_deserializes() -> Result<()> {
+        let response: AudioDbAlbumResponse = serde_json::from_str(AUDIODB_RESPONSE)?;
+        assert_eq!(
+            audiodb_album_thumb(&response),
+            Some("https://www.theaudiodb.com/images/media/album/thumb/sample.jpg")
+        );
+
+        // No match comes back as a null list, and a missing image as null or "null"
+        let none: AudioDbAlbumResponse = serde_json::from_str(r#"{"album":null}"#)?;
+        assert_eq!(audiodb_album_thumb(&none), None);
+        for thumb in ["null", r#""null""#, r#""""#] {
+            let json = format!(r#"{{"album":[{{"strAlbumThumb":{}}}]}}"#, thumb);
+            let response: AudioDbAlbumResponse = serde_json::from_str(&json)?;
+            assert_eq!(audiodb_album_thumb(&response), None);
+        }
+
+        // A changed shape is an error, not a silent miss
+        assert!(
+            serde_json::from_str::<AudioDbAlbumResponse>(r#"{"album":{"strAlbumThumb":1}}"#)
+                .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_probe_cover_art_sources_reports_availability_without_downloading() -> Result<()> {
+        let network = Network::default();
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let base_url = format!("http://{}", listener.local_addr()?);
+        let requests = Arc::new(std::sync::Mutex::new(Vec::new()));
+        tokio::spawn(serve_probes(listener, requests.clone()));
+
+        let found = probe_cover_art_sources_from(
+            &network,
+            &base_url,
+            &base_url,
+            "Artist",
+            "Found",
+            Some("with-art"),
+        )
+        .await?;
+        assert!(found.cover_art_archive && found.audiodb);
+        assert_eq!(
+            found.to_string(),
+            "Artist - Found: Cover Art Archive: yes, AudioDB: yes"
+        );
+
+        let missing = probe_cover_art_sources_from(
+            &network,
+            &base_url,
+            &base_url,
+            "Artist",
+            "Missing",
+            Some("bare"),
+        )
+        .await?;
+        assert!(!missing.any());
+
+        let untagged =
+            probe_cover_art_sources_from(&network, &base_url, &base_url, "Artist", "Found", None)
+                .await?;
+        assert!(!untagged.cover_art_archive && untagged.audiodb);
+        assert_eq!(
+            untagged.to_string(),
+            "Artist - Found: Cover Art Archive: no release ID, AudioDB: yes"
+        );
+
+        // Only the probes themselves went out: no thumbnail or image GETs
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 5);
+        assert!(requests
+            .iter()
+            .all(|r| !r.contains("thumb") && !r.starts_with("GET /release")));
+
+        Ok(())
+    }
 }
@@ -0,0 +1,356 @@
+use crate::utils::Naming;
+use crate::{gapless, metadata};
+use anyhow::{Context, Result};
+use lofty::tag::{Accessor, ItemKey};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+/// Extensions of lossless formats; tracks split from these are encoded as FLAC
+const LOSSLESS_EXTENSIONS: &[&str] = &["flac", "wav", "aiff", "aif", "ape", "wv"];
+
+/// One TRACK of a cue sheet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CueTrack {
+    pub number: u32,
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    /// The FILE holding the track's audio, as named in the sheet
+    pub file: String,
+    /// Where the track starts (its INDEX 01) within that file
+    pub start: Duration,
+    /// Where the next track in the same file starts; None for the file's last track
+    pub end: Option<Duration>,
+}
+
+/// The album-level PERFORMER and TITLE of a cue sheet and its tracks
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CueSheet {
+    pub performer: Option<String>,
+    pub title: Option<String>,
+    pub tracks: Vec<CueTrack>,
+}
+
+/// Parse the text of a cue sheet
+/// Only the commands needed to name and split tracks are read (FILE, TRACK, TITLE, PERFORMER
+/// and INDEX 01); REM lines and the rest are ignored
+pub fn parse_cue(text: &str) -> Result<CueSheet> {
+    let mut sheet = CueSheet::default();
+    let mut file: Option<String> = None;
+    let mut starts: Vec<Option<Duration>> = Vec::new();
+
+    for (line_number, line) in text.lines().enumerate() {
+        let line = line.trim_start_matches('\u{FEFF}').trim();
+        let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        let context = || format!("Invalid cue sheet line {}: {}", line_number + 1, line);
+
+        match command.to_ascii_uppercase().as_str() {
+            "FILE" => {
+                // The file type (WAVE, MP3, ...) follows the name
+                let name = match rest.strip_prefix('"') {
+                    Some(quoted) => quoted.split('"').next().unwrap_or_default(),
+                    None => rest.rsplit_once(' ').map_or(rest, |(name, _)| name),
+                };
+                file = Some(name.to_string());
+            }
+            "TRACK" => {
+                let number = rest
+                    .split_whitespace()
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .with_context(context)?;
+                let file = file.clone().with_context(context)?;
+                sheet.tracks.push(CueTrack {
+                    number,
+                    title: None,
+                    performer: None,
+                    file,
+                    start: Duration::ZERO,
+                    end: None,
+                });
+                starts.push(None);
+            }
+            "TITLE" | "PERFORMER" => {
+                let value = Some(unquote(rest).to_string()).filter(|v| !v.is_empty());
+                let is_title = command.eq_ignore_ascii_case("TITLE");
+                match (sheet.tracks.last_mut(), is_title) {
+                    (Some(track), true) => track.title = value,
+                    (Some(track), false) => track.performer = value,
+                    (None, true) => sheet.title = value,
+                    (None, false) => sheet.performer = value,
+                }
+            }
+            "INDEX" => {
+                let mut parts = rest.split_whitespace();
+                // INDEX 00 is the pregap, which belongs to the previous track
+                if parts.next() == Some("01") {
+                    let start = parts
+                        .next()
+                        .and_then(parse_cue_time)
+                        .with_context(context)?;
+                    let slot = starts.last_mut().with_context(context)?;
+                    *slot = Some(start);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for (track, start) in sheet.tracks.iter_mut().zip(starts) {
+        track.start =
+            start.with_context(|| format!("Cue sheet track {} has no INDEX 01", track.number))?;
+    }
+    let starts: Vec<(String, Duration)> = sheet
+        .tracks
+        .iter()
+        .map(|t| (t.file.clone(), t.start))
+        .collect();
+    for (i, track) in sheet.tracks.iter_mut().enumerate() {
+        track.end = starts
+            .get(i + 1)
+            .filter(|(file, _)| *file == track.file)
+            .map(|(_, start)| *start);
+    }
+
+    Ok(sheet)
+}
+
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+/// Parse an `mm:ss:ff` cue time, where a frame is 1/75 of a second
+fn parse_cue_time(time: &str) -> Option<Duration> {
+    let mut parts = time.split(':').map(|p| p.parse::<u64>().ok());
+    let (minutes, seconds, frames) = (parts.next()??, parts.next()??, parts.next()??);
+    if parts.next().is_some() || seconds >= 60 || frames >= 75 {
+        return None;
+    }
+    Some(
+        Duration::from_secs(minutes * 60 + seconds) + Duration::from_nanos(frames * 40_000_000 / 3),
+    )
+}
+
+/// Read and parse a cue sheet, which may be UTF-8 or, as older rippers wrote them, Latin-1
+pub fn read_cue(path: &Path) -> Result<CueSheet> {
+    let bytes = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let text = String::from_utf8(bytes)
+        .unwrap_or_else(|e| e.into_bytes().iter().map(|&b| char::from(b)).collect());
+    parse_cue(&text).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Find the cue sheet next to `audio_path` that splits it into several tracks
+/// A sheet matches when its FILE names the audio file, or a file with the same stem (sheets
+/// often still name the WAV a FLAC was encoded from). The returned sheet only has the tracks
+/// in this file; sheets with one track per file describe an album that is already split.
+pub fn find_cue_sheet(audio_path: &Path) -> Option<(PathBuf, CueSheet)> {
+    let file_name = audio_path.file_name()?.to_str()?;
+    let stem = audio_path.file_stem()?.to_str()?;
+    let names_this_file = |file: &str| {
+        let named = Path::new(file).file_name().and_then(|n| n.to_str());
+        let named_stem = Path::new(file).file_stem().and_then(|n| n.to_str());
+        named == Some(file_name) || named_stem == Some(stem)
+    };
+
+    let mut cue_paths: Vec<PathBuf> = fs::read_dir(audio_path.parent()?)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            p.is_file()
+                && p.extension()
+                    .and_then(|e| e.to_str())
+                    .is_some_and(|e| e.eq_ignore_ascii_case("cue"))
+        })
+        .collect();
+    cue_paths.sort();
+
+    cue_paths.into_iter().find_map(|cue_path| {
+        let mut sheet = read_cue(&cue_path).ok()?;
+        sheet.tracks.retain(|track| names_this_file(&track.file));
+        (sheet.tracks.len() > 1).then_some((cue_path, sheet))
+    })
+}
+
+/// Extension for tracks split from `source`: FLAC for lossless sources, otherwise the source's
+/// own, since lossy audio is cut without re-encoding
+pub fn split_extension(source: &Path) -> String {
+    let extension = source
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    if LOSSLESS_EXTENSIONS.contains(&extension.as_str()) {
+        "flac".to_string()
+    } else {
+        extension
+    }
+}
+
+/// File name for a split track, e.g. `03 - Title.flac`, its title made safe by `naming`
+pub fn track_file_name(track: &CueTrack, extension: &str, naming: &Naming) -> String {
+    let title = track
+        .title
+        .as_deref()
+        .map(|title| naming.sanitize(&metadata::normalize_tag_text(title, false)))
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| format!("Track {:02}", track.number));
+    format!("{:02} - {}.{}", track.number, title, extension)
+}
+
+/// Cut `track` out of `source` into `dest` with the `ffmpeg` tool
+/// Lossless audio is re-encoded as FLAC so the cut is sample-accurate; lossy audio is copied
+pub fn split_track(source: &Path, track: &CueTrack, dest: &Path) -> Result<()> {
+    let codec = if split_extension(source) == "flac" {
+        "flac"
+    } else {
+        "copy"
+    };
+    let mut command = Command::new("ffmpeg");
+    command
+        .args(["-nostdin", "-hide_banner", "-loglevel", "error", "-n"])
+        .arg("-ss")
+        .arg(format!("{:.6}", track.start.as_secs_f64()))
+        .arg("-i")
+        .arg(source);
+    if let Some(end) = track.end {
+        command.arg("-t").arg(format!(
+            "{:.6}",
+            end.saturating_sub(track.start).as_secs_f64()
+        ));
+    }
+    let output = command
+        .args(["-map", "0:a:0", "-map_metadata", "-1", "-c:a", codec])
+        .arg(dest)
+        .output()
+        .context("Failed to run ffmpeg; is it installed?")?;
+
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "ffmpeg failed to split track {} of '{}': {}",
+            track.number,
+            source.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// Tag a split track with the album's names and its own title, artist and position
+pub fn tag_split_track(
+    path: &Path,
+    album_artist: &str,
+    album: &str,
+    track: &CueTrack,
+    track_total: u32,
+) -> Result<()> {
+    let mut tagged_file = lofty::read_from_path(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    metadata::update_file_tags(&mut tagged_file, |tag| {
+        tag.insert_text(ItemKey::AlbumArtist, album_artist.to_string());
+        tag.set_artist(
+            track
+                .performer
+                .as_deref()
+                .unwrap_or(album_artist)
+                .to_string(),
+        );
+        tag.set_album(album.to_string());
+        if let Some(title) = &track.title {
+            tag.set_title(title.clone());
+        }
+        tag.set_track(track.number);
+        tag.set_track_total(track_total);
+    })?;
+    gapless::save_tags(&tagged_file, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const SHEET: &str = "\u{FEFF}REM GENRE Rock
+REM DATE 1973
+PERFORMER \"Pink Floyd\"
+TITLE \"The Dark Side of the Moon\"
+FILE \"Pink Floyd - The Dark Side of the Moon.wav\" WAVE
+  TRACK 01 AUDIO
+    TITLE \"Speak to Me\"
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    TITLE \"Breathe (In the Air)\"
+    PERFORMER \"Pink Floyd feat. Nobody\"
+    INDEX 00 01:05:70
+    INDEX 01 01:07:30
+  TRACK 03 AUDIO
+    TITLE \"On the Run\"
+    INDEX 01 03:56:45
+";
+
+    #[test]
+    fn test_parse_cue_tracks_and_indices() -> Result<()> {
+        let sheet = parse_cue(SHEET)?;
+        assert_eq!(sheet.performer.as_deref(), Some("Pink Floyd"));
+        assert_eq!(sheet.title.as_deref(), Some("The Dark Side of the Moon"));
+
+        let titles: Vec<&str> = sheet
+            .tracks
+            .iter()
+            .filter_map(|t| t.title.as_deref())
+            .collect();
+        assert_eq!(
+            titles,
+            ["Speak to Me", "Breathe (In the Air)", "On the Run"]
+        );
+        let numbers: Vec<u32> = sheet.tracks.iter().map(|t| t.number).collect();
+        assert_eq!(numbers, [1, 2, 3]);
+        assert!(sheet
+            .tracks
+            .iter()
+            .all(|t| t.file == "Pink Floyd - The Dark Side of the Moon.wav"));
+
+        // INDEX 01 marks the start; the pregap's INDEX 00 is ignored
+        let second = &sheet.tracks[1];
+        assert_eq!(second.start, Duration::from_millis(67_400));
+        assert_eq!(second.end, Some(sheet.tracks[2].start));
+        assert_eq!(second.performer.as_deref(), Some("Pink Floyd feat. Nobody"));
+        assert_eq!(sheet.tracks[0].end, Some(second.start));
+        assert_eq!(sheet.tracks[2].start, Duration::from_millis(236_600));
+        assert_eq!(sheet.tracks[2].end, None);
+
+        assert_eq!(
+            track_file_name(second, "flac", &Naming::default()),
+            "02 - Breathe (In the Air).flac"
+        );
+
+        assert!(parse_cue("TRACK 01 AUDIO\n").is_err());
+        assert!(parse_cue("FILE \"a.wav\" WAVE\nTRACK 01 AUDIO\nINDEX 01 00:61:00\n").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_cue_sheet_matches_by_stem() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let flac = temp_dir
+            .path()
+            .join("Pink Floyd - The Dark Side of the Moon.flac");
+        fs::write(&flac, b"audio")?;
+        fs::write(temp_dir.path().join("album.cue"), SHEET)?;
+        let other = temp_dir.path().join("Bonus.flac");
+        fs::write(&other, b"audio")?;
+
+        let (cue_path, sheet) = find_cue_sheet(&flac).unwrap();
+        assert_eq!(cue_path, temp_dir.path().join("album.cue"));
+        assert_eq!(sheet.tracks.len(), 3);
+        assert!(find_cue_sheet(&other).is_none());
+
+        Ok(())
+    }
+}
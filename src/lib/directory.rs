@@ -1,7 +1,16 @@
+use crate::utils::Naming;
+use crate::{audio, metadata};
 use anyhow::{Context, Result};
+use rayon::prelude::*;
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
 use std::fs;
 use std::path::{Path, PathBuf};
-use tracing::info;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tracing::{info, warn};
 
 /// Directory operations and file organization utilities
 /// Common patterns for creating directories and organizing files
@@ -51,12 +60,14 @@ pub fn create_album_directory_with_dry_run(
 
 /// Move a file to an album directory
 /// Handles filename conflicts and provides detailed error messages
+/// Returns whether the file was (or in a dry run would be) moved; files already in place or
+/// whose destination exists are skipped
 pub fn move_file_to_album(
     file_path: &Path,
     album_path: &Path,
     dry_run: bool,
     quiet: bool,
-) -> Result<()> {
+) -> Result<bool> {
     let file_name = file_path
         .file_name()
         .ok_or_else(|| anyhow::anyhow!("File '{}' has no filename", file_path.display()))?;
@@ -65,7 +76,7 @@ pub fn move_file_to_album(
 
     // Skip if source and destination are the same
     if file_path == dest_path {
-        return Ok(());
+        return Ok(false);
     }
 
     if dry_run {
@@ -86,11 +97,11 @@ pub fn move_file_to_album(
                     dest_path.display()
                 );
             }
-            return Ok(());
+            return Ok(false);
         }
 
         // Move the file
-        fs::rename(file_path, &dest_path).with_context(|| {
+        retry_busy_io(|| fs::rename(file_path, &dest_path)).with_context(|| {
             format!(
                 "Failed to move '{}' to '{}': {}",
                 file_path.display(),
@@ -104,17 +115,20 @@ pub fn move_file_to_album(
         }
     }
 
-    Ok(())
+    Ok(true)
 }
 
 /// Copy a file to an album directory
 /// Handles filename conflicts and provides detailed error messages
+/// When `preserve_mtime` is set the copy keeps the source's access and modification times
+/// Returns whether the file was (or in a dry run would be) copied, as [`move_file_to_album`]
 pub fn copy_file_to_album(
     file_path: &Path,
     album_path: &Path,
     dry_run: bool,
     quiet: bool,
-) -> Result<()> {
+    preserve_mtime: bool,
+) -> Result<bool> {
     let file_name = file_path
         .file_name()
         .ok_or_else(|| anyhow::anyhow!("File '{}' has no filename", file_path.display()))?;
@@ -123,7 +137,7 @@ pub fn copy_file_to_album(
 
     // Skip if source and destination are the same
     if file_path == dest_path {
-        return Ok(());
+        return Ok(false);
     }
 
     if dry_run {
@@ -144,11 +158,11 @@ pub fn copy_file_to_album(
                     dest_path.display()
                 );
             }
-            return Ok(());
+            return Ok(false);
         }
 
         // Copy the file
-        fs::copy(file_path, &dest_path).with_context(|| {
+        retry_busy_io(|| fs::copy(file_path, &dest_path)).with_context(|| {
             format!(
                 "Failed to copy '{}' to '{}': {}",
                 file_path.display(),
@@ -157,32 +171,612 @@ pub fn copy_file_to_album(
             )
         })?;
 
+        if preserve_mtime {
+            if let Err(e) = copy_file_times(file_path, &dest_path) {
+                warn!("{:?}", e);
+            }
+        }
+
         if !quiet {
             info!("Copied: {} -> {}", file_path.display(), dest_path.display());
         }
     }
 
+    Ok(true)
+}
+
+/// Maximum attempts at a file operation that keeps failing because the file is busy
+pub const BUSY_ATTEMPTS: u32 = 5;
+
+/// Pause before the first retry of a busy file; each later retry waits one step longer
+const BUSY_RETRY_DELAY: Duration = Duration::from_millis(50);
+
+/// Whether `e` means another program has the file open or locked for now: `EBUSY` or
+/// `ETXTBSY` on Unix, a sharing or lock violation on Windows
+/// A media player scanning the library or a sync client uploading it can cause these
+pub fn is_busy_error(e: &std::io::Error) -> bool {
+    if matches!(
+        e.kind(),
+        std::io::ErrorKind::ResourceBusy | std::io::ErrorKind::ExecutableFileBusy
+    ) {
+        return true;
+    }
+    // ERROR_SHARING_VIOLATION and ERROR_LOCK_VIOLATION
+    cfg!(windows) && matches!(e.raw_os_error(), Some(32 | 33))
+}
+
+/// Run `op`, retrying after a short delay while it fails with an error `busy` accepts, up to
+/// [`BUSY_ATTEMPTS`] tries in all
+/// Any other error is returned at once, as is the last busy one
+/// The waits block the calling thread with [`std::thread::sleep`], so call this only from
+/// blocking or rayon threads, never directly on an async runtime's worker threads
+pub fn retry_busy<T, E>(
+    mut op: impl FnMut() -> std::result::Result<T, E>,
+    busy: impl Fn(&E) -> bool,
+) -> std::result::Result<T, E> {
+    let mut attempt = 1;
+    loop {
+        match op() {
+            Err(e) if busy(&e) && attempt < BUSY_ATTEMPTS => {
+                std::thread::sleep(BUSY_RETRY_DELAY * attempt);
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// [`retry_busy`] for file system calls; a file still busy after every try gives an error
+/// saying so, of the same kind
+pub fn retry_busy_io<T>(op: impl FnMut() -> std::io::Result<T>) -> std::io::Result<T> {
+    retry_busy(op, is_busy_error).map_err(|e| {
+        if is_busy_error(&e) {
+            std::io::Error::new(
+                e.kind(),
+                format!("{} (still in use after {} attempts)", e, BUSY_ATTEMPTS),
+            )
+        } else {
+            e
+        }
+    })
+}
+
+/// Copy a file's access and modification times onto another file
+/// Used after copying so "recently added" sorting and incremental backups see the original times
+pub fn copy_file_times(file_path: &Path, dest_path: &Path) -> Result<()> {
+    let metadata = fs::metadata(file_path)
+        .with_context(|| format!("Failed to read times of '{}'", file_path.display()))?;
+    let times = fs::FileTimes::new()
+        .set_accessed(metadata.accessed()?)
+        .set_modified(metadata.modified()?);
+
+    fs::File::options()
+        .write(true)
+        .open(dest_path)
+        .and_then(|dest| dest.set_times(times))
+        .with_context(|| format!("Failed to set times on '{}'", dest_path.display()))
+}
+
+/// How a file was transferred to its destination
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferMethod {
+    /// Renamed in place on the same filesystem
+    Rename,
+    /// Copied across filesystems, then the original removed
+    CopyAndDelete,
+    /// Copied, leaving the original untouched
+    Copy,
+}
+
+impl std::fmt::Display for TransferMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TransferMethod::Rename => write!(f, "rename"),
+            TransferMethod::CopyAndDelete => write!(f, "copy+delete"),
+            TransferMethod::Copy => write!(f, "copy"),
+        }
+    }
+}
+
+/// Move a file, falling back to copy+delete when the rename crosses filesystems (EXDEV)
+pub fn move_file_across_devices(file_path: &Path, dest_path: &Path) -> Result<TransferMethod> {
+    move_file_with(file_path, dest_path, |from, to| fs::rename(from, to))
+}
+
+fn move_file_with<F>(file_path: &Path, dest_path: &Path, mut rename: F) -> Result<TransferMethod>
+where
+    F: FnMut(&Path, &Path) -> std::io::Result<()>,
+{
+    match retry_busy_io(|| rename(file_path, dest_path)) {
+        Ok(()) => Ok(TransferMethod::Rename),
+        Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+            retry_busy_io(|| fs::copy(file_path, dest_path)).with_context(|| {
+                format!(
+                    "Failed to copy '{}' to '{}' across filesystems",
+                    file_path.display(),
+                    dest_path.display()
+                )
+            })?;
+            fs::remove_file(file_path).with_context(|| {
+                format!(
+                    "Copied '{}' but failed to remove the original",
+                    file_path.display()
+                )
+            })?;
+            Ok(TransferMethod::CopyAndDelete)
+        }
+        Err(e) => Err(anyhow::anyhow!(
+            "Failed to move '{}' to '{}': {}",
+            file_path.display(),
+            dest_path.display(),
+            e
+        )),
+    }
+}
+
+/// Copy or move a file to its destination, reporting which strategy was used
+pub fn transfer_file(file_path: &Path, dest_path: &Path, copy: bool) -> Result<TransferMethod> {
+    if copy {
+        retry_busy_io(|| fs::copy(file_path, dest_path)).with_context(|| {
+            format!(
+                "Failed to copy '{}' to '{}'",
+                file_path.display(),
+                dest_path.display()
+            )
+        })?;
+        Ok(TransferMethod::Copy)
+    } else {
+        move_file_across_devices(file_path, dest_path)
+    }
+}
+
+/// Keep a copy of `file_path` under `quarantine`, at its path relative to `root`, so it can be
+/// put back if a move goes wrong
+/// The copy is a hard link where possible; an existing quarantined file is never replaced
+pub fn quarantine_file(file_path: &Path, root: &Path, quarantine: &Path) -> Result<PathBuf> {
+    let relative = file_path.strip_prefix(root).with_context(|| {
+        format!(
+            "'{}' is not inside '{}'",
+            file_path.display(),
+            root.display()
+        )
+    })?;
+    let kept = quarantine.join(relative);
+    if kept.exists() {
+        anyhow::bail!("'{}' is already in quarantine", kept.display());
+    }
+    if let Some(parent) = kept.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create '{}'", parent.display()))?;
+    }
+    if fs::hard_link(file_path, &kept).is_err() {
+        fs::copy(file_path, &kept).with_context(|| {
+            format!(
+                "Failed to quarantine '{}' as '{}'",
+                file_path.display(),
+                kept.display()
+            )
+        })?;
+    }
+    Ok(kept)
+}
+
+/// A file that could not be processed, along with the reason it was skipped
+#[derive(Debug, Clone, Serialize)]
+pub struct FileFailure {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+impl FileFailure {
+    pub fn new(path: &Path, reason: impl ToString) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+/// Log every failed file at the end of a run so one bad file doesn't go unnoticed
+pub fn report_failures(failures: &[FileFailure], action: &str) {
+    if failures.is_empty() {
+        return;
+    }
+
+    warn!("{} files could not be {}:", failures.len(), action);
+    for failure in failures {
+        warn!("  {}: {}", failure.path.display(), failure.reason);
+    }
+}
+
+/// Write one `path<TAB>reason` line per file, e.g. the files an import excluded
+pub fn write_file_list(path: &Path, files: &[FileFailure]) -> Result<()> {
+    let list: String = files
+        .iter()
+        .map(|file| format!("{}\t{}\n", file.path.display(), file.reason))
+        .collect();
+    fs::write(path, list).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Warn about files that fell back to the Unknown Artist/Unknown Album placeholders
+/// These are easy to miss once buried in the library, so this is logged regardless of `quiet`
+pub fn report_unknown_placeholders(count: usize, strict: bool) {
+    if count == 0 {
+        return;
+    }
+
+    if strict {
+        warn!(
+            "{} files have no usable artist/album tags and were left in place",
+            count
+        );
+    } else {
+        warn!(
+            "{} files were filed under '{}' / '{}' because they lack artist/album tags",
+            count,
+            metadata::UNKNOWN_ARTIST,
+            metadata::UNKNOWN_ALBUM
+        );
+    }
+    warn!("Tag these files with their artist and album, then run reorganize again");
+}
+
+/// A single file transfer, either planned during a dry run or actually performed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileMove {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+/// The Artists tree a run places files into: artist, then album, then how many of the run's
+/// files end up there. Built from the grouping before anything moves, for `--tree`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct AlbumTree {
+    pub artists: BTreeMap<String, BTreeMap<String, usize>>,
+}
+
+impl AlbumTree {
+    /// Tree of the (artist, album) groups files are filed under
+    pub fn from_groups(groups: &FxHashMap<(String, String), Vec<PathBuf>>) -> Self {
+        let mut artists: BTreeMap<String, BTreeMap<String, usize>> = BTreeMap::new();
+        for ((artist, album), files) in groups {
+            *artists
+                .entry(artist.clone())
+                .or_default()
+                .entry(album.clone())
+                .or_default() += files.len();
+        }
+        Self { artists }
+    }
+}
+
+impl fmt::Display for AlbumTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Artists/")?;
+        let last_artist = self.artists.len().saturating_sub(1);
+        for (i, (artist, albums)) in self.artists.iter().enumerate() {
+            let (branch, indent) = if i == last_artist {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+            writeln!(f, "{}{}/", branch, artist)?;
+            let last_album = albums.len().saturating_sub(1);
+            for (j, (album, files)) in albums.iter().enumerate() {
+                let branch = if j == last_album {
+                    "└── "
+                } else {
+                    "├── "
+                };
+                let noun = if *files == 1 { "file" } else { "files" };
+                writeln!(f, "{}{}{}/ ({} {})", indent, branch, album, files, noun)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(test)]
+thread_local! {
+    /// Interrupt checks let through before [`interrupted`] reports one, so a test can stop a
+    /// run part way
+    pub(crate) static INTERRUPT_AFTER: std::cell::Cell<Option<usize>> =
+        const { std::cell::Cell::new(None) };
+}
+
+/// Ask imports and CD rips to stop once the file in hand is finished, as the first Ctrl-C or
+/// cancelling the progress display does
+pub fn request_interrupt() {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/// Forget an earlier [`request_interrupt`], so a new import or rip in the same process runs
+/// Called as each of them starts
+pub fn clear_interrupt() {
+    INTERRUPTED.store(false, Ordering::SeqCst);
+}
+
+/// Whether the run has been asked to stop
+/// Loops check this between files, never part way through one, so no file is left half-done
+pub fn interrupted() -> bool {
+    #[cfg(test)]
+    if let Some(remaining) = INTERRUPT_AFTER.get() {
+        INTERRUPT_AFTER.set(Some(remaining.saturating_sub(1)));
+        return remaining == 0;
+    }
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// `<path>.part`, where a file is written before it is renamed into place
+fn part_path(path: &Path) -> PathBuf {
+    let mut part_name = path.as_os_str().to_owned();
+    part_name.push(".part");
+    PathBuf::from(part_name)
+}
+
+/// Write `data` to `<path>.part` and rename it over `path` once it is complete, so an
+/// interrupted write never leaves a truncated file where a finished one is expected
+/// The partial file is removed if the write fails
+pub fn write_via_part_file(path: &Path, data: &[u8]) -> Result<()> {
+    let part_path = part_path(path);
+    let written = fs::write(&part_path, data).and_then(|()| fs::rename(&part_path, path));
+    if let Err(e) = written {
+        let _ = fs::remove_file(&part_path);
+        return Err(anyhow::anyhow!("Failed to write {}: {}", path.display(), e));
+    }
     Ok(())
 }
 
-/// Organize files by artist and album into the proper directory structure
-/// This is a comprehensive function that handles both moving and copying files
-pub struct FileOrganizationResult {
-    pub files_processed: usize,
-    pub directories_created: usize,
+/// [`fs::copy`] by way of `<dest_path>.part`, renamed into place once the copy is complete
+/// A copy cut short by a failure or a kill never shows up under the real name
+pub fn copy_via_part_file(file_path: &Path, dest_path: &Path) -> std::io::Result<u64> {
+    let part_path = part_path(dest_path);
+    let copied = retry_busy_io(|| fs::copy(file_path, &part_path))
+        .and_then(|bytes| retry_busy_io(|| fs::rename(&part_path, dest_path)).map(|()| bytes));
+    if copied.is_err() {
+        let _ = fs::remove_file(&part_path);
+    }
+    copied
+}
+
+/// Where `file_path` goes in `dest_dir`: under its own name, or with its extension lowercased
+/// (`Track.MP3` -> `Track.mp3`) when `naming` normalizes extensions
+/// With normalization on, fails if `dest_dir` holds another file whose name differs only in
+/// case, since on a case-insensitive filesystem the two would be the same file. A file whose
+/// contents aren't in the format its extension names is warned about
+/// (see [`audio::mislabeled_audio_type`])
+pub fn destination_path(file_path: &Path, dest_dir: &Path, naming: &Naming) -> Result<PathBuf> {
+    let file_name = file_path.file_name().context("File has no filename")?;
+    if let Some(actual) = audio::mislabeled_audio_type(file_path) {
+        warn!(
+            "'{}' holds {:?} audio despite its extension; players may refuse it until renamed",
+            file_path.display(),
+            actual
+        );
+    }
+    if !naming.normalize_extensions {
+        return Ok(dest_dir.join(file_name));
+    }
+
+    let name = Path::new(file_name);
+    let dest_path = match name.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => dest_dir.join(name.with_extension(ext.to_ascii_lowercase())),
+        None => dest_dir.join(name),
+    };
+    let Some(dest_name) = dest_path.file_name().and_then(|n| n.to_str()) else {
+        return Ok(dest_path);
+    };
+    if let Ok(entries) = fs::read_dir(dest_dir) {
+        for entry in entries.filter_map(|e| e.ok()) {
+            let existing = entry.path();
+            let same_name = entry
+                .file_name()
+                .to_str()
+                .is_some_and(|n| n.eq_ignore_ascii_case(dest_name));
+            // The file itself (being renamed in place) and an exact match are not collisions
+            if same_name && existing != dest_path && existing != file_path {
+                anyhow::bail!(
+                    "'{}' already exists and differs from '{}' only in case",
+                    existing.display(),
+                    dest_name
+                );
+            }
+        }
+    }
+    Ok(dest_path)
+}
+
+/// Albums with at least this many discs may be box sets
+pub const BOXSET_MIN_DISCS: usize = 4;
+
+/// Albums with at least this many tracks may be box sets, whatever their disc count
+pub const BOXSET_MIN_TRACKS: usize = 50;
+
+/// (artist, album) groups of files, each with the disc subfolder of the album folder its files
+/// go in, if any
+pub type DiscGroups = FxHashMap<(String, String, Option<String>), Vec<PathBuf>>;
+
+/// Split box sets among `file_groups` into one subfolder per disc
+/// A group counts as a box set when its files carry at least two different disc subtitles and
+/// it has [`BOXSET_MIN_DISCS`] discs or [`BOXSET_MIN_TRACKS`] tracks. Each disc's files are
+/// regrouped under a `<subtitle>` subfolder ("Disc N" for a disc without one), the subtitle
+/// made safe with `naming`; other groups get no subfolder
+pub fn split_boxset_groups(
+    file_groups: FxHashMap<(String, String), Vec<PathBuf>>,
+    naming: &Naming,
+    quiet: bool,
+) -> DiscGroups {
+    let mut split = DiscGroups::default();
+    for ((artist, album), files) in file_groups {
+        let discs: Vec<_> = files
+            .par_iter()
+            .map(|file| metadata::read_disc_info(file))
+            .collect();
+        let Some(folders) = boxset_disc_folders(&discs, naming) else {
+            split
+                .entry((artist, album, None))
+                .or_default()
+                .extend(files);
+            continue;
+        };
+
+        if !quiet {
+            let disc_count = folders.iter().flatten().collect::<FxHashSet<_>>().len();
+            info!(
+                "Splitting box set {} / {} into {} disc folders",
+                artist, album, disc_count
+            );
+        }
+        for (file, folder) in files.into_iter().zip(folders) {
+            split
+                .entry((artist.clone(), album.clone(), folder))
+                .or_default()
+                .push(file);
+        }
+    }
+    split
+}
+
+/// The disc subfolder for each file of a box set, or `None` if the files don't look like one
+/// `discs` holds each file's disc number and subtitle; files with neither stay in the album
+/// folder, and a subtitle shared by several discs gets the disc number in front
+fn boxset_disc_folders(
+    discs: &[(Option<u32>, Option<String>)],
+    naming: &Naming,
+) -> Option<Vec<Option<String>>> {
+    let mut subtitle_discs: FxHashMap<&str, FxHashSet<Option<u32>>> = FxHashMap::default();
+    for (disc, subtitle) in discs {
+        if let Some(subtitle) = subtitle {
+            subtitle_discs
+                .entry(subtitle.as_str())
+                .or_default()
+                .insert(*disc);
+        }
+    }
+    let disc_count = discs
+        .iter()
+        .filter_map(|(disc, _)| *disc)
+        .collect::<FxHashSet<_>>()
+        .len();
+    let large = disc_count >= BOXSET_MIN_DISCS || discs.len() >= BOXSET_MIN_TRACKS;
+    if subtitle_discs.len() < 2 || !large {
+        return None;
+    }
+
+    let folders = discs
+        .iter()
+        .map(|(disc, subtitle)| {
+            let shared = subtitle
+                .as_deref()
+                .and_then(|s| subtitle_discs.get(s))
+                .is_some_and(|discs| discs.len() > 1);
+            let subtitle = subtitle
+                .as_deref()
+                .map(|subtitle| naming.sanitize(subtitle))
+                .filter(|s| !s.is_empty());
+            match (disc, subtitle) {
+                (Some(disc), Some(subtitle)) if shared => {
+                    Some(format!("Disc {} - {}", disc, subtitle))
+                }
+                (_, Some(subtitle)) => Some(subtitle),
+                (Some(disc), None) => Some(format!("Disc {}", disc)),
+                (None, None) => None,
+            }
+        })
+        .collect();
+    Some(folders)
+}
+
+/// Summary of an organize, reorganize, or import run
+/// Shared by every command that places files into the Artists/Artist/Album structure
+#[derive(Debug, Clone, Default)]
+pub struct OrganizeSummary {
+    /// Whether this summary describes a dry run (nothing was touched)
+    pub dry_run: bool,
+    /// Files moved or copied into place
+    pub files_moved: usize,
+    /// Files left alone because they were already in place or the destination existed
     pub files_skipped: usize,
+    /// Album directories created (or that would be created in a dry run)
+    pub dirs_created: usize,
+    /// Files excluded up front, e.g. for missing metadata, with the reason
+    pub excluded: Vec<FileFailure>,
+    /// Files whose artist or album fell back to the Unknown placeholders
+    pub unknown_placeholders: usize,
+    /// Planned (dry run) or performed moves
+    pub moves: Vec<FileMove>,
+    /// Files that could not be processed
+    pub failures: Vec<FileFailure>,
+    /// Where the run's files are (or would be) filed, by artist and album
+    pub tree: AlbumTree,
 }
 
+impl OrganizeSummary {
+    pub fn new(dry_run: bool) -> Self {
+        Self {
+            dry_run,
+            ..Self::default()
+        }
+    }
+
+    /// Record a planned or performed move
+    pub fn record_move(&mut self, from: &Path, to: &Path) {
+        if !self.dry_run {
+            self.files_moved += 1;
+        }
+        self.moves.push(FileMove {
+            from: from.to_path_buf(),
+            to: to.to_path_buf(),
+        });
+    }
+}
+
+impl std::fmt::Display for OrganizeSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.dry_run {
+            write!(
+                f,
+                "Would place {} files, creating {} directories",
+                self.moves.len(),
+                self.dirs_created
+            )?;
+        } else {
+            write!(
+                f,
+                "Placed {} files, created {} directories",
+                self.files_moved, self.dirs_created
+            )?;
+        }
+        write!(
+            f,
+            " ({} skipped, {} excluded, {} failed)",
+            self.files_skipped,
+            self.excluded.len(),
+            self.failures.len()
+        )?;
+        if self.unknown_placeholders > 0 {
+            write!(
+                f,
+                "; {} files with unknown artist/album",
+                self.unknown_placeholders
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Organize files by artist and album into the proper directory structure
+/// This is a comprehensive function that handles both moving and copying files
 pub fn organize_files_by_metadata(
     files: &[(PathBuf, String, String)], // (file_path, artist, album)
     music_dir: &Path,
     dry_run: bool,
     quiet: bool,
-) -> Result<FileOrganizationResult> {
+) -> Result<OrganizeSummary> {
     let artists_path = music_dir.join("Artists");
-    let mut files_processed = 0;
-    let mut directories_created = 0;
-    let mut files_skipped = 0;
+    let mut summary = OrganizeSummary::new(dry_run);
 
     // Group files by artist and album
     let mut file_groups: std::collections::HashMap<(String, String), Vec<PathBuf>> =
@@ -197,28 +791,27 @@ pub fn organize_files_by_metadata(
 
     // Process each group
     for ((artist, album), files) in file_groups {
+        let album_existed = artists_path.join(&artist).join(&album).exists();
         let album_path =
             create_album_directory_with_dry_run(&artists_path, &artist, &album, dry_run, quiet)?;
 
-        if dry_run {
-            directories_created += 0;
-        } else if !album_path.exists() {
-            directories_created += 1;
+        if !album_existed {
+            summary.dirs_created += 1;
         }
 
         for file_path in files {
             match move_file_to_album(&file_path, &album_path, dry_run, quiet) {
-                Ok(_) => files_processed += 1,
-                Err(_) => files_skipped += 1,
+                Ok(true) => {
+                    let dest_path = album_path.join(file_path.file_name().unwrap_or_default());
+                    summary.record_move(&file_path, &dest_path);
+                }
+                Ok(false) => summary.files_skipped += 1,
+                Err(e) => summary.failures.push(FileFailure::new(&file_path, e)),
             }
         }
     }
 
-    Ok(FileOrganizationResult {
-        files_processed,
-        directories_created,
-        files_skipped,
-    })
+    Ok(summary)
 }
 
 /// Copy files by artist and album into the proper directory structure
@@ -228,11 +821,10 @@ pub fn copy_files_by_metadata(
     music_dir: &Path,
     dry_run: bool,
     quiet: bool,
-) -> Result<FileOrganizationResult> {
+    preserve_mtime: bool,
+) -> Result<OrganizeSummary> {
     let artists_path = music_dir.join("Artists");
-    let mut files_processed = 0;
-    let mut directories_created = 0;
-    let mut files_skipped = 0;
+    let mut summary = OrganizeSummary::new(dry_run);
 
     // Group files by artist and album
     let mut file_groups: std::collections::HashMap<(String, String), Vec<PathBuf>> =
@@ -247,28 +839,27 @@ pub fn copy_files_by_metadata(
 
     // Process each group
     for ((artist, album), files) in file_groups {
+        let album_existed = artists_path.join(&artist).join(&album).exists();
         let album_path =
             create_album_directory_with_dry_run(&artists_path, &artist, &album, dry_run, quiet)?;
 
-        if dry_run {
-            directories_created += 1;
-        } else if !album_path.exists() {
-            directories_created += 0;
+        if !album_existed {
+            summary.dirs_created += 1;
         }
 
         for file_path in files {
-            match copy_file_to_album(&file_path, &album_path, dry_run, quiet) {
-                Ok(_) => files_processed += 1,
-                Err(_) => files_skipped += 1,
+            match copy_file_to_album(&file_path, &album_path, dry_run, quiet, preserve_mtime) {
+                Ok(true) => {
+                    let dest_path = album_path.join(file_path.file_name().unwrap_or_default());
+                    summary.record_move(&file_path, &dest_path);
+                }
+                Ok(false) => summary.files_skipped += 1,
+                Err(e) => summary.failures.push(FileFailure::new(&file_path, e)),
             }
         }
     }
 
-    Ok(FileOrganizationResult {
-        files_processed,
-        directories_created,
-        files_skipped,
-    })
+    Ok(summary)
 }
 
 #[cfg(test)]
@@ -276,6 +867,30 @@ mod tests {
     use super::*;
     use tempfile::TempDir;
 
+    #[test]
+    fn test_album_tree_renders_artists_albums_and_counts() {
+        let mut groups: FxHashMap<(String, String), Vec<PathBuf>> = FxHashMap::default();
+        let files = |n: usize| -> Vec<PathBuf> {
+            (0..n)
+                .map(|i| PathBuf::from(format!("{}.flac", i)))
+                .collect()
+        };
+        groups.insert(("Beta".into(), "Second".into()), files(1));
+        groups.insert(("Alpha".into(), "One".into()), files(12));
+        groups.insert(("Alpha".into(), "Two".into()), files(3));
+
+        let tree = AlbumTree::from_groups(&groups);
+        assert_eq!(
+            tree.to_string(),
+            "Artists/\n\
+             ├── Alpha/\n\
+             │   ├── One/ (12 files)\n\
+             │   └── Two/ (3 files)\n\
+             └── Beta/\n\
+             \x20   └── Second/ (1 file)\n"
+        );
+    }
+
     #[test]
     fn test_create_album_directory() -> Result<()> {
         let temp_dir = TempDir::new()?;
@@ -343,6 +958,81 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_move_file_falls_back_to_copy_on_cross_device() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let source_file = temp_dir.path().join("source.mp3");
+        let dest_file = temp_dir.path().join("dest.mp3");
+
+        fs::write(&source_file, b"test audio content")?;
+
+        // Simulate a rename across mount points failing with EXDEV
+        let method = move_file_with(&source_file, &dest_file, |_, _| {
+            Err(std::io::Error::from(std::io::ErrorKind::CrossesDevices))
+        })?;
+
+        assert_eq!(method, TransferMethod::CopyAndDelete);
+        assert!(!source_file.exists());
+        assert_eq!(fs::read(&dest_file)?, b"test audio content");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_move_file_retries_while_busy() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let source_file = temp_dir.path().join("source.mp3");
+        let dest_file = temp_dir.path().join("dest.mp3");
+
+        fs::write(&source_file, b"test audio content")?;
+
+        // Another program has the file open for the first two tries
+        let mut tries = 0;
+        let method = move_file_with(&source_file, &dest_file, |from, to| {
+            tries += 1;
+            if tries <= 2 {
+                return Err(std::io::Error::from(std::io::ErrorKind::ResourceBusy));
+            }
+            fs::rename(from, to)
+        })?;
+
+        assert_eq!(method, TransferMethod::Rename);
+        assert_eq!(tries, 3);
+        assert!(!source_file.exists());
+        assert_eq!(fs::read(&dest_file)?, b"test audio content");
+
+        // A file that stays busy fails after the last try and says why
+        fs::write(&source_file, b"test audio content")?;
+        let mut tries = 0;
+        let err = move_file_with(&source_file, &dest_file, |_, _| {
+            tries += 1;
+            Err(std::io::Error::from(std::io::ErrorKind::ResourceBusy))
+        })
+        .unwrap_err();
+        assert_eq!(tries, BUSY_ATTEMPTS);
+        assert!(err.to_string().contains("still in use"));
+        assert!(source_file.exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_transfer_file_copy_keeps_original() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let source_file = temp_dir.path().join("source.mp3");
+        let dest_file = temp_dir.path().join("dest.mp3");
+
+        fs::write(&source_file, b"test audio content")?;
+
+        let method = transfer_file(&source_file, &dest_file, true)?;
+
+        assert_eq!(method, TransferMethod::Copy);
+        assert!(source_file.exists());
+        assert!(dest_file.exists());
+
+        Ok(())
+    }
+
     #[test]
     fn test_copy_file_to_album_dry_run() -> Result<()> {
         let temp_dir = TempDir::new()?;
@@ -353,11 +1043,75 @@ mod tests {
         fs::write(&source_file, b"test audio content")?;
 
         // Test dry run - should not copy file
-        copy_file_to_album(&source_file, &album_path, true, true)?;
+        copy_file_to_album(&source_file, &album_path, true, true, false)?;
 
         assert!(source_file.exists());
         assert!(!album_path.exists());
 
         Ok(())
     }
+
+    #[test]
+    fn test_copy_file_to_album_preserves_mtime() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let source_file = temp_dir.path().join("source.mp3");
+        let album_path = temp_dir.path().join("album");
+        fs::create_dir(&album_path)?;
+        fs::write(&source_file, b"test audio content")?;
+
+        // Backdate the source so a fresh copy would clearly differ
+        let old_time =
+            std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000);
+        fs::File::options()
+            .write(true)
+            .open(&source_file)?
+            .set_times(fs::FileTimes::new().set_modified(old_time))?;
+
+        copy_file_to_album(&source_file, &album_path, false, true, true)?;
+
+        let dest_file = album_path.join("source.mp3");
+        assert_eq!(fs::metadata(&dest_file)?.modified()?, old_time);
+        assert_eq!(
+            fs::metadata(&dest_file)?.modified()?,
+            fs::metadata(&source_file)?.modified()?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_existing_destinations_are_counted_as_skipped() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_dir = temp_dir.path();
+        let album_path = music_dir.join("Artists").join("Band").join("Record");
+        fs::create_dir_all(&album_path)?;
+        fs::write(album_path.join("01.mp3"), b"already filed")?;
+
+        let incoming = music_dir.join("incoming");
+        fs::create_dir(&incoming)?;
+        fs::write(incoming.join("01.mp3"), b"new copy")?;
+        fs::write(incoming.join("02.mp3"), b"new track")?;
+        let files: Vec<_> = ["01.mp3", "02.mp3"]
+            .iter()
+            .map(|name| {
+                (
+                    incoming.join(name),
+                    "Band".to_string(),
+                    "Record".to_string(),
+                )
+            })
+            .collect();
+
+        let summary = copy_files_by_metadata(&files, music_dir, false, true, false)?;
+        assert_eq!(summary.files_moved, 1);
+        assert_eq!(summary.files_skipped, 1);
+        assert_eq!(fs::read(album_path.join("01.mp3"))?, b"already filed");
+
+        let summary = organize_files_by_metadata(&files, music_dir, false, true)?;
+        assert_eq!(summary.files_moved, 0);
+        assert_eq!(summary.files_skipped, 2);
+        assert!(incoming.join("01.mp3").exists());
+
+        Ok(())
+    }
 }
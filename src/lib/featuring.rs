@@ -0,0 +1,238 @@
+use crate::{gapless, metadata};
+use anyhow::{Context, Result};
+use lofty::tag::ItemKey;
+use std::path::Path;
+
+/// Words that introduce featured artists, longest first so "feat." wins over "feat"
+const FEATURING_MARKERS: [&str; 5] = ["featuring", "feat.", "feat", "ft.", "ft"];
+
+/// An artist credit split into the main artist and any featured artists
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArtistCredit {
+    pub main: String,
+    pub featured: Option<String>,
+}
+
+impl ArtistCredit {
+    /// The credit written the one way: "Main feat. Guest"
+    pub fn canonical(&self) -> String {
+        match &self.featured {
+            Some(featured) => format!("{} feat. {}", self.main, featured),
+            None => self.main.clone(),
+        }
+    }
+}
+
+/// The featuring marker `word` starts with, if it is followed by a space
+fn featuring_marker(word: &str) -> Option<&'static str> {
+    FEATURING_MARKERS.into_iter().find(|marker| {
+        word.get(..marker.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(marker))
+            && word[marker.len()..].starts_with(char::is_whitespace)
+    })
+}
+
+/// Split an artist name at its first featuring marker ("feat.", "feat", "ft.", "ft" or
+/// "featuring", in any case and optionally in brackets), as "A ft B" or "A (featuring B)"
+/// The marker has to start a word after the main artist, so "Little Feat" is left whole
+pub fn split_featuring(name: &str) -> ArtistCredit {
+    for (start, c) in name.char_indices() {
+        if !name[..start].ends_with(char::is_whitespace) {
+            continue;
+        }
+        let (close, word_start) = match c {
+            '(' => (Some(')'), start + 1),
+            '[' => (Some(']'), start + 1),
+            _ => (None, start),
+        };
+        let Some(marker) = featuring_marker(&name[word_start..]) else {
+            continue;
+        };
+        let mut featured = &name[word_start + marker.len()..];
+        if let Some(close) = close {
+            featured = featured.split(close).next().unwrap_or_default();
+        }
+        let (main, featured) = (name[..start].trim(), featured.trim());
+        if !main.is_empty() && !featured.is_empty() {
+            return ArtistCredit {
+                main: main.to_string(),
+                featured: Some(featured.to_string()),
+            };
+        }
+    }
+    ArtistCredit {
+        main: name.trim().to_string(),
+        featured: None,
+    }
+}
+
+/// Whether a MusicBrainz join phrase such as " feat. " or " (featuring " introduces guests
+fn is_featuring_phrase(join_phrase: &str) -> bool {
+    let word = join_phrase
+        .trim()
+        .trim_start_matches(['(', '['])
+        .trim_start();
+    FEATURING_MARKERS
+        .iter()
+        .any(|marker| word.eq_ignore_ascii_case(marker))
+}
+
+/// Split a release's or track's MusicBrainz `artist-credit` at its first featuring join
+/// phrase; the artists after it are the featured ones, joined by their own phrases
+pub fn parse_artist_credit(value: &serde_json::Value) -> Option<ArtistCredit> {
+    let credits = value.get("artist-credit")?.as_array()?;
+    let mut main = String::new();
+    let mut featured: Option<String> = None;
+    for credit in credits {
+        let name = credit.get("name")?.as_str()?;
+        let join_phrase = credit
+            .get("joinphrase")
+            .and_then(|j| j.as_str())
+            .unwrap_or_default();
+        match &mut featured {
+            Some(guests) => {
+                guests.push_str(name);
+                guests.push_str(join_phrase);
+            }
+            None if is_featuring_phrase(join_phrase) => {
+                main.push_str(name);
+                featured = Some(String::new());
+            }
+            None => {
+                main.push_str(name);
+                main.push_str(join_phrase);
+            }
+        }
+    }
+
+    let tidy = |part: &str| part.trim().trim_end_matches([')', ']']).trim().to_string();
+    let main = tidy(&main);
+    let featured = featured
+        .map(|guests| tidy(&guests))
+        .filter(|g| !g.is_empty());
+    (!main.is_empty()).then_some(ArtistCredit { main, featured })
+}
+
+/// Rewrite a track's artist and album artist tags with featured artists written as
+/// [`ArtistCredit::canonical`] does, using the MusicBrainz credits when given and splitting
+/// the tags' own text otherwise
+/// With `strip_album_artist` the album artist keeps only the main artist, so every track of
+/// the album files under one folder; the artist tag keeps the full credit either way
+/// Returns whether the file was written
+pub fn normalize_featuring_tags(
+    file_path: &Path,
+    album_credit: Option<&ArtistCredit>,
+    track_credit: Option<&ArtistCredit>,
+    strip_album_artist: bool,
+) -> Result<bool> {
+    let Some(fields) = metadata::read_track_fields(file_path) else {
+        return Ok(false);
+    };
+    let artist = track_credit
+        .cloned()
+        .or_else(|| fields.artist.as_deref().map(split_featuring))
+        .map(|credit| credit.canonical());
+    let album_artist = album_credit
+        .cloned()
+        .or_else(|| fields.album_artist.as_deref().map(split_featuring))
+        .map(|credit| {
+            if strip_album_artist {
+                credit.main
+            } else {
+                credit.canonical()
+            }
+        });
+    if artist == fields.artist && album_artist == fields.album_artist {
+        return Ok(false);
+    }
+
+    let mut tagged_file = lofty::read_from_path(file_path)
+        .with_context(|| format!("Failed to read {}", file_path.display()))?;
+    metadata::update_file_tags(&mut tagged_file, |tag| {
+        if let Some(artist) = &artist {
+            tag.insert_text(ItemKey::TrackArtist, artist.clone());
+        }
+        if let Some(album_artist) = &album_artist {
+            tag.insert_text(ItemKey::AlbumArtist, album_artist.clone());
+        }
+    })?;
+    gapless::save_tags(&tagged_file, file_path)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::write_silent_wav;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    const VARIANTS: [&str; 7] = [
+        "Main Act feat. Guest",
+        "Main Act ft Guest",
+        "Main Act ft. Guest",
+        "Main Act featuring Guest",
+        "Main Act Feat Guest",
+        "Main Act (feat. Guest)",
+        "Main Act [Featuring Guest]",
+    ];
+
+    #[test]
+    fn test_split_featuring_variants() {
+        for variant in VARIANTS {
+            assert_eq!(
+                split_featuring(variant).canonical(),
+                "Main Act feat. Guest",
+                "{}",
+                variant
+            );
+        }
+        assert_eq!(split_featuring("Little Feat").featured, None);
+        assert_eq!(split_featuring("Left Boy").featured, None);
+        assert_eq!(
+            split_featuring("Simon & Garfunkel").canonical(),
+            "Simon & Garfunkel"
+        );
+
+        // MusicBrainz join phrases say where the guests start
+        let credit = parse_artist_credit(&json!({
+            "artist-credit": [
+                {"name": "Main Act", "joinphrase": " (featuring "},
+                {"name": "Guest", "joinphrase": " & "},
+                {"name": "Other", "joinphrase": ")"}
+            ]
+        }));
+        assert_eq!(
+            credit.map(|c| c.canonical()).as_deref(),
+            Some("Main Act feat. Guest & Other")
+        );
+    }
+
+    #[test]
+    fn test_featuring_variants_file_under_one_folder() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut album_artists = Vec::new();
+        for (i, variant) in VARIANTS.iter().enumerate() {
+            let track = temp_dir.path().join(format!("{:02}.wav", i + 1));
+            write_silent_wav(&track)?;
+            let mut tagged_file = lofty::read_from_path(&track)?;
+            metadata::update_file_tags(&mut tagged_file, |tag| {
+                tag.insert_text(ItemKey::TrackArtist, variant.to_string());
+                tag.insert_text(ItemKey::AlbumArtist, variant.to_string());
+                tag.insert_text(ItemKey::AlbumTitle, "Album".to_string());
+            })?;
+            gapless::save_tags(&tagged_file, &track)?;
+
+            assert!(normalize_featuring_tags(&track, None, None, true)?);
+            let fields = metadata::read_track_fields(&track).expect("tags were written");
+            assert_eq!(fields.artist.as_deref(), Some("Main Act feat. Guest"));
+            album_artists.push(metadata::extract_artist_album_from_file(&track)?.album_artist);
+
+            // Already normalized, so nothing is rewritten
+            assert!(!normalize_featuring_tags(&track, None, None, true)?);
+        }
+        assert!(album_artists.iter().all(|artist| artist == "Main Act"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,255 @@
+use crate::directory;
+use anyhow::{Context, Result};
+use lofty::config::{ParseOptions, WriteOptions};
+use lofty::error::{ErrorKind, LoftyError};
+use lofty::file::{AudioFile, FileType, TaggedFile};
+use lofty::id3::v2::{Frame, Id3v2Tag};
+use lofty::iff::aiff::AiffFile;
+use lofty::iff::wav::WavFile;
+use lofty::mp4::{Atom, AtomIdent, Ilst, Mp4File};
+use lofty::mpeg::MpegFile;
+use lofty::tag::TagExt;
+use std::fs::File;
+use std::path::Path;
+
+/// Names gapless playback info is stored under: iTunes' `iTunSMPB` (encoder delay, padding
+/// and sample count), and the separate delay and padding fields some encoders write
+const GAPLESS_NAMES: [&str; 3] = ["iTunSMPB", "ENCODER_DELAY", "ENCODER_PADDING"];
+
+fn gapless_name(name: &str) -> Option<&str> {
+    GAPLESS_NAMES
+        .iter()
+        .any(|gapless| gapless.eq_ignore_ascii_case(name))
+        .then_some(name)
+}
+
+/// A file's gapless playback frames, as read from its native tags
+/// lofty's generic tags have no keys for these, so a save through them can drop the frames
+#[derive(Debug, Clone, Default)]
+pub struct GaplessTags {
+    id3v2: Vec<Frame<'static>>,
+    mp4: Vec<Atom<'static>>,
+}
+
+impl GaplessTags {
+    /// Whether the file had no gapless frames
+    pub fn is_empty(&self) -> bool {
+        self.id3v2.is_empty() && self.mp4.is_empty()
+    }
+}
+
+/// `iTunSMPB` and the like are user text (TXXX) or comment (COMM) frames in ID3v2
+fn id3v2_gapless_name<'a>(frame: &'a Frame<'_>) -> Option<&'a str> {
+    match frame {
+        Frame::UserText(text) => gapless_name(&text.description),
+        Frame::Comment(comment) => gapless_name(&comment.description),
+        _ => None,
+    }
+}
+
+/// ...and freeform `----:com.apple.iTunes:<name>` atoms in MP4
+fn mp4_gapless_name<'a>(atom: &'a Atom<'_>) -> Option<&'a str> {
+    match atom.ident() {
+        AtomIdent::Freeform { name, .. } => gapless_name(name),
+        _ => None,
+    }
+}
+
+/// The file's own ID3v2 tag, for the formats that carry gapless info in one
+pub(crate) fn read_id3v2(file_path: &Path) -> Option<Id3v2Tag> {
+    let mut file = File::open(file_path).ok()?;
+    let options = ParseOptions::new();
+    match FileType::from_path(file_path)? {
+        FileType::Mpeg => MpegFile::read_from(&mut file, options)
+            .ok()?
+            .id3v2()
+            .cloned(),
+        FileType::Wav => WavFile::read_from(&mut file, options)
+            .ok()?
+            .id3v2()
+            .cloned(),
+        FileType::Aiff => AiffFile::read_from(&mut file, options)
+            .ok()?
+            .id3v2()
+            .cloned(),
+        _ => None,
+    }
+}
+
+/// The file's own MP4 `ilst`, if it is an MP4/M4A file
+pub(crate) fn read_ilst(file_path: &Path) -> Option<Ilst> {
+    if FileType::from_path(file_path)? != FileType::Mp4 {
+        return None;
+    }
+    let mut file = File::open(file_path).ok()?;
+    Mp4File::read_from(&mut file, ParseOptions::new())
+        .ok()?
+        .ilst()
+        .cloned()
+}
+
+/// Read the gapless frames from `file_path`'s ID3v2 tag (MP3, WAV, AIFF) or MP4 `ilst`
+/// Other formats keep gapless info in the stream itself (Opus pre-skip, the LAME header), and
+/// files that can't be read have none
+pub fn read_gapless_tags(file_path: &Path) -> GaplessTags {
+    let id3v2 = read_id3v2(file_path)
+        .map(|tag| {
+            tag.into_iter()
+                .filter(|frame| id3v2_gapless_name(frame).is_some())
+                .collect()
+        })
+        .unwrap_or_default();
+    let mp4 = read_ilst(file_path)
+        .map(|ilst| {
+            ilst.into_iter()
+                .filter(|atom| mp4_gapless_name(atom).is_some())
+                .collect()
+        })
+        .unwrap_or_default();
+    GaplessTags { id3v2, mp4 }
+}
+
+/// Put back any of `gapless` that `file_path` no longer has, e.g. after a tag save dropped it
+/// Frames the file still has are left alone, so the file is only rewritten when needed
+pub fn restore_gapless_tags(file_path: &Path, gapless: &GaplessTags) -> Result<()> {
+    if !gapless.id3v2.is_empty() {
+        let mut tag = read_id3v2(file_path).unwrap_or_default();
+        let present: Vec<String> = (&tag)
+            .into_iter()
+            .filter_map(id3v2_gapless_name)
+            .map(str::to_string)
+            .collect();
+        let missing: Vec<&Frame<'static>> = gapless
+            .id3v2
+            .iter()
+            .filter(|frame| {
+                !present
+                    .iter()
+                    .any(|name| Some(name.as_str()) == id3v2_gapless_name(frame))
+            })
+            .collect();
+        if !missing.is_empty() {
+            for frame in missing {
+                tag.insert(frame.clone());
+            }
+            retry_busy_save(|| tag.save_to_path(file_path, WriteOptions::default())).with_context(
+                || format!("Failed to restore gapless tags of {}", file_path.display()),
+            )?;
+        }
+    }
+
+    if !gapless.mp4.is_empty() {
+        let mut ilst = read_ilst(file_path).unwrap_or_default();
+        let present: Vec<String> = (&ilst)
+            .into_iter()
+            .filter_map(mp4_gapless_name)
+            .map(str::to_string)
+            .collect();
+        let missing: Vec<&Atom<'static>> = gapless
+            .mp4
+            .iter()
+            .filter(|atom| {
+                !present
+                    .iter()
+                    .any(|name| Some(name.as_str()) == mp4_gapless_name(atom))
+            })
+            .collect();
+        if !missing.is_empty() {
+            for atom in missing {
+                ilst.insert(atom.clone());
+            }
+            retry_busy_save(|| ilst.save_to_path(file_path, WriteOptions::default()))
+                .with_context(|| {
+                    format!("Failed to restore gapless tags of {}", file_path.display())
+                })?;
+        }
+    }
+
+    Ok(())
+}
+
+fn is_busy_save_error(e: &LoftyError) -> bool {
+    matches!(e.kind(), ErrorKind::Io(io) if directory::is_busy_error(io))
+}
+
+/// Run a tag save, retrying it while another program has the file open or locked (see
+/// [`directory::retry_busy`])
+pub(crate) fn retry_busy_save(save: impl FnMut() -> Result<(), LoftyError>) -> Result<()> {
+    directory::retry_busy(save, is_busy_save_error).map_err(|e| {
+        if is_busy_save_error(&e) {
+            anyhow::anyhow!(
+                "{} (still in use after {} attempts)",
+                e,
+                directory::BUSY_ATTEMPTS
+            )
+        } else {
+            e.into()
+        }
+    })
+}
+
+/// Save `tagged_file`'s tags to `file_path` without losing its gapless playback frames
+/// Use this instead of `save_to_path` wherever tags are written
+/// A save that finds the file busy is retried (see [`retry_busy_save`]), blocking the thread
+/// between tries
+pub fn save_tags(tagged_file: &TaggedFile, file_path: &Path) -> Result<()> {
+    let gapless = read_gapless_tags(file_path);
+    retry_busy_save(|| tagged_file.save_to_path(file_path, WriteOptions::default()))
+        .with_context(|| format!("Failed to write tags to {}", file_path.display()))?;
+    restore_gapless_tags(file_path, &gapless)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata;
+    use crate::test_support::write_silent_wav;
+    use lofty::file::TaggedFileExt;
+    use lofty::id3::v2::ExtendedTextFrame;
+    use lofty::tag::{Accessor, TagType};
+    use lofty::TextEncoding;
+    use tempfile::TempDir;
+
+    const ITUNSMPB: &str =
+        " 00000000 00000840 000001C0 0000000000004A00 00000000 00000000 00000000 00000000";
+
+    #[test]
+    fn test_gapless_tags_survive_metadata_enhancement() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let track = temp_dir.path().join("01 Track.wav");
+        write_silent_wav(&track)?;
+
+        // As an encoder would leave it: a name plus iTunes' delay/padding frame
+        let mut tag = Id3v2Tag::new();
+        tag.set_artist("Encoder Artist".to_string());
+        tag.insert(Frame::UserText(ExtendedTextFrame::new(
+            TextEncoding::UTF8,
+            "iTunSMPB".to_string(),
+            ITUNSMPB.to_string(),
+        )));
+        tag.save_to_path(&track, WriteOptions::default())?;
+        assert!(!read_gapless_tags(&track).is_empty());
+
+        metadata::set_enhanced_metadata(
+            &track,
+            "Tagged Artist",
+            "Tagged Album",
+            "release-id",
+            None,
+            false,
+            None,
+        )?;
+
+        let tagged_file = lofty::read_from_path(&track)?;
+        let artist = tagged_file.tag(TagType::Id3v2).and_then(|tag| tag.artist());
+        assert_eq!(artist.as_deref(), Some("Tagged Artist"));
+        let gapless = read_gapless_tags(&track);
+        assert_eq!(gapless.id3v2.len(), 1);
+        match &gapless.id3v2[0] {
+            Frame::UserText(text) => assert_eq!(text.content, ITUNSMPB),
+            other => panic!("unexpected gapless frame: {:?}", other),
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,243 @@
+use crate::musicbrainz::Network;
+use crate::progress::ProgressSink;
+use crate::{gapless, metadata, musicbrainz};
+use anyhow::{Context, Result};
+use lofty::file::{AudioFile, TaggedFileExt};
+use lofty::tag::ItemKey;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Environment variable pointing lyrics requests at an LRCLIB mirror
+pub const LRCLIB_BASE_ENV: &str = "MFUTIL_LRCLIB_BASE";
+
+/// LRCLIB API root used unless `MFUTIL_LRCLIB_BASE` is set
+pub const DEFAULT_LRCLIB_BASE: &str = "https://lrclib.net/api";
+
+/// A track's lyrics as LRCLIB has them: plain text, and the time-synced LRC form
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Lyrics {
+    pub plain: Option<String>,
+    pub synced: Option<String>,
+}
+
+impl Lyrics {
+    /// The plain text to embed, taken from the synced form without its timestamps when
+    /// LRCLIB has no plain version
+    pub fn plain_text(&self) -> Option<String> {
+        self.plain
+            .clone()
+            .or_else(|| self.synced.as_deref().map(strip_lrc_timestamps))
+            .filter(|text| !text.trim().is_empty())
+    }
+}
+
+/// Lyrics from an LRCLIB `/get` response; blank fields count as missing
+pub fn parse_lrclib_response(response: &serde_json::Value) -> Option<Lyrics> {
+    let text = |key: &str| {
+        response
+            .get(key)
+            .and_then(|v| v.as_str())
+            .filter(|text| !text.trim().is_empty())
+            .map(str::to_string)
+    };
+    let lyrics = Lyrics {
+        plain: text("plainLyrics"),
+        synced: text("syncedLyrics"),
+    };
+    (lyrics.plain.is_some() || lyrics.synced.is_some()).then_some(lyrics)
+}
+
+/// LRC text without its `[mm:ss.xx]` timestamps, one lyric line per line
+/// ID tag lines such as `[ar:Artist]` are left out
+pub fn strip_lrc_timestamps(synced: &str) -> String {
+    synced
+        .lines()
+        .filter(|line| {
+            let line = line.trim();
+            !(line.starts_with('[')
+                && line.ends_with(']')
+                && line[1..].starts_with(|c: char| c.is_ascii_alphabetic()))
+        })
+        .map(|line| {
+            let mut rest = line.trim_start();
+            while let Some(end) = rest
+                .strip_prefix('[')
+                .and_then(|tagged| tagged.find(']'))
+                .filter(|&end| rest[1..=end].starts_with(|c: char| c.is_ascii_digit()))
+            {
+                rest = rest[end + 2..].trim_start();
+            }
+            rest
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Look a track up on LRCLIB by artist, title, album and length
+/// Returns None when LRCLIB has no lyrics for it, or while offline
+pub async fn fetch_lyrics(
+    network: &Network,
+    artist: &str,
+    title: &str,
+    album: &str,
+    duration_secs: u64,
+) -> Result<Option<Lyrics>> {
+    if network.is_offline() {
+        return Ok(None);
+    }
+    let url = format!(
+        "{}/get?artist_name={}&track_name={}&album_name={}&duration={}",
+        musicbrainz::base_url(LRCLIB_BASE_ENV, DEFAULT_LRCLIB_BASE),
+        urlencoding::encode(artist),
+        urlencoding::encode(title),
+        urlencoding::encode(album),
+        duration_secs
+    );
+    let response = network
+        .http_client()?
+        .get(&url)
+        .send()
+        .await
+        .context("LRCLIB request failed")?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    let json: serde_json::Value = response
+        .error_for_status()
+        .context("LRCLIB request failed")?
+        .json()
+        .await
+        .context("Failed to parse LRCLIB response")?;
+    Ok(parse_lrclib_response(&json))
+}
+
+/// The unsynced lyrics embedded in a file's tags, if any
+pub fn read_embedded_lyrics(file_path: &Path) -> Option<String> {
+    let tagged_file = lofty::read_from_path(file_path).ok()?;
+    tagged_file.tags().iter().find_map(|tag| {
+        tag.get_string(&ItemKey::Lyrics)
+            .filter(|text| !text.trim().is_empty())
+            .map(str::to_string)
+    })
+}
+
+/// Embed `text` as the file's unsynced lyrics (`USLT` in ID3v2, `©lyr` in MP4, `LYRICS`
+/// in Vorbis comments)
+/// Files that already have lyrics are left alone unless `force` is set; returns whether the
+/// file was written
+pub fn embed_lyrics(file_path: &Path, text: &str, force: bool) -> Result<bool> {
+    if !force && read_embedded_lyrics(file_path).is_some() {
+        return Ok(false);
+    }
+    let mut tagged_file = lofty::read_from_path(file_path)
+        .with_context(|| format!("Failed to read {}", file_path.display()))?;
+    metadata::update_file_tags(&mut tagged_file, |tag| {
+        tag.insert_text(ItemKey::Lyrics, text.to_string());
+    })?;
+    gapless::save_tags(&tagged_file, file_path)?;
+    Ok(true)
+}
+
+/// Write synced lyrics next to the track as `<track>.lrc`, where players look for them
+/// An existing sidecar is kept unless `force` is set; returns the path if one was written
+pub fn write_lrc_sidecar(file_path: &Path, synced: &str, force: bool) -> Result<Option<PathBuf>> {
+    let lrc_path = file_path.with_extension("lrc");
+    if lrc_path.exists() && !force {
+        return Ok(None);
+    }
+    fs::write(&lrc_path, synced)
+        .with_context(|| format!("Failed to write {}", lrc_path.display()))?;
+    Ok(Some(lrc_path))
+}
+
+/// Fetch a tagged track's lyrics, embed the plain text and save the synced form as a sidecar
+/// Tracks that already have embedded lyrics are skipped without a lookup unless `force` is
+/// set, as are tracks without artist and title tags
+pub async fn save_track_lyrics(
+    network: &Network,
+    file_path: &Path,
+    force: bool,
+    progress: &dyn ProgressSink,
+) -> Result<()> {
+    if !force && read_embedded_lyrics(file_path).is_some() {
+        return Ok(());
+    }
+    let Some(fields) = metadata::read_track_fields(file_path) else {
+        return Ok(());
+    };
+    let (Some(artist), Some(title)) = (fields.artist.or(fields.album_artist), fields.title) else {
+        return Ok(());
+    };
+    let duration_secs = lofty::read_from_path(file_path)
+        .map(|tagged_file| tagged_file.properties().duration().as_secs())
+        .unwrap_or_default();
+
+    let Some(lyrics) = fetch_lyrics(
+        network,
+        &artist,
+        &title,
+        fields.album.as_deref().unwrap_or_default(),
+        duration_secs,
+    )
+    .await?
+    else {
+        return Ok(());
+    };
+
+    if let Some(text) = lyrics.plain_text() {
+        if embed_lyrics(file_path, &text, force)? {
+            progress.custom(format!("Embedded lyrics in {}", file_path.display()));
+        }
+    }
+    if let Some(synced) = &lyrics.synced {
+        if let Some(lrc_path) = write_lrc_sidecar(file_path, synced, force)? {
+            progress.custom(format!("Saved synced lyrics to {}", lrc_path.display()));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::write_silent_wav;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_embedded_lyrics_round_trip() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let track = temp_dir.path().join("01 Track.wav");
+        write_silent_wav(&track)?;
+
+        // Only the synced form: it is embedded without timestamps and kept as a sidecar
+        let lyrics = parse_lrclib_response(&json!({
+            "plainLyrics": "",
+            "syncedLyrics": "[ar:Artist]\n[00:01.00] First line\n[00:04.50][00:30.00] Chorus"
+        }))
+        .expect("lyrics were found");
+        let text = lyrics.plain_text().expect("plain text was derived");
+        assert_eq!(text, "First line\nChorus");
+
+        assert!(embed_lyrics(&track, &text, false)?);
+        let tagged_file = lofty::read_from_path(&track)?;
+        let tag = tagged_file.primary_tag().expect("tag was written");
+        assert_eq!(tag.get_string(&ItemKey::Lyrics), Some("First line\nChorus"));
+
+        // Lyrics already there are kept unless forced
+        assert!(!embed_lyrics(&track, "Other words", false)?);
+        assert_eq!(read_embedded_lyrics(&track).as_deref(), Some(text.as_str()));
+        assert!(embed_lyrics(&track, "Other words", true)?);
+        assert_eq!(read_embedded_lyrics(&track).as_deref(), Some("Other words"));
+
+        let synced = lyrics.synced.as_deref().unwrap();
+        let lrc_path = write_lrc_sidecar(&track, synced, false)?;
+        assert_eq!(lrc_path, Some(temp_dir.path().join("01 Track.lrc")));
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("01 Track.lrc"))?,
+            synced
+        );
+
+        Ok(())
+    }
+}
@@ -1,63 +1,465 @@
-use anyhow::Result;
-use lofty::config::WriteOptions;
-use lofty::file::{AudioFile, TaggedFileExt};
-use lofty::tag::ItemKey;
+use crate::utils::Naming;
+use crate::{gapless, tag_backup};
+use anyhow::{Context, Result};
+use lofty::file::{TaggedFile, TaggedFileExt};
+use lofty::tag::{Accessor, ItemKey, Tag, TagType};
 use std::path::Path;
 use tracing::warn;
 
-/// Extract artist and album information from a music file
-pub fn extract_artist_album_from_file(file_path: &Path) -> Result<(String, String)> {
-    match lofty::read_from_path(file_path) {
+/// Artist placeholder used when neither tags nor the filename provide one
+pub const UNKNOWN_ARTIST: &str = "Unknown Artist";
+
+/// Album placeholder used when neither tags nor the path provide one
+pub const UNKNOWN_ALBUM: &str = "Unknown Album";
+
+/// Album artist of compilations, as MusicBrainz credits them
+pub const VARIOUS_ARTISTS: &str = "Various Artists";
+
+/// Album folder for tracks found loose in an artist folder that have no album tag
+pub const SINGLES_ALBUM: &str = "Singles";
+
+/// Leading articles moved to the end of derived artist sort names unless others are given
+pub const DEFAULT_SORT_ARTICLES: [&str; 3] = ["The", "A", "An"];
+
+/// Sort name for an artist, with a leading article moved to the end: "The Beatles" becomes
+/// "Beatles, The". Articles ending in an apostrophe (French "L'") need no space after them;
+/// names without an article are returned as they are
+/// `articles` replaces [`DEFAULT_SORT_ARTICLES`] for libraries in other languages (e.g.
+/// "Die", "Der", "Das"), as `--sort-articles` does
+pub fn artist_sort_name(name: &str, articles: Option<&[String]>) -> String {
+    let name = name.trim();
+    let articles: Vec<&str> = match articles {
+        Some(articles) => articles.iter().map(String::as_str).collect(),
+        None => DEFAULT_SORT_ARTICLES.to_vec(),
+    };
+    for article in articles {
+        let Some(prefix) = name.get(..article.len()) else {
+            continue;
+        };
+        if article.is_empty() || !prefix.eq_ignore_ascii_case(article) {
+            continue;
+        }
+        let rest = &name[article.len()..];
+        let rest = if article.ends_with(['\'', '’']) {
+            rest
+        } else if let Some(rest) = rest.strip_prefix(' ') {
+            rest
+        } else {
+            continue;
+        };
+        let rest = rest.trim_start();
+        if !rest.is_empty() {
+            return format!("{}, {}", rest, prefix);
+        }
+    }
+    name.to_string()
+}
+
+/// Write the album artist and track artist sort tags that are missing, deriving them with
+/// [`artist_sort_name`] with `articles`. `album_artist_sort` (MusicBrainz's sort name) replaces
+/// the album artist's and is reused for the track artist when the two artists are the same
+pub fn fill_sort_tags(tag: &mut Tag, album_artist_sort: Option<&str>, articles: Option<&[String]>) {
+    let album_artist = tag.get_string(&ItemKey::AlbumArtist).map(str::to_string);
+    if let Some(sort) = album_artist_sort.filter(|sort| !sort.trim().is_empty()) {
+        tag.insert_text(ItemKey::AlbumArtistSortOrder, sort.to_string());
+    }
+
+    for (name_key, sort_key) in [
+        (ItemKey::AlbumArtist, ItemKey::AlbumArtistSortOrder),
+        (ItemKey::TrackArtist, ItemKey::TrackArtistSortOrder),
+    ] {
+        if tag
+            .get_string(&sort_key)
+            .is_some_and(|sort| !sort.trim().is_empty())
+        {
+            continue;
+        }
+        let Some(name) = tag
+            .get_string(&name_key)
+            .filter(|name| !name.trim().is_empty())
+            .map(str::to_string)
+        else {
+            continue;
+        };
+        let sort = match album_artist_sort {
+            Some(sort) if album_artist.as_deref() == Some(name.as_str()) => sort.to_string(),
+            _ => artist_sort_name(&name, articles),
+        };
+        tag.insert_text(sort_key, sort);
+    }
+}
+
+/// Whether an artist/album pair fell back to one of the Unknown placeholders
+pub fn is_unknown_placeholder(artist: &str, album: &str) -> bool {
+    artist == UNKNOWN_ARTIST || album == UNKNOWN_ALBUM
+}
+
+/// Artist and album names read from a music file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArtistAlbum {
+    /// Artist the album is filed under; groups tracks into artist folders
+    pub album_artist: String,
+    /// Artist of this particular track, for display and tagging
+    pub track_artist: String,
+    pub album: String,
+}
+
+/// Extract album artist, track artist and album from a music file
+/// Each artist falls back to the other tag, then to the filename; without tags both
+/// come from the path
+pub fn extract_artist_album_from_file(file_path: &Path) -> Result<ArtistAlbum> {
+    match read_tagged_file(file_path) {
         Ok(tagged_file) => {
             let tags = tagged_file.tags();
-            if let Some(tag) = tags.first() {
-                // Try multiple artist fields in order of preference
-                let artist = tag
-                    .get_string(&ItemKey::AlbumArtist)
-                    .or_else(|| tag.get_string(&ItemKey::TrackArtist))
-                    .unwrap_or_else(|| {
-                        // Try to extract from filename if no artist metadata
-                        file_path
-                            .file_stem()
-                            .and_then(|s| s.to_str())
-                            .unwrap_or("Unknown Artist")
-                            .split(" - ")
-                            .next()
-                            .unwrap_or("Unknown Artist")
-                    })
+            if !tags.is_empty() {
+                // Try to extract from filename if no artist metadata
+                let filename_artist = || {
+                    file_path
+                        .file_stem()
+                        .and_then(|s| s.to_str())
+                        .unwrap_or(UNKNOWN_ARTIST)
+                        .split(" - ")
+                        .next()
+                        .unwrap_or(UNKNOWN_ARTIST)
+                };
+                let album_artist_tag = album_artist_text(tags);
+                let track_artist_tag = tag_text(tags, &ItemKey::TrackArtist);
+                let album_artist = album_artist_tag
+                    .or(track_artist_tag)
+                    .unwrap_or_else(filename_artist)
+                    .to_string();
+                let track_artist = track_artist_tag
+                    .or(album_artist_tag)
+                    .unwrap_or_else(filename_artist)
                     .to_string();
 
-                // Try multiple album fields in order of preference
-                let album = tag
-                    .get_string(&ItemKey::AlbumTitle)
+                let album = tag_text(tags, &ItemKey::AlbumTitle)
                     .unwrap_or_else(|| {
                         // Try to extract from parent directory name
                         file_path
                             .parent()
                             .and_then(|p| p.file_name())
                             .and_then(|n| n.to_str())
-                            .unwrap_or("Unknown Album")
+                            .unwrap_or(UNKNOWN_ALBUM)
                     })
                     .to_string();
 
-                Ok((artist, album))
+                Ok(ArtistAlbum {
+                    album_artist,
+                    track_artist,
+                    album,
+                })
             } else {
                 // Fallback to path-based extraction
-                extract_from_path(file_path)
+                artist_album_from_path(file_path)
             }
         }
         Err(_) => {
             // Fallback to path-based extraction
-            extract_from_path(file_path)
+            artist_album_from_path(file_path)
+        }
+    }
+}
+
+#[cfg(test)]
+thread_local! {
+    static TAG_READS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// How many files this thread has read tags from, so a test can check whether a run opened
+/// any without seeing reads made by tests on other threads
+#[cfg(test)]
+pub(crate) fn tag_reads() -> usize {
+    TAG_READS.with(std::cell::Cell::get)
+}
+
+/// Read a file and its tags with lofty; every tag read in this module goes through here
+fn read_tagged_file(file_path: &Path) -> Result<TaggedFile, lofty::error::LoftyError> {
+    #[cfg(test)]
+    TAG_READS.with(|reads| reads.set(reads.get() + 1));
+    lofty::read_from_path(file_path)
+}
+
+/// The value of `key` from the first of a file's tags that has one
+/// WAV and AIFF files can carry ID3v2 next to RIFF INFO or AIFF text chunks, and the
+/// values may be in any of them
+fn tag_text<'a>(tags: &'a [Tag], key: &ItemKey) -> Option<&'a str> {
+    tags.iter().find_map(|tag| tag.get_string(key))
+}
+
+/// Whether a file's tags carry the compilation flag (`TCMP`, `cpil`, `COMPILATION`)
+fn is_compilation(tags: &[Tag]) -> bool {
+    tag_text(tags, &ItemKey::FlagCompilation)
+        .is_some_and(|flag| flag == "1" || flag.eq_ignore_ascii_case("true"))
+}
+
+/// The album artist tag, or [`VARIOUS_ARTISTS`] for a flagged compilation without one, so
+/// its tracks are grouped together rather than under each track's artist
+fn album_artist_text(tags: &[Tag]) -> Option<&str> {
+    tag_text(tags, &ItemKey::AlbumArtist)
+        .filter(|artist| !artist.trim().is_empty())
+        .or_else(|| is_compilation(tags).then_some(VARIOUS_ARTISTS))
+}
+
+/// Whether a file is flagged as part of a compilation
+pub fn read_compilation_flag(file_path: &Path) -> bool {
+    read_tagged_file(file_path).is_ok_and(|tagged_file| is_compilation(tagged_file.tags()))
+}
+
+/// Artist and album from the file's folders alone (see [`extract_from_path`]), without
+/// opening the file; the track artist is the album artist
+pub fn artist_album_from_path(file_path: &Path) -> Result<ArtistAlbum> {
+    let (artist, album) = extract_from_path(file_path)?;
+    Ok(ArtistAlbum {
+        album_artist: artist.clone(),
+        track_artist: artist,
+        album,
+    })
+}
+
+/// In classical mode (see [`Naming::classical`]), put the composer from `file_path`'s tags
+/// in place of the album artist, so the track is filed under the composer
+/// Files without a composer tag, and every file outside classical mode, keep `names`
+pub fn file_under_composer(
+    file_path: &Path,
+    mut names: ArtistAlbum,
+    naming: &Naming,
+) -> ArtistAlbum {
+    if naming.classical {
+        if let Ok(tagged_file) = read_tagged_file(file_path) {
+            let composer = tag_text(tagged_file.tags(), &ItemKey::Composer)
+                .filter(|composer| !composer.trim().is_empty());
+            if let Some(composer) = composer {
+                names.album_artist = composer.to_string();
+            }
+        }
+    }
+    names
+}
+
+/// Artist and album of a track lying directly in an artist folder (`Artists/<artist>/<file>`)
+/// Tags are used where present; otherwise the artist is the folder's name and the album is
+/// [`SINGLES_ALBUM`], since the path has no album folder to fall back on. With `path_only`
+/// the file isn't opened and those fallbacks are used as they are
+pub fn loose_track_artist_album(file_path: &Path, path_only: bool) -> ArtistAlbum {
+    let folder_artist = file_path
+        .parent()
+        .and_then(|p| p.file_name())
+        .and_then(|n| n.to_str())
+        .unwrap_or(UNKNOWN_ARTIST);
+    let tagged_file = (!path_only)
+        .then(|| read_tagged_file(file_path).ok())
+        .flatten();
+    let tags = tagged_file.as_ref().map(|t| t.tags()).unwrap_or_default();
+    let album_artist_tag = album_artist_text(tags);
+    let track_artist_tag = tag_text(tags, &ItemKey::TrackArtist);
+
+    ArtistAlbum {
+        album_artist: album_artist_tag
+            .or(track_artist_tag)
+            .unwrap_or(folder_artist)
+            .to_string(),
+        track_artist: track_artist_tag
+            .or(album_artist_tag)
+            .unwrap_or(folder_artist)
+            .to_string(),
+        album: tag_text(tags, &ItemKey::AlbumTitle)
+            .unwrap_or(SINGLES_ALBUM)
+            .to_string(),
+    }
+}
+
+/// Read artist and album strictly from a file's tags, without any path-based fallback
+/// Returns `None` if the file has no tags or either value is missing
+pub fn read_artist_album_tags(file_path: &Path) -> Option<(String, String)> {
+    let tagged_file = read_tagged_file(file_path).ok()?;
+    let tags = tagged_file.tags();
+    let artist = album_artist_text(tags).or_else(|| tag_text(tags, &ItemKey::TrackArtist))?;
+    let album = tag_text(tags, &ItemKey::AlbumTitle)?;
+
+    Some((artist.to_string(), album.to_string()))
+}
+
+/// Read the MusicBrainz release ID from any of a file's tags
+/// Returns `None` if the file can't be read or has no non-empty release ID
+pub fn read_musicbrainz_release_id(file_path: &Path) -> Option<String> {
+    let tagged_file = read_tagged_file(file_path).ok()?;
+    tagged_file.tags().iter().find_map(|tag| {
+        tag.get_string(&ItemKey::MusicBrainzReleaseId)
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(str::to_string)
+    })
+}
+
+/// Read a file's position in its release as (disc, track) from any of its tags
+/// The disc defaults to 1; returns `None` if no tag carries a track number
+pub fn read_track_position(file_path: &Path) -> Option<(u32, u32)> {
+    let tagged_file = read_tagged_file(file_path).ok()?;
+    tagged_file
+        .tags()
+        .iter()
+        .find_map(|tag| Some((tag.disk().unwrap_or(1), tag.track()?)))
+}
+
+/// Tag values a track's file name can be built from
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrackFields {
+    pub track: Option<u32>,
+    pub disc: Option<u32>,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album_artist: Option<String>,
+    pub album: Option<String>,
+    pub year: Option<u32>,
+}
+
+/// Read a file's track and disc numbers, title, artists, album and year from any of its tags
+/// Blank values count as missing; returns `None` if the file can't be read
+pub fn read_track_fields(file_path: &Path) -> Option<TrackFields> {
+    let tagged_file = read_tagged_file(file_path).ok()?;
+    let tags = tagged_file.tags();
+    let text = |key: ItemKey| {
+        tags.iter()
+            .filter_map(|tag| tag.get_string(&key))
+            .map(str::trim)
+            .find(|value| !value.is_empty())
+            .map(str::to_string)
+    };
+
+    Some(TrackFields {
+        track: tags.iter().find_map(|tag| tag.track()),
+        disc: tags.iter().find_map(|tag| tag.disk()),
+        title: text(ItemKey::TrackTitle),
+        artist: text(ItemKey::TrackArtist),
+        album_artist: text(ItemKey::AlbumArtist),
+        album: text(ItemKey::AlbumTitle),
+        year: tags
+            .iter()
+            .find_map(|tag| tag.year())
+            .filter(|year| *year > 0),
+    })
+}
+
+/// Read a file's release year from any of its tags (a year tag, or the start of a date)
+pub fn read_year(file_path: &Path) -> Option<u32> {
+    let tagged_file = read_tagged_file(file_path).ok()?;
+    tagged_file
+        .tags()
+        .iter()
+        .find_map(|tag| tag.year())
+        .filter(|year| *year > 0)
+}
+
+/// Read a file's disc number and disc subtitle (a box-set disc's own title), where tagged
+pub fn read_disc_info(file_path: &Path) -> (Option<u32>, Option<String>) {
+    let Ok(tagged_file) = read_tagged_file(file_path) else {
+        return (None, None);
+    };
+    let tags = tagged_file.tags();
+    let disc = tags.iter().find_map(|tag| tag.disk());
+    let subtitle = tag_text(tags, &ItemKey::SetSubtitle)
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string);
+    (disc, subtitle)
+}
+
+/// Normalize tag text by trimming and collapsing runs of whitespace to a single space
+/// When `ascii_punctuation` is set, smart quotes and dashes are also converted to ASCII
+pub fn normalize_tag_text(text: &str, ascii_punctuation: bool) -> String {
+    let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if !ascii_punctuation {
+        return collapsed;
+    }
+
+    collapsed
+        .chars()
+        .map(|c| match c {
+            '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}' | '\u{2032}' => '\'',
+            '\u{201C}' | '\u{201D}' | '\u{201E}' | '\u{201F}' | '\u{2033}' => '"',
+            '\u{2010}'..='\u{2015}' | '\u{2212}' => '-',
+            c => c,
+        })
+        .collect()
+}
+
+/// [`normalize_tag_text`] for a name a folder is made from: text that normalizes to nothing,
+/// such as a tag of only spaces, gives `missing` (e.g. [`UNKNOWN_ARTIST`]) as an absent tag
+/// would, so the folder never collapses into its parent
+pub fn normalize_folder_text(text: &str, ascii_punctuation: bool, missing: &str) -> String {
+    let normalized = normalize_tag_text(text, ascii_punctuation);
+    if normalized.is_empty() {
+        missing.to_string()
+    } else {
+        normalized
+    }
+}
+
+/// Characters Windows-1252 puts at bytes 0x80-0x9F; the five bytes it leaves undefined
+/// decode to the matching C1 control, as they do in Latin-1
+const CP1252_HIGH: [char; 32] = [
+    '\u{20AC}', '\u{81}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{8D}', '\u{017D}', '\u{8F}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\u{9D}', '\u{017E}', '\u{0178}',
+];
+
+/// Turn text back into the bytes it was decoded from as Latin-1/Windows-1252 and read
+/// them as UTF-8, or None if that is not how the text came about
+fn undo_latin1_decoding(text: &str) -> Option<String> {
+    if text.is_ascii() {
+        return None;
+    }
+    let bytes = text
+        .chars()
+        .map(|c| match u32::from(c) {
+            code @ 0..=0xFF => Some(code as u8),
+            _ => CP1252_HIGH
+                .iter()
+                .position(|&high| high == c)
+                .map(|i| 0x80 + i as u8),
+        })
+        .collect::<Option<Vec<u8>>>()?;
+    let decoded = String::from_utf8(bytes).ok()?;
+    // Control characters in the result mean the bytes were never meant as UTF-8
+    if decoded
+        .chars()
+        .any(|c| c.is_control() && !c.is_whitespace())
+    {
+        return None;
+    }
+    Some(decoded)
+}
+
+/// Repair mojibake left by UTF-8 tags that were read as Latin-1 or Windows-1252 and saved
+/// again, e.g. "BeyoncÃ©" becomes "Beyoncé"; text encoded twice over is undone twice
+/// Returns None when the text does not look mis-encoded. Genuine accented text never
+/// decodes as UTF-8 this way, so it is left alone.
+pub fn repair_mojibake(text: &str) -> Option<String> {
+    let mut repaired = undo_latin1_decoding(text)?;
+    for _ in 0..2 {
+        match undo_latin1_decoding(&repaired) {
+            Some(again) => repaired = again,
+            None => break,
         }
     }
+    Some(repaired)
 }
 
 /// Extract artist and album from file path when tags are not available
+/// Files in a disc folder (`Album/CD1/track.mp3`) take the album from the folder above it
 pub fn extract_from_path(file_path: &Path) -> Result<(String, String)> {
-    let parent = file_path
+    let mut parent = file_path
         .parent()
         .ok_or_else(|| anyhow::anyhow!("File '{}' has no parent directory", file_path.display()))?;
+    let in_disc_folder = parent
+        .file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(super::utils::is_disc_folder_name);
+    if let Some(album_dir) = parent.parent().filter(|_| in_disc_folder) {
+        parent = album_dir;
+    }
 
     // Try to extract album from parent directory name
     let album = parent
@@ -80,12 +482,12 @@ pub fn extract_from_path(file_path: &Path) -> Result<(String, String)> {
                 .join(" ");
 
             if cleaned.trim().is_empty() {
-                "Unknown Album".to_string()
+                UNKNOWN_ALBUM.to_string()
             } else {
                 cleaned.trim().to_string()
             }
         })
-        .unwrap_or_else(|| "Unknown Album".to_string());
+        .unwrap_or_else(|| UNKNOWN_ALBUM.to_string());
 
     let grandparent = parent
         .parent()
@@ -112,26 +514,63 @@ pub fn extract_from_path(file_path: &Path) -> Result<(String, String)> {
                 .join(" ");
 
             if cleaned.trim().is_empty() {
-                "Various Artists".to_string()
+                VARIOUS_ARTISTS.to_string()
             } else {
                 cleaned.trim().to_string()
             }
         })
-        .unwrap_or_else(|| "Various Artists".to_string());
+        .unwrap_or_else(|| VARIOUS_ARTISTS.to_string());
 
     Ok((artist, album))
 }
 
+/// Apply `update` to a file's primary tag, first creating one of the format's preferred type
+/// (ID3v2 for WAV and AIFF) if the file has none
+/// A RIFF INFO or AIFF text chunk the file already carries is updated too, so readers of
+/// either tag see the same values
+pub fn update_file_tags(tagged_file: &mut TaggedFile, update: impl Fn(&mut Tag)) -> Result<()> {
+    let primary_type = tagged_file.primary_tag_type();
+    if tagged_file.primary_tag().is_none() {
+        tagged_file.insert_tag(Tag::new(primary_type));
+    }
+    let tag = tagged_file
+        .primary_tag_mut()
+        .context("File format does not support tags")?;
+    update(tag);
+
+    for tag_type in [TagType::RiffInfo, TagType::AiffText] {
+        if tag_type == primary_type {
+            continue;
+        }
+        if let Some(tag) = tagged_file.tag_mut(tag_type) {
+            update(tag);
+        }
+    }
+    Ok(())
+}
+
 /// Set enhanced metadata with MusicBrainz release ID
+/// Missing artist sort tags are filled in, from `artist_sort` (MusicBrainz's sort name for the
+/// album artist) when given. A `compilation` is flagged as one, so players group it under
+/// Various Artists. Derived sort tags move `sort_articles` (see [`artist_sort_name`])
 pub fn set_enhanced_metadata(
     file_path: &Path,
     artist: &str,
     album: &str,
     release_id: &str,
+    artist_sort: Option<&str>,
+    compilation: bool,
+    sort_articles: Option<&[String]>,
 ) -> Result<()> {
-    match lofty::read_from_path(file_path) {
+    match read_tagged_file(file_path) {
         Ok(mut tagged_file) => {
-            if let Some(tag) = tagged_file.primary_tag_mut() {
+            update_file_tags(&mut tagged_file, |tag| {
+                // Sort tags for a different artist would be stale; they are derived afresh below
+                if !artist.is_empty() && tag.get_string(&ItemKey::AlbumArtist) != Some(artist) {
+                    tag.remove_key(&ItemKey::AlbumArtistSortOrder);
+                    tag.remove_key(&ItemKey::TrackArtistSortOrder);
+                }
+
                 // Set standard metadata
                 tag.insert_text(ItemKey::TrackArtist, artist.to_string());
                 tag.insert_text(ItemKey::AlbumArtist, artist.to_string());
@@ -139,15 +578,20 @@ pub fn set_enhanced_metadata(
 
                 // Add MusicBrainz release ID
                 tag.insert_text(ItemKey::MusicBrainzReleaseId, release_id.to_string());
-
-                // Try to save the enhanced metadata
-                if let Err(e) = tagged_file.save_to_path(file_path, WriteOptions::default()) {
-                    warn!(
-                        "Failed to save enhanced metadata for {}: {}",
-                        file_path.display(),
-                        e
-                    );
+                if compilation {
+                    tag.insert_text(ItemKey::FlagCompilation, "1".to_string());
                 }
+
+                fill_sort_tags(tag, artist_sort, sort_articles);
+            })?;
+
+            // Try to save the enhanced metadata
+            if let Err(e) = gapless::save_tags(&tagged_file, file_path) {
+                warn!(
+                    "Failed to save enhanced metadata for {}: {}",
+                    file_path.display(),
+                    e
+                );
             }
         }
         Err(e) => {
@@ -161,3 +605,341 @@ pub fn set_enhanced_metadata(
 
     Ok(())
 }
+
+/// Tag fields [`fix_tag_encoding`] repairs
+const ENCODING_FIX_KEYS: [ItemKey; 4] = [
+    ItemKey::TrackArtist,
+    ItemKey::AlbumArtist,
+    ItemKey::AlbumTitle,
+    ItemKey::TrackTitle,
+];
+
+/// Repair mojibake (see [`repair_mojibake`]) in a file's artist, album and title tags
+/// Returns whether anything was rewritten; files with nothing to repair are not touched
+/// With `backup_tags` the tags are backed up (see [`tag_backup`]) before the first repair
+pub fn fix_tag_encoding(file_path: &Path, backup_tags: bool) -> Result<bool> {
+    let mut tagged_file = read_tagged_file(file_path)
+        .with_context(|| format!("Failed to read {}", file_path.display()))?;
+
+    // ID3v1 can only hold Latin-1, so a repaired name could not be stored there
+    let repairs: Vec<(TagType, ItemKey, String)> = tagged_file
+        .tags()
+        .iter()
+        .filter(|tag| tag.tag_type() != TagType::Id3v1)
+        .flat_map(|tag| {
+            ENCODING_FIX_KEYS.iter().filter_map(move |key| {
+                let repaired = repair_mojibake(tag.get_string(key)?)?;
+                Some((tag.tag_type(), key.clone(), repaired))
+            })
+        })
+        .collect();
+    if repairs.is_empty() {
+        return Ok(false);
+    }
+
+    if backup_tags {
+        tag_backup::backup_file_tags(file_path)?;
+    }
+    for (tag_type, key, repaired) in repairs {
+        if let Some(tag) = tagged_file.tag_mut(tag_type) {
+            tag.insert_text(key, repaired);
+        }
+    }
+    gapless::save_tags(&tagged_file, file_path)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::write_silent_wav;
+    use lofty::config::WriteOptions;
+    use lofty::file::AudioFile;
+    use tempfile::TempDir;
+
+    fn write_silent_aiff(path: &Path) -> Result<()> {
+        let samples = [0u8; 400];
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"FORM");
+        bytes.extend_from_slice(&(4 + 26 + 16 + samples.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(b"AIFFCOMM");
+        bytes.extend_from_slice(&18u32.to_be_bytes());
+        bytes.extend_from_slice(&1u16.to_be_bytes()); // mono
+        bytes.extend_from_slice(&(samples.len() as u32 / 2).to_be_bytes()); // frames
+        bytes.extend_from_slice(&16u16.to_be_bytes());
+        // 44100 Hz as an 80-bit extended float
+        bytes.extend_from_slice(&[0x40, 0x0E, 0xAC, 0x44, 0, 0, 0, 0, 0, 0]);
+        bytes.extend_from_slice(b"SSND");
+        bytes.extend_from_slice(&(8 + samples.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(&[0u8; 8]); // offset and block size
+        bytes.extend_from_slice(&samples);
+        std::fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_wav_riff_info_tags_are_read_and_kept_in_step() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let track = temp_dir.path().join("track.wav");
+        write_silent_wav(&track)?;
+        let mut tagged_file = lofty::read_from_path(&track)?;
+        let mut info = Tag::new(TagType::RiffInfo);
+        info.insert_text(ItemKey::TrackArtist, "Info Artist".to_string());
+        info.insert_text(ItemKey::AlbumTitle, "Info Album".to_string());
+        tagged_file.insert_tag(info);
+        tagged_file.save_to_path(&track, WriteOptions::default())?;
+
+        // Only a RIFF INFO chunk, no ID3v2: the names still come from the tags
+        let names = extract_artist_album_from_file(&track)?;
+        assert_eq!(names.album_artist, "Info Artist");
+        assert_eq!(names.album, "Info Album");
+        assert_eq!(
+            read_artist_album_tags(&track),
+            Some(("Info Artist".to_string(), "Info Album".to_string()))
+        );
+
+        // Writing adds the primary ID3v2 tag and updates the INFO chunk to match
+        set_enhanced_metadata(
+            &track,
+            "New Artist",
+            "New Album",
+            "release-id",
+            None,
+            false,
+            None,
+        )?;
+        let tagged_file = lofty::read_from_path(&track)?;
+        for tag_type in [TagType::Id3v2, TagType::RiffInfo] {
+            let tag = tagged_file.tag(tag_type).expect("tag was written");
+            assert_eq!(tag.get_string(&ItemKey::TrackArtist), Some("New Artist"));
+            assert_eq!(tag.get_string(&ItemKey::AlbumTitle), Some("New Album"));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_enhanced_metadata_round_trips_untagged_wav_and_aiff() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let wav = temp_dir.path().join("track.wav");
+        let aiff = temp_dir.path().join("track.aiff");
+        write_silent_wav(&wav)?;
+        write_silent_aiff(&aiff)?;
+
+        for track in [wav, aiff] {
+            assert!(lofty::read_from_path(&track)?.tags().is_empty());
+
+            set_enhanced_metadata(
+                &track,
+                "Tagged Artist",
+                "Tagged Album",
+                "release-id",
+                None,
+                false,
+                None,
+            )?;
+
+            let names = extract_artist_album_from_file(&track)?;
+            assert_eq!(names.album_artist, "Tagged Artist", "{}", track.display());
+            assert_eq!(names.track_artist, "Tagged Artist", "{}", track.display());
+            assert_eq!(names.album, "Tagged Album", "{}", track.display());
+            assert_eq!(
+                read_musicbrainz_release_id(&track).as_deref(),
+                Some("release-id")
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compilation_flag_is_written_and_groups_under_various_artists() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let track = temp_dir.path().join("track.wav");
+        write_silent_wav(&track)?;
+
+        set_enhanced_metadata(
+            &track,
+            "Track Artist",
+            "Now That's Music",
+            "release-id",
+            None,
+            true,
+            None,
+        )?;
+        assert!(read_compilation_flag(&track));
+
+        // Without an album artist, the flag keeps the track with the rest of the compilation
+        let mut tagged_file = lofty::read_from_path(&track)?;
+        tagged_file
+            .primary_tag_mut()
+            .expect("tag was written")
+            .remove_key(&ItemKey::AlbumArtist);
+        tagged_file.save_to_path(&track, WriteOptions::default())?;
+        let names = extract_artist_album_from_file(&track)?;
+        assert_eq!(names.album_artist, VARIOUS_ARTISTS);
+        assert_eq!(names.track_artist, "Track Artist");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_artist_sort_tags_move_leading_article() -> Result<()> {
+        assert_eq!(artist_sort_name("The Beatles", None), "Beatles, The");
+        assert_eq!(
+            artist_sort_name("A Tribe Called Quest", None),
+            "Tribe Called Quest, A"
+        );
+        // Only whole words are articles, and a name that is only an article stays put
+        assert_eq!(
+            artist_sort_name("Theory of a Deadman", None),
+            "Theory of a Deadman"
+        );
+        assert_eq!(artist_sort_name("The", None), "The");
+        // Other languages' articles replace the English ones
+        let german = ["Die".to_string(), "L'".to_string()];
+        assert_eq!(artist_sort_name("Die Ärzte", Some(&german)), "Ärzte, Die");
+        assert_eq!(artist_sort_name("L'Arc", Some(&german)), "Arc, L'");
+        assert_eq!(artist_sort_name("The Cure", Some(&german)), "The Cure");
+
+        let temp_dir = TempDir::new()?;
+        let track = temp_dir.path().join("track.wav");
+        write_silent_wav(&track)?;
+        set_enhanced_metadata(
+            &track,
+            "The Beatles",
+            "Abbey Road",
+            "release-id",
+            None,
+            false,
+            None,
+        )?;
+        let tagged_file = lofty::read_from_path(&track)?;
+        let tag = tagged_file.primary_tag().expect("tag was written");
+        assert_eq!(
+            tag.get_string(&ItemKey::AlbumArtistSortOrder),
+            Some("Beatles, The")
+        );
+        assert_eq!(
+            tag.get_string(&ItemKey::TrackArtistSortOrder),
+            Some("Beatles, The")
+        );
+
+        // MusicBrainz's sort name wins over the derived one
+        set_enhanced_metadata(
+            &track,
+            "The The",
+            "Soul Mining",
+            "release-id",
+            Some("The The"),
+            false,
+            None,
+        )?;
+        let tagged_file = lofty::read_from_path(&track)?;
+        let tag = tagged_file.primary_tag().expect("tag was written");
+        assert_eq!(
+            tag.get_string(&ItemKey::AlbumArtistSortOrder),
+            Some("The The")
+        );
+        assert_eq!(
+            tag.get_string(&ItemKey::TrackArtistSortOrder),
+            Some("The The")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_from_path_skips_disc_folder() -> Result<()> {
+        let file_path = Path::new("/music/Pink Floyd/The Wall/CD2/track.flac");
+        let (artist, album) = extract_from_path(file_path)?;
+
+        assert_eq!(artist, "Pink Floyd");
+        assert_eq!(album, "The Wall");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_tag_text_whitespace() {
+        assert_eq!(normalize_tag_text("  The   Beatles ", false), "The Beatles");
+        assert_eq!(normalize_tag_text("Abbey\tRoad\n", false), "Abbey Road");
+        assert_eq!(normalize_tag_text("   ", false), "");
+        assert_eq!(
+            normalize_folder_text(" \t ", false, UNKNOWN_ARTIST),
+            UNKNOWN_ARTIST
+        );
+        assert_eq!(
+            normalize_folder_text(" Abbey  Road ", false, UNKNOWN_ALBUM),
+            "Abbey Road"
+        );
+    }
+
+    #[test]
+    fn test_normalize_tag_text_ascii_punctuation() {
+        let text = "Guns N\u{2019} Roses \u{2013} \u{201C}Live\u{201D}";
+        assert_eq!(normalize_tag_text(text, true), "Guns N' Roses - \"Live\"");
+        // Smart punctuation is left alone unless requested
+        assert_eq!(normalize_tag_text(text, false), text);
+    }
+
+    #[test]
+    fn test_repair_mojibake_known_samples() {
+        let samples = [
+            ("BeyoncÃ©", "Beyoncé"),
+            ("Sigur RÃ³s", "Sigur Rós"),
+            ("MotÃ¶rhead", "Motörhead"),
+            ("ÐšÐ¸Ð½Ð¾", "Кино"),
+            ("Donâ€™t", "Don’t"),
+            // UTF-8 read as Latin-1 keeps the C1 controls for bytes 0x80-0x9F
+            ("æ\u{9D}±äº¬äº\u{8B}å¤\u{89}", "東京事変"),
+            // Encoded twice over
+            ("BeyoncÃƒÂ©", "Beyoncé"),
+        ];
+        for (mojibake, expected) in samples {
+            assert_eq!(repair_mojibake(mojibake).as_deref(), Some(expected));
+        }
+    }
+
+    #[test]
+    fn test_repair_mojibake_leaves_correct_text_alone() {
+        for text in [
+            "Björk",
+            "Beyoncé",
+            "Кино",
+            "東京事変",
+            "AC/DC",
+            "Café © 1999",
+        ] {
+            assert_eq!(repair_mojibake(text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_fix_tag_encoding_rewrites_mojibake_tags() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let track = temp_dir.path().join("track.wav");
+        write_silent_wav(&track)?;
+        let mut tagged_file = lofty::read_from_path(&track)?;
+        let mut tag = Tag::new(TagType::Id3v2);
+        tag.insert_text(ItemKey::TrackArtist, "Sigur RÃ³s".to_string());
+        tag.insert_text(ItemKey::AlbumTitle, "Takkâ€¦".to_string());
+        tag.insert_text(ItemKey::TrackTitle, "Glósóli".to_string());
+        tagged_file.insert_tag(tag);
+        tagged_file.save_to_path(&track, WriteOptions::default())?;
+
+        assert!(fix_tag_encoding(&track, true)?);
+        let backup = tag_backup::backup_path(&track).unwrap();
+        assert!(std::fs::read_to_string(backup)?.contains("Sigur RÃ³s"));
+        let tagged_file = lofty::read_from_path(&track)?;
+        let tag = tagged_file.tag(TagType::Id3v2).unwrap();
+        assert_eq!(tag.get_string(&ItemKey::TrackArtist), Some("Sigur Rós"));
+        assert_eq!(tag.get_string(&ItemKey::AlbumTitle), Some("Takk…"));
+        assert_eq!(tag.get_string(&ItemKey::TrackTitle), Some("Glósóli"));
+
+        // Nothing left to repair the second time round
+        assert!(!fix_tag_encoding(&track, false)?);
+
+        Ok(())
+    }
+}
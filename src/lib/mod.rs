@@ -3,13 +3,109 @@
 //! This library contains common functionality shared across the music-folder-utils application.
 //! It provides reusable components for MusicBrainz integration, cover art fetching, file processing,
 //! and other utilities used by the various command modules.
+//!
+//! ## Embedding
+//!
+//! Every `mfutil` subcommand is implemented in [`commands`] so that other programs (GUI
+//! frontends, scripts, services) can drive the same core as the CLI. The main entry points
+//! are re-exported at the crate root:
+//!
+//! - [`organize_music_library`] - move loose audio files into `Artists/<artist>/<album>`
+//! - [`reorganize_misplaced_files`] - fix files that sit in the wrong album folder
+//! - [`import_and_organize_files`] / [`import_and_organize_files_with_musicbrainz`] - copy
+//!   files from one or more external directories into the library
+//! - [`rename_folders_to_match_tags`] - rename folders whose names disagree with the tags
+//! - [`check_tag_folder_mismatches`] - list files whose tags disagree with their album folder
+//! - [`rename_files_to_template`] - rename track files to a [`FileTemplate`] filled in from tags
+//! - [`restore_tags`] - roll files back to the tags saved by [`tag_backup`]
+//! - [`process_single_album_sync_tags`] - sync one album's tags with MusicBrainz
+//! - [`fix_tag_encoding`] - repair mojibake in the library's artist, album and title tags
+//! - [`extract_artist_art`], [`fetch_placeholders`], [`process_single_album_art`] and
+//!   [`set_folder_icons_callback`] - album and artist art
+//! - [`embed_album_cover_art`] - embed an album's cover file into its tracks
+//! - [`process_single_album_symlink`] / [`process_single_track_symlink`] - symlink views
+//! - [`process_single_album_links`] and [`prune_stale_links`] - rebuild every view in one pass
+//! - [`import_cd`] - rip an audio CD into the library
+//! - [`verify_checksums`] - check files against the [`checksums`] manifests written after imports
+//! - [`provenance_report`] - count files by the [`provenance`] tag left by rips and imports
+//! - [`scan_library`] - time walking the library and reading every file's tags
+//!
+//! To walk the library without collecting it first, [`utils::iter_albums`] streams album
+//! folders as [`utils::AlbumRef`]s (folder names, with tags read on demand) and
+//! [`utils::iter_tracks`] streams their audio files.
+//!
+//! Commands that name folders or files take a [`Naming`]; its default names things as the
+//! CLI does without `--sanitize-replacement` or `--sanitize-delete`.
+//!
+//! Functions that report progress take a [`progress::ProgressSink`]. The CLI passes its TUI
+//! channel (`mpsc::Sender<String>` implements the trait); embedders can use
+//! [`progress::NoopSink`], [`progress::CollectingSink`] or their own implementation. Functions
+//! not yet migrated still take an `mpsc::Sender<String>` using the TUI's message protocol
+//! (`TOTAL_FILES:`, `COMPLETED:`, `ERROR:` prefixes).
+//!
+//! [`RunReport`] records what each operation did (counts, per-file moves, failures) so a run
+//! can be saved as a JSON audit trail, as the CLI's `--report-file` option does.
+//!
+//! Paths are used as given: expand `~` (and resolve relative paths) before calling in, as the
+//! CLI does once at startup.
+//!
+//! ```no_run
+//! let options = mfutil::OrganizeOptions {
+//!     dry_run: true,
+//!     quiet: true,
+//!     ..Default::default()
+//! };
+//! let summary = mfutil::organize_music_library("/srv/music", &options)?;
+//! println!("{}", summary);
+//! # Ok::<(), anyhow::Error>(())
+//! ```
 
+pub mod aliases;
 pub mod audio;
 pub mod cd;
+pub mod checksums;
+pub mod commands;
 pub mod cover_art;
+pub mod cue;
 pub mod directory;
+pub mod featuring;
+pub mod gapless;
+pub mod lyrics;
 pub mod metadata;
 pub mod musicbrainz;
 pub mod progress;
+pub mod provenance;
+pub mod report;
+pub mod tag_backup;
 pub mod tagging;
+#[cfg(test)]
+mod test_support;
 pub mod utils;
+
+pub use commands::albums::process_single_album_symlink;
+pub use commands::art::{
+    extract_artist_art, fetch_placeholders, process_single_album_art, set_folder_icons_callback,
+};
+pub use commands::cd::import_cd;
+pub use commands::check::{check_tag_folder_mismatches, CheckSummary};
+pub use commands::embed_art::{embed_album_cover_art, ArtClobber, EmbedArtSummary};
+pub use commands::fix_encoding::{fix_tag_encoding, FixEncodingSummary};
+pub use commands::import::{
+    import_and_organize_files, import_and_organize_files_with_musicbrainz, ImportOptions,
+};
+pub use commands::links::{process_single_album_links, prune_stale_links, LinkViews};
+pub use commands::organize::{organize_music_library, OrganizeOptions};
+pub use commands::provenance::{provenance_report, ProvenanceSummary};
+pub use commands::rename::rename_folders_to_match_tags;
+pub use commands::rename_files::{rename_files_to_template, FileTemplate};
+pub use commands::reorganize::reorganize_misplaced_files;
+pub use commands::restore_tags::{restore_tags, RestoreTagsSummary};
+pub use commands::scan::{scan_library, ScanSummary};
+pub use commands::sync::{
+    process_single_album_sync_tags, SyncOptions, SyncOutcome, UnmatchedAlbum,
+};
+pub use commands::tracks::process_single_track_symlink;
+pub use commands::verify::{verify_checksums, VerifySummary};
+pub use directory::OrganizeSummary;
+pub use report::RunReport;
+pub use utils::Naming;
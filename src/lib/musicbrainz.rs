@@ -1,31 +1,305 @@
+use crate::featuring::{self, ArtistCredit};
+use crate::metadata;
+use crate::progress::{NoopSink, ProgressSink};
+use crate::utils::Naming;
 use anyhow::{Context, Result};
-use musicbrainz_rs::{entity::release::Release, prelude::*, MusicBrainzClient};
-use std::path::Path;
-use std::sync::mpsc;
-use tracing::warn;
+use musicbrainz_rs::{entity::release::Release, prelude::*, ApiRequest, MusicBrainzClient};
+use rustc_hash::FxHashMap;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tracing::{info, warn};
 
-/// Create and configure a MusicBrainz client with the standard user agent
-pub fn create_musicbrainz_client() -> Result<MusicBrainzClient> {
-    let mut client = MusicBrainzClient::default();
-    client
-        .set_user_agent("mfutil/0.1.1 (https://github.com/anoraktrend/music-folder-utils)")
-        .context("Failed to set user agent")?;
-    Ok(client)
+/// User agent sent to MusicBrainz and the cover art services
+pub const USER_AGENT: &str = "mfutil/0.1.1 (https://github.com/anoraktrend/music-folder-utils)";
+
+/// Environment variable overriding the HTTP timeout, in seconds
+pub const HTTP_TIMEOUT_ENV: &str = "MFUTIL_HTTP_TIMEOUT";
+
+/// Environment variable pointing MusicBrainz requests at a mirror, e.g. `http://mirror:5000/ws/2`
+pub const MB_BASE_ENV: &str = "MFUTIL_MB_BASE";
+
+/// Environment variable pointing Cover Art Archive requests at a mirror
+pub const CAA_BASE_ENV: &str = "MFUTIL_CAA_BASE";
+
+/// MusicBrainz web service root used unless `MFUTIL_MB_BASE` is set
+pub const DEFAULT_MB_BASE: &str = "https://musicbrainz.org/ws/2";
+
+/// Cover Art Archive root used unless `MFUTIL_CAA_BASE` is set
+pub const DEFAULT_CAA_BASE: &str = "https://coverartarchive.org";
+
+/// HTTP connect/read timeout used when neither `--http-timeout` nor the environment sets one
+pub const DEFAULT_HTTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Most releases offered to a [`ReleaseChooser`] when a search matches several
+pub const MAX_RELEASE_CANDIDATES: usize = 8;
+
+/// How a run reaches MusicBrainz and the cover art services: not at all with `--offline`,
+/// else through one HTTP client with the run's timeout
+/// Commands that look things up or download take one; clones share the client
+#[derive(Debug, Clone, Default)]
+pub struct Network {
+    offline: bool,
+    timeout: Option<Duration>,
+    client: Arc<OnceLock<reqwest::Client>>,
+}
+
+impl Network {
+    /// Network access with `timeout` (`--http-timeout`) for connects and reads; `None` takes
+    /// `MFUTIL_HTTP_TIMEOUT`, then [`DEFAULT_HTTP_TIMEOUT`]
+    pub fn new(timeout: Option<Duration>) -> Self {
+        Network {
+            timeout,
+            ..Network::default()
+        }
+    }
+
+    /// No network access at all, as `--offline` gives
+    /// Lookups then find no match and downloads are skipped, leaving tags, paths and local art
+    pub fn offline() -> Self {
+        Network {
+            offline: true,
+            ..Network::default()
+        }
+    }
+
+    /// Whether network requests are turned off
+    pub fn is_offline(&self) -> bool {
+        self.offline
+    }
+
+    /// Refuse to hand out a client while offline, so no request can slip through
+    fn ensure_online(&self) -> Result<()> {
+        if self.offline {
+            anyhow::bail!("Network access is disabled (--offline)");
+        }
+        Ok(())
+    }
+
+    /// The HTTP timeout: the one given, then `MFUTIL_HTTP_TIMEOUT`, then the default
+    pub fn timeout(&self) -> Duration {
+        self.timeout.unwrap_or_else(|| {
+            env::var(HTTP_TIMEOUT_ENV)
+                .ok()
+                .and_then(|secs| parse_timeout_secs(&secs))
+                .unwrap_or(DEFAULT_HTTP_TIMEOUT)
+        })
+    }
+
+    /// Create and configure a MusicBrainz client with the standard user agent
+    pub fn musicbrainz_client(&self) -> Result<MusicBrainzClient> {
+        self.ensure_online()?;
+        let mut client = MusicBrainzClient::default();
+        client
+            .set_user_agent(USER_AGENT)
+            .context("Failed to set user agent")?;
+        client.musicbrainz_domain = musicbrainz_domain(&musicbrainz_base());
+        client.coverart_archive_url = cover_art_archive_base();
+        Ok(client)
+    }
+
+    /// HTTP client for cover art and image downloads
+    /// Built on first use with the standard user agent and [`Network::timeout`], so a stalled
+    /// connection fails the request instead of hanging the run
+    pub fn http_client(&self) -> Result<&reqwest::Client> {
+        self.ensure_online()?;
+        if let Some(client) = self.client.get() {
+            return Ok(client);
+        }
+
+        let timeout = self.timeout();
+        let client = reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .connect_timeout(timeout)
+            .read_timeout(timeout)
+            .build()
+            .context("Failed to build HTTP client")?;
+        Ok(self.client.get_or_init(|| client))
+    }
+}
+
+/// The MusicBrainz web service root, from `MFUTIL_MB_BASE` or the public server
+pub fn musicbrainz_base() -> String {
+    base_url(MB_BASE_ENV, DEFAULT_MB_BASE)
+}
+
+/// The server root under which `base` serves its web service, as the client wants it
+fn musicbrainz_domain(base: &str) -> String {
+    base.strip_suffix("/ws/2").unwrap_or(base).to_string()
+}
+
+/// The Cover Art Archive root, from `MFUTIL_CAA_BASE` or the public server
+pub fn cover_art_archive_base() -> String {
+    base_url(CAA_BASE_ENV, DEFAULT_CAA_BASE)
+}
+
+/// A base URL from `var` without its trailing slash, or `default` if it is unset or blank
+pub(crate) fn base_url(var: &str, default: &str) -> String {
+    base_url_from(|var| env::var(var).ok(), var, default)
+}
+
+/// [`base_url`], reading `var` through `lookup` instead of the environment
+fn base_url_from(lookup: impl Fn(&str) -> Option<String>, var: &str, default: &str) -> String {
+    lookup(var)
+        .map(|base| base.trim().trim_end_matches('/').to_string())
+        .filter(|base| !base.is_empty())
+        .unwrap_or_else(|| default.to_string())
+}
+
+/// Parse a timeout given in whole seconds; zero and garbage are rejected
+fn parse_timeout_secs(secs: &str) -> Option<Duration> {
+    secs.trim()
+        .parse::<u64>()
+        .ok()
+        .filter(|&secs| secs > 0)
+        .map(Duration::from_secs)
+}
+
+/// A release offered when a MusicBrainz search matches more than one
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReleaseCandidate {
+    pub id: String,
+    pub artist: String,
+    pub title: String,
+    pub year: Option<String>,
+    pub country: Option<String>,
+    pub track_count: Option<u32>,
+}
+
+impl ReleaseCandidate {
+    /// Read a candidate from a release as MusicBrainz returns it in JSON
+    /// `fallback_artist` stands in for a missing artist credit
+    pub fn from_json(release: &serde_json::Value, fallback_artist: &str) -> Self {
+        let text = |key: &str| {
+            release
+                .get(key)
+                .and_then(|v| v.as_str())
+                .filter(|v| !v.is_empty())
+                .map(str::to_string)
+        };
+        let artist = release
+            .get("artist-credit")
+            .and_then(|credits| credits.as_array())
+            .map(|credits| {
+                credits
+                    .iter()
+                    .filter_map(|c| c.get("name").and_then(|n| n.as_str()))
+                    .collect::<Vec<_>>()
+                    .join(" & ")
+            })
+            .filter(|artist| !artist.is_empty())
+            .unwrap_or_else(|| fallback_artist.to_string());
+        let year = text("date")
+            .and_then(|date| date.get(..4).map(str::to_string))
+            .filter(|year| year.chars().all(|c| c.is_ascii_digit()));
+        // Search results carry a total; a lookup only has it per medium
+        let track_count = release
+            .get("track-count")
+            .and_then(|c| c.as_u64())
+            .or_else(|| {
+                let media = release.get("media")?.as_array()?;
+                media
+                    .iter()
+                    .map(|m| m.get("track-count").and_then(|c| c.as_u64()))
+                    .sum()
+            })
+            .filter(|&c| c > 0)
+            .and_then(|c| u32::try_from(c).ok());
+
+        ReleaseCandidate {
+            id: text("id").unwrap_or_default(),
+            artist,
+            title: text("title").unwrap_or_default(),
+            year,
+            country: text("country"),
+            track_count,
+        }
+    }
+}
+
+impl std::fmt::Display for ReleaseCandidate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} - {}", self.artist, self.title)?;
+        let mut details = Vec::new();
+        details.extend(self.year.clone());
+        details.extend(self.country.clone());
+        if let Some(count) = self.track_count {
+            details.push(format!("{} tracks", count));
+        }
+        if !details.is_empty() {
+            write!(f, " ({})", details.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// What a [`ReleaseChooser`] decided
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReleaseChoice {
+    /// Use the candidate at this index
+    Pick(usize),
+    /// Use none of them, leaving the album untagged
+    Skip,
 }
 
+/// Picks among several releases matching an album, e.g. by asking the user
+pub trait ReleaseChooser: Send + Sync {
+    fn choose(&self, artist: &str, album: &str, candidates: &[ReleaseCandidate]) -> ReleaseChoice;
+}
+
+/// Index of the candidate to use, or None if the chooser skipped the album
+/// A single candidate, or no chooser, means the first one
+fn pick_release(
+    chooser: Option<&dyn ReleaseChooser>,
+    artist: &str,
+    album: &str,
+    candidates: &[ReleaseCandidate],
+) -> Option<usize> {
+    let Some(chooser) = chooser.filter(|_| candidates.len() > 1) else {
+        return Some(0);
+    };
+    match chooser.choose(artist, album, candidates) {
+        ReleaseChoice::Pick(index) if index < candidates.len() => Some(index),
+        ReleaseChoice::Pick(_) | ReleaseChoice::Skip => None,
+    }
+}
+
+/// Words that mark an album title suffix as an edition rather than part of the title
+const EDITION_WORDS: &[&str] = &[
+    "remaster",
+    "deluxe",
+    "edition",
+    "expanded",
+    "anniversary",
+    "bonus",
+    "reissue",
+    "version",
+    "mono",
+    "stereo",
+];
+
 /// Look up release information from MusicBrainz
+/// Falls back to a release-group search when no release matches directly. When several
+/// releases match, `chooser` picks one or skips the album; without one the best-scored
+/// release is used, as in non-interactive runs
 pub async fn lookup_musicbrainz_release(
+    network: &Network,
+    chooser: Option<&dyn ReleaseChooser>,
     artist: &str,
     album: &str,
-    tx: &mpsc::Sender<String>,
+    progress: &dyn ProgressSink,
 ) -> Result<Option<(String, String, String)>> {
-    tx.send(format!(
+    if network.is_offline() {
+        return Ok(None);
+    }
+
+    progress.custom(format!(
         "Looking up MusicBrainz release: {} - {}",
         artist, album
-    ))
-    .context("Failed to send MusicBrainz lookup message")?;
+    ));
 
-    let client = create_musicbrainz_client()?;
+    let client = network.musicbrainz_client()?;
 
     // Search for releases by artist and album
     let query = musicbrainz_rs::entity::release::ReleaseSearchQuery::query_builder()
@@ -36,32 +310,46 @@ pub async fn lookup_musicbrainz_release(
 
     match Release::search(query).execute_with_client(&client).await {
         Ok(search_result) => {
-            if let Some(release) = search_result.entities.into_iter().next() {
-                let artist_credit = release
-                    .artist_credit
-                    .as_ref()
-                    .map(|credits| {
-                        credits
-                            .iter()
-                            .map(|c| c.name.clone())
-                            .collect::<Vec<_>>()
-                            .join(" & ")
-                    })
-                    .unwrap_or_else(|| artist.to_string());
-
-                tx.send(format!(
+            let mut candidates: Vec<ReleaseCandidate> = search_result
+                .entities
+                .iter()
+                .take(MAX_RELEASE_CANDIDATES)
+                .map(|release| {
+                    let json = serde_json::to_value(release).unwrap_or_default();
+                    ReleaseCandidate {
+                        id: release.id.clone(),
+                        title: release.title.clone(),
+                        ..ReleaseCandidate::from_json(&json, artist)
+                    }
+                })
+                .collect();
+            if !candidates.is_empty() {
+                let Some(index) = pick_release(chooser, artist, album, &candidates) else {
+                    progress.custom(format!(
+                        "Skipped choosing a MusicBrainz release for {} - {}",
+                        artist, album
+                    ));
+                    return Ok(None);
+                };
+                let release = candidates.swap_remove(index);
+
+                progress.custom(format!(
                     "Found MusicBrainz release: {} - {} ({})",
-                    artist_credit, release.title, release.id
-                ))
-                .context("Failed to send release found message")?;
+                    release.artist, release.title, release.id
+                ));
 
-                Ok(Some((artist_credit, release.title, release.id)))
+                Ok(Some((release.artist, release.title, release.id)))
             } else {
-                tx.send(format!(
+                match lookup_release_group_fallback(&client, artist, album, progress).await {
+                    Ok(Some(found)) => return Ok(Some(found)),
+                    Ok(None) => {}
+                    Err(e) => warn!("{:?}", e),
+                }
+
+                progress.custom(format!(
                     "No MusicBrainz release found for {} - {}",
                     artist, album
-                ))
-                .context("Failed to send no release message")?;
+                ));
                 Ok(None)
             }
         }
@@ -72,36 +360,406 @@ pub async fn lookup_musicbrainz_release(
     }
 }
 
-/// Enhanced metadata extraction with MusicBrainz lookup
+/// Search release groups by artist/title and pick a representative release from the best match
+async fn lookup_release_group_fallback(
+    client: &MusicBrainzClient,
+    artist: &str,
+    album: &str,
+    progress: &dyn ProgressSink,
+) -> Result<Option<(String, String, String)>> {
+    let title = strip_edition_suffix(album);
+    progress.custom(format!(
+        "Trying MusicBrainz release-group search: {} - {}",
+        artist, title
+    ));
+
+    let query = format!(
+        "releasegroup:\"{}\" AND artist:\"{}\"",
+        escape_query_phrase(title),
+        escape_query_phrase(artist)
+    );
+    let url = format!(
+        "{}/release-group/?query={}&fmt=json",
+        client.api_root(),
+        urlencoding::encode(&query)
+    );
+    let response = ApiRequest::new(url)
+        .get_json(client)
+        .await
+        .map_err(|e| anyhow::anyhow!("MusicBrainz release-group search failed: {:?}", e))?;
+
+    let found = pick_release_from_release_groups(&response, artist);
+    if let Some((artist_credit, title, release_id)) = &found {
+        progress.custom(format!(
+            "Found MusicBrainz release via release group: {} - {} ({})",
+            artist_credit, title, release_id
+        ));
+    }
+
+    Ok(found)
+}
+
+/// What sync uses from a release lookup besides its ID
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReleaseDetails {
+    /// (disc, track) positions of the release's tracks
+    pub tracklist: Vec<(u32, u32)>,
+    /// Sort name of the release's artist credit, e.g. "Beatles, The"
+    pub artist_sort_name: Option<String>,
+    /// Whether the release is a compilation (see [`parse_release_compilation`])
+    pub compilation: bool,
+    /// The release's artist credit, split at any featuring join phrase
+    pub artist_credit: Option<ArtistCredit>,
+    /// Each track's artist credit by (disc, track) position
+    pub track_credits: Vec<((u32, u32), ArtistCredit)>,
+}
+
+impl ReleaseDetails {
+    /// The artist credit of the track at (disc, track) `position`, if the release has one
+    pub fn track_credit(&self, position: (u32, u32)) -> Option<&ArtistCredit> {
+        self.track_credits
+            .iter()
+            .find(|(at, _)| *at == position)
+            .map(|(_, credit)| credit)
+    }
+}
+
+/// Fetch a release's tracklist and artist sort name in one lookup
+pub async fn fetch_release_details(network: &Network, release_id: &str) -> Result<ReleaseDetails> {
+    if network.is_offline() {
+        return Ok(ReleaseDetails::default());
+    }
+    let client = network.musicbrainz_client()?;
+    let url = format!(
+        "{}/release/{}?inc=recordings+artist-credits&fmt=json",
+        client.api_root(),
+        urlencoding::encode(release_id)
+    );
+    let response = ApiRequest::new(url)
+        .get_json(&client)
+        .await
+        .map_err(|e| anyhow::anyhow!("MusicBrainz release lookup failed: {:?}", e))?;
+    Ok(ReleaseDetails {
+        tracklist: parse_release_tracklist(&response),
+        artist_sort_name: parse_artist_sort_name(&response),
+        compilation: parse_release_compilation(&response),
+        artist_credit: featuring::parse_artist_credit(&response),
+        track_credits: parse_release_track_credits(&response),
+    })
+}
+
+/// Join the sort names of a release's credited artists with the credit's join phrases,
+/// as "Simon, Paul & Garfunkel, Art"
+pub fn parse_artist_sort_name(response: &serde_json::Value) -> Option<String> {
+    let credits = response.get("artist-credit")?.as_array()?;
+    let mut sort_name = String::new();
+    for credit in credits {
+        sort_name.push_str(credit.get("artist")?.get("sort-name")?.as_str()?);
+        if let Some(join_phrase) = credit.get("joinphrase").and_then(|j| j.as_str()) {
+            sort_name.push_str(join_phrase);
+        }
+    }
+    Some(sort_name).filter(|name| !name.trim().is_empty())
+}
+
+/// MusicBrainz's ID for the Various Artists special-purpose artist
+const VARIOUS_ARTISTS_ID: &str = "89ad4ac3-39f7-470e-963a-56509c546377";
+
+/// IDs of the artists in a release's or track's artist credit
+fn credited_artist_ids(value: &serde_json::Value) -> Vec<&str> {
+    value
+        .get("artist-credit")
+        .and_then(|c| c.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|credit| credit.get("artist")?.get("id")?.as_str())
+        .collect()
+}
+
+/// Whether a release lookup response is a compilation: credited to Various Artists, or with
+/// tracks by different artists, some of them not in the release's own credit
+/// A guest on one track of an artist's album still credits the artist, so doesn't count
+pub fn parse_release_compilation(response: &serde_json::Value) -> bool {
+    let release_artists = credited_artist_ids(response);
+    if release_artists.contains(&VARIOUS_ARTISTS_ID) {
+        return true;
+    }
+
+    let track_artists: Vec<Vec<&str>> = response
+        .get("media")
+        .and_then(|m| m.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|medium| medium.get("tracks")?.as_array())
+        .flatten()
+        .map(credited_artist_ids)
+        .filter(|ids| !ids.is_empty())
+        .collect();
+    let heterogeneous = track_artists.windows(2).any(|pair| pair[0] != pair[1]);
+    heterogeneous
+        && track_artists
+            .iter()
+            .any(|ids| !ids.iter().any(|id| release_artists.contains(id)))
+}
+
+/// Extract (disc, track) positions from the media of a release lookup response
+pub fn parse_release_tracklist(response: &serde_json::Value) -> Vec<(u32, u32)> {
+    release_tracks(response)
+        .into_iter()
+        .map(|(position, _)| position)
+        .collect()
+}
+
+/// Each track's artist credit in a release lookup response, by (disc, track) position
+pub fn parse_release_track_credits(
+    response: &serde_json::Value,
+) -> Vec<((u32, u32), ArtistCredit)> {
+    release_tracks(response)
+        .into_iter()
+        .filter_map(|(position, track)| Some((position, featuring::parse_artist_credit(track)?)))
+        .collect()
+}
+
+/// A release's tracks with their (disc, track) positions, numbered in order where the
+/// response leaves a position out
+fn release_tracks(response: &serde_json::Value) -> Vec<((u32, u32), &serde_json::Value)> {
+    let Some(media) = response.get("media").and_then(|m| m.as_array()) else {
+        return Vec::new();
+    };
+
+    let position = |value: &serde_json::Value, fallback: usize| {
+        value
+            .get("position")
+            .and_then(|p| p.as_u64())
+            .and_then(|p| u32::try_from(p).ok())
+            .unwrap_or(fallback as u32)
+    };
+    media
+        .iter()
+        .enumerate()
+        .flat_map(|(i, medium)| {
+            let disc = position(medium, i + 1);
+            medium
+                .get("tracks")
+                .and_then(|t| t.as_array())
+                .into_iter()
+                .flatten()
+                .enumerate()
+                .map(move |(j, track)| ((disc, position(track, j + 1)), track))
+        })
+        .collect()
+}
+
+/// Pick a release from the first release group in a release-group search response
+/// Official releases are preferred; the album title returned is the release group's
+pub fn pick_release_from_release_groups(
+    response: &serde_json::Value,
+    fallback_artist: &str,
+) -> Option<(String, String, String)> {
+    let group = response.get("release-groups")?.as_array()?.first()?;
+    let title = group.get("title")?.as_str()?;
+    let releases = group.get("releases")?.as_array()?;
+
+    let release = releases
+        .iter()
+        .find(|r| r.get("status").and_then(|s| s.as_str()) == Some("Official"))
+        .or_else(|| releases.first())?;
+    let release_id = release.get("id")?.as_str()?;
+
+    let artist_credit = group
+        .get("artist-credit")
+        .and_then(|ac| ac.as_array())
+        .map(|credits| {
+            credits
+                .iter()
+                .filter_map(|c| c.get("name").and_then(|n| n.as_str()))
+                .collect::<Vec<_>>()
+                .join(" & ")
+        })
+        .filter(|credit| !credit.is_empty())
+        .unwrap_or_else(|| fallback_artist.to_string());
+
+    Some((artist_credit, title.to_string(), release_id.to_string()))
+}
+
+/// Strip a trailing edition marker such as "(Remastered)", "[Deluxe Edition]"
+/// or " - 2011 Remaster" from an album title
+pub fn strip_edition_suffix(album: &str) -> &str {
+    let trimmed = album.trim();
+    let is_edition = |suffix: &str| {
+        let lower = suffix.to_lowercase();
+        EDITION_WORDS.iter().any(|word| lower.contains(word))
+    };
+
+    let start = match trimmed.chars().last() {
+        Some(')') => trimmed.rfind('('),
+        Some(']') => trimmed.rfind('['),
+        _ => trimmed.rfind(" - "),
+    };
+
+    match start {
+        Some(start) if start > 0 && is_edition(&trimmed[start..]) => trimmed[..start].trim_end(),
+        _ => trimmed,
+    }
+}
+
+/// Minimum [`name_similarity`] for a MusicBrainz name to replace a local folder name
+pub const MB_FOLDER_NAME_MIN_SIMILARITY: f64 = 0.6;
+
+/// Similarity of two names from 0.0 to 1.0 (Levenshtein ratio), ignoring case and punctuation
+pub fn name_similarity(a: &str, b: &str) -> f64 {
+    let simplify = |name: &str| -> Vec<char> {
+        name.to_lowercase()
+            .chars()
+            .filter(|c| c.is_alphanumeric() || c.is_whitespace())
+            .collect::<String>()
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .chars()
+            .collect()
+    };
+    let (a, b) = (simplify(a), simplify(b));
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+
+    1.0 - previous[b.len()] as f64 / longest as f64
+}
+
+/// Pick the artist/album to name a folder after
+/// The MusicBrainz names win only when both are close to the local ones, so a wrong match
+/// (a different album by the same artist, say) never renames a folder
+pub fn reconcile_folder_names(
+    local_artist: &str,
+    local_album: &str,
+    mb_artist: &str,
+    mb_album: &str,
+) -> Option<(String, String)> {
+    let confident = name_similarity(local_artist, mb_artist) >= MB_FOLDER_NAME_MIN_SIMILARITY
+        && name_similarity(local_album, mb_album) >= MB_FOLDER_NAME_MIN_SIMILARITY;
+    confident.then(|| (mb_artist.to_string(), mb_album.to_string()))
+}
+
+/// Rename artist/album groups to their MusicBrainz names where a confident match exists
+/// Names are normalized and sanitized like tag-derived ones, with `naming`; groups that end up
+/// with the same names are merged, and groups under the Unknown placeholders are never looked up
+/// Where several releases match, the best-scored one is used
+pub fn apply_musicbrainz_folder_names(
+    groups: FxHashMap<(String, String), Vec<PathBuf>>,
+    ascii_punctuation: bool,
+    naming: &Naming,
+    network: &Network,
+    quiet: bool,
+) -> Result<FxHashMap<(String, String), Vec<PathBuf>>> {
+    if network.is_offline() {
+        return Ok(groups);
+    }
+
+    let rt = tokio::runtime::Runtime::new()?;
+    let mut renamed: FxHashMap<(String, String), Vec<PathBuf>> = FxHashMap::default();
+
+    for ((artist, album), files) in groups {
+        let mut names = (artist, album);
+        if !metadata::is_unknown_placeholder(&names.0, &names.1) {
+            match rt.block_on(lookup_musicbrainz_release(
+                network, None, &names.0, &names.1, &NoopSink,
+            )) {
+                Ok(Some((mb_artist, mb_album, _))) => {
+                    let mb_artist = metadata::normalize_folder_text(
+                        &mb_artist,
+                        ascii_punctuation,
+                        metadata::UNKNOWN_ARTIST,
+                    );
+                    let mb_album = metadata::normalize_folder_text(
+                        &mb_album,
+                        ascii_punctuation,
+                        metadata::UNKNOWN_ALBUM,
+                    );
+                    let mb_artist = naming.sanitize(&mb_artist);
+                    let mb_album = naming.sanitize(&mb_album);
+                    match reconcile_folder_names(&names.0, &names.1, &mb_artist, &mb_album) {
+                        Some(mb_names) => {
+                            if !quiet && mb_names != names {
+                                info!(
+                                    "Using MusicBrainz names: {} / {} -> {} / {}",
+                                    names.0, names.1, mb_names.0, mb_names.1
+                                );
+                            }
+                            names = mb_names;
+                        }
+                        None => {
+                            if !quiet {
+                                info!(
+                                    "Ignoring MusicBrainz match {} / {} for {} / {} (names too different)",
+                                    mb_artist, mb_album, names.0, names.1
+                                );
+                            }
+                        }
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => warn!(
+                    "MusicBrainz lookup failed for {} / {}: {:?}",
+                    names.0, names.1, e
+                ),
+            }
+        }
+        renamed.entry(names).or_default().extend(files);
+    }
+
+    Ok(renamed)
+}
+
+/// Escape a value for use inside a quoted MusicBrainz search phrase
+fn escape_query_phrase(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Enhanced metadata extraction with MusicBrainz lookup, using the best-scored release
 pub async fn extract_and_enhance_metadata(
+    network: &Network,
     file_path: &Path,
-    tx: &mpsc::Sender<String>,
+    progress: &dyn ProgressSink,
 ) -> Result<(String, String, Option<String>)> {
     // First try to extract from file metadata
-    let (artist, album) = super::metadata::extract_artist_album_from_file(file_path)?;
+    let super::metadata::ArtistAlbum {
+        album_artist: artist,
+        album,
+        ..
+    } = super::metadata::extract_artist_album_from_file(file_path)?;
 
     // If we have basic metadata, try to enhance it with MusicBrainz
     if artist != "Unknown Artist" && album != "Unknown Album" {
-        match lookup_musicbrainz_release(&artist, &album, tx).await {
+        match lookup_musicbrainz_release(network, None, &artist, &album, progress).await {
             Ok(Some((enhanced_artist, enhanced_album, release_id))) => {
-                tx.send(format!(
+                progress.custom(format!(
                     "Enhanced metadata for {}: '{}' -> '{}' / '{}' -> '{}'",
                     file_path.display(),
                     &artist,
                     &enhanced_artist,
                     &album,
                     &enhanced_album
-                ))
-                .context("Failed to send enhancement message")?;
+                ));
                 return Ok((enhanced_artist, enhanced_album, Some(release_id)));
             }
             Ok(None) => {
                 // No enhancement available, use original metadata
-                tx.send(format!(
+                progress.custom(format!(
                     "No MusicBrainz match found for {} - {} (using original metadata)",
                     &artist, &album
-                ))
-                .context("Failed to send no match message")?;
+                ));
             }
             Err(e) => {
                 warn!(
@@ -114,3 +772,304 @@ pub async fn extract_and_enhance_metadata(
 
     Ok((artist.to_string(), album.to_string(), None))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_timeout_secs() {
+        assert_eq!(parse_timeout_secs("45"), Some(Duration::from_secs(45)));
+        assert_eq!(parse_timeout_secs(" 10\n"), Some(Duration::from_secs(10)));
+        assert_eq!(parse_timeout_secs("0"), None);
+        assert_eq!(parse_timeout_secs("soon"), None);
+    }
+
+    #[test]
+    fn test_configured_base_urls_are_used() {
+        let mirror = |var: &str| match var {
+            MB_BASE_ENV => Some("http://mirror.local:5000/ws/2/".to_string()),
+            CAA_BASE_ENV => Some(" http://mirror.local:8080 ".to_string()),
+            crate::lyrics::LRCLIB_BASE_ENV => Some("  ".to_string()),
+            _ => None,
+        };
+        let mb_base = base_url_from(mirror, MB_BASE_ENV, DEFAULT_MB_BASE);
+        assert_eq!(mb_base, "http://mirror.local:5000/ws/2");
+        assert_eq!(musicbrainz_domain(&mb_base), "http://mirror.local:5000");
+        assert_eq!(
+            base_url_from(mirror, CAA_BASE_ENV, DEFAULT_CAA_BASE),
+            "http://mirror.local:8080"
+        );
+
+        // Unset or blank, the public server is used
+        assert_eq!(
+            base_url_from(mirror, crate::lyrics::LRCLIB_BASE_ENV, "https://lrclib.net"),
+            "https://lrclib.net"
+        );
+        assert_eq!(
+            base_url_from(|_| None, MB_BASE_ENV, DEFAULT_MB_BASE),
+            DEFAULT_MB_BASE
+        );
+        assert_eq!(
+            musicbrainz_domain(DEFAULT_MB_BASE),
+            "https://musicbrainz.org"
+        );
+    }
+
+    struct FixedChooser(ReleaseChoice);
+
+    impl ReleaseChooser for FixedChooser {
+        fn choose(&self, _: &str, _: &str, candidates: &[ReleaseCandidate]) -> ReleaseChoice {
+            assert!(
+                candidates.len() > 1,
+                "asked to choose between fewer than two"
+            );
+            self.0
+        }
+    }
+
+    #[test]
+    fn test_release_candidates_and_choice() {
+        let first = ReleaseCandidate::from_json(
+            &json!({
+                "id": "a1",
+                "title": "Rumours",
+                "artist-credit": [{"name": "Fleetwood Mac"}],
+                "date": "1977-02-04",
+                "country": "US",
+                "track-count": 11
+            }),
+            "Fleetwood Mac",
+        );
+        assert_eq!(
+            first.to_string(),
+            "Fleetwood Mac - Rumours (1977, US, 11 tracks)"
+        );
+        let second = ReleaseCandidate::from_json(
+            &json!({
+                "id": "b2",
+                "title": "Rumours",
+                "media": [{"track-count": 11}, {"track-count": 8}]
+            }),
+            "Fleetwood Mac",
+        );
+        assert_eq!(second.to_string(), "Fleetwood Mac - Rumours (19 tracks)");
+
+        let candidates = [first, second];
+        let pick = FixedChooser(ReleaseChoice::Pick(1));
+        let skip = FixedChooser(ReleaseChoice::Skip);
+        let stale = FixedChooser(ReleaseChoice::Pick(5));
+        assert_eq!(pick_release(None, "A", "B", &candidates), Some(0));
+        assert_eq!(pick_release(Some(&pick), "A", "B", &candidates), Some(1));
+        assert_eq!(pick_release(Some(&skip), "A", "B", &candidates), None);
+        assert_eq!(pick_release(Some(&stale), "A", "B", &candidates), None);
+        // A lone match is used without asking
+        assert_eq!(
+            pick_release(Some(&skip), "A", "B", &candidates[..1]),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn test_strip_edition_suffix() {
+        assert_eq!(
+            strip_edition_suffix("Abbey Road (Remastered)"),
+            "Abbey Road"
+        );
+        assert_eq!(
+            strip_edition_suffix("Rumours [Super Deluxe Edition]"),
+            "Rumours"
+        );
+        assert_eq!(
+            strip_edition_suffix("Pet Sounds - 2012 Remaster"),
+            "Pet Sounds"
+        );
+        // Parentheses and dashes that are part of the title are kept
+        assert_eq!(
+            strip_edition_suffix("(What's the Story) Morning Glory?"),
+            "(What's the Story) Morning Glory?"
+        );
+        assert_eq!(strip_edition_suffix("Live - Side A"), "Live - Side A");
+    }
+
+    #[test]
+    fn test_parse_release_tracklist() {
+        let response = json!({
+            "media": [
+                {"position": 1, "tracks": [{"position": 1}, {"position": 2}]},
+                {"position": 2, "tracks": [{"position": 1}, {}]}
+            ]
+        });
+        assert_eq!(
+            parse_release_tracklist(&response),
+            vec![(1, 1), (1, 2), (2, 1), (2, 2)]
+        );
+        assert!(parse_release_tracklist(&json!({})).is_empty());
+    }
+
+    #[test]
+    fn test_parse_release_track_credits_by_position() {
+        let response = json!({
+            "media": [{"position": 1, "tracks": [
+                {"position": 1, "artist-credit": [{"name": "A"}]},
+                {"position": 2, "artist-credit": [
+                    {"name": "A", "joinphrase": " ft. "},
+                    {"name": "B"}
+                ]}
+            ]}]
+        });
+        let details = ReleaseDetails {
+            track_credits: parse_release_track_credits(&response),
+            ..ReleaseDetails::default()
+        };
+        assert_eq!(
+            details
+                .track_credit((1, 2))
+                .map(ArtistCredit::canonical)
+                .as_deref(),
+            Some("A feat. B")
+        );
+        assert_eq!(details.track_credit((1, 1)).unwrap().featured, None);
+        assert_eq!(details.track_credit((2, 1)), None);
+    }
+
+    #[test]
+    fn test_parse_artist_sort_name_joins_credits() {
+        let response = json!({
+            "artist-credit": [
+                {"name": "Simon", "joinphrase": " & ", "artist": {"sort-name": "Simon, Paul"}},
+                {"name": "Garfunkel", "joinphrase": "", "artist": {"sort-name": "Garfunkel, Art"}}
+            ]
+        });
+        assert_eq!(
+            parse_artist_sort_name(&response).as_deref(),
+            Some("Simon, Paul & Garfunkel, Art")
+        );
+        assert_eq!(parse_artist_sort_name(&json!({})), None);
+    }
+
+    #[test]
+    fn test_parse_release_compilation() {
+        let credit = |id: &str| json!([{"name": id, "artist": {"id": id}}]);
+        let release = |artist: serde_json::Value, tracks: Vec<serde_json::Value>| {
+            let tracks: Vec<_> = tracks
+                .into_iter()
+                .map(|artist| json!({"artist-credit": artist}))
+                .collect();
+            json!({"artist-credit": artist, "media": [{"tracks": tracks}]})
+        };
+
+        assert!(parse_release_compilation(&release(
+            credit(VARIOUS_ARTISTS_ID),
+            vec![credit("a"), credit("a")]
+        )));
+        // Tracks by artists the release doesn't credit
+        assert!(parse_release_compilation(&release(
+            credit("label"),
+            vec![credit("a"), credit("b")]
+        )));
+        // One artist's album, with a guest on one track
+        let guest = json!([
+            {"name": "a", "joinphrase": " feat. ", "artist": {"id": "a"}},
+            {"name": "b", "artist": {"id": "b"}}
+        ]);
+        assert!(!parse_release_compilation(&release(
+            credit("a"),
+            vec![credit("a"), guest]
+        )));
+        assert!(!parse_release_compilation(&json!({})));
+    }
+
+    #[test]
+    fn test_name_similarity() {
+        assert_eq!(name_similarity("Abbey Road", "abbey road"), 1.0);
+        assert_eq!(name_similarity("AC/DC", "ACDC"), 1.0);
+        assert_eq!(name_similarity("", ""), 1.0);
+        assert!(name_similarity("Abbey Road 1969", "Abbey Road") > 0.6);
+        assert!(name_similarity("Abbey Road", "Let It Be") < 0.3);
+    }
+
+    #[test]
+    fn test_reconcile_folder_names_similarity_gate() {
+        // Close matches on both names take the canonical MusicBrainz spelling
+        assert_eq!(
+            reconcile_folder_names("Beatles", "Abbey Road 1969", "The Beatles", "Abbey Road"),
+            Some(("The Beatles".to_string(), "Abbey Road".to_string()))
+        );
+        // A different album by the same artist is rejected
+        assert_eq!(
+            reconcile_folder_names("The Beatles", "Abbey Road", "The Beatles", "Let It Be"),
+            None
+        );
+        // So is the right title credited to a different artist
+        assert_eq!(
+            reconcile_folder_names("Weezer", "Blue Album", "Bill Evans", "Blue Album"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_escape_query_phrase() {
+        assert_eq!(
+            escape_query_phrase(r#"The "Best" Of"#),
+            r#"The \"Best\" Of"#
+        );
+    }
+
+    #[test]
+    fn test_pick_release_from_release_groups_prefers_official() {
+        let response = json!({
+            "release-groups": [{
+                "id": "rg-1",
+                "title": "Abbey Road",
+                "artist-credit": [{ "name": "The Beatles" }],
+                "releases": [
+                    { "id": "bootleg-1", "title": "Abbey Road", "status": "Bootleg" },
+                    { "id": "official-1", "title": "Abbey Road", "status": "Official" }
+                ]
+            }]
+        });
+
+        assert_eq!(
+            pick_release_from_release_groups(&response, "Beatles"),
+            Some((
+                "The Beatles".to_string(),
+                "Abbey Road".to_string(),
+                "official-1".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_pick_release_from_release_groups_falls_back_to_first_release() {
+        let response = json!({
+            "release-groups": [{
+                "title": "Demo Tape",
+                "releases": [{ "id": "release-1", "title": "Demo Tape" }]
+            }]
+        });
+
+        assert_eq!(
+            pick_release_from_release_groups(&response, "Local Band"),
+            Some((
+                "Local Band".to_string(),
+                "Demo Tape".to_string(),
+                "release-1".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_pick_release_from_release_groups_without_matches() {
+        assert_eq!(
+            pick_release_from_release_groups(&json!({ "release-groups": [] }), "Anyone"),
+            None
+        );
+        let no_releases = json!({ "release-groups": [{ "title": "Empty", "releases": [] }] });
+        assert_eq!(
+            pick_release_from_release_groups(&no_releases, "Anyone"),
+            None
+        );
+    }
+}
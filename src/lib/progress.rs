@@ -1,4 +1,4 @@
-use std::sync::mpsc;
+use std::sync::{mpsc, Mutex};
 
 /// Progress reporting utilities for consistent TUI messaging
 /// These functions provide standardized progress messages across all commands
@@ -35,6 +35,15 @@ pub enum ProgressMessage {
     },
     /// Album skipped due to no MusicBrainz match
     AlbumSkipped { artist: String, album: String },
+    /// Album skipped because every file already carries a MusicBrainz release ID
+    AlbumAlreadySynced { folder_name: String },
+    /// Local files don't match the matched release's tracklist (informational)
+    TracklistMismatch {
+        album: String,
+        missing: Vec<String>,
+        extra: Vec<String>,
+        unnumbered: usize,
+    },
     /// Final completion message
     FinalComplete { folder_name: String },
     /// Custom message
@@ -100,6 +109,30 @@ impl ProgressMessage {
                     artist, album
                 )
             }
+            ProgressMessage::AlbumAlreadySynced { folder_name } => {
+                format!(
+                    "COMPLETED: Skipped {} (already tagged with a MusicBrainz release ID)",
+                    folder_name
+                )
+            }
+            ProgressMessage::TracklistMismatch {
+                album,
+                missing,
+                extra,
+                unnumbered,
+            } => {
+                let mut parts = Vec::new();
+                if !missing.is_empty() {
+                    parts.push(format!("missing tracks {}", missing.join(", ")));
+                }
+                if !extra.is_empty() {
+                    parts.push(format!("extra tracks {}", extra.join(", ")));
+                }
+                if *unnumbered > 0 {
+                    parts.push(format!("{} files without track numbers", unnumbered));
+                }
+                format!("Album {}: {}", album, parts.join("; "))
+            }
             ProgressMessage::FinalComplete { folder_name } => {
                 format!("Successfully synchronized all files in {}", folder_name)
             }
@@ -108,6 +141,132 @@ impl ProgressMessage {
     }
 }
 
+/// Destination for progress messages from library functions
+/// The CLI uses the mpsc-backed sink feeding the TUI; embedders and tests can supply their own
+pub trait ProgressSink: Send + Sync {
+    /// Deliver a progress message; sinks must not fail the operation that reports progress
+    fn send(&self, msg: ProgressMessage);
+}
+
+/// Forward formatted messages to the TUI channel, ignoring a closed receiver
+impl ProgressSink for mpsc::Sender<String> {
+    fn send(&self, msg: ProgressMessage) {
+        let _ = mpsc::Sender::send(self, msg.format());
+    }
+}
+
+impl dyn ProgressSink + '_ {
+    /// Send a free-form message
+    pub fn custom(&self, message: impl Into<String>) {
+        self.send(ProgressMessage::Custom {
+            message: message.into(),
+        });
+    }
+}
+
+/// Sink for one of several tasks running at once, such as an album synced alongside others
+/// Messages are labelled with the task and passed on as plain text. The task's own totals
+/// are dropped and its completions no longer count, so the progress bar tracks whole tasks,
+/// which the caller completes as each one finishes
+pub struct LabelledSink<'a> {
+    inner: &'a dyn ProgressSink,
+    label: String,
+}
+
+impl<'a> LabelledSink<'a> {
+    pub fn new(inner: &'a dyn ProgressSink, label: impl Into<String>) -> Self {
+        Self {
+            inner,
+            label: label.into(),
+        }
+    }
+}
+
+impl ProgressSink for LabelledSink<'_> {
+    fn send(&self, msg: ProgressMessage) {
+        if let ProgressMessage::TotalFiles { .. } = msg {
+            return;
+        }
+        let text = msg.format();
+        let text = text
+            .strip_prefix("COMPLETED:")
+            .map_or(text.as_str(), str::trim_start);
+        self.inner.send(ProgressMessage::Custom {
+            message: format!("[{}] {}", self.label, text),
+        });
+    }
+}
+
+/// Sink that discards every message
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoopSink;
+
+impl ProgressSink for NoopSink {
+    fn send(&self, _msg: ProgressMessage) {}
+}
+
+/// Sink that keeps every message, for tests and callers that inspect progress afterwards
+#[derive(Debug, Default)]
+pub struct CollectingSink {
+    messages: Mutex<Vec<ProgressMessage>>,
+}
+
+impl CollectingSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Messages received so far, in order
+    pub fn messages(&self) -> Vec<ProgressMessage> {
+        self.messages
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+impl ProgressSink for CollectingSink {
+    fn send(&self, msg: ProgressMessage) {
+        self.messages
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(msg);
+    }
+}
+
+/// Running count of the progress messages the TUI has received
+/// Completions are counted by their `COMPLETED:` prefix alone, so messages sent from parallel
+/// workers can arrive in any order; the latest `TOTAL_FILES:` is the denominator
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ProgressTally {
+    pub total: usize,
+    pub completed: usize,
+}
+
+impl ProgressTally {
+    /// Count one formatted message, returning whether it should be shown (totals aren't)
+    pub fn record(&mut self, msg: &str) -> bool {
+        if let Some(total) = msg.strip_prefix("TOTAL_FILES:") {
+            if let Ok(total) = total.parse() {
+                self.total = total;
+            }
+            return false;
+        }
+        if msg.starts_with("COMPLETED:") {
+            self.completed += 1;
+        }
+        true
+    }
+
+    /// Share of tasks completed, never past 1.0 even if completions outrun a stale total
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        (self.completed as f32 / self.total as f32).min(1.0)
+    }
+}
+
 /// Send a progress message to the TUI channel
 pub fn send_progress_message(
     tx: &mpsc::Sender<String>,
@@ -131,121 +290,156 @@ pub fn send_progress_message_with_context(
 
 /// Convenience functions for common progress messages
 pub fn send_scan_complete(
-    tx: &mpsc::Sender<String>,
+    progress: &dyn ProgressSink,
     files_scanned: usize,
     audio_files_found: usize,
     files_skipped: usize,
-) -> anyhow::Result<()> {
-    send_progress_message(
-        tx,
-        ProgressMessage::ScanComplete {
-            files_scanned,
-            audio_files_found,
-            files_skipped,
-        },
-    )
+) {
+    progress.send(ProgressMessage::ScanComplete {
+        files_scanned,
+        audio_files_found,
+        files_skipped,
+    });
 }
 
-pub fn send_total_files(tx: &mpsc::Sender<String>, count: usize) -> anyhow::Result<()> {
-    send_progress_message(tx, ProgressMessage::TotalFiles { count })
+pub fn send_total_files(progress: &dyn ProgressSink, count: usize) {
+    progress.send(ProgressMessage::TotalFiles { count });
 }
 
 pub fn send_grouping_complete(
-    tx: &mpsc::Sender<String>,
+    progress: &dyn ProgressSink,
     audio_files_count: usize,
     album_groups_count: usize,
-) -> anyhow::Result<()> {
-    send_progress_message(
-        tx,
-        ProgressMessage::GroupingComplete {
-            audio_files_count,
-            album_groups_count,
-        },
-    )
+) {
+    progress.send(ProgressMessage::GroupingComplete {
+        audio_files_count,
+        album_groups_count,
+    });
 }
 
 pub fn send_musicbrainz_search_complete(
-    tx: &mpsc::Sender<String>,
+    progress: &dyn ProgressSink,
     artist: &str,
     album: &str,
     success: bool,
-) -> anyhow::Result<()> {
-    send_progress_message(
-        tx,
-        ProgressMessage::MusicBrainzSearchComplete {
-            artist: artist.to_string(),
-            album: album.to_string(),
-            success,
-        },
-    )
+) {
+    progress.send(ProgressMessage::MusicBrainzSearchComplete {
+        artist: artist.to_string(),
+        album: album.to_string(),
+        success,
+    });
 }
 
-pub fn send_processing_group(
-    tx: &mpsc::Sender<String>,
-    artist: &str,
-    album: &str,
-) -> anyhow::Result<()> {
-    send_progress_message(
-        tx,
-        ProgressMessage::ProcessingGroup {
-            artist: artist.to_string(),
-            album: album.to_string(),
-        },
-    )
+pub fn send_processing_group(progress: &dyn ProgressSink, artist: &str, album: &str) {
+    progress.send(ProgressMessage::ProcessingGroup {
+        artist: artist.to_string(),
+        album: album.to_string(),
+    });
 }
 
 pub fn send_album_processing_complete(
-    tx: &mpsc::Sender<String>,
+    progress: &dyn ProgressSink,
     artist: &str,
     album: &str,
     files_processed: usize,
-) -> anyhow::Result<()> {
-    send_progress_message(
-        tx,
-        ProgressMessage::AlbumProcessingComplete {
-            artist: artist.to_string(),
-            album: album.to_string(),
-            files_processed,
-        },
-    )
+) {
+    progress.send(ProgressMessage::AlbumProcessingComplete {
+        artist: artist.to_string(),
+        album: album.to_string(),
+        files_processed,
+    });
 }
 
-pub fn send_album_skipped(
-    tx: &mpsc::Sender<String>,
-    artist: &str,
+pub fn send_album_skipped(progress: &dyn ProgressSink, artist: &str, album: &str) {
+    progress.send(ProgressMessage::AlbumSkipped {
+        artist: artist.to_string(),
+        album: album.to_string(),
+    });
+}
+
+pub fn send_album_already_synced(progress: &dyn ProgressSink, folder_name: &str) {
+    progress.send(ProgressMessage::AlbumAlreadySynced {
+        folder_name: folder_name.to_string(),
+    });
+}
+
+pub fn send_tracklist_mismatch(
+    progress: &dyn ProgressSink,
     album: &str,
-) -> anyhow::Result<()> {
-    send_progress_message(
-        tx,
-        ProgressMessage::AlbumSkipped {
-            artist: artist.to_string(),
-            album: album.to_string(),
-        },
-    )
+    missing: Vec<String>,
+    extra: Vec<String>,
+    unnumbered: usize,
+) {
+    progress.send(ProgressMessage::TracklistMismatch {
+        album: album.to_string(),
+        missing,
+        extra,
+        unnumbered,
+    });
 }
 
-pub fn send_final_complete(tx: &mpsc::Sender<String>, folder_name: &str) -> anyhow::Result<()> {
-    send_progress_message(
-        tx,
-        ProgressMessage::FinalComplete {
-            folder_name: folder_name.to_string(),
-        },
-    )
+pub fn send_final_complete(progress: &dyn ProgressSink, folder_name: &str) {
+    progress.send(ProgressMessage::FinalComplete {
+        folder_name: folder_name.to_string(),
+    });
 }
 
-pub fn send_custom_message(tx: &mpsc::Sender<String>, message: &str) -> anyhow::Result<()> {
-    send_progress_message(
-        tx,
-        ProgressMessage::Custom {
-            message: message.to_string(),
-        },
-    )
+pub fn send_custom_message(progress: &dyn ProgressSink, message: &str) {
+    progress.custom(message);
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_tally_counts_parallel_completions() {
+        let (tx, rx) = mpsc::channel::<String>();
+        send_total_files(&tx, 4 * 25);
+        std::thread::scope(|scope| {
+            for worker in 0..4 {
+                let tx = tx.clone();
+                scope.spawn(move || {
+                    for file in 0..25 {
+                        send_custom_message(&tx, &format!("Worker {} file {}", worker, file));
+                        send_musicbrainz_search_complete(&tx, "Artist", "Album", file % 2 == 0);
+                    }
+                });
+            }
+        });
+        drop(tx);
+
+        let mut tally = ProgressTally::default();
+        let shown = rx.iter().filter(|msg| tally.record(msg)).count();
+        assert_eq!(shown, 200);
+        assert_eq!(
+            tally,
+            ProgressTally {
+                total: 100,
+                completed: 100
+            }
+        );
+        assert_eq!(tally.fraction(), 1.0);
+    }
+
+    #[test]
+    fn test_labelled_sink_leaves_progress_to_the_caller() {
+        let collected = CollectingSink::new();
+        let sink = LabelledSink::new(&collected, "Album");
+        send_total_files(&sink, 12);
+        send_grouping_complete(&sink, 12, 1);
+        send_custom_message(&sink, "Scanning album folder: Album");
+
+        let messages: Vec<String> = collected.messages().iter().map(|m| m.format()).collect();
+        assert_eq!(
+            messages,
+            [
+                "[Album] Grouped 12 audio files into 1 album groups",
+                "[Album] Scanning album folder: Album",
+            ]
+        );
+    }
+
     #[test]
     fn test_progress_message_formatting() {
         let scan_msg = ProgressMessage::ScanComplete {
@@ -308,6 +502,25 @@ mod tests {
             "COMPLETED: Skipped Test Artist - Test Album (no MusicBrainz match found)"
         );
 
+        let already_synced_msg = ProgressMessage::AlbumAlreadySynced {
+            folder_name: "Test Album".to_string(),
+        };
+        assert_eq!(
+            already_synced_msg.format(),
+            "COMPLETED: Skipped Test Album (already tagged with a MusicBrainz release ID)"
+        );
+
+        let mismatch_msg = ProgressMessage::TracklistMismatch {
+            album: "Test Album".to_string(),
+            missing: vec!["4".to_string(), "7".to_string()],
+            extra: vec!["13".to_string()],
+            unnumbered: 0,
+        };
+        assert_eq!(
+            mismatch_msg.format(),
+            "Album Test Album: missing tracks 4, 7; extra tracks 13"
+        );
+
         let final_msg = ProgressMessage::FinalComplete {
             folder_name: "Test Album".to_string(),
         };
@@ -321,4 +534,34 @@ mod tests {
         };
         assert_eq!(custom_msg.format(), "Custom message");
     }
+
+    #[test]
+    fn test_collecting_sink_keeps_messages_in_order() {
+        let sink = CollectingSink::new();
+        send_total_files(&sink, 3);
+        send_album_skipped(&sink, "Artist", "Album");
+        (&sink as &dyn ProgressSink).custom("done");
+
+        let formatted: Vec<String> = sink.messages().iter().map(|m| m.format()).collect();
+        assert_eq!(
+            formatted,
+            vec![
+                "TOTAL_FILES:3",
+                "COMPLETED: Skipped Artist - Album (no MusicBrainz match found)",
+                "done"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_channel_sink_sends_formatted_messages() {
+        let (tx, rx) = mpsc::channel::<String>();
+        send_total_files(&tx, 7);
+        assert_eq!(rx.try_recv().unwrap(), "TOTAL_FILES:7");
+
+        // A closed receiver must not panic the sender
+        drop(rx);
+        send_final_complete(&tx, "Album");
+        send_final_complete(&NoopSink, "Album");
+    }
 }
@@ -0,0 +1,106 @@
+use crate::{gapless, metadata, utils};
+use anyhow::{Context, Result};
+use lofty::config::WriteOptions;
+use lofty::file::{FileType, TaggedFileExt};
+use lofty::mp4::{Atom, AtomData, AtomIdent};
+use lofty::tag::{ItemKey, ItemValue, TagExt, TagItem};
+use std::borrow::Cow;
+use std::fmt;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// Name the provenance is stored under: a Vorbis comment or APE item of its own, a `TXXX`
+/// frame in ID3v2 (MP3, WAV, AIFF), or a freeform `----:com.apple.iTunes:` atom in MP4
+pub const PROVENANCE_FIELD: &str = "MFUTIL_SOURCE";
+
+/// The operation that brought a file into the library
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    CdRip,
+    Import,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Source::CdRip => "cd-rip",
+            Source::Import => "import",
+        })
+    }
+}
+
+/// The value recorded for `source` at `at`: the operation, then when it ran in UTC
+/// (`cd-rip 2024-03-01T18:30:00Z`)
+pub fn provenance_value(source: Source, at: SystemTime) -> String {
+    format!("{} {}", source, utils::format_utc(at))
+}
+
+/// The operation named by a recorded provenance value, e.g. `cd-rip`
+pub fn provenance_source(value: &str) -> &str {
+    value.split_whitespace().next().unwrap_or_default()
+}
+
+fn mp4_ident() -> AtomIdent<'static> {
+    AtomIdent::Freeform {
+        mean: Cow::Borrowed("com.apple.iTunes"),
+        name: Cow::Borrowed(PROVENANCE_FIELD),
+    }
+}
+
+/// Record in `file_path`'s tags that `source` added it just now, replacing any earlier record
+/// Only the provenance field is written; the file's other tags are left as they are
+pub fn write_provenance(file_path: &Path, source: Source) -> Result<()> {
+    let value = provenance_value(source, SystemTime::now());
+    let failed = || format!("Failed to write provenance to {}", file_path.display());
+
+    match FileType::from_path(file_path) {
+        Some(FileType::Mpeg | FileType::Wav | FileType::Aiff) => {
+            let mut tag = gapless::read_id3v2(file_path).unwrap_or_default();
+            tag.insert_user_text(PROVENANCE_FIELD.to_string(), value);
+            gapless::retry_busy_save(|| tag.save_to_path(file_path, WriteOptions::default()))
+                .with_context(failed)
+        }
+        Some(FileType::Mp4) => {
+            let mut ilst = gapless::read_ilst(file_path).unwrap_or_default();
+            ilst.remove(&mp4_ident()).for_each(drop);
+            ilst.insert(Atom::new(mp4_ident(), AtomData::UTF8(value)));
+            gapless::retry_busy_save(|| ilst.save_to_path(file_path, WriteOptions::default()))
+                .with_context(failed)
+        }
+        _ => {
+            let mut tagged_file = lofty::read_from_path(file_path).with_context(failed)?;
+            metadata::update_file_tags(&mut tagged_file, |tag| {
+                // Keys of our own aren't in lofty's mapping, so `insert_text` would drop them
+                tag.insert_unchecked(TagItem::new(
+                    ItemKey::Unknown(PROVENANCE_FIELD.to_string()),
+                    ItemValue::Text(value.clone()),
+                ));
+            })?;
+            gapless::save_tags(&tagged_file, file_path)
+        }
+    }
+}
+
+/// The provenance recorded in `file_path`'s tags, or None for files mfutil didn't tag
+pub fn read_provenance(file_path: &Path) -> Option<String> {
+    match FileType::from_path(file_path)? {
+        FileType::Mpeg | FileType::Wav | FileType::Aiff => gapless::read_id3v2(file_path)?
+            .get_user_text(PROVENANCE_FIELD)
+            .map(str::to_string),
+        FileType::Mp4 => gapless::read_ilst(file_path)?
+            .get(&mp4_ident())?
+            .data()
+            .find_map(|data| match data {
+                AtomData::UTF8(text) => Some(text.clone()),
+                _ => None,
+            }),
+        _ => {
+            let key = ItemKey::Unknown(PROVENANCE_FIELD.to_string());
+            lofty::read_from_path(file_path)
+                .ok()?
+                .tags()
+                .iter()
+                .find_map(|tag| tag.get_string(&key).map(str::to_string))
+        }
+    }
+}
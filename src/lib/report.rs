@@ -0,0 +1,277 @@
+use crate::commands::albums::DuplicateLink;
+use crate::commands::check::CheckSummary;
+use crate::commands::fix_encoding::FixEncodingSummary;
+use crate::commands::provenance::ProvenanceSummary;
+use crate::commands::restore_tags::RestoreTagsSummary;
+use crate::commands::scan::ScanSummary;
+use crate::commands::sync::UnmatchedAlbum;
+use crate::commands::verify::VerifySummary;
+use crate::directory::{FileFailure, FileMove, OrganizeSummary};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Machine-readable record of a run, written as JSON for `--report-file`
+/// Steps are appended as they finish, so one report can span every stage of `all`
+#[derive(Debug)]
+pub struct RunReport {
+    command: String,
+    started_at: u64,
+    steps: Mutex<Vec<ReportStep>>,
+}
+
+/// One operation within a run, with its counts, per-file actions and errors
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReportStep {
+    pub operation: String,
+    /// Seconds since the Unix epoch
+    pub started_at: u64,
+    /// Seconds since the Unix epoch
+    pub finished_at: u64,
+    pub dry_run: bool,
+    pub succeeded: bool,
+    /// The error that stopped the step, if it failed
+    pub error: Option<String>,
+    pub counts: BTreeMap<&'static str, usize>,
+    /// Planned (dry run) or performed file moves
+    pub actions: Vec<FileMove>,
+    /// Files that could not be processed
+    pub errors: Vec<FileFailure>,
+    /// Files left out up front, e.g. an import's files without usable tags
+    pub excluded: Vec<FileFailure>,
+}
+
+/// Results that can fill in the details of a report step
+pub trait ReportDetails {
+    fn fill_step(&self, step: &mut ReportStep);
+}
+
+impl ReportDetails for () {
+    fn fill_step(&self, _step: &mut ReportStep) {}
+}
+
+impl ReportDetails for OrganizeSummary {
+    fn fill_step(&self, step: &mut ReportStep) {
+        step.counts.insert("moved", self.files_moved);
+        step.counts.insert("skipped", self.files_skipped);
+        step.counts.insert("dirs_created", self.dirs_created);
+        step.counts.insert("excluded", self.excluded.len());
+        step.counts
+            .insert("unknown_placeholders", self.unknown_placeholders);
+        step.counts.insert("failed", self.failures.len());
+        step.actions = self.moves.clone();
+        step.errors = self.failures.clone();
+        step.excluded = self.excluded.clone();
+    }
+}
+
+impl ReportDetails for Vec<FileMove> {
+    fn fill_step(&self, step: &mut ReportStep) {
+        step.counts.insert("renamed", self.len());
+        step.actions = self.clone();
+    }
+}
+
+impl ReportDetails for RestoreTagsSummary {
+    fn fill_step(&self, step: &mut ReportStep) {
+        step.counts.insert("restored", self.restored.len());
+        step.counts.insert("failed", self.failures.len());
+        step.errors = self.failures.clone();
+    }
+}
+
+impl ReportDetails for FixEncodingSummary {
+    fn fill_step(&self, step: &mut ReportStep) {
+        step.counts.insert("fixed", self.fixed.len());
+        step.counts.insert("failed", self.failures.len());
+        step.errors = self.failures.clone();
+    }
+}
+
+impl ReportDetails for Vec<UnmatchedAlbum> {
+    fn fill_step(&self, step: &mut ReportStep) {
+        step.counts.insert("unmatched", self.len());
+    }
+}
+
+impl ReportDetails for Vec<DuplicateLink> {
+    fn fill_step(&self, step: &mut ReportStep) {
+        step.counts.insert("duplicates", self.len());
+    }
+}
+
+impl ReportDetails for CheckSummary {
+    fn fill_step(&self, step: &mut ReportStep) {
+        step.counts.insert("checked", self.checked);
+        step.counts.insert("untagged", self.untagged);
+        step.counts.insert("misfiled", self.mismatches.len());
+    }
+}
+
+impl ReportDetails for ProvenanceSummary {
+    fn fill_step(&self, step: &mut ReportStep) {
+        let count = |source: &str| self.sources.get(source).copied().unwrap_or_default();
+        step.counts.insert("cd_rip", count("cd-rip"));
+        step.counts.insert("import", count("import"));
+        step.counts.insert("external", self.external);
+    }
+}
+
+impl ReportDetails for ScanSummary {
+    fn fill_step(&self, step: &mut ReportStep) {
+        step.counts.insert("albums", self.albums);
+        step.counts.insert("files", self.files);
+        step.counts.insert("unreadable", self.unreadable);
+    }
+}
+
+impl ReportDetails for VerifySummary {
+    fn fill_step(&self, step: &mut ReportStep) {
+        step.counts.insert("manifests", self.manifests);
+        step.counts.insert("verified", self.verified);
+        step.counts.insert("failed", self.failures.len());
+        step.errors = self.failures.clone();
+    }
+}
+
+#[derive(Serialize)]
+struct ReportFile<'a> {
+    command: &'a str,
+    started_at: u64,
+    finished_at: u64,
+    steps: &'a [ReportStep],
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+impl RunReport {
+    pub fn new(command: &str) -> Self {
+        Self {
+            command: command.to_string(),
+            started_at: unix_now(),
+            steps: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Run `operation`, recording its timing and outcome as a step named `name`
+    /// The result is passed through unchanged, so failures still propagate to the caller
+    pub fn run_step<T: ReportDetails>(
+        &self,
+        name: &str,
+        dry_run: bool,
+        operation: impl FnOnce() -> Result<T>,
+    ) -> Result<T> {
+        let mut step = ReportStep {
+            operation: name.to_string(),
+            started_at: unix_now(),
+            dry_run,
+            ..ReportStep::default()
+        };
+        let result = operation();
+        step.finished_at = unix_now();
+        match &result {
+            Ok(details) => {
+                step.succeeded = true;
+                details.fill_step(&mut step);
+            }
+            Err(e) => step.error = Some(format!("{:#}", e)),
+        }
+        self.steps.lock().unwrap().push(step);
+        result
+    }
+
+    /// Steps recorded so far, in the order they finished
+    pub fn steps(&self) -> Vec<ReportStep> {
+        self.steps.lock().unwrap().clone()
+    }
+
+    /// Serialize the report as pretty-printed JSON
+    pub fn to_json(&self) -> Result<String> {
+        let steps = self.steps.lock().unwrap();
+        let file = ReportFile {
+            command: &self.command,
+            started_at: self.started_at,
+            finished_at: unix_now(),
+            steps: &steps,
+        };
+        serde_json::to_string_pretty(&file).context("Failed to serialize run report")
+    }
+
+    /// Write the report to `path`, replacing any previous report there
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let json = self.to_json()?;
+        fs::write(path, json + "\n")
+            .with_context(|| format!("Failed to write run report: {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::import::{import_and_organize_files, ImportOptions};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_report_records_dry_run_import() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let import_dir = temp_dir.path().join("import");
+        let album_dir = import_dir.join("Some Artist").join("Some Album");
+        fs::create_dir_all(&album_dir)?;
+        fs::write(album_dir.join("01 - Track.mp3"), b"not really audio")?;
+        let library = temp_dir.path().join("library");
+        fs::create_dir(&library)?;
+
+        let report = RunReport::new("import");
+        report.run_step("import", true, || {
+            import_and_organize_files(
+                &[import_dir.to_str().unwrap()],
+                library.to_str().unwrap(),
+                &ImportOptions {
+                    dry_run: true,
+                    quiet: true,
+                    ..Default::default()
+                },
+            )
+        })?;
+        let _ = report.run_step("sync", false, || -> Result<()> { anyhow::bail!("offline") });
+
+        let report_path = temp_dir.path().join("report.json");
+        report.write(&report_path)?;
+        let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(&report_path)?)?;
+
+        assert_eq!(json["command"], "import");
+        let steps = json["steps"].as_array().unwrap();
+        assert_eq!(steps.len(), 2);
+
+        let import = &steps[0];
+        assert_eq!(import["operation"], "import");
+        assert_eq!(import["dry_run"], true);
+        assert_eq!(import["succeeded"], true);
+        assert_eq!(import["counts"]["moved"], 0);
+        let actions = import["actions"].as_array().unwrap();
+        assert_eq!(actions.len(), 1);
+        assert!(actions[0]["from"]
+            .as_str()
+            .unwrap()
+            .ends_with("01 - Track.mp3"));
+        assert!(actions[0]["to"]
+            .as_str()
+            .unwrap()
+            .starts_with(library.to_str().unwrap()));
+        assert!(!library.join("Artists").exists());
+
+        assert_eq!(steps[1]["succeeded"], false);
+        assert_eq!(steps[1]["error"], "offline");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,241 @@
+use crate::gapless;
+use anyhow::{Context, Result};
+use lofty::{
+    self,
+    config::WriteOptions,
+    file::TaggedFileExt,
+    tag::{ItemKey, ItemValue, Tag, TagExt, TagItem, TagType},
+};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directory next to the audio files that holds their original tags, one JSON file per track
+pub const BACKUP_DIR: &str = ".mfutil-backup";
+
+/// A file's tags as they were before mfutil first wrote to them
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct TagBackup {
+    tags: Vec<BackedUpTag>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BackedUpTag {
+    tag_type: String,
+    items: Vec<BackedUpItem>,
+}
+
+/// One tag item, keyed by its name in the tag's own format (e.g. `TPE1` for ID3v2)
+#[derive(Debug, Serialize, Deserialize)]
+struct BackedUpItem {
+    key: String,
+    value: BackedUpValue,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum BackedUpValue {
+    Text(String),
+    Locator(String),
+    Binary(Vec<u8>),
+}
+
+const TAG_TYPES: &[(TagType, &str)] = &[
+    (TagType::Ape, "ape"),
+    (TagType::Id3v1, "id3v1"),
+    (TagType::Id3v2, "id3v2"),
+    (TagType::Mp4Ilst, "mp4ilst"),
+    (TagType::VorbisComments, "vorbiscomments"),
+    (TagType::RiffInfo, "riffinfo"),
+    (TagType::AiffText, "aifftext"),
+];
+
+fn tag_type_name(tag_type: TagType) -> Option<&'static str> {
+    TAG_TYPES
+        .iter()
+        .find(|(t, _)| *t == tag_type)
+        .map(|(_, name)| *name)
+}
+
+fn tag_type_from_name(name: &str) -> Option<TagType> {
+    TAG_TYPES.iter().find(|(_, n)| *n == name).map(|(t, _)| *t)
+}
+
+/// Where the backup of `file_path`'s tags is kept
+pub fn backup_path(file_path: &Path) -> Option<PathBuf> {
+    let file_name = file_path.file_name()?.to_str()?;
+    Some(
+        file_path
+            .parent()?
+            .join(BACKUP_DIR)
+            .join(format!("{}.json", file_name)),
+    )
+}
+
+/// The audio file a backup in a [`BACKUP_DIR`] belongs to
+pub fn backed_up_file(backup: &Path) -> Option<PathBuf> {
+    let file_name = backup.file_name()?.to_str()?.strip_suffix(".json")?;
+    Some(backup.parent()?.parent()?.join(file_name))
+}
+
+/// Save `file_path`'s current tags to its backup, returning false if it already has one
+/// An existing backup is never overwritten, so it keeps the tags from before the first write
+/// Call this before writing tags when `--backup-tags` is on, and skip the write if it fails
+pub fn backup_file_tags(file_path: &Path) -> Result<bool> {
+    let backup = backup_path(file_path)
+        .with_context(|| format!("Cannot back up tags of {}", file_path.display()))?;
+    if backup.exists() {
+        return Ok(false);
+    }
+
+    let tagged_file = lofty::read_from_path(file_path)
+        .with_context(|| format!("Failed to read tags to back up: {}", file_path.display()))?;
+    let tags = tagged_file
+        .tags()
+        .iter()
+        .filter_map(|tag| {
+            let tag_type = tag.tag_type();
+            let items = tag
+                .items()
+                .filter_map(|item| {
+                    let key = item.key().map_key(tag_type, true)?.to_string();
+                    let value = match item.value() {
+                        ItemValue::Text(text) => BackedUpValue::Text(text.clone()),
+                        ItemValue::Locator(url) => BackedUpValue::Locator(url.clone()),
+                        ItemValue::Binary(data) => BackedUpValue::Binary(data.clone()),
+                    };
+                    Some(BackedUpItem { key, value })
+                })
+                .collect();
+            Some(BackedUpTag {
+                tag_type: tag_type_name(tag_type)?.to_string(),
+                items,
+            })
+        })
+        .collect();
+
+    let backup_dir = backup.parent().context("Backup path has no parent")?;
+    fs::create_dir_all(backup_dir)
+        .with_context(|| format!("Failed to create {}", backup_dir.display()))?;
+    fs::write(&backup, serde_json::to_vec_pretty(&TagBackup { tags })?)
+        .with_context(|| format!("Failed to write tag backup: {}", backup.display()))?;
+    Ok(true)
+}
+
+/// Put back the tags saved in `file_path`'s backup, then remove the backup
+/// Tag types the file gained since the backup are removed; embedded pictures and gapless
+/// playback frames are left as they are. Returns false if the file has no backup.
+pub fn restore_file_tags(file_path: &Path) -> Result<bool> {
+    let backup = backup_path(file_path)
+        .with_context(|| format!("Cannot restore tags of {}", file_path.display()))?;
+    if !backup.exists() {
+        return Ok(false);
+    }
+    let saved: TagBackup = serde_json::from_slice(&fs::read(&backup)?)
+        .with_context(|| format!("Invalid tag backup: {}", backup.display()))?;
+    let saved_tags: Vec<(TagType, &[BackedUpItem])> = saved
+        .tags
+        .iter()
+        .filter_map(|tag| Some((tag_type_from_name(&tag.tag_type)?, tag.items.as_slice())))
+        .collect();
+
+    let gapless = gapless::read_gapless_tags(file_path);
+    let tagged_file = lofty::read_from_path(file_path)
+        .with_context(|| format!("Failed to read {}", file_path.display()))?;
+    for tag in tagged_file.tags() {
+        if !saved_tags.iter().any(|(t, _)| *t == tag.tag_type()) {
+            tag.remove_from_path(file_path)
+                .with_context(|| format!("Failed to remove tag from {}", file_path.display()))?;
+        }
+    }
+
+    for (tag_type, items) in saved_tags {
+        let mut tag = Tag::new(tag_type);
+        for item in items {
+            let value = match &item.value {
+                BackedUpValue::Text(text) => ItemValue::Text(text.clone()),
+                BackedUpValue::Locator(url) => ItemValue::Locator(url.clone()),
+                BackedUpValue::Binary(data) => ItemValue::Binary(data.clone()),
+            };
+            tag.push_unchecked(TagItem::new(ItemKey::from_key(tag_type, &item.key), value));
+        }
+        if let Some(current) = tagged_file.tag(tag_type) {
+            for picture in current.pictures() {
+                tag.push_picture(picture.clone());
+            }
+        }
+        gapless::retry_busy_save(|| tag.save_to_path(file_path, WriteOptions::default()))
+            .with_context(|| format!("Failed to write tags to {}", file_path.display()))?;
+    }
+    gapless::restore_gapless_tags(file_path, &gapless)?;
+
+    fs::remove_file(&backup)?;
+    if let Some(backup_dir) = backup.parent() {
+        // Only succeeds once the last backup in the folder is gone
+        let _ = fs::remove_dir(backup_dir);
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::write_silent_wav;
+    use lofty::file::AudioFile;
+    use tempfile::TempDir;
+
+    fn tag_text(path: &Path, tag_type: TagType, key: &ItemKey) -> Option<String> {
+        let tagged_file = lofty::read_from_path(path).ok()?;
+        tagged_file
+            .tag(tag_type)?
+            .get_string(key)
+            .map(str::to_string)
+    }
+
+    #[test]
+    fn test_backup_and_restore_round_trip() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let track = temp_dir.path().join("01 Song.wav");
+        write_silent_wav(&track)?;
+        let mut tagged_file = lofty::read_from_path(&track)?;
+        let mut tag = Tag::new(TagType::Id3v2);
+        tag.insert_text(ItemKey::TrackArtist, "Curated Artist".to_string());
+        tag.insert_text(ItemKey::AlbumTitle, "Curated Album".to_string());
+        tag.insert_text(ItemKey::Comment, "hand-tagged".to_string());
+        tagged_file.insert_tag(tag);
+        tagged_file.save_to_path(&track, WriteOptions::default())?;
+
+        assert!(backup_file_tags(&track)?);
+        let backup = temp_dir.path().join(BACKUP_DIR).join("01 Song.wav.json");
+        assert!(backup.exists());
+        assert_eq!(backed_up_file(&backup), Some(track.clone()));
+
+        // A bad match overwrites the names and adds a RIFF INFO tag
+        let mut tagged_file = lofty::read_from_path(&track)?;
+        let tag = tagged_file.tag_mut(TagType::Id3v2).unwrap();
+        tag.insert_text(ItemKey::TrackArtist, "Wrong Artist".to_string());
+        tag.insert_text(ItemKey::Comment, "overwritten".to_string());
+        let mut info = Tag::new(TagType::RiffInfo);
+        info.insert_text(ItemKey::AlbumTitle, "Wrong Album".to_string());
+        tagged_file.insert_tag(info);
+        tagged_file.save_to_path(&track, WriteOptions::default())?;
+
+        // A second backup keeps the original tags rather than the bad ones
+        assert!(!backup_file_tags(&track)?);
+
+        assert!(restore_file_tags(&track)?);
+        let artist = tag_text(&track, TagType::Id3v2, &ItemKey::TrackArtist);
+        assert_eq!(artist.as_deref(), Some("Curated Artist"));
+        let comment = tag_text(&track, TagType::Id3v2, &ItemKey::Comment);
+        assert_eq!(comment.as_deref(), Some("hand-tagged"));
+        assert!(lofty::read_from_path(&track)?
+            .tag(TagType::RiffInfo)
+            .is_none());
+
+        // The backup is used up, and its folder removed with it
+        assert!(!temp_dir.path().join(BACKUP_DIR).exists());
+        assert!(!restore_file_tags(&track)?);
+
+        Ok(())
+    }
+}
@@ -1,28 +1,41 @@
+use crate::progress::ProgressSink;
 use anyhow::Result;
 use musicbrainz_rs::entity::release::Release;
 use std::path::Path;
-use std::sync::mpsc;
 
-/// Update MusicBrainz release ID on a music file
+/// Update MusicBrainz release ID on a music file, filling in missing artist sort tags
+/// (from `artist_sort`, the release's sort name, when MusicBrainz had one) and flagging
+/// tracks of a `compilation`; derived sort tags move `sort_articles`
 pub fn update_musicbrainz_release_id(
     file_path: &Path,
     release_id: &str,
-    tx: &mpsc::Sender<String>,
+    artist_sort: Option<&str>,
+    compilation: bool,
+    sort_articles: Option<&[String]>,
+    progress: &dyn ProgressSink,
 ) -> Result<()> {
     // Use the library function to set enhanced metadata
-    match super::metadata::set_enhanced_metadata(file_path, "", "", release_id) {
+    match super::metadata::set_enhanced_metadata(
+        file_path,
+        "",
+        "",
+        release_id,
+        artist_sort,
+        compilation,
+        sort_articles,
+    ) {
         Ok(_) => {
-            tx.send(format!(
+            progress.custom(format!(
                 "COMPLETED: {} - MusicBrainz ID updated",
                 file_path.display()
-            ))?;
+            ));
         }
         Err(e) => {
-            tx.send(format!(
+            progress.custom(format!(
                 "COMPLETED: {} - Failed to save MusicBrainz ID: {}",
                 file_path.display(),
                 e
-            ))?;
+            ));
         }
     }
 
@@ -36,7 +49,7 @@ pub fn extract_artist_album_from_path_with_fallback(
     folder_album: &str,
 ) -> (String, String) {
     match super::metadata::extract_artist_album_from_file(file_path) {
-        Ok((artist, album)) => (artist, album),
+        Ok(names) => (names.album_artist, names.album),
         Err(_) => (folder_artist.to_string(), folder_album.to_string()),
     }
 }
@@ -45,8 +58,11 @@ pub fn extract_artist_album_from_path_with_fallback(
 pub fn process_music_file_with_musicbrainz(
     file_path: &Path,
     release_id: &str,
+    artist_sort: Option<&str>,
+    compilation: bool,
+    sort_articles: Option<&[String]>,
     _relative_path: &str,
-    tx: &mpsc::Sender<String>,
+    progress: &dyn ProgressSink,
 ) -> Result<()> {
     // Create a minimal Release instance for compatibility
     let _dummy_release = Release {
@@ -76,5 +92,12 @@ pub fn process_music_file_with_musicbrainz(
         release_events: None,
     };
 
-    update_musicbrainz_release_id(file_path, release_id, tx)
+    update_musicbrainz_release_id(
+        file_path,
+        release_id,
+        artist_sort,
+        compilation,
+        sort_articles,
+        progress,
+    )
 }
@@ -0,0 +1,42 @@
+//! Fixtures shared by the unit tests
+
+use anyhow::Result;
+use lofty::config::WriteOptions;
+use lofty::file::{AudioFile, TaggedFileExt};
+use lofty::tag::{ItemKey, Tag};
+use std::path::Path;
+
+/// Write a short silent PCM WAV with no tags, small enough to tag and move around freely
+pub(crate) fn write_silent_wav(path: &Path) -> Result<()> {
+    let samples = [0u8; 400];
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + samples.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    bytes.extend_from_slice(&1u16.to_le_bytes()); // mono
+    bytes.extend_from_slice(&44100u32.to_le_bytes());
+    bytes.extend_from_slice(&88200u32.to_le_bytes());
+    bytes.extend_from_slice(&2u16.to_le_bytes());
+    bytes.extend_from_slice(&16u16.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&(samples.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&samples);
+    std::fs::write(path, bytes)?;
+    Ok(())
+}
+
+/// Write a silent WAV (see [`write_silent_wav`]) carrying the given tags
+pub(crate) fn write_tagged_wav(path: &Path, tags: &[(ItemKey, &str)]) -> Result<()> {
+    write_silent_wav(path)?;
+
+    let mut tagged_file = lofty::read_from_path(path)?;
+    let mut tag = Tag::new(tagged_file.primary_tag_type());
+    for (key, value) in tags {
+        tag.insert_text(key.clone(), value.to_string());
+    }
+    tagged_file.insert_tag(tag);
+    tagged_file.save_to_path(path, WriteOptions::default())?;
+    Ok(())
+}
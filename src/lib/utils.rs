@@ -1,90 +1,439 @@
-use crate::audio;
-use anyhow::Result;
+use crate::aliases::Aliases;
+use crate::commands::rename_files::FileTemplate;
+use crate::metadata::TrackFields;
+use crate::{audio, metadata};
+use anyhow::{Context, Result};
+use std::fmt;
+use std::fs;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use walkdir::WalkDir;
 use shellexpand;
 
+/// Environment variable the CLI reads [`Naming::sanitize_replacement`] from
+pub const SANITIZE_REPLACEMENT_ENV: &str = "MFUTIL_SANITIZE_REPLACEMENT";
+
+/// Replacement for unsafe characters unless `--sanitize-replacement` or the environment sets one
+pub const DEFAULT_SANITIZE_REPLACEMENT: &str = "_";
+
+/// Marker files that keep an album folder out of sync, art and symlink runs when placed in it
+pub const SKIP_MARKERS: &[&str] = &[".mfutil-skip", ".nomedia"];
+
+/// Whether `dir` holds one of the [`SKIP_MARKERS`]
+pub fn has_skip_marker(dir: &Path) -> bool {
+    SKIP_MARKERS.iter().any(|marker| dir.join(marker).exists())
+}
+
 pub fn get_default_music_dir() -> String {
-    std::env::var("XDG_MUSIC_DIR").unwrap_or_else(|_| "~/Music".to_string())
+    std::env::var("XDG_MUSIC_DIR").unwrap_or_else(|_| shellexpand::tilde("~/Music").into_owned())
 }
 
-/// Sanitize filename to be safe for filesystem
-pub fn sanitize_filename(name: &str) -> String {
-    // Replace problematic characters with safe alternatives
-    name.chars()
-        .map(|c| match c {
-            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' | '\'' => '_',
-            c if c.is_control() => '_',
-            c => c,
-        })
-        .collect::<String>()
-        .trim()
-        .to_string()
+/// Whether `c` is left out of file and folder names
+fn is_unsafe_filename_char(c: char) -> bool {
+    matches!(
+        c,
+        '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' | '\''
+    ) || c.is_control()
 }
 
-/// Get all album paths from the music directory
-pub fn get_all_album_paths(music_dir: &str) -> Result<Vec<PathBuf>> {
-    let expanded_music_dir = shellexpand::tilde(music_dir).into_owned();
-    let music_path = Path::new(&expanded_music_dir);
-    let artists_path = music_path.join("Artists");
+/// How names read from tags become folder and file names
+/// Commands that file or name tracks take one, so a whole run names things the same way;
+/// the default is what the CLI does without naming options
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Naming {
+    /// Put in place of characters names can't hold, as `--sanitize-replacement` does; empty
+    /// deletes them. A replacement that is itself unsafe would defeat the point, so it is
+    /// ignored for [`DEFAULT_SANITIZE_REPLACEMENT`]
+    pub sanitize_replacement: String,
+    /// Folder layout to file albums under, as `--group-by` sets it; `None` keeps the one the
+    /// library was organized with
+    pub group_by: Option<GroupBy>,
+    /// Lowercase file extensions as files are moved or copied in (`Track.MP3` ->
+    /// `Track.mp3`), as `--normalize-extensions` does
+    pub normalize_extensions: bool,
+    /// File tracks that name a composer under the composer instead of the performer, as
+    /// `--classical` does
+    pub classical: bool,
+    /// Give box sets a subfolder per disc subtitle when organizing, as `--split-boxsets` does
+    pub split_boxsets: bool,
+    /// Canonical artist and album names to file and link under, as `--aliases` loads them
+    pub aliases: Option<Arc<Aliases>>,
+}
+
+impl Default for Naming {
+    fn default() -> Self {
+        Naming {
+            sanitize_replacement: DEFAULT_SANITIZE_REPLACEMENT.to_string(),
+            group_by: None,
+            normalize_extensions: false,
+            classical: false,
+            split_boxsets: false,
+            aliases: None,
+        }
+    }
+}
+
+impl Naming {
+    /// Make `name` safe for a folder or file name (see [`sanitize_filename_with`])
+    pub fn sanitize(&self, name: &str) -> String {
+        let replacement = self.sanitize_replacement.as_str();
+        let replacement = if replacement.chars().any(is_unsafe_filename_char) {
+            DEFAULT_SANITIZE_REPLACEMENT
+        } else {
+            replacement
+        };
+        sanitize_filename_with(name, replacement)
+    }
+
+    /// The layout albums of the library at `music_dir` are filed under: `group_by` if set,
+    /// else the library's own (see [`group_by`])
+    pub fn layout(&self, music_dir: &Path) -> GroupBy {
+        self.group_by.unwrap_or_else(|| group_by(music_dir))
+    }
+
+    /// The canonical name for `artist` under the run's aliases
+    pub fn canonical_artist(&self, artist: &str) -> String {
+        self.aliases
+            .as_ref()
+            .map_or(artist, |aliases| aliases.artist(artist))
+            .to_string()
+    }
 
-    if !artists_path.exists() {
-        return Ok(Vec::new());
+    /// The canonical name for `album` under the run's aliases
+    pub fn canonical_album(&self, album: &str) -> String {
+        self.aliases
+            .as_ref()
+            .map_or(album, |aliases| aliases.album(album))
+            .to_string()
     }
+}
 
-    let mut album_paths = Vec::new();
+/// Sanitize filename to be safe for filesystem, replacing unsafe characters with
+/// [`DEFAULT_SANITIZE_REPLACEMENT`]; [`Naming::sanitize`] uses the run's replacement
+pub fn sanitize_filename(name: &str) -> String {
+    sanitize_filename_with(name, DEFAULT_SANITIZE_REPLACEMENT)
+}
 
-    for artist_entry in WalkDir::new(&artists_path)
+/// Sanitize filename with `replacement` in place of each unsafe character (slashes, colons,
+/// quotes, control characters and the like); an empty `replacement` deletes them
+/// A name made only of unsafe characters ("???", "/") would come out empty and fold its
+/// folder into the parent, so it gets [`DEFAULT_SANITIZE_REPLACEMENT`] instead
+pub fn sanitize_filename_with(name: &str, replacement: &str) -> String {
+    let replace = |replacement: &str| {
+        let mut sanitized = String::with_capacity(name.len());
+        for c in name.chars() {
+            if is_unsafe_filename_char(c) {
+                sanitized.push_str(replacement);
+            } else {
+                sanitized.push(c);
+            }
+        }
+        sanitized.trim().to_string()
+    };
+    let sanitized = replace(replacement);
+    if sanitized.is_empty() && !name.trim().is_empty() {
+        replace(DEFAULT_SANITIZE_REPLACEMENT)
+    } else {
+        sanitized
+    }
+}
+
+/// Whether a directory name marks one disc of a multi-disc album, e.g. "CD1", "Disc 2" or "disk_3"
+/// Disc folders are part of their parent album, never albums of their own
+pub fn is_disc_folder_name(name: &str) -> bool {
+    let lower = name.trim().to_lowercase();
+    let Some(number) = ["disc", "disk", "cd"]
+        .iter()
+        .find_map(|prefix| lower.strip_prefix(prefix))
+    else {
+        return false;
+    };
+    let number = number.trim_start_matches([' ', '_', '-']);
+    !number.is_empty() && number.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Directories holding an album's tracks: the album folder itself followed by any disc folders
+pub fn album_content_dirs(album_path: &Path) -> Vec<PathBuf> {
+    let mut disc_dirs: Vec<PathBuf> = WalkDir::new(album_path)
         .min_depth(1)
         .max_depth(1)
         .into_iter()
         .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_dir() && e.file_name().to_str().is_some_and(is_disc_folder_name))
+        .map(|e| e.path().to_path_buf())
+        .collect();
+    disc_dirs.sort();
+
+    let mut dirs = vec![album_path.to_path_buf()];
+    dirs.extend(disc_dirs);
+    dirs
+}
+
+/// How album folders are laid out under `Artists/`, chosen with `--group-by`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GroupBy {
+    /// `<artist>/<album>`
+    #[default]
+    Artist,
+    /// `<artist>/<year> - <album>`, or `<artist>/<album>` for albums with no year tag
+    ArtistYear,
+    /// `<artist> - <album>`, one folder per album with no artist level
+    Flat,
+}
+
+/// Where the scheme a library was organized with is recorded, inside its `Artists/`
+pub const GROUP_BY_FILE: &str = ".mfutil-group-by";
+
+impl GroupBy {
+    /// File templates (see [`FileTemplate`]) for each folder level from `Artists/` down
+    fn folder_templates(self) -> &'static [&'static str] {
+        match self {
+            GroupBy::Artist => &["{albumartist}", "{album}"],
+            GroupBy::ArtistYear => &["{albumartist}", "{year} - {album}"],
+            GroupBy::Flat => &["{albumartist} - {album}"],
+        }
+    }
+
+    /// How many folder levels below `Artists/` album folders sit
+    pub fn depth(self) -> usize {
+        self.folder_templates().len()
+    }
+
+    /// The album folder for already sanitized `artist` and `album` folder names
+    /// The year, for schemes that use one, is read from the first of `tracks` that has it
+    pub fn album_dir<I>(self, artists_path: &Path, artist: &str, album: &str, tracks: I) -> PathBuf
+    where
+        I: IntoIterator,
+        I::Item: AsRef<Path>,
     {
-        if artist_entry.path().is_dir() {
-            for album_entry in WalkDir::new(artist_entry.path())
-                .min_depth(1)
-                .max_depth(1)
-                .into_iter()
-                .filter_map(|e| e.ok())
-            {
-                if album_entry.path().is_dir() {
-                    album_paths.push(album_entry.path().to_path_buf());
-                }
+        let year = (self == GroupBy::ArtistYear)
+            .then(|| {
+                tracks
+                    .into_iter()
+                    .find_map(|track| metadata::read_year(track.as_ref()))
+            })
+            .flatten();
+        let fields = TrackFields {
+            album_artist: Some(artist.to_string()),
+            album: Some(album.to_string()),
+            year,
+            ..TrackFields::default()
+        };
+        let folders: Option<Vec<String>> = self
+            .folder_templates()
+            .iter()
+            // The names are sanitized already, so the default replacement is never used
+            .map(|template| {
+                template
+                    .parse::<FileTemplate>()
+                    .ok()?
+                    .render(&fields, &Naming::default())
+            })
+            .collect();
+
+        match folders {
+            Some(folders) => folders
+                .iter()
+                .fold(artists_path.to_path_buf(), |dir, folder| dir.join(folder)),
+            // No year to put in the name, or nothing usable left after sanitizing
+            None if self != GroupBy::Artist => {
+                GroupBy::Artist.album_dir(artists_path, artist, album, [] as [&Path; 0])
             }
+            None => artists_path.join(artist).join(album),
         }
     }
 
-    Ok(album_paths)
+    /// Artist and album names read back from an album folder this scheme laid out
+    /// The year prefix is dropped, and a flat folder is split at its last " - ", since
+    /// artist names carry the separator more often than the album titles filed under them
+    pub fn album_names(self, album_path: &Path) -> (String, String) {
+        let folder_name = |path: Option<&Path>| {
+            path.and_then(Path::file_name)
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        };
+        let album_folder = folder_name(Some(album_path));
+        match self {
+            GroupBy::Artist => (folder_name(album_path.parent()), album_folder),
+            GroupBy::ArtistYear => {
+                let album = match album_folder.split_once(" - ") {
+                    Some((year, album))
+                        if year.len() == 4 && year.bytes().all(|b| b.is_ascii_digit()) =>
+                    {
+                        album.to_string()
+                    }
+                    _ => album_folder,
+                };
+                (folder_name(album_path.parent()), album)
+            }
+            GroupBy::Flat => match album_folder.rsplit_once(" - ") {
+                Some((artist, album)) => (artist.to_string(), album.to_string()),
+                None => (String::new(), album_folder),
+            },
+        }
+    }
 }
 
-/// Get all track paths from the music directory
-pub fn get_all_track_paths(music_dir: &str) -> Result<Vec<PathBuf>> {
-    let mut track_paths = Vec::new();
+impl fmt::Display for GroupBy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            GroupBy::Artist => "artist",
+            GroupBy::ArtistYear => "artist-year",
+            GroupBy::Flat => "flat",
+        })
+    }
+}
 
-    let album_paths = get_all_album_paths(music_dir)?;
+impl FromStr for GroupBy {
+    type Err = String;
 
-    for album_path in album_paths {
-        for entry in WalkDir::new(&album_path).into_iter().filter_map(|e| e.ok()) {
-            if entry.path().is_file() && audio::is_audio_file(entry.path()) {
-                track_paths.push(entry.path().to_path_buf());
-            }
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "artist" => Ok(GroupBy::Artist),
+            "artist-year" => Ok(GroupBy::ArtistYear),
+            "flat" => Ok(GroupBy::Flat),
+            other => Err(format!(
+                "unknown grouping '{}' (expected artist, artist-year or flat)",
+                other
+            )),
         }
     }
+}
 
-    Ok(track_paths)
+/// The scheme for the library at `music_dir`: the one it was last organized with (see
+/// [`record_group_by`]), else [`GroupBy::Artist`]
+/// Commands that file albums use [`Naming::layout`], which a `--group-by` choice overrides
+pub fn group_by(music_dir: &Path) -> GroupBy {
+    recorded_group_by(music_dir).unwrap_or_default()
 }
 
-/// Get all folder paths from the music directory
-pub fn get_all_folder_paths(music_dir: &str) -> Result<Vec<PathBuf>> {
-    let mut folder_paths = Vec::new();
+fn recorded_group_by(music_dir: &Path) -> Option<GroupBy> {
+    fs::read_to_string(music_dir.join("Artists").join(GROUP_BY_FILE))
+        .ok()?
+        .parse()
+        .ok()
+}
+
+/// Note in the library's `Artists/` that it is organized with `group_by`, so later runs
+/// without `--group-by` find its albums. Nothing is written for the default scheme unless
+/// another one was recorded before
+pub fn record_group_by(music_dir: &Path, group_by: GroupBy) -> Result<()> {
+    let recorded = recorded_group_by(music_dir);
+    if recorded == Some(group_by) || (recorded.is_none() && group_by == GroupBy::default()) {
+        return Ok(());
+    }
+    let path = music_dir.join("Artists").join(GROUP_BY_FILE);
+    fs::write(&path, format!("{}\n", group_by))
+        .with_context(|| format!("Failed to record the folder layout in {}", path.display()))
+}
 
-    let album_paths = get_all_album_paths(music_dir)?;
+/// An album folder in the library, e.g. `Artists/<artist>/<album>`, as yielded by [`iter_albums`]
+#[derive(Debug, Clone)]
+pub struct AlbumRef {
+    /// The album folder
+    pub path: PathBuf,
+    /// Name of the artist the album is filed under, from its folder names
+    pub artist_folder: String,
+    /// Name of the album, from its folder name
+    pub album_folder: String,
+    tags: OnceLock<Option<(String, String)>>,
+}
 
-    for album_path in album_paths {
-        folder_paths.push(album_path);
+impl AlbumRef {
+    fn new(path: PathBuf, group_by: GroupBy) -> Self {
+        let (artist_folder, album_folder) = group_by.album_names(&path);
+        Self {
+            artist_folder,
+            album_folder,
+            path,
+            tags: OnceLock::new(),
+        }
     }
 
-    Ok(folder_paths)
+    /// Artist and album tags of the album's first track that has both
+    /// Read on the first call and cached; None if no track carries them
+    pub fn tags(&self) -> Option<(&str, &str)> {
+        self.tags
+            .get_or_init(|| {
+                iter_album_tracks(&self.path)
+                    .find_map(|track| metadata::read_artist_album_tags(&track))
+            })
+            .as_ref()
+            .map(|(artist, album)| (artist.as_str(), album.as_str()))
+    }
+}
+
+/// Subdirectories directly inside `dir`, following symlinks; empty if `dir` can't be read
+fn child_dirs(dir: &Path) -> impl Iterator<Item = PathBuf> {
+    WalkDir::new(dir)
+        .min_depth(1)
+        .max_depth(1)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_dir())
+        .map(|e| e.into_path())
+}
+
+/// Stream the album folders under the music directory's `Artists/`, one directory read at a time
+/// Albums are looked for at the depth the library's [`GroupBy`] scheme puts them. Folders with
+/// no audio files anywhere under them aren't albums, and those marked with a skip marker (see
+/// [`SKIP_MARKERS`]) are left out
+pub fn iter_albums(music_dir: &str) -> impl Iterator<Item = AlbumRef> {
+    let group_by = group_by(Path::new(music_dir));
+    let artists_path = Path::new(music_dir).join("Artists");
+    let album_paths: Box<dyn Iterator<Item = PathBuf>> = match group_by.depth() {
+        1 => Box::new(child_dirs(&artists_path)),
+        _ => Box::new(child_dirs(&artists_path).flat_map(|artist_path| child_dirs(&artist_path))),
+    };
+    album_paths
+        .filter(|album_path| {
+            !has_skip_marker(album_path) && iter_album_tracks(album_path).next().is_some()
+        })
+        .map(move |album_path| AlbumRef::new(album_path, group_by))
+}
+
+/// Stream every track in the library, album by album (see [`iter_albums`])
+pub fn iter_tracks(music_dir: &str) -> impl Iterator<Item = PathBuf> {
+    iter_albums(music_dir).flat_map(|album| iter_album_tracks(&album.path))
+}
+
+/// Stream the audio files anywhere under an album folder
+pub fn iter_album_tracks(album_path: &Path) -> impl Iterator<Item = PathBuf> {
+    WalkDir::new(album_path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_file() && audio::is_audio_file(e.path()))
+        .map(|e| e.into_path())
+}
+
+/// Get all album paths from the music directory
+pub fn get_all_album_paths(music_dir: &str) -> Result<Vec<PathBuf>> {
+    Ok(iter_albums(music_dir).map(|album| album.path).collect())
+}
+
+/// Get all track paths from the music directory
+pub fn get_all_track_paths(music_dir: &str) -> Result<Vec<PathBuf>> {
+    Ok(iter_tracks(music_dir).collect())
+}
+
+/// All audio files anywhere under an album folder
+pub fn album_track_paths(album_path: &Path) -> Vec<PathBuf> {
+    iter_album_tracks(album_path).collect()
+}
+
+/// Get all folder paths from the music directory, the directory itself included
+/// Folders with a skip marker (see [`SKIP_MARKERS`]) are left out along with everything in them
+pub fn get_all_folder_paths(music_dir: &str) -> Result<Vec<PathBuf>> {
+    Ok(WalkDir::new(music_dir)
+        .follow_links(true)
+        .into_iter()
+        .filter_entry(|e| !has_skip_marker(e.path()))
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_dir())
+        .map(|e| e.into_path())
+        .collect())
 }
 
 /// Scan a directory for audio files and return statistics
@@ -95,6 +444,15 @@ pub struct FileScanResult {
 }
 
 pub fn scan_directory_for_audio_files(dir_path: &Path) -> Result<FileScanResult> {
+    scan_directory_for_audio_files_since(dir_path, None)
+}
+
+/// Like [`scan_directory_for_audio_files`], leaving out audio files not modified after `cutoff`
+/// (the `--since` option; see [`is_modified_since`])
+pub fn scan_directory_for_audio_files_since(
+    dir_path: &Path,
+    cutoff: Option<SystemTime>,
+) -> Result<FileScanResult> {
     let mut audio_files = Vec::new();
     let mut files_scanned = 0;
     let mut files_skipped = 0;
@@ -107,7 +465,9 @@ pub fn scan_directory_for_audio_files(dir_path: &Path) -> Result<FileScanResult>
         files_scanned += 1;
 
         if audio::is_audio_file(entry.path()) {
-            audio_files.push(entry.path().to_path_buf());
+            if is_modified_since(entry.path(), cutoff) {
+                audio_files.push(entry.path().to_path_buf());
+            }
         } else {
             files_skipped += 1;
         }
@@ -119,3 +479,437 @@ pub fn scan_directory_for_audio_files(dir_path: &Path) -> Result<FileScanResult>
         files_skipped,
     })
 }
+
+/// Whether `path` was modified after `cutoff`; always true without a cutoff
+pub fn is_modified_since(path: &Path, cutoff: Option<SystemTime>) -> bool {
+    let Some(cutoff) = cutoff else {
+        return true;
+    };
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .is_ok_and(|modified| modified > cutoff)
+}
+
+/// Parse a `--since` value into a cutoff time
+/// Accepts an age such as `30m`, `12h`, `7d` or `2w`, a Unix timestamp in seconds,
+/// or a UTC date/time such as `2024-05-01` or `2024-05-01T18:30[:00]`
+pub fn parse_since(value: &str) -> Result<SystemTime, String> {
+    parse_since_at(value, SystemTime::now())
+}
+
+fn parse_since_at(value: &str, now: SystemTime) -> Result<SystemTime, String> {
+    let value = value.trim();
+    let invalid = || {
+        format!(
+            "invalid time '{}' (expected an age like 7d, a Unix timestamp or a date like 2024-05-01)",
+            value
+        )
+    };
+
+    if let Ok(secs) = value.parse::<u64>() {
+        return Ok(UNIX_EPOCH + Duration::from_secs(secs));
+    }
+
+    let unit_secs = match value.chars().last() {
+        Some('s') => Some(1),
+        Some('m') => Some(60),
+        Some('h') => Some(60 * 60),
+        Some('d') => Some(24 * 60 * 60),
+        Some('w') => Some(7 * 24 * 60 * 60),
+        _ => None,
+    };
+    if let Some(unit_secs) = unit_secs {
+        let count: u64 = value[..value.len() - 1].parse().map_err(|_| invalid())?;
+        let age = count.checked_mul(unit_secs).ok_or_else(invalid)?;
+        return now
+            .checked_sub(Duration::from_secs(age))
+            .ok_or_else(invalid);
+    }
+
+    let (date, time) = value
+        .split_once(['T', ' '])
+        .map_or((value, None), |(date, time)| (date, Some(time)));
+    let date: Vec<u32> = date
+        .split('-')
+        .map(|part| part.parse().map_err(|_| invalid()))
+        .collect::<Result<_, _>>()?;
+    let time: Vec<u32> = match time {
+        Some(time) => time
+            .split(':')
+            .map(|part| part.parse().map_err(|_| invalid()))
+            .collect::<Result<_, _>>()?,
+        None => vec![0, 0],
+    };
+    let ([year, month, day], [hour, minute, second @ ..]) = (date.as_slice(), time.as_slice())
+    else {
+        return Err(invalid());
+    };
+    let second = match second {
+        [] => 0,
+        [second] => *second,
+        _ => return Err(invalid()),
+    };
+    if !(1..=12).contains(month)
+        || !(1..=31).contains(day)
+        || *hour > 23
+        || *minute > 59
+        || second > 59
+    {
+        return Err(invalid());
+    }
+
+    let days = days_from_civil(i64::from(*year), *month, *day);
+    let secs = days * 24 * 60 * 60 + i64::from(hour * 3600 + minute * 60 + second);
+    u64::try_from(secs)
+        .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+        .map_err(|_| invalid())
+}
+
+/// Days from 1970-01-01 to a proleptic Gregorian date
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = i64::from(month);
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The proleptic Gregorian date `days` after 1970-01-01; the inverse of [`days_from_civil`]
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Format `time` as a UTC date and time, e.g. `2024-03-01T18:30:00Z`
+pub fn format_utc(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default();
+    let (year, month, day) = civil_from_days(secs.div_euclid(86_400));
+    let second_of_day = secs.rem_euclid(86_400);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        second_of_day / 3600,
+        second_of_day / 60 % 60,
+        second_of_day % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_sanitize_filename_with_custom_replacement_or_deletion() {
+        assert_eq!(sanitize_filename_with("AC/DC", "_"), "AC_DC");
+        assert_eq!(sanitize_filename_with("AC/DC", "-"), "AC-DC");
+        assert_eq!(sanitize_filename_with("AC/DC", ""), "ACDC");
+        // Control characters are handled the same way
+        assert_eq!(
+            sanitize_filename_with("Tab\there: Now", "-"),
+            "Tab-here- Now"
+        );
+        assert_eq!(sanitize_filename_with("Tab\there: Now", ""), "Tabhere Now");
+        // Deleting can leave surrounding spaces, which are trimmed as before
+        assert_eq!(sanitize_filename_with("? Album ?", ""), "Album");
+    }
+
+    #[test]
+    fn test_sanitize_filename_with_never_empties_a_name() {
+        // Names of nothing but unsafe characters fall back to the default replacement
+        assert_eq!(sanitize_filename_with("???", ""), "___");
+        assert_eq!(sanitize_filename_with("/", ""), "_");
+        assert_eq!(sanitize_filename_with("? ?", " "), "_ _");
+        // so an album folder never collapses into its artist folder
+        let album_dir = Path::new("Artists")
+            .join(sanitize_filename_with("Artist", ""))
+            .join(sanitize_filename_with("???", ""));
+        assert_eq!(album_dir, Path::new("Artists/Artist/___"));
+        // A name that was empty to begin with stays empty
+        assert_eq!(sanitize_filename_with("", ""), "");
+        assert_eq!(sanitize_filename_with("   ", ""), "");
+    }
+
+    #[test]
+    fn test_naming_sanitizes_with_its_replacement() {
+        let naming = |replacement: &str| Naming {
+            sanitize_replacement: replacement.to_string(),
+            ..Naming::default()
+        };
+        assert_eq!(Naming::default().sanitize("AC/DC"), "AC_DC");
+        assert_eq!(naming("-").sanitize("AC/DC"), "AC-DC");
+        assert_eq!(naming("").sanitize("AC/DC"), "ACDC");
+        // An unsafe replacement is ignored rather than put back into the name
+        assert_eq!(naming("/").sanitize("AC/DC"), "AC_DC");
+    }
+
+    #[test]
+    fn test_naming_layout_overrides_the_recorded_one() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_dir = temp_dir.path();
+        fs::create_dir_all(music_dir.join("Artists"))?;
+
+        let naming = Naming::default();
+        assert_eq!(naming.layout(music_dir), GroupBy::Artist);
+        record_group_by(music_dir, GroupBy::Flat)?;
+        assert_eq!(naming.layout(music_dir), GroupBy::Flat);
+
+        let naming = Naming {
+            group_by: Some(GroupBy::ArtistYear),
+            ..Naming::default()
+        };
+        assert_eq!(naming.layout(music_dir), GroupBy::ArtistYear);
+        // The recorded scheme is only changed by the commands that file albums
+        assert_eq!(group_by(music_dir), GroupBy::Flat);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_album_names_read_back_each_layout() {
+        let names = |group_by: GroupBy, path: &str| group_by.album_names(Path::new(path));
+        let pair = |artist: &str, album: &str| (artist.to_string(), album.to_string());
+
+        assert_eq!(
+            names(GroupBy::Artist, "Artists/Band/Debut"),
+            pair("Band", "Debut")
+        );
+        assert_eq!(
+            names(GroupBy::ArtistYear, "Artists/Band/1999 - Debut"),
+            pair("Band", "Debut")
+        );
+        assert_eq!(
+            names(GroupBy::ArtistYear, "Artists/Band/Live - Tokyo"),
+            pair("Band", "Live - Tokyo")
+        );
+        assert_eq!(
+            names(GroupBy::Flat, "Artists/Band - Debut"),
+            pair("Band", "Debut")
+        );
+        assert_eq!(
+            names(GroupBy::Flat, "Artists/Sly - The Family Stone - Fresh"),
+            pair("Sly - The Family Stone", "Fresh")
+        );
+        assert_eq!(names(GroupBy::Flat, "Artists/Debut"), pair("", "Debut"));
+    }
+
+    #[test]
+    fn test_parse_since() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let ago = |secs| -> Result<SystemTime, String> { Ok(now - Duration::from_secs(secs)) };
+        assert_eq!(parse_since_at("90s", now), ago(90));
+        assert_eq!(parse_since_at("12h", now), ago(12 * 3600));
+        assert_eq!(parse_since_at("7d", now), ago(7 * 86400));
+        assert_eq!(parse_since_at("2w", now), ago(14 * 86400));
+        assert_eq!(
+            parse_since_at("1700000000", now),
+            Ok(UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+        );
+        assert_eq!(
+            parse_since_at("2024-03-01", now),
+            Ok(UNIX_EPOCH + Duration::from_secs(1_709_251_200))
+        );
+        assert_eq!(
+            parse_since_at("2024-03-01T18:30", now),
+            Ok(UNIX_EPOCH + Duration::from_secs(1_709_251_200 + 18 * 3600 + 30 * 60))
+        );
+        for bad in [
+            "",
+            "d",
+            "7x",
+            "yesterday",
+            "2024-13-01",
+            "2024-03-01T25:00",
+            "1969-12-31",
+        ] {
+            assert!(
+                parse_since_at(bad, now).is_err(),
+                "{:?} should be rejected",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn test_format_utc_round_trips_through_parse_since() {
+        for secs in [0, 951_782_400, 1_709_251_200 + 18 * 3600 + 30 * 60 + 5] {
+            let time = UNIX_EPOCH + Duration::from_secs(secs);
+            let formatted = format_utc(time);
+            let parsed = parse_since(formatted.trim_end_matches('Z'));
+            assert_eq!(parsed, Ok(time), "{}", formatted);
+        }
+        assert_eq!(
+            format_utc(UNIX_EPOCH + Duration::from_secs(1_709_251_200)),
+            "2024-03-01T00:00:00Z"
+        );
+    }
+
+    #[test]
+    fn test_scan_skips_files_not_modified_since_cutoff() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let old_track = temp_dir.path().join("old.mp3");
+        let new_track = temp_dir.path().join("new.mp3");
+        let cutoff = SystemTime::now() - Duration::from_secs(24 * 60 * 60);
+        File::create(&old_track)?.set_modified(cutoff - Duration::from_secs(60))?;
+        File::create(&new_track)?;
+
+        let scan = scan_directory_for_audio_files_since(temp_dir.path(), Some(cutoff))?;
+        assert_eq!(scan.audio_files, vec![new_track]);
+
+        let scan = scan_directory_for_audio_files_since(temp_dir.path(), None)?;
+        assert_eq!(scan.audio_files.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_iter_albums_streams_library() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_dir = temp_dir.path().to_str().unwrap();
+        assert_eq!(iter_albums(music_dir).count(), 0);
+
+        let album = temp_dir.path().join("Artists").join("Artist").join("Album");
+        fs::create_dir_all(album.join("CD2"))?;
+        File::create(album.join("01.mp3"))?;
+        File::create(album.join("CD2").join("02.mp3"))?;
+        File::create(album.join("cover.jpg"))?;
+        fs::write(temp_dir.path().join("Artists").join("notes.txt"), b"")?;
+
+        let albums: Vec<AlbumRef> = iter_albums(music_dir).collect();
+        assert_eq!(albums.len(), 1);
+        assert_eq!(albums[0].path, album);
+        assert_eq!(albums[0].artist_folder, "Artist");
+        assert_eq!(albums[0].album_folder, "Album");
+        // Empty files have no tags to read, and no path-based guess is made
+        assert_eq!(albums[0].tags(), None);
+
+        let mut tracks: Vec<PathBuf> = iter_tracks(music_dir).collect();
+        tracks.sort();
+        assert_eq!(
+            tracks,
+            [album.join("01.mp3"), album.join("CD2").join("02.mp3")]
+        );
+        assert_eq!(get_all_album_paths(music_dir)?, [album]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_all_album_paths_needs_audio_in_the_album() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_dir = temp_dir.path().to_str().unwrap();
+        let artist = temp_dir.path().join("Artists").join("Artist");
+        let album = artist.join("Album");
+        let audiobook = artist.join("Audiobook");
+        let singles = artist.join("Singles");
+        for dir in [&album, &audiobook, &singles] {
+            fs::create_dir_all(dir)?;
+        }
+        File::create(album.join("01.opus"))?;
+        File::create(audiobook.join("book.m4b"))?;
+        File::create(singles.join("info.txt"))?;
+
+        let mut album_paths = get_all_album_paths(music_dir)?;
+        album_paths.sort();
+        assert_eq!(album_paths, [album, audiobook]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_box_set_is_one_album() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_dir = temp_dir.path().to_str().unwrap();
+        let box_set = temp_dir.path().join("Artists").join("Band").join("The Box");
+        let early = box_set.join("The Early Years");
+        let live = box_set.join("Disc 2 - Live_ Part 1");
+        fs::create_dir_all(&early)?;
+        fs::create_dir_all(&live)?;
+        File::create(early.join("01.flac"))?;
+        File::create(live.join("01.flac"))?;
+
+        // None of the subtitled disc folders is a disc name, but their tracks still count
+        assert_eq!(
+            get_all_album_paths(music_dir)?,
+            std::slice::from_ref(&box_set)
+        );
+        let mut track_paths = get_all_track_paths(music_dir)?;
+        track_paths.sort();
+        assert_eq!(track_paths, [live.join("01.flac"), early.join("01.flac")]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_all_folder_paths_includes_artists_but_not_marked_folders() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_dir = temp_dir.path().to_str().unwrap();
+        let artists = temp_dir.path().join("Artists");
+        let artist = artists.join("Artist");
+        let album = artist.join("Album");
+        let bootleg = artist.join("Bootleg");
+        fs::create_dir_all(album.join("CD1"))?;
+        fs::create_dir_all(bootleg.join("CD1"))?;
+        File::create(bootleg.join(".mfutil-skip"))?;
+
+        let mut folder_paths = get_all_folder_paths(music_dir)?;
+        folder_paths.sort();
+        assert_eq!(
+            folder_paths,
+            [
+                temp_dir.path().to_path_buf(),
+                artists,
+                artist,
+                album.clone(),
+                album.join("CD1"),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_marked_albums_are_skipped() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music_dir = temp_dir.path().to_str().unwrap();
+        let artist = temp_dir.path().join("Artists").join("Artist");
+        let album = artist.join("Album");
+        let bootleg = artist.join("Bootleg");
+        let work_in_progress = artist.join("Work in Progress");
+        for dir in [&album, &bootleg, &work_in_progress] {
+            fs::create_dir_all(dir)?;
+            File::create(dir.join("01.mp3"))?;
+        }
+        File::create(bootleg.join(".mfutil-skip"))?;
+        File::create(work_in_progress.join(".nomedia"))?;
+
+        assert_eq!(
+            get_all_album_paths(music_dir)?,
+            std::slice::from_ref(&album)
+        );
+        assert_eq!(get_all_track_paths(music_dir)?, [album.join("01.mp3")]);
+
+        Ok(())
+    }
+}
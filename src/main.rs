@@ -1,16 +1,40 @@
 use anyhow::{Context, Result};
-use clap::Parser;
+use checkpoint::Checkpoint;
+use clap::{CommandFactory, FromArgMatches, Parser};
 use dotenvy::dotenv;
-use ffmpeg_next as ffmpeg;
-use magick_rust::magick_wand_genesis;
+use mfutil::aliases::Aliases;
+use mfutil::audio::QualityFloor;
+use mfutil::checksums::ChecksumAlgorithm;
+use mfutil::commands;
+use mfutil::commands::art::ArtDir;
+use mfutil::commands::embed_art::ArtClobber;
+use mfutil::commands::import::ImportOptions;
+use mfutil::commands::links::LinkViews;
+use mfutil::commands::organize::OrganizeOptions;
+use mfutil::commands::rename_files::FileTemplate;
+use mfutil::commands::sync::{SyncOptions, UnmatchedAlbum};
+use mfutil::cover_art::{CoverFormat, CoverMode, CoverSettings};
+use mfutil::musicbrainz::{Network, ReleaseChooser};
+use mfutil::report::{ReportDetails, RunReport};
+use mfutil::utils::{
+    get_all_album_paths, get_all_folder_paths, get_all_track_paths, GroupBy, Naming,
+    DEFAULT_SANITIZE_REPLACEMENT, SANITIZE_REPLACEMENT_ENV,
+};
+use pipeline::{AllOptions, StepResults};
+use rayon::ThreadPool;
+use std::collections::HashSet;
+use std::io::IsTerminal;
+use std::num::{NonZeroU64, NonZeroUsize};
 use std::path::{Path, PathBuf};
 use std::sync::{
     atomic::{AtomicBool, Ordering},
     mpsc, Arc,
 };
 use std::thread;
+use std::time::{Duration, SystemTime};
 
-mod commands;
+mod checkpoint;
+mod pipeline;
 mod tui;
 mod utils;
 
@@ -52,7 +76,7 @@ fn run_album_tui<F>(title: &'static str, music_dir: &str, operation: F) -> Resul
 where
     F: Fn(&Path) -> Result<()> + Send + Sync + 'static,
 {
-    let album_paths = utils::get_all_album_paths(music_dir)?;
+    let album_paths = get_all_album_paths(music_dir)?;
     let op = Arc::new(operation);
     run_with_tui(title, album_paths, move |path: PathBuf| {
         let op = op.clone();
@@ -66,7 +90,7 @@ fn run_track_tui<F>(title: &'static str, music_dir: &str, operation: F) -> Resul
 where
     F: Fn(&Path) -> Result<()> + Send + Sync + 'static,
 {
-    let track_paths = utils::get_all_track_paths(music_dir)?;
+    let track_paths = get_all_track_paths(music_dir)?;
     let op = Arc::new(operation);
     run_with_tui(title, track_paths, move |path: PathBuf| {
         let op = op.clone();
@@ -80,7 +104,7 @@ fn run_folder_tui<F>(title: &'static str, music_dir: &str, operation: F) -> Resu
 where
     F: Fn(&Path) -> Result<()> + Send + Sync + 'static,
 {
-    let folder_paths = utils::get_all_folder_paths(music_dir)?;
+    let folder_paths = get_all_folder_paths(music_dir)?;
     let op = Arc::new(operation);
     run_with_tui(title, folder_paths, move |path: PathBuf| {
         let op = op.clone();
@@ -89,51 +113,250 @@ where
     })
 }
 
+// Album TUI for `all` steps: albums the checkpoint already has for `step` are skipped,
+// and each finished album is recorded
+fn run_checkpointed_album_tui<F>(
+    title: &'static str,
+    step: &'static str,
+    music_dir: &str,
+    checkpoint: &Arc<Checkpoint>,
+    operation: F,
+) -> Result<()>
+where
+    F: Fn(&Path) -> Result<()> + Send + Sync + 'static,
+{
+    let album_paths: Vec<PathBuf> = get_all_album_paths(music_dir)?
+        .into_iter()
+        .filter(|path| !checkpoint.album_done(step, path))
+        .collect();
+    let checkpoint = checkpoint.clone();
+    run_with_tui(title, album_paths, move |path: PathBuf| {
+        operation(&path)?;
+        checkpoint.mark_album(step, &path);
+        Ok(path.display().to_string())
+    })
+}
+
+// Thread pool for the rayon-parallel phases; `None` keeps rayon's default of one thread per core
+fn build_thread_pool(jobs: Option<NonZeroUsize>) -> Result<Arc<ThreadPool>> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(jobs.map_or(0, NonZeroUsize::get))
+        .build()
+        .context("Failed to build thread pool")?;
+    Ok(Arc::new(pool))
+}
+
+// How folders and files are named: in the `--group-by` layout, under composers with
+// `--classical`, with extensions lowercased by `--normalize-extensions`, and made safe by
+// `--sanitize-delete`, then `--sanitize-replacement`, then MFUTIL_SANITIZE_REPLACEMENT, with
+// names canonicalized by the `--aliases` file and box sets split by `--split-boxsets`
+fn naming(cli: &Cli) -> Result<Naming> {
+    let sanitize_replacement = if cli.sanitize_delete {
+        String::new()
+    } else {
+        cli.sanitize_replacement
+            .clone()
+            .or_else(|| std::env::var(SANITIZE_REPLACEMENT_ENV).ok())
+            .unwrap_or_else(|| DEFAULT_SANITIZE_REPLACEMENT.to_string())
+    };
+    let aliases = match &cli.aliases {
+        Some(path) => Some(Arc::new(Aliases::load(path)?)),
+        None => None,
+    };
+    Ok(Naming {
+        sanitize_replacement,
+        group_by: cli.group_by,
+        normalize_extensions: cli.normalize_extensions,
+        classical: cli.classical,
+        split_boxsets: cli.split_boxsets,
+        aliases,
+    })
+}
+
+// How the run reaches MusicBrainz and the cover art services: not at all with `--offline`,
+// else with connects and reads given up after `--http-timeout`
+fn network(cli: &Cli) -> Network {
+    if cli.offline {
+        Network::offline()
+    } else {
+        Network::new(cli.http_timeout.map(|secs| Duration::from_secs(secs.get())))
+    }
+}
+
+// Ask which release to use when a search matches several, unless nobody is there to answer
+fn release_chooser() -> Option<&'static dyn ReleaseChooser> {
+    let interactive = std::io::stdout().is_terminal() && std::io::stdin().is_terminal();
+    interactive.then_some(&tui::TuiReleaseChooser)
+}
+
+// Where `--art-dir` collects extracted album art, and whether `--art-dir-only` keeps it out of
+// the album folders
+fn art_dir(cli: &Cli) -> Option<ArtDir> {
+    cli.art_dir.clone().map(|path| ArtDir {
+        path,
+        in_place: !cli.art_dir_only,
+    })
+}
+
+// How downloaded cover art and folder icons are saved: in `--cover-format`, where
+// `--cover-mode` says, and only when at least `--cover-min-size` pixels
+fn cover_settings(cli: &Cli) -> CoverSettings {
+    CoverSettings {
+        format: cli.cover_format.unwrap_or_default(),
+        mode: cli.cover_mode.unwrap_or_default(),
+        min_size: cli.cover_min_size,
+    }
+}
+
 // Helper function for the All command steps
-fn run_all_sync_tags(music_dir: &str, rt: &tokio::runtime::Runtime) -> Result<()> {
-    let album_paths = utils::get_all_album_paths(music_dir)?;
+// With a checkpoint, albums synced by an interrupted run are skipped and new ones recorded
+// Returns the albums MusicBrainz had no match for, in folder order
+fn run_all_sync_tags(
+    music_dir: &str,
+    options: SyncOptions,
+    rt: &tokio::runtime::Runtime,
+    pool: &Arc<ThreadPool>,
+    checkpoint: Option<&Arc<Checkpoint>>,
+) -> Result<Vec<UnmatchedAlbum>> {
+    let album_paths: Vec<PathBuf> = get_all_album_paths(music_dir)?
+        .into_iter()
+        .filter(|path| checkpoint.is_none_or(|c| !c.album_done("sync", path)))
+        .collect();
+    let checkpoint = checkpoint.cloned();
     let total_albums = album_paths.len();
     let cancel_token = Arc::new(AtomicBool::new(true));
     let (tx, rx) = mpsc::channel();
 
     let thread_cancel_token = cancel_token.clone();
     let rt_handle = rt.handle().clone();
-    let _music_dir_clone = music_dir.to_string();
-    let handle = thread::spawn(move || -> Result<()> {
+    let pool = pool.clone();
+    let handle = thread::spawn(move || -> Result<Vec<UnmatchedAlbum>> {
         tx.send(format!("TOTAL_FILES:{}", total_albums))?;
         tx.send("Syncing Tags with MusicBrainz".to_string())?;
-        for album_path in album_paths {
-            if !thread_cancel_token.load(Ordering::SeqCst) {
-                break;
-            }
-            rt_handle.block_on(commands::sync::process_single_album_sync_tags(
-                &album_path,
-                tx.clone(),
-            ))?;
-        }
-        Ok(())
+        let _rt = rt_handle.enter();
+        commands::sync::sync_albums(
+            album_paths,
+            &options,
+            release_chooser(),
+            &pool,
+            &tx,
+            &thread_cancel_token,
+            |album_path| {
+                if let Some(checkpoint) = &checkpoint {
+                    checkpoint.mark_album("sync", album_path);
+                }
+            },
+        )
     });
 
     tui::run_tui(rx, cancel_token).map_err(anyhow::Error::from)?;
 
-    handle.join().unwrap()?;
+    handle.join().unwrap()
+}
 
-    Ok(())
+/// Probe every album's cover art sources, listing per-album availability without saving anything
+fn run_all_probe_cover_art(
+    music_dir: &str,
+    match_by_folder: bool,
+    since: Option<SystemTime>,
+    network: &Network,
+    rt: &tokio::runtime::Runtime,
+) -> Result<()> {
+    let network = network.clone();
+    let album_paths = get_all_album_paths(music_dir)?;
+    let rt_handle = rt.handle().clone();
+    run_with_tui(
+        "Probing Cover Art Sources",
+        album_paths,
+        move |path: PathBuf| {
+            let probe = commands::sync::probe_album_cover_art(
+                &network,
+                &path,
+                match_by_folder,
+                since,
+                release_chooser(),
+            );
+            match rt_handle.block_on(probe)? {
+                Some(availability) => {
+                    tracing::info!("Cover art probe: {}", availability);
+                    Ok(availability.to_string())
+                }
+                None => Ok(format!("{}: no audio files", path.display())),
+            }
+        },
+    )
 }
 
-fn run_all_artist_art(music_dir: &str, rt: &tokio::runtime::Runtime) -> Result<()> {
-    commands::art::extract_artist_art(music_dir)?;
-    rt.block_on(commands::art::fetch_placeholders(music_dir))
+fn run_all_artist_art(
+    music_dir: &str,
+    cover: CoverSettings,
+    network: &Network,
+    rt: &tokio::runtime::Runtime,
+) -> Result<()> {
+    commands::art::extract_artist_art(music_dir, false, network)?;
+    rt.block_on(commands::art::fetch_placeholders(
+        music_dir,
+        cover.format,
+        network,
+        commands::art::placeholder_delay(),
+    ))
 }
 
-fn run_all_album_art(music_dir: &str) -> Result<()> {
-    run_album_tui(
+fn run_all_album_art(
+    music_dir: &str,
+    art_dir: Option<ArtDir>,
+    since: Option<SystemTime>,
+    checkpoint: &Arc<Checkpoint>,
+) -> Result<()> {
+    run_checkpointed_album_tui(
         "Extracting Album Art",
+        "album-art",
         music_dir,
-        commands::art::process_single_album_art,
+        checkpoint,
+        move |album_path| {
+            commands::art::process_single_album_art(album_path, art_dir.as_ref(), since)
+        },
     )
 }
 
+// Album art extraction spread over the --jobs pool, with the bar counting finished albums
+fn run_parallel_album_art_tui(
+    music_dir: &str,
+    art_dir: Option<ArtDir>,
+    since: Option<SystemTime>,
+    pool: &Arc<ThreadPool>,
+) -> Result<()> {
+    let album_paths = get_all_album_paths(music_dir)?;
+    let total_albums = album_paths.len();
+    let cancel_token = Arc::new(AtomicBool::new(true));
+    let (tx, rx) = mpsc::channel();
+
+    let thread_cancel_token = cancel_token.clone();
+    let pool = pool.clone();
+    let handle = thread::spawn(move || -> Result<()> {
+        tx.send(format!("TOTAL_FILES:{}", total_albums))?;
+        tx.send("Extracting Album Art".to_string())?;
+        pool.install(|| {
+            commands::art::extract_album_art_parallel(
+                &album_paths,
+                art_dir.as_ref(),
+                since,
+                &thread_cancel_token,
+                |album_path| {
+                    tx.send(format!("COMPLETED: {}", album_path.display()))?;
+                    Ok(())
+                },
+            )
+        })
+    });
+
+    tui::run_tui(rx, cancel_token).map_err(anyhow::Error::from)?;
+
+    handle.join().unwrap()?;
+
+    Ok(())
+}
+
 fn run_all_folder_icons(music_dir: &str) -> Result<()> {
     run_folder_tui(
         "Setting Folder Icons",
@@ -142,71 +365,181 @@ fn run_all_folder_icons(music_dir: &str) -> Result<()> {
     )
 }
 
-fn run_all_album_symlinks(music_dir: &str) -> Result<()> {
+fn run_all_album_symlinks(
+    music_dir: &str,
+    naming: &Naming,
+    step: &'static str,
+    checkpoint: &Arc<Checkpoint>,
+) -> Result<()> {
     let music_dir_owned = music_dir.to_string();
-    run_album_tui("Creating Album Symlinks", music_dir, move |album_path| {
-        commands::albums::process_single_album_symlink(album_path, &music_dir_owned)
-    })
+    let naming = naming.clone();
+    run_checkpointed_album_tui(
+        "Creating Album Symlinks",
+        step,
+        music_dir,
+        checkpoint,
+        move |album_path| {
+            commands::albums::process_single_album_symlink(
+                album_path,
+                &music_dir_owned,
+                &naming,
+                false,
+                true,
+            )
+        },
+    )
 }
 
 fn run_all_track_symlinks(music_dir: &str) -> Result<()> {
     let music_dir_owned = music_dir.to_string();
     run_track_tui("Creating Track Symlinks", music_dir, move |track_path| {
-        commands::tracks::process_single_track_symlink(track_path, &music_dir_owned)
+        commands::tracks::process_single_track_symlink(track_path, &music_dir_owned, false)
     })
 }
 
-fn run_all_organize(music_dir: &str) -> Result<()> {
+// Run one `all` step unless an interrupted run already finished it, then checkpoint it
+// A failure is recorded in `steps` instead of returned, so the steps after it still run;
+// the failed step is left out of the checkpoint and retried on resume
+// Once the user has cancelled, the remaining steps are no-ops
+fn run_all_step<T: ReportDetails>(
+    step: &str,
+    checkpoint: &Checkpoint,
+    report: &RunReport,
+    steps: &StepResults,
+    operation: impl FnOnce() -> Result<T>,
+) {
+    if tui::cancelled() || checkpoint.step_done(step) {
+        return;
+    }
+    let result = report.run_step(step, false, operation).and_then(|_| {
+        if !tui::cancelled() {
+            checkpoint.mark_step(step);
+        }
+        checkpoint.save()
+    });
+    steps.record(step, result);
+}
+
+fn run_all_organize(
+    music_dir: &str,
+    options: &AllOptions,
+    pool: &Arc<ThreadPool>,
+    report: &Arc<RunReport>,
+    checkpoint: &Arc<Checkpoint>,
+    steps: &Arc<StepResults>,
+) -> Result<()> {
     let music_dir_owned = music_dir.to_string();
+    let AllOptions {
+        views,
+        organize: options,
+        sync,
+        import,
+        ..
+    } = options.clone();
+    let pool = pool.clone();
+    let report = report.clone();
+    let checkpoint = checkpoint.clone();
+    let steps = steps.clone();
     let cancel_token = Arc::new(AtomicBool::new(true));
     let (tx, rx) = mpsc::channel();
 
-    let thread_cancel_token = cancel_token.clone();
     let handle = thread::spawn(move || -> Result<()> {
-        tx.send("TOTAL_FILES:6".to_string())?;
+        let remaining = [
+            "organize/sync",
+            "organize/reorganize",
+            "organize/import",
+            "organize/organize",
+            "organize/albums",
+            "organize/tracks",
+        ]
+        .iter()
+        .filter(|step| match **step {
+            "organize/albums" => views.albums,
+            "organize/tracks" => views.tracks,
+            _ => true,
+        })
+        .filter(|step| !checkpoint.step_done(step))
+        .count();
+        tx.send(format!("TOTAL_FILES:{}", remaining))?;
         tx.send("Organizing Music Library".to_string())?;
 
-        if !thread_cancel_token.load(Ordering::SeqCst) {
-            return Ok(());
-        }
         let rt = tokio::runtime::Runtime::new()?;
-        run_all_sync_tags(&music_dir_owned, &rt)?;
-        tx.send("COMPLETED: Synced tags with MusicBrainz".to_string())?;
+        run_all_step("organize/sync", &checkpoint, &report, &steps, || {
+            run_all_sync_tags(&music_dir_owned, sync, &rt, &pool, None)?;
+            tx.send("COMPLETED: Synced tags with MusicBrainz".to_string())?;
+            Ok(())
+        });
 
-        if !thread_cancel_token.load(Ordering::SeqCst) {
-            return Ok(());
-        }
-        commands::reorganize::reorganize_misplaced_files(&music_dir_owned, false, true)?;
-        tx.send("COMPLETED: Reorganized misplaced files".to_string())?;
+        run_all_step("organize/reorganize", &checkpoint, &report, &steps, || {
+            let summary = pool.install(|| {
+                commands::reorganize::reorganize_misplaced_files(
+                    &music_dir_owned,
+                    &OrganizeOptions {
+                        quiet: true,
+                        ..options.clone()
+                    },
+                    false,
+                    None,
+                )
+            })?;
+            tx.send(format!(
+                "COMPLETED: Reorganized misplaced files ({})",
+                summary
+            ))?;
+            Ok(summary)
+        });
 
-        if !thread_cancel_token.load(Ordering::SeqCst) {
-            return Ok(());
-        }
-        commands::import::import_and_organize_files(
-            &music_dir_owned,
-            &music_dir_owned,
-            false,
-            true,
-        )?;
-        tx.send("COMPLETED: Imported external files".to_string())?;
-
-        if !thread_cancel_token.load(Ordering::SeqCst) {
-            return Ok(());
-        }
-        commands::organize::organize_music_library(&music_dir_owned, false, true)?;
-        tx.send("COMPLETED: Organized files by metadata".to_string())?;
+        run_all_step("organize/import", &checkpoint, &report, &steps, || {
+            let summary = commands::import::import_and_organize_files(
+                &[music_dir_owned.as_str()],
+                &music_dir_owned,
+                &ImportOptions {
+                    quiet: true,
+                    naming: options.naming.clone(),
+                    ..import.clone()
+                },
+            )?;
+            tx.send(format!("COMPLETED: Imported external files ({})", summary))?;
+            Ok(summary)
+        });
+
+        run_all_step("organize/organize", &checkpoint, &report, &steps, || {
+            let summary = pool.install(|| {
+                commands::organize::organize_music_library(
+                    &music_dir_owned,
+                    &OrganizeOptions {
+                        quiet: true,
+                        ..options.clone()
+                    },
+                )
+            })?;
+            tx.send(format!(
+                "COMPLETED: Organized files by metadata ({})",
+                summary
+            ))?;
+            Ok(summary)
+        });
 
-        if !thread_cancel_token.load(Ordering::SeqCst) {
-            return Ok(());
+        if views.albums {
+            run_all_step("organize/albums", &checkpoint, &report, &steps, || {
+                run_all_album_symlinks(
+                    &music_dir_owned,
+                    &options.naming,
+                    "organize/albums",
+                    &checkpoint,
+                )?;
+                tx.send("COMPLETED: Created album symlinks".to_string())?;
+                Ok(())
+            });
         }
-        run_all_album_symlinks(&music_dir_owned)?;
-        tx.send("COMPLETED: Created album symlinks".to_string())?;
 
-        if !thread_cancel_token.load(Ordering::SeqCst) {
-            return Ok(());
+        if views.tracks {
+            run_all_step("organize/tracks", &checkpoint, &report, &steps, || {
+                run_all_track_symlinks(&music_dir_owned)?;
+                tx.send("COMPLETED: Created track symlinks".to_string())?;
+                Ok(())
+            });
         }
-        run_all_track_symlinks(&music_dir_owned)?;
-        tx.send("COMPLETED: Created track symlinks".to_string())?;
 
         Ok(())
     });
@@ -218,9 +551,154 @@ fn run_all_organize(music_dir: &str) -> Result<()> {
     Ok(())
 }
 
+// Run the `all` pipeline, skipping steps (and albums) that the checkpoint records as done
+// Every step runs even if an earlier one failed; the error names the steps that failed
+// A symlink view turned off in `options` is built by neither its own step nor organize's
+fn run_all(
+    music_dir: &str,
+    skip_set: &HashSet<String>,
+    options: &AllOptions,
+    checkpoint: &Arc<Checkpoint>,
+    rt: &tokio::runtime::Runtime,
+    pool: &Arc<ThreadPool>,
+    report: &Arc<RunReport>,
+) -> Result<()> {
+    let steps = Arc::new(StepResults::default());
+
+    // 1. Sync Tags with MusicBrainz (first step)
+    if !skip_set.contains("sync") {
+        run_all_step("sync", checkpoint, report, &steps, || {
+            run_all_sync_tags(music_dir, options.sync.clone(), rt, pool, Some(checkpoint))
+        });
+    }
+
+    // 2. Handle artist images
+    if !skip_set.contains("art") {
+        run_all_step("artist-art", checkpoint, report, &steps, || {
+            run_all_artist_art(music_dir, options.cover, &options.network, rt)
+        });
+    }
+
+    // 3. Setting Folder Icons
+    if !skip_set.contains("icons") && !skip_set.contains("art") {
+        run_all_step("folder-icons", checkpoint, report, &steps, || {
+            run_all_folder_icons(music_dir)
+        });
+    }
+
+    // 4. Extracting Album Art
+    if !skip_set.contains("art") {
+        run_all_step("album-art", checkpoint, report, &steps, || {
+            run_all_album_art(
+                music_dir,
+                options.art_dir.clone(),
+                options.since,
+                checkpoint,
+            )
+        });
+    }
+
+    // 5. Creating Album Symlinks
+    if options.views.albums && !skip_set.contains("albums") {
+        run_all_step("albums", checkpoint, report, &steps, || {
+            run_all_album_symlinks(music_dir, &options.organize.naming, "albums", checkpoint)
+        });
+    }
+
+    // 6. Creating Track Symlinks
+    if options.views.tracks && !skip_set.contains("tracks") {
+        run_all_step("tracks", checkpoint, report, &steps, || {
+            run_all_track_symlinks(music_dir)
+        });
+    }
+
+    // 7. Organizing Music Library
+    if !skip_set.contains("organize") && !tui::cancelled() {
+        if let Err(e) = run_all_organize(music_dir, options, pool, report, checkpoint, &steps) {
+            steps.record("organize", Err(e));
+        }
+    }
+
+    steps.finish()
+}
+
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
+    /// Number of threads for parallel phases (default: one per CPU core; lower values can be faster on HDDs)
+    #[arg(long, global = true)]
+    jobs: Option<NonZeroUsize>,
+    /// Seconds to wait on a network connection or read before giving up (default: 30, or MFUTIL_HTTP_TIMEOUT)
+    #[arg(long, global = true)]
+    http_timeout: Option<NonZeroU64>,
+    /// Format to save downloaded cover art and folder icons in: jpeg (default), png or webp
+    #[arg(long, global = true)]
+    cover_format: Option<CoverFormat>,
+    /// Where sync and import store downloaded cover art: sidecar (cover.jpg, default), embed (in the audio files) or both
+    #[arg(long, global = true)]
+    cover_mode: Option<CoverMode>,
+    /// Reject downloaded cover art whose smaller side is under PX pixels and try the next source
+    #[arg(long, global = true, value_name = "PX")]
+    cover_min_size: Option<u32>,
+    /// Also collect album art extracted by art and all under DIR, as DIR/<artist>/<album>.jpg
+    #[arg(long, global = true, value_name = "DIR")]
+    art_dir: Option<PathBuf>,
+    /// Write extracted album art only to --art-dir, leaving the album folders without .folder.jpg
+    #[arg(long, global = true, requires = "art_dir")]
+    art_dir_only: bool,
+    /// Leading articles moved to the end of derived artist sort tags (default: The,A,An)
+    #[arg(long, global = true, value_delimiter = ',', value_name = "ARTICLES")]
+    sort_articles: Option<Vec<String>>,
+    /// CSV file of artist/album aliases (artist,Beyonce,Beyoncé) applied when grouping and linking
+    #[arg(long, global = true)]
+    aliases: Option<PathBuf>,
+    /// Only process files modified since this time: an age (30m, 12h, 7d, 2w), a Unix timestamp or a UTC date (2024-05-01)
+    #[arg(long, global = true, value_parser = mfutil::utils::parse_since)]
+    since: Option<SystemTime>,
+    /// Make no network requests: skip MusicBrainz lookups and cover art, artist image and placeholder downloads
+    #[arg(long, global = true, visible_alias = "no-network")]
+    offline: bool,
+    /// Album folder layout under Artists/: artist (<artist>/<album>, default), artist-year (<artist>/<year> - <album>) or flat (<artist> - <album>); remembered by the library once organized
+    #[arg(long, global = true, value_name = "SCHEME")]
+    group_by: Option<GroupBy>,
+    /// Give box sets tagged with one album title a subfolder per disc, named by disc subtitle, when organizing
+    #[arg(long, global = true)]
+    split_boxsets: bool,
+    /// File tracks with a composer tag under Artists/<composer>/ instead of the performer when organizing
+    #[arg(long, global = true)]
+    classical: bool,
+    /// Put TEXT in place of characters folder and file names can't hold, e.g. "-" for AC-DC (default: _, or MFUTIL_SANITIZE_REPLACEMENT)
+    #[arg(
+        long,
+        global = true,
+        value_name = "TEXT",
+        conflicts_with = "sanitize_delete"
+    )]
+    sanitize_replacement: Option<String>,
+    /// Delete characters folder and file names can't hold instead of replacing them (AC/DC -> ACDC)
+    #[arg(long, global = true)]
+    sanitize_delete: bool,
+    /// Lowercase file extensions (Track.MP3 -> Track.mp3) as organize, reorganize and import move or copy files
+    #[arg(long, global = true)]
+    normalize_extensions: bool,
+    /// Save each file's tags to .mfutil-backup/ before sync or import first rewrites them (recommended; undo with restore-tags)
+    #[arg(long, global = true)]
+    backup_tags: bool,
+    /// Tag files ripped by cd or copied in by import with the operation and time (MFUTIL_SOURCE); see the provenance command
+    #[arg(long, global = true)]
+    provenance: bool,
+    /// Run even if the music directory is the filesystem root, the home directory, or has neither an Artists folder nor audio files
+    #[arg(long, global = true)]
+    force_unsafe_dir: bool,
+    /// Write a checksum manifest (checksums.sha256 or checksums.md5) in each album folder after import or cd: sha256, md5 or none (default)
+    #[arg(long, global = true, default_value = "none")]
+    checksums: ChecksumAlgorithm,
+    /// Write a JSON report of what the run did (counts, per-file actions, errors) to this file
+    #[arg(long, global = true)]
+    report_file: Option<PathBuf>,
+    /// Don't print the summary line that commands without a progress display end with
+    #[arg(long, short, global = true)]
+    quiet: bool,
     #[command(subcommand)]
     command: Commands,
 }
@@ -231,53 +709,287 @@ enum Commands {
     Art {
         /// Music directory
         music_dir: Option<String>,
+        /// Crop each artist's folder image to a centred square
+        #[arg(long)]
+        square_art: bool,
+    },
+    /// Embed each album's cover file into its audio files
+    EmbedArt {
+        /// Music directory
+        music_dir: Option<String>,
+        /// Show which files would get the cover without writing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// What to do with files that already have embedded art: skip (default), replace, or keep-larger (replace only with art of more pixels)
+        #[arg(long, value_name = "POLICY")]
+        art_clobber: Option<ArtClobber>,
+        /// Replace art that is already embedded in a file (same as --art-clobber replace)
+        #[arg(long, conflicts_with = "art_clobber")]
+        force: bool,
     },
     /// Create album symlinks
     Albums {
         /// Music directory
         music_dir: Option<String>,
+        /// Log the links that would be created, replaced or skipped without changing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Recreate links whose target folder no longer exists, reporting each repair
+        #[arg(long)]
+        link_target_check: bool,
+        /// Instead of linking albums, report links whose albums hold identical tracks
+        #[arg(long, conflicts_with_all = ["dry_run", "link_target_check"])]
+        dedupe_symlinks: bool,
+        /// With --dedupe-symlinks, remove the duplicate links instead of only reporting them
+        #[arg(long, requires = "dedupe_symlinks")]
+        apply: bool,
     },
     /// Create track symlinks
     Tracks {
         /// Music directory
         music_dir: Option<String>,
+        /// Log the links that would be created, replaced or skipped without changing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Rebuild the Albums and Tracks symlink views in one pass, removing stale links
+    Links {
+        /// Music directory
+        music_dir: Option<String>,
+        /// Log the links that would be removed, created, replaced or skipped without changing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Don't build the Albums/ symlink view
+        #[arg(long, visible_alias = "tracks-only", overrides_with = "album_links")]
+        no_album_links: bool,
+        /// Build the Albums/ symlink view (the default; overrides an earlier --no-album-links)
+        #[arg(long, overrides_with = "no_album_links")]
+        album_links: bool,
+        /// Don't build the Tracks/ symlink view
+        #[arg(long, visible_alias = "albums-only", overrides_with = "track_links")]
+        no_track_links: bool,
+        /// Build the Tracks/ symlink view (the default; overrides an earlier --no-track-links)
+        #[arg(long, overrides_with = "no_track_links")]
+        track_links: bool,
     },
     /// Sync music tags with MusicBrainz and fetch cover art
     SyncWithArt {
         /// Music directory to sync
         music_dir: Option<String>,
+        /// Re-sync albums whose files already carry a MusicBrainz release ID
+        #[arg(long)]
+        force: bool,
+        /// Only report which sources have cover art for each album; nothing is tagged or saved
+        #[arg(long, conflicts_with = "force")]
+        probe: bool,
+        /// Search MusicBrainz by the Artists/<artist>/<album> folder names, falling back to the tags
+        #[arg(long)]
+        match_by_folder: bool,
+        /// Sync up to N albums at once (default 1); MusicBrainz requests stay rate limited
+        #[arg(long, value_name = "N")]
+        parallel_albums: Option<NonZeroUsize>,
+        /// Repair mojibake (e.g. "BeyoncÃ©") in artist, album and title tags before syncing
+        #[arg(long, conflicts_with = "probe")]
+        fix_encoding: bool,
+        /// Write the albums MusicBrainz couldn't match to FILE, one artist<TAB>album<TAB>folder line each
+        #[arg(long, value_name = "FILE", conflicts_with = "probe")]
+        report_unmatched: Option<PathBuf>,
+        /// Fetch lyrics from LRCLIB, embedding the plain text and saving synced lyrics as .lrc
+        #[arg(long, conflicts_with = "probe")]
+        embed_lyrics: bool,
+        /// Write featured artists as "A feat. B" in artist and album artist tags, whether tagged ft, featuring or (feat. B)
+        #[arg(long, conflicts_with = "probe")]
+        normalize_featuring: bool,
+        /// With --normalize-featuring, keep only the main artist in the album artist tag so the album files under one folder
+        #[arg(long, requires = "normalize_featuring")]
+        strip_featured: bool,
+    },
+    /// Roll files back to the tags saved by --backup-tags
+    RestoreTags {
+        /// Music directory
+        music_dir: Option<String>,
+        /// List the files whose tags would be restored without changing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Check files against the checksum manifests written by --checksums and report mismatches
+    Verify {
+        /// Music directory
+        music_dir: Option<String>,
+    },
+    /// List files whose tags place them in a different album folder, without moving anything
+    Check {
+        /// Music directory
+        music_dir: Option<String>,
+        /// Convert smart quotes and dashes in tags to ASCII, as reorganize would
+        #[arg(long)]
+        ascii_punctuation: bool,
+        /// Print the mismatches as JSON on stdout
+        #[arg(long)]
+        json: bool,
+    },
+    /// Count the library's files by where they came from (cd rip, import or external), from the tags --provenance writes
+    Provenance {
+        /// Music directory
+        music_dir: Option<String>,
+        /// Print the counts as JSON on stdout
+        #[arg(long)]
+        json: bool,
+    },
+    /// Time walking the library and reading every file's tags, without changing anything or using the network
+    Scan {
+        /// Music directory
+        music_dir: Option<String>,
+        /// Print the counts and timings as JSON on stdout
+        #[arg(long)]
+        json: bool,
+    },
+    /// Rename album folders to match the artist/album in their tags
+    Rename {
+        /// Music directory
+        music_dir: Option<String>,
+        /// Instead of matching tags, move the year in album folder names to the front ("Abbey Road (1969)" -> "1969 - Abbey Road")
+        #[arg(long)]
+        normalize_album_folders: bool,
+        /// Show proposed renames without changing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Rename track files to a template built from their tags, e.g. "03 - Title.flac"
+    RenameFiles {
+        /// Music directory
+        music_dir: Option<String>,
+        /// File name template; fields: {track}, {disc}, {title}, {artist}, {albumartist}, {album}
+        #[arg(long, default_value = commands::rename_files::DEFAULT_FILE_TEMPLATE)]
+        file_template: FileTemplate,
+        /// Show proposed renames without changing anything
+        #[arg(long)]
+        dry_run: bool,
     },
     /// Reorganize misplaced files to their proper artist/album structure
     Reorganize {
         /// Music directory
         music_dir: Option<String>,
+        /// Copy files into place instead of moving them, leaving the originals untouched
+        #[arg(long)]
+        copy: bool,
+        /// Convert smart quotes and dashes in tags to ASCII before naming folders
+        #[arg(long)]
+        ascii_punctuation: bool,
+        /// Leave files without artist/album tags in place instead of filing them under Unknown
+        #[arg(long)]
+        strict: bool,
+        /// Name folders after the matching MusicBrainz artist/album when it is close to the tags
+        #[arg(long)]
+        mb_folder_names: bool,
+        /// Keep each original under this directory, at its old path, before moving it
+        #[arg(long, value_name = "DIR", conflicts_with = "copy")]
+        quarantine: Option<String>,
+        /// Place files by their folder names alone, without reading any tags (fast on untagged collections)
+        #[arg(long)]
+        path_only: bool,
+        /// Show what would be moved without changing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// With --dry-run, print the Artists tree the files would be filed into
+        #[arg(long, requires = "dry_run")]
+        tree: bool,
+        /// With --dry-run, list every file that would be moved
+        #[arg(short, long, requires = "dry_run")]
+        verbose: bool,
     },
     /// Import music files from an external directory into the music library
     Import {
-        /// Path to the directory containing files to import
-        import_path: String,
-        /// Music directory to import into
-        music_dir: Option<String>,
+        /// Directories containing files to import, then the music directory to import into
+        /// (the last of two or more paths, as with cp; the default music directory otherwise)
+        #[arg(required = true, value_name = "PATH")]
+        paths: Vec<String>,
+        /// Import into this root instead of the music directory; every path is then a source
+        #[arg(long)]
+        dest_root: Option<String>,
         /// Perform a dry run without actually importing files
         #[arg(long)]
         dry_run: bool,
+        /// With --dry-run, print the Artists tree the files would be filed into
+        #[arg(long, requires = "dry_run")]
+        tree: bool,
+        /// With --dry-run, list every file that would be imported
+        #[arg(short, long, requires = "dry_run")]
+        verbose: bool,
+        /// Convert smart quotes and dashes in tags to ASCII before naming folders
+        #[arg(long)]
+        ascii_punctuation: bool,
+        /// Keep the source files' access and modification times on the imported copies
+        #[arg(long)]
+        preserve_mtime: bool,
+        /// Delete source files once their imported copies are verified
+        #[arg(long)]
+        prune_after_import: bool,
+        /// With --prune-after-import, also remove the source folders left empty
+        #[arg(long, requires = "prune_after_import")]
+        prune_empty_dirs: bool,
+        /// Import files without usable artist/album tags under Unknown Artist/Unknown Album
+        #[arg(long)]
+        import_unknown: bool,
+        /// Split single-file albums with a cue sheet into one tagged file per track (needs ffmpeg)
+        #[arg(long)]
+        split_cue: bool,
+        /// Copy the album folders of a source that is itself a library (Artists/<artist>/<album>) whole, sidecars and all
+        #[arg(long)]
+        preserve_structure: bool,
+        /// With --preserve-structure, file those albums by their folder names instead of their tags
+        #[arg(long, requires = "preserve_structure")]
+        path_only: bool,
+        /// Exclude lossy files below this bitrate, in kbps; lossless files always pass
+        #[arg(long, value_name = "KBPS")]
+        min_bitrate: Option<u32>,
+        /// Exclude files below this sample rate, in Hz, lossless ones included
+        #[arg(long, value_name = "HZ")]
+        min_sample_rate: Option<u32>,
+        /// Write the files excluded for lacking metadata, with the reason, to this file
+        #[arg(long, value_name = "PATH")]
+        excluded_file: Option<PathBuf>,
     },
     /// Import music files with MusicBrainz integration and cover art fetching
     ImportEnhanced {
-        /// Path to the directory containing files to import
-        import_path: String,
-        /// Music directory to import into
-        music_dir: Option<String>,
+        /// Directories containing files to import, then the music directory to import into
+        /// (the last of two or more paths, as with cp; the default music directory otherwise)
+        #[arg(required = true, value_name = "PATH")]
+        paths: Vec<String>,
         /// Perform a dry run without actually importing files
         #[arg(long)]
         dry_run: bool,
+        /// Keep the source files' access and modification times on the imported copies
+        #[arg(long)]
+        preserve_mtime: bool,
+        /// Also save the release's back cover, booklet and other artwork next to the cover
+        #[arg(long)]
+        all_art: bool,
     },
     /// Import music from a CD
     Cd {
-        /// CD device path (e.g., /dev/cdrom)
-        device: String,
+        /// CD device path (e.g., /dev/cdrom), or "auto" to detect the drive; a directory given
+        /// on its own is the music directory instead
+        device: Option<String>,
         /// Music directory
         music_dir: Option<String>,
+        /// Re-read each track after ripping and report tracks whose second read differs
+        #[arg(long)]
+        verify: bool,
+        /// Leave leading and trailing silence out of the ripped files (the checksums still cover it)
+        #[arg(long)]
+        trim_silence: bool,
+        /// With --trim-silence, the level in dBFS below which audio counts as silence (default -60)
+        #[arg(
+            long,
+            value_name = "DB",
+            requires = "trim_silence",
+            allow_negative_numbers = true
+        )]
+        silence_threshold: Option<f64>,
+        /// With --trim-silence, milliseconds of silence kept at each end of a track (default 2000)
+        #[arg(long, value_name = "MS", requires = "trim_silence")]
+        silence_keep: Option<u64>,
     },
     /// Run all tasks (art, icons, albums, tracks)
     All {
@@ -286,6 +998,24 @@ enum Commands {
         /// Comma-separated list of subcommands to skip when running `all` (examples: sync,art,albums,tracks,organize,reorganize,import)
         #[arg(long, value_delimiter = ',')]
         skip: Vec<String>,
+        /// Continue an interrupted run, skipping the steps and albums it already finished
+        #[arg(long)]
+        resume: bool,
+        /// Organize and reorganize by folder names alone, without reading any tags
+        #[arg(long)]
+        path_only: bool,
+        /// Don't build the Albums/ symlink view
+        #[arg(long, visible_alias = "tracks-only", overrides_with = "album_links")]
+        no_album_links: bool,
+        /// Build the Albums/ symlink view (the default; overrides an earlier --no-album-links)
+        #[arg(long, overrides_with = "no_album_links")]
+        album_links: bool,
+        /// Don't build the Tracks/ symlink view
+        #[arg(long, visible_alias = "albums-only", overrides_with = "track_links")]
+        no_track_links: bool,
+        /// Build the Tracks/ symlink view (the default; overrides an earlier --no-track-links)
+        #[arg(long, overrides_with = "no_track_links")]
+        track_links: bool,
     },
 }
 
@@ -304,188 +1034,806 @@ fn main() -> Result<()> {
         .with_writer(non_blocking)
         .init();
 
-    ffmpeg::init().context("Failed to initialize ffmpeg")?;
-    magick_wand_genesis();
-    let cli = Cli::parse();
+    mfutil::cover_art::init_magick();
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
 
     let rt = tokio::runtime::Runtime::new()?;
-    let command_to_execute = cli.command.clone();
-    match command_to_execute {
-        Commands::Art { music_dir } => {
-            let music_dir = music_dir.unwrap_or_else(utils::get_default_music_dir);
-            let music_dir = shellexpand::tilde(&music_dir).into_owned();
+    let pool = build_thread_pool(cli.jobs)?;
+    let report = Arc::new(RunReport::new(
+        matches.subcommand_name().unwrap_or_default(),
+    ));
+    let naming = naming(&cli)?;
+    let result = run_command(
+        &cli,
+        &naming,
+        cover_settings(&cli),
+        &network(&cli),
+        &rt,
+        &pool,
+        &report,
+    );
+    mfutil::cover_art::shutdown_magick();
+
+    // Write the report even when the run failed, so the failing step is on record
+    if let Some(report_file) = &cli.report_file {
+        report.write(report_file)?;
+    }
+
+    result
+}
+
+/// Let the first Ctrl-C stop an import between files instead of killing it part way through
+/// one; a second Ctrl-C quits at once
+fn stop_between_files_on_ctrl_c(rt: &tokio::runtime::Runtime) {
+    rt.spawn(async {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            mfutil::directory::request_interrupt();
+            eprintln!("Stopping after the current file (press Ctrl-C again to quit now)");
+            if tokio::signal::ctrl_c().await.is_ok() {
+                std::process::exit(130);
+            }
+        }
+    });
+}
+
+// Log a command's closing summary and print it to stdout, so it is seen without a log file
+fn print_summary(quiet: bool, command: &str, summary: impl std::fmt::Display) {
+    tracing::info!("{}: {}", command, summary);
+    if !quiet {
+        println!("{}: {}", command, summary);
+    }
+}
+
+// Dry-run presentations of an organize-style plan: every file with --verbose, the resulting
+// Artists tree with --tree
+fn print_dry_run_plan(summary: &mfutil::directory::OrganizeSummary, tree: bool, verbose: bool) {
+    if verbose {
+        for planned in &summary.moves {
+            println!(
+                "Would place: {} -> {}",
+                planned.from.display(),
+                planned.to.display()
+            );
+        }
+    }
+    if tree {
+        print!("{}", summary.tree);
+    }
+}
+
+fn run_command(
+    cli: &Cli,
+    naming: &Naming,
+    cover: CoverSettings,
+    network: &Network,
+    rt: &tokio::runtime::Runtime,
+    pool: &Arc<ThreadPool>,
+    report: &Arc<RunReport>,
+) -> Result<()> {
+    let quiet = cli.quiet;
+    let force_unsafe_dir = cli.force_unsafe_dir;
+    match cli.command.clone() {
+        Commands::Art {
+            music_dir,
+            square_art,
+        } => {
+            let music_dir = utils::resolve_checked_music_dir(music_dir, force_unsafe_dir)?;
             // Handle artist images first
-            commands::art::extract_artist_art(&music_dir).context(format!(
-                "Failed to extract artist art for music directory: {}",
-                music_dir
-            ))?;
-            rt.handle()
-                .block_on(commands::art::fetch_placeholders(&music_dir))
+            report
+                .run_step("artist-art", false, || {
+                    commands::art::extract_artist_art(&music_dir, square_art, network)
+                })
+                .context(format!(
+                    "Failed to extract artist art for music directory: {}",
+                    music_dir
+                ))?;
+            report
+                .run_step("placeholders", false, || {
+                    rt.block_on(commands::art::fetch_placeholders(
+                        &music_dir,
+                        cover.format,
+                        network,
+                        commands::art::placeholder_delay(),
+                    ))
+                })
                 .context(format!(
                     "Failed to fetch placeholders for music directory: {}",
                     music_dir
                 ))?;
 
             // Set folder icons
-            run_folder_tui(
-                "Setting Folder Icons",
-                &music_dir,
-                commands::art::set_folder_icons_callback,
-            )
-            .context(format!(
-                "Failed to set folder icons for music directory: {}",
-                music_dir
-            ))?;
+            report
+                .run_step("folder-icons", false, || {
+                    run_folder_tui(
+                        "Setting Folder Icons",
+                        &music_dir,
+                        commands::art::set_folder_icons_callback,
+                    )
+                })
+                .context(format!(
+                    "Failed to set folder icons for music directory: {}",
+                    music_dir
+                ))?;
 
             // Extract album art
-            run_album_tui(
-                "Extracting Album Art",
-                &music_dir,
-                commands::art::process_single_album_art,
-            )
-            .context(format!(
-                "Failed to extract album art for music directory: {}",
-                music_dir
-            ))?;
+            report
+                .run_step("album-art", false, || {
+                    run_parallel_album_art_tui(&music_dir, art_dir(cli), cli.since, pool)
+                })
+                .context(format!(
+                    "Failed to extract album art for music directory: {}",
+                    music_dir
+                ))?;
+        }
+        Commands::EmbedArt {
+            music_dir,
+            dry_run,
+            art_clobber,
+            force,
+        } => {
+            let music_dir = utils::resolve_checked_music_dir(music_dir, force_unsafe_dir)?;
+            let clobber = match art_clobber {
+                Some(clobber) => clobber,
+                None if force => ArtClobber::Replace,
+                None => ArtClobber::default(),
+            };
+            let album_paths = get_all_album_paths(&music_dir)?;
+            report
+                .run_step("embed-art", dry_run, || {
+                    run_with_tui("Embedding Cover Art", album_paths, move |path: PathBuf| {
+                        let summary =
+                            commands::embed_art::embed_album_cover_art(&path, dry_run, clobber)?;
+                        Ok(format!("{}: {}", path.display(), summary))
+                    })
+                })
+                .context(format!(
+                    "Failed to embed cover art for music directory: {}",
+                    music_dir
+                ))?;
+        }
+        Commands::Albums {
+            music_dir,
+            dedupe_symlinks: true,
+            apply,
+            ..
+        } => {
+            let music_dir = utils::resolve_checked_music_dir(music_dir, force_unsafe_dir)?;
+            let duplicates = report
+                .run_step("dedupe-symlinks", !apply, || {
+                    commands::albums::dedupe_album_links(&music_dir, apply)
+                })
+                .context(format!(
+                    "Failed to dedupe album symlinks for music directory: {}",
+                    music_dir
+                ))?;
+            let action = if apply { "removed" } else { "found" };
+            print_summary(
+                quiet,
+                "Dedupe symlinks",
+                format!("{} duplicate album links {}", duplicates.len(), action),
+            );
         }
-        Commands::Albums { music_dir } => {
-            let music_dir = music_dir.unwrap_or_else(utils::get_default_music_dir);
-            let music_dir = shellexpand::tilde(&music_dir).into_owned();
+        Commands::Albums {
+            music_dir,
+            dry_run,
+            link_target_check,
+            ..
+        } => {
+            let music_dir = utils::resolve_checked_music_dir(music_dir, force_unsafe_dir)?;
             let music_dir_owned = music_dir.clone();
-            run_album_tui("Creating Album Symlinks", &music_dir, move |album_path| {
-                commands::albums::process_single_album_symlink(album_path, &music_dir_owned)
-            })
-            .context(format!(
-                "Failed to create album symlinks for music directory: {}",
-                music_dir
-            ))?;
+            let naming = naming.clone();
+            report
+                .run_step("albums", dry_run, || {
+                    run_album_tui("Creating Album Symlinks", &music_dir, move |album_path| {
+                        commands::albums::process_single_album_symlink(
+                            album_path,
+                            &music_dir_owned,
+                            &naming,
+                            dry_run,
+                            link_target_check,
+                        )
+                    })
+                })
+                .context(format!(
+                    "Failed to create album symlinks for music directory: {}",
+                    music_dir
+                ))?;
         }
-        Commands::Tracks { music_dir } => {
-            let music_dir = music_dir.unwrap_or_else(utils::get_default_music_dir);
-            let music_dir = shellexpand::tilde(&music_dir).into_owned();
+        Commands::Tracks { music_dir, dry_run } => {
+            let music_dir = utils::resolve_checked_music_dir(music_dir, force_unsafe_dir)?;
             let music_dir_owned = music_dir.clone();
-            run_track_tui("Creating Track Symlinks", &music_dir, move |track_path| {
-                commands::tracks::process_single_track_symlink(track_path, &music_dir_owned)
-            })
-            .context(format!(
-                "Failed to create track symlinks for music directory: {}",
-                music_dir
-            ))?;
+            report
+                .run_step("tracks", dry_run, || {
+                    run_track_tui("Creating Track Symlinks", &music_dir, move |track_path| {
+                        commands::tracks::process_single_track_symlink(
+                            track_path,
+                            &music_dir_owned,
+                            dry_run,
+                        )
+                    })
+                })
+                .context(format!(
+                    "Failed to create track symlinks for music directory: {}",
+                    music_dir
+                ))?;
         }
-        Commands::SyncWithArt { music_dir } => {
-            let music_dir = music_dir.unwrap_or_else(utils::get_default_music_dir);
-            let music_dir = shellexpand::tilde(&music_dir).into_owned();
-            run_all_sync_tags(&music_dir, &rt)?;
+        Commands::Links {
+            music_dir,
+            dry_run,
+            no_album_links,
+            no_track_links,
+            ..
+        } => {
+            let music_dir = utils::resolve_checked_music_dir(music_dir, force_unsafe_dir)?;
+            let music_dir_owned = music_dir.clone();
+            let naming = naming.clone();
+            let views = LinkViews {
+                albums: !no_album_links,
+                tracks: !no_track_links,
+            };
+            report
+                .run_step("links", dry_run, || {
+                    let stale = commands::links::prune_stale_links(&music_dir, views, dry_run)?;
+                    tracing::info!("Links: {} stale links found", stale.len());
+                    run_album_tui("Rebuilding Symlink Views", &music_dir, move |album_path| {
+                        commands::links::process_single_album_links(
+                            album_path,
+                            &music_dir_owned,
+                            &naming,
+                            views,
+                            dry_run,
+                        )
+                    })
+                })
+                .context(format!(
+                    "Failed to rebuild symlink views for music directory: {}",
+                    music_dir
+                ))?;
+        }
+        Commands::SyncWithArt {
+            music_dir,
+            force,
+            probe,
+            match_by_folder,
+            parallel_albums,
+            fix_encoding,
+            report_unmatched,
+            embed_lyrics,
+            normalize_featuring,
+            strip_featured,
+        } => {
+            let music_dir = utils::resolve_checked_music_dir(music_dir, force_unsafe_dir)?;
+            if fix_encoding {
+                // Repaired names give the MusicBrainz search something to match
+                let summary = report
+                    .run_step("fix-encoding", false, || {
+                        pool.install(|| {
+                            commands::fix_encoding::fix_tag_encoding(&music_dir, cli.backup_tags)
+                        })
+                    })
+                    .context(format!(
+                        "Failed to repair tag encoding in music directory: {}",
+                        music_dir
+                    ))?;
+                print_summary(quiet, "Fix encoding", summary);
+            }
+            if probe {
+                report.run_step("sync", true, || {
+                    run_all_probe_cover_art(&music_dir, match_by_folder, cli.since, network, rt)
+                })?;
+            } else {
+                let unmatched = report.run_step("sync", false, || {
+                    run_all_sync_tags(
+                        &music_dir,
+                        SyncOptions {
+                            force,
+                            match_by_folder,
+                            normalize_featuring,
+                            strip_featured_album_artist: strip_featured,
+                            cover,
+                            backup_tags: cli.backup_tags,
+                            embed_lyrics,
+                            sort_articles: cli.sort_articles.clone(),
+                            since: cli.since,
+                            network: network.clone(),
+                            parallel_albums: parallel_albums.map_or(1, NonZeroUsize::get),
+                        },
+                        rt,
+                        pool,
+                        None,
+                    )
+                })?;
+                if let Some(report_unmatched) = report_unmatched {
+                    commands::sync::write_unmatched_list(&report_unmatched, &unmatched)?;
+                }
+            }
         }
-        Commands::Reorganize { music_dir } => {
-            let music_dir = music_dir.unwrap_or_else(utils::get_default_music_dir);
-            let music_dir = shellexpand::tilde(&music_dir).into_owned();
-            commands::reorganize::reorganize_misplaced_files(&music_dir, false, false).context(
-                format!(
+        Commands::Reorganize {
+            music_dir,
+            copy,
+            ascii_punctuation,
+            strict,
+            mb_folder_names,
+            quarantine,
+            path_only,
+            dry_run,
+            tree,
+            verbose,
+        } => {
+            let music_dir = utils::resolve_checked_music_dir(music_dir, force_unsafe_dir)?;
+            let quarantine = quarantine.map(|dir| utils::expand_path(&dir));
+            let summary = report
+                .run_step("reorganize", dry_run, || {
+                    pool.install(|| {
+                        commands::reorganize::reorganize_misplaced_files(
+                            &music_dir,
+                            &OrganizeOptions {
+                                dry_run,
+                                quiet: false,
+                                ascii_punctuation,
+                                strict,
+                                mb_folder_names,
+                                path_only,
+                                naming: naming.clone(),
+                                since: cli.since,
+                                network: network.clone(),
+                            },
+                            copy,
+                            quarantine.as_deref().map(Path::new),
+                        )
+                    })
+                })
+                .context(format!(
                     "Failed to reorganize misplaced files in music directory: {}",
                     music_dir
-                ),
-            )?;
+                ))?;
+            print_dry_run_plan(&summary, tree, verbose);
+            print_summary(quiet, "Reorganize", summary);
         }
-        Commands::Import {
-            import_path,
+        Commands::RestoreTags { music_dir, dry_run } => {
+            let music_dir = utils::resolve_checked_music_dir(music_dir, force_unsafe_dir)?;
+            let summary = report
+                .run_step("restore-tags", dry_run, || {
+                    commands::restore_tags::restore_tags(&music_dir, dry_run)
+                })
+                .context(format!(
+                    "Failed to restore tags in music directory: {}",
+                    music_dir
+                ))?;
+            print_summary(quiet, "Restore tags", summary);
+        }
+        Commands::Verify { music_dir } => {
+            let music_dir = utils::resolve_checked_music_dir(music_dir, force_unsafe_dir)?;
+            let summary = report
+                .run_step("verify", false, || {
+                    pool.install(|| commands::verify::verify_checksums(&music_dir))
+                })
+                .context(format!(
+                    "Failed to verify checksums in music directory: {}",
+                    music_dir
+                ))?;
+            print_summary(quiet, "Verify", &summary);
+            if !summary.failures.is_empty() {
+                anyhow::bail!(
+                    "{} files failed checksum verification",
+                    summary.failures.len()
+                );
+            }
+        }
+        Commands::Check {
             music_dir,
+            ascii_punctuation,
+            json,
+        } => {
+            let music_dir = utils::resolve_checked_music_dir(music_dir, force_unsafe_dir)?;
+            let summary = report
+                .run_step("check", true, || {
+                    commands::check::check_tag_folder_mismatches(
+                        &music_dir,
+                        ascii_punctuation,
+                        naming,
+                    )
+                })
+                .context(format!(
+                    "Failed to check tags against folders in music directory: {}",
+                    music_dir
+                ))?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&summary)?);
+            } else {
+                for mismatch in &summary.mismatches {
+                    println!(
+                        "{}\n  in:       {}\n  expected: {}",
+                        mismatch.path.display(),
+                        mismatch.current.display(),
+                        mismatch.expected.display()
+                    );
+                }
+                print_summary(quiet, "Check", &summary);
+            }
+        }
+        Commands::Provenance { music_dir, json } => {
+            let music_dir = utils::resolve_checked_music_dir(music_dir, force_unsafe_dir)?;
+            let summary = report
+                .run_step("provenance", true, || {
+                    commands::provenance::provenance_report(&music_dir)
+                })
+                .context(format!(
+                    "Failed to report provenance in music directory: {}",
+                    music_dir
+                ))?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&summary)?);
+            } else {
+                for (source, count) in &summary.sources {
+                    println!("{:>8}  {}", count, source);
+                }
+                println!("{:>8}  external", summary.external);
+            }
+        }
+        Commands::Scan { music_dir, json } => {
+            let music_dir = utils::resolve_checked_music_dir(music_dir, force_unsafe_dir)?;
+            let summary = report
+                .run_step("scan", true, || {
+                    pool.install(|| commands::scan::scan_library(&music_dir))
+                })
+                .context(format!("Failed to scan music directory: {}", music_dir))?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&summary)?);
+            } else {
+                if !summary.slowest.is_empty() {
+                    println!("Slowest album folders:");
+                }
+                for dir in &summary.slowest {
+                    println!(
+                        "{:>8.3}s  {:>5} files  {}",
+                        dir.secs,
+                        dir.files,
+                        dir.path.display()
+                    );
+                }
+                print_summary(quiet, "Scan", &summary);
+            }
+        }
+        Commands::Rename {
+            music_dir,
+            normalize_album_folders,
             dry_run,
         } => {
-            let import_path = shellexpand::tilde(&import_path).into_owned();
-            let music_dir = music_dir.unwrap_or_else(utils::get_default_music_dir);
-            let music_dir = shellexpand::tilde(&music_dir).into_owned();
-            commands::import::import_and_organize_files(&import_path, &music_dir, dry_run, false)
+            let music_dir = utils::resolve_checked_music_dir(music_dir, force_unsafe_dir)?;
+            let renames = report
+                .run_step("rename", dry_run, || {
+                    if normalize_album_folders {
+                        commands::rename::normalize_album_folder_years(&music_dir, dry_run, false)
+                    } else {
+                        commands::rename::rename_folders_to_match_tags(
+                            &music_dir, naming, dry_run, false,
+                        )
+                    }
+                })
                 .context(format!(
-                "Failed to import files from {} to music directory: {}",
-                import_path, music_dir
-            ))?;
+                    "Failed to rename album folders in music directory: {}",
+                    music_dir
+                ))?;
+            print_summary(quiet, "Rename", format!("{} album folders", renames.len()));
         }
-        Commands::ImportEnhanced {
-            import_path,
+        Commands::RenameFiles {
             music_dir,
+            file_template,
             dry_run,
         } => {
-            let import_path = shellexpand::tilde(&import_path).into_owned();
-            let music_dir = music_dir.unwrap_or_else(utils::get_default_music_dir);
-            let music_dir = shellexpand::tilde(&music_dir).into_owned();
+            let music_dir = utils::resolve_checked_music_dir(music_dir, force_unsafe_dir)?;
+            let renames = report
+                .run_step("rename-files", dry_run, || {
+                    commands::rename_files::rename_files_to_template(
+                        &music_dir,
+                        &file_template,
+                        naming,
+                        dry_run,
+                        false,
+                    )
+                })
+                .context(format!(
+                    "Failed to rename track files in music directory: {}",
+                    music_dir
+                ))?;
+            print_summary(
+                quiet,
+                "Rename files",
+                format!("{} track files", renames.len()),
+            );
+        }
+        Commands::Import {
+            paths,
+            dest_root,
+            dry_run,
+            tree,
+            verbose,
+            ascii_punctuation,
+            preserve_mtime,
+            prune_after_import,
+            prune_empty_dirs,
+            import_unknown,
+            split_cue,
+            preserve_structure,
+            path_only,
+            min_bitrate,
+            min_sample_rate,
+            excluded_file,
+        } => {
+            let (import_paths, dest_root) = match dest_root {
+                Some(dest_root) => (
+                    paths.iter().map(|path| utils::expand_path(path)).collect(),
+                    utils::expand_path(&dest_root),
+                ),
+                None => utils::split_import_paths(paths),
+            };
+            utils::guard_music_dir(&dest_root, force_unsafe_dir)?;
+            stop_between_files_on_ctrl_c(rt);
+            let import_path_refs: Vec<&str> = import_paths.iter().map(String::as_str).collect();
+            let summary = report
+                .run_step("import", dry_run, || {
+                    commands::import::import_and_organize_files(
+                        &import_path_refs,
+                        &dest_root,
+                        &ImportOptions {
+                            dry_run,
+                            quiet: false,
+                            ascii_punctuation,
+                            preserve_mtime,
+                            prune: prune_after_import,
+                            prune_empty_dirs,
+                            import_unknown,
+                            split_cue,
+                            preserve_structure,
+                            path_only,
+                            naming: naming.clone(),
+                            quality: QualityFloor {
+                                min_bitrate,
+                                min_sample_rate,
+                            },
+                            provenance: cli.provenance,
+                            checksums: cli.checksums,
+                            ..Default::default()
+                        },
+                    )
+                })
+                .context(format!(
+                    "Failed to import files from {} to destination root: {}",
+                    import_paths.join(", "),
+                    dest_root
+                ))?;
+            print_dry_run_plan(&summary, tree, verbose);
+            print_summary(quiet, "Import", &summary);
+            if let Some(excluded_file) = excluded_file {
+                mfutil::directory::write_file_list(&excluded_file, &summary.excluded)?;
+            }
+        }
+        Commands::ImportEnhanced {
+            paths,
+            dry_run,
+            preserve_mtime,
+            all_art,
+        } => {
+            let (import_paths, music_dir) = utils::split_import_paths(paths);
+            utils::guard_music_dir(&music_dir, force_unsafe_dir)?;
             let cancel_token = Arc::new(AtomicBool::new(true));
             let (tx, rx) = mpsc::channel();
             let rt_handle = rt.handle().clone();
             let _thread_cancel_token = cancel_token.clone();
-            let import_path_clone = import_path.clone();
+            let import_paths_clone = import_paths.clone();
             let music_dir_clone = music_dir.clone();
+            let naming = naming.clone();
+            let provenance = cli.provenance;
+            let checksums = cli.checksums;
+            let backup_tags = cli.backup_tags;
+            let sort_articles = cli.sort_articles.clone();
+            let network = network.clone();
             let handle = thread::spawn(move || -> Result<()> {
                 rt_handle.block_on(
                     commands::import::import_and_organize_files_with_musicbrainz(
-                        &import_path_clone,
+                        &import_paths_clone
+                            .iter()
+                            .map(String::as_str)
+                            .collect::<Vec<_>>(),
                         &music_dir_clone,
-                        dry_run,
-                        false,
+                        &ImportOptions {
+                            dry_run,
+                            preserve_mtime,
+                            naming,
+                            cover,
+                            provenance,
+                            checksums,
+                            backup_tags,
+                            sort_articles,
+                            network,
+                            ..Default::default()
+                        },
+                        all_art,
                         tx,
                     ),
                 )
             });
-            tui::run_tui(rx, cancel_token).map_err(anyhow::Error::from)?;
-            handle.join().unwrap()?;
+            report.run_step("import-enhanced", dry_run, || {
+                tui::run_tui(rx, cancel_token).map_err(anyhow::Error::from)?;
+                handle.join().unwrap()
+            })?;
         }
-        Commands::Cd { device, music_dir } => {
-            let music_dir = music_dir.unwrap_or_else(utils::get_default_music_dir);
-            let music_dir = shellexpand::tilde(&music_dir).into_owned();
+        Commands::Cd {
+            device,
+            music_dir,
+            verify,
+            trim_silence,
+            silence_threshold,
+            silence_keep,
+        } => {
+            let (device, music_dir) = utils::split_cd_paths(device, music_dir);
+            let music_dir = utils::resolve_checked_music_dir(music_dir, force_unsafe_dir)?;
+            let trim_silence = trim_silence.then(|| {
+                let default = mfutil::cd::SilenceTrim::default();
+                mfutil::cd::SilenceTrim {
+                    threshold_db: silence_threshold.unwrap_or(default.threshold_db),
+                    keep: silence_keep.map_or(default.keep, Duration::from_millis),
+                }
+            });
             let cancel_token = Arc::new(AtomicBool::new(true));
             let (tx, rx) = mpsc::channel();
             let rt_handle = rt.handle().clone();
             let _thread_cancel_token = cancel_token.clone();
             let device_clone = device.clone();
             let music_dir_clone = music_dir.clone();
+            let naming = naming.clone();
+            let options = mfutil::cd::RipOptions {
+                verify,
+                trim_silence,
+                provenance: cli.provenance,
+                checksums: cli.checksums,
+            };
+            let network = network.clone();
             let handle = thread::spawn(move || -> Result<()> {
-                rt_handle.block_on(commands::cd::import_cd(&device_clone, &music_dir_clone, tx))
+                rt_handle.block_on(commands::cd::import_cd(
+                    &device_clone,
+                    &music_dir_clone,
+                    &naming,
+                    &cover,
+                    &network,
+                    options,
+                    tx,
+                ))
             });
-            tui::run_tui(rx, cancel_token).map_err(anyhow::Error::from)?;
-            handle.join().unwrap()?;
+            report.run_step("cd", false, || {
+                tui::run_tui(rx, cancel_token).map_err(anyhow::Error::from)?;
+                handle.join().unwrap()
+            })?;
         }
-        Commands::All { music_dir, skip } => {
-            let music_dir = music_dir.unwrap_or_else(utils::get_default_music_dir);
-            let music_dir = shellexpand::tilde(&music_dir).into_owned();
-            use std::collections::HashSet;
+        Commands::All {
+            music_dir,
+            skip,
+            resume,
+            path_only,
+            no_album_links,
+            no_track_links,
+            ..
+        } => {
+            let music_dir = utils::resolve_checked_music_dir(music_dir, force_unsafe_dir)?;
             let skip_set: HashSet<String> = skip.into_iter().map(|s| s.to_lowercase()).collect();
+            let views = LinkViews {
+                albums: !no_album_links,
+                tracks: !no_track_links,
+            };
 
-            // 1. Sync Tags with MusicBrainz (first step)
-            if !skip_set.contains("sync") {
-                run_all_sync_tags(&music_dir, &rt)?;
-            }
+            let checkpoint_path = checkpoint::checkpoint_path(&music_dir);
+            let resume =
+                resume || (checkpoint_path.exists() && checkpoint::prompt_resume(&music_dir)?);
+            let checkpoint = Arc::new(Checkpoint::open(checkpoint_path, &music_dir, resume)?);
 
-            // 2. Handle artist images
-            if !skip_set.contains("art") {
-                run_all_artist_art(&music_dir, &rt)?;
+            let result = run_all(
+                &music_dir,
+                &skip_set,
+                &AllOptions {
+                    views,
+                    organize: OrganizeOptions {
+                        path_only,
+                        naming: naming.clone(),
+                        since: cli.since,
+                        network: network.clone(),
+                        ..Default::default()
+                    },
+                    cover,
+                    art_dir: art_dir(cli),
+                    since: cli.since,
+                    network: network.clone(),
+                    sync: SyncOptions {
+                        cover,
+                        backup_tags: cli.backup_tags,
+                        sort_articles: cli.sort_articles.clone(),
+                        since: cli.since,
+                        network: network.clone(),
+                        ..Default::default()
+                    },
+                    import: ImportOptions {
+                        provenance: cli.provenance,
+                        checksums: cli.checksums,
+                        network: network.clone(),
+                        ..Default::default()
+                    },
+                },
+                &checkpoint,
+                rt,
+                pool,
+                report,
+            );
+            // Keep the checkpoint after a cancel or failure so the next run can resume
+            if result.is_ok() && !tui::cancelled() {
+                checkpoint.clear()?;
+            } else {
+                checkpoint.save()?;
             }
+            result?;
+        }
+    }
 
-            // 3. Setting Folder Icons
-            if !skip_set.contains("icons") && !skip_set.contains("art") {
-                run_all_folder_icons(&music_dir)?;
-            }
+    Ok(())
+}
 
-            // 4. Extracting Album Art
-            if !skip_set.contains("art") {
-                run_all_album_art(&music_dir)?;
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
 
-            // 5. Creating Album Symlinks
-            if !skip_set.contains("albums") {
-                run_all_album_symlinks(&music_dir)?;
-            }
+    #[test]
+    fn test_failed_all_step_does_not_stop_later_steps() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let checkpoint = Checkpoint::open(temp_dir.path().join("music.json"), "/srv/music", false)?;
+        let report = RunReport::new("all");
+        let steps = StepResults::default();
+        let mut ran = Vec::new();
 
-            // 6. Creating Track Symlinks
-            if !skip_set.contains("tracks") {
-                run_all_track_symlinks(&music_dir)?;
-            }
+        run_all_step("sync", &checkpoint, &report, &steps, || {
+            ran.push("sync");
+            Ok(())
+        });
+        run_all_step("artist-art", &checkpoint, &report, &steps, || {
+            ran.push("artist-art");
+            Err::<(), _>(anyhow::anyhow!("network unreachable"))
+        });
+        run_all_step("tracks", &checkpoint, &report, &steps, || {
+            ran.push("tracks");
+            Ok(())
+        });
 
-            // 7. Organizing Music Library
-            if !skip_set.contains("organize") {
-                run_all_organize(&music_dir)?;
-            }
-        }
+        assert_eq!(ran, ["sync", "artist-art", "tracks"]);
+        assert!(checkpoint.step_done("sync"));
+        // The failed step is retried when the run is resumed
+        assert!(!checkpoint.step_done("artist-art"));
+        assert!(checkpoint.step_done("tracks"));
+        assert_eq!(
+            steps.failures(),
+            [("artist-art".to_string(), "network unreachable".to_string())]
+        );
+        let error = steps.finish().unwrap_err().to_string();
+        assert_eq!(
+            error,
+            "1 of 3 steps failed: artist-art (network unreachable)"
+        );
+
+        Ok(())
     }
 
-    Ok(())
+    #[test]
+    fn test_cd_takes_the_device_then_the_music_dir() {
+        let cli = Cli::try_parse_from(["mfutil", "cd", "/dev/sr0", "/srv/music"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Cd { device: Some(device), music_dir: Some(music_dir), .. }
+                if device == "/dev/sr0" && music_dir == "/srv/music"
+        ));
+
+        let cli = Cli::try_parse_from(["mfutil", "cd", "/dev/sr1"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Cd { device: Some(device), music_dir: None, .. } if device == "/dev/sr1"
+        ));
+    }
 }
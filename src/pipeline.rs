@@ -0,0 +1,88 @@
+use anyhow::Result;
+use mfutil::commands::art::ArtDir;
+use mfutil::commands::import::ImportOptions;
+use mfutil::commands::links::LinkViews;
+use mfutil::commands::organize::OrganizeOptions;
+use mfutil::commands::sync::SyncOptions;
+use mfutil::cover_art::CoverSettings;
+use mfutil::musicbrainz::Network;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// How an `all` run builds the library
+#[derive(Debug, Clone)]
+pub struct AllOptions {
+    /// The symlink views built, by their own steps and by organize's
+    pub views: LinkViews,
+    /// Organize's steps, and the album links, are run with these
+    pub organize: OrganizeOptions,
+    /// How cover art and placeholder icons fetched by the art steps are saved
+    pub cover: CoverSettings,
+    /// The album art step also collects art here (`--art-dir`)
+    pub art_dir: Option<ArtDir>,
+    /// The art steps fetch over this
+    pub network: Network,
+    /// The album art step only extracts from albums with files modified after this (`--since`)
+    pub since: Option<SystemTime>,
+    /// The sync steps sync with these
+    pub sync: SyncOptions,
+    /// Organize's import step imports with these, named as `organize` says
+    pub import: ImportOptions,
+}
+
+/// How each step of an `all` run ended, so one failed step doesn't stop the ones after it
+#[derive(Debug, Default)]
+pub struct StepResults {
+    /// Step names in the order they finished, with the error of those that failed
+    steps: Mutex<Vec<(String, Option<String>)>>,
+}
+
+impl StepResults {
+    /// Record how a step ended, logging its error so the run can carry on without it
+    pub fn record(&self, step: &str, result: Result<()>) {
+        let error = result.err().map(|e| format!("{:#}", e));
+        if let Some(error) = &error {
+            tracing::error!(
+                "Step {} failed, continuing with the remaining steps: {}",
+                step,
+                error
+            );
+        }
+        self.steps.lock().unwrap().push((step.to_string(), error));
+    }
+
+    /// The steps that failed, with their errors
+    pub fn failures(&self) -> Vec<(String, String)> {
+        self.steps
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|(step, error)| Some((step.clone(), error.clone()?)))
+            .collect()
+    }
+
+    /// Log how many steps succeeded and failed, returning an error that names the failed ones
+    pub fn finish(&self) -> Result<()> {
+        let total = self.steps.lock().unwrap().len();
+        let failures = self.failures();
+        tracing::info!(
+            "all: {} steps succeeded, {} failed",
+            total - failures.len(),
+            failures.len()
+        );
+        if failures.is_empty() {
+            return Ok(());
+        }
+
+        let failed: Vec<String> = failures
+            .iter()
+            .map(|(step, error)| format!("{} ({})", step, error))
+            .collect();
+        anyhow::bail!(
+            "{} of {} steps failed: {}",
+            failures.len(),
+            total,
+            failed.join("; ")
+        )
+    }
+}
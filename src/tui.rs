@@ -5,23 +5,175 @@ use crossterm::{
     style::Print,
     terminal::{self, Clear, ClearType},
 };
+use mfutil::musicbrainz::{ReleaseCandidate, ReleaseChoice, ReleaseChooser};
+use mfutil::progress::ProgressTally;
+use std::collections::VecDeque;
 use std::io::{self, stdout, Write};
 use std::sync::{
     atomic::{AtomicBool, Ordering},
-    mpsc, Arc,
+    mpsc, Arc, Mutex,
 };
 use std::time::Duration;
 
 const TOTAL_PROGRESS_WIDTH: u16 = 50;
 
+/// Set once the user cancels any TUI, so multi-step runs know to stop
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+/// Release choices waiting for the running TUI to put to the user
+static CHOICES: Mutex<ChoiceQueue> = Mutex::new(ChoiceQueue {
+    tuis_running: 0,
+    pending: VecDeque::new(),
+});
+
+struct ChoiceQueue {
+    /// TUIs on screen; a step of `all` can run its own while the outer one is still up
+    tuis_running: usize,
+    pending: VecDeque<PendingChoice>,
+}
+
+/// A release choice for the user, and where to send their answer
+struct PendingChoice {
+    artist: String,
+    album: String,
+    candidates: Vec<ReleaseCandidate>,
+    reply: mpsc::Sender<ReleaseChoice>,
+}
+
+/// Whether the user has cancelled a TUI during this run
+pub fn cancelled() -> bool {
+    CANCELLED.load(Ordering::SeqCst)
+}
+
+/// Asks the user to pick among several matching releases on the running TUI's screen
+/// The choice is made once per album and tag group. Without a TUI on screen, the first
+/// release is used
+pub struct TuiReleaseChooser;
+
+impl ReleaseChooser for TuiReleaseChooser {
+    fn choose(&self, artist: &str, album: &str, candidates: &[ReleaseCandidate]) -> ReleaseChoice {
+        let (reply, answer) = mpsc::channel();
+        {
+            let mut choices = CHOICES.lock().unwrap_or_else(|e| e.into_inner());
+            if choices.tuis_running == 0 {
+                return ReleaseChoice::Pick(0);
+            }
+            choices.pending.push_back(PendingChoice {
+                artist: artist.to_string(),
+                album: album.to_string(),
+                candidates: candidates.to_vec(),
+                reply,
+            });
+        }
+        // A TUI that closes without answering drops `reply`, which skips the album
+        answer.recv().unwrap_or(ReleaseChoice::Skip)
+    }
+}
+
+/// Counts a TUI as on screen until dropped, errors out of [`run_tui`] included, so release
+/// choices are never left waiting on a screen that is gone
+struct TuiRunning;
+
+impl TuiRunning {
+    fn start() -> Self {
+        CHOICES
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .tuis_running += 1;
+        TuiRunning
+    }
+}
+
+impl Drop for TuiRunning {
+    fn drop(&mut self) {
+        let mut choices = CHOICES.lock().unwrap_or_else(|e| e.into_inner());
+        choices.tuis_running -= 1;
+        // A nested TUI closing leaves the outer one to answer what is still pending
+        if choices.tuis_running == 0 {
+            choices.pending.clear();
+        }
+    }
+}
+
+// Cut `line` to fit the terminal, so every line of a prompt takes exactly one row
+fn fit_to_width(line: &str, width: u16) -> String {
+    line.chars()
+        .take(usize::from(width.saturating_sub(1)))
+        .collect()
+}
+
+/// Show a release choice below the progress line and wait for the user's answer
+/// Returns None if the user quit the TUI instead
+fn prompt_release_choice(
+    stdout: &mut io::Stdout,
+    choice: &PendingChoice,
+) -> io::Result<Option<ReleaseChoice>> {
+    let count = choice.candidates.len();
+    let rows = count as u16 + 2;
+    let mut selected = 0;
+    execute!(stdout, Print("\r\n"))?;
+
+    let answer = loop {
+        let (width, _) = terminal::size()?;
+        let mut lines = vec![format!(
+            "Several MusicBrainz releases match {} - {}:",
+            choice.artist, choice.album
+        )];
+        for (i, candidate) in choice.candidates.iter().enumerate() {
+            let marker = if i == selected { '>' } else { ' ' };
+            lines.push(format!("{} {}. {}", marker, i + 1, candidate));
+        }
+        lines.push("Up/Down to move, 1-9 or Enter to use a release, s to skip".to_string());
+        execute!(stdout, Clear(ClearType::FromCursorDown))?;
+        for line in &lines {
+            execute!(stdout, Print(fit_to_width(line, width)), Print("\r\n"))?;
+        }
+        stdout.flush()?;
+
+        if let Event::Key(KeyEvent {
+            code, modifiers, ..
+        }) = event::read()?
+        {
+            match code {
+                KeyCode::Char('c') if modifiers == KeyModifiers::CONTROL => break None,
+                KeyCode::Char('q') => break None,
+                KeyCode::Up | KeyCode::Char('k') => selected = selected.saturating_sub(1),
+                KeyCode::Down | KeyCode::Char('j') => selected = (selected + 1).min(count - 1),
+                KeyCode::Enter => break Some(ReleaseChoice::Pick(selected)),
+                KeyCode::Char('s') | KeyCode::Esc => break Some(ReleaseChoice::Skip),
+                KeyCode::Char(digit) => {
+                    if let Some(index) =
+                        digit.to_digit(10).and_then(|d| (d as usize).checked_sub(1))
+                    {
+                        if index < count {
+                            break Some(ReleaseChoice::Pick(index));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        execute!(stdout, cursor::MoveUp(rows))?;
+    };
+
+    // Clear the prompt and go back to the progress line
+    execute!(
+        stdout,
+        cursor::MoveUp(rows),
+        Clear(ClearType::FromCursorDown),
+        cursor::MoveUp(1)
+    )?;
+    Ok(answer)
+}
+
 pub fn run_tui(rx: mpsc::Receiver<String>, cancel_token: Arc<AtomicBool>) -> Result<(), io::Error> {
     let mut stdout = stdout();
     terminal::enable_raw_mode()?;
     execute!(stdout, cursor::Hide)?;
+    let _running = TuiRunning::start();
 
     let mut last_message = String::new();
-    let mut total_files = 0;
-    let mut completed_files = 0;
+    let mut tally = ProgressTally::default();
 
     loop {
         if !cancel_token.load(Ordering::SeqCst) {
@@ -37,35 +189,49 @@ pub fn run_tui(rx: mpsc::Receiver<String>, cancel_token: Arc<AtomicBool>) -> Res
                     || code == KeyCode::Char('q')
                 {
                     cancel_token.store(false, Ordering::SeqCst);
+                    CANCELLED.store(true, Ordering::SeqCst);
+                    mfutil::directory::request_interrupt();
                     break;
                 }
             }
         }
 
-        match rx.try_recv() {
-            Ok(msg) => {
-                if msg.starts_with("TOTAL_FILES:") {
-                    if let Ok(num) = msg.replace("TOTAL_FILES:", "").parse::<usize>() {
-                        total_files = num;
+        let pending = CHOICES
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .pending
+            .pop_front();
+        if let Some(choice) = pending {
+            let Some(answer) = prompt_release_choice(&mut stdout, &choice)? else {
+                cancel_token.store(false, Ordering::SeqCst);
+                CANCELLED.store(true, Ordering::SeqCst);
+                mfutil::directory::request_interrupt();
+                break;
+            };
+            let _ = choice.reply.send(answer);
+        }
+
+        // Take everything sent since the last tick, so parallel workers can't outpace the bar
+        let mut disconnected = false;
+        loop {
+            match rx.try_recv() {
+                Ok(msg) => {
+                    if tally.record(&msg) {
+                        last_message = msg;
                     }
-                } else if msg.starts_with("COMPLETED:") {
-                    completed_files += 1;
-                    last_message = msg;
-                } else {
-                    last_message = msg;
+                }
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    disconnected = true;
+                    break;
                 }
             }
-            Err(mpsc::TryRecvError::Empty) => {}
-            Err(mpsc::TryRecvError::Disconnected) => {
-                break;
-            }
+        }
+        if disconnected {
+            break;
         }
 
-        let main_progress = if total_files > 0 {
-            completed_files as f32 / total_files as f32
-        } else {
-            0.0
-        };
+        let main_progress = tally.fraction();
 
         let filled_width = (main_progress * TOTAL_PROGRESS_WIDTH as f32) as u16;
         let empty_width = TOTAL_PROGRESS_WIDTH.saturating_sub(filled_width);
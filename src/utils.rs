@@ -1,98 +1,105 @@
 use anyhow::Result;
+use mfutil::audio::is_audio_file;
 use std::fs;
 use std::path::Path;
 use walkdir::WalkDir;
 
+/// Folder levels searched for audio files when deciding whether a directory is a music library:
+/// enough for `<artist>/<album>/<disc>/<track>`
+const LIBRARY_PROBE_DEPTH: usize = 4;
+
 pub fn get_default_music_dir() -> String {
-    std::env::var("XDG_MUSIC_DIR").unwrap_or_else(|_| shellexpand::tilde("~/Music").into_owned())
+    std::env::var("XDG_MUSIC_DIR").unwrap_or_else(|_| "~/Music".to_string())
 }
 
-// Supported audio file extensions
-const AUDIO_EXTENSIONS: &[&str] = &["mp3", "flac", "m4a", "ogg"];
-
-fn is_audio_file(path: &Path) -> bool {
-    path.extension()
-        .and_then(|ext| ext.to_str())
-        .is_some_and(|ext| AUDIO_EXTENSIONS.contains(&ext))
+/// Expand `~` and make a user-supplied path absolute
+/// This happens once at the CLI boundary; commands use the paths they're given as-is
+pub fn expand_path(path: &str) -> String {
+    let expanded = shellexpand::tilde(path);
+    std::path::absolute(expanded.as_ref())
+        .map(|path| path.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| expanded.into_owned())
 }
 
-fn contains_audio_files(path: &Path) -> bool {
-    if let Ok(entries) = fs::read_dir(path) {
-        for entry in entries.filter_map(|e| e.ok()) {
-            let entry_path = entry.path();
-            if entry_path.is_file() && is_audio_file(&entry_path) {
-                return true;
-            }
-        }
-    }
-    false
+/// The music directory given on the command line, or the default, expanded with [`expand_path`]
+pub fn resolve_music_dir(music_dir: Option<String>) -> String {
+    expand_path(&music_dir.unwrap_or_else(get_default_music_dir))
 }
 
-pub fn get_all_album_paths(music_dir: &str) -> Result<Vec<std::path::PathBuf>> {
-    let music_dir = shellexpand::tilde(music_dir).to_string();
-    let artists_path = Path::new(&music_dir).join("Artists");
-    let mut album_paths = Vec::new();
-
-    if !artists_path.exists() {
-        return Ok(album_paths);
-    }
+/// [`resolve_music_dir`], refusing a directory that [`check_music_dir`] finds unsafe unless `force`
+pub fn resolve_checked_music_dir(music_dir: Option<String>, force: bool) -> Result<String> {
+    let music_dir = resolve_music_dir(music_dir);
+    guard_music_dir(&music_dir, force)?;
+    Ok(music_dir)
+}
 
-    for artist_entry in fs::read_dir(&artists_path)?.filter_map(|e| e.ok()) {
-        let artist_path = artist_entry.path();
-        if artist_path.is_dir() {
-            for album_entry in fs::read_dir(&artist_path)?.filter_map(|e| e.ok()) {
-                let album_path = album_entry.path();
-                if album_path.is_dir() && contains_audio_files(&album_path) {
-                    album_paths.push(album_path);
-                }
-            }
-        }
+/// Refuse `music_dir` as [`check_music_dir`] would, unless `force` (`--force-unsafe-dir`) is set
+pub fn guard_music_dir(music_dir: &str, force: bool) -> Result<()> {
+    if force {
+        return Ok(());
     }
-    Ok(album_paths)
+    let home = shellexpand::tilde("~");
+    check_music_dir(Path::new(music_dir), Path::new(home.as_ref()))
 }
 
-pub fn get_all_track_paths(music_dir: &str) -> Result<Vec<std::path::PathBuf>> {
-    let music_dir = shellexpand::tilde(music_dir).to_string();
-    let artists_path = Path::new(&music_dir).join("Artists");
-    let mut track_paths = Vec::new();
+/// Refuse a music directory that organizing could wreak havoc in: the filesystem root, the
+/// `home` directory, or a folder with files in it but neither an `Artists` folder nor audio
+/// files near the top, as a mistyped path would be
+/// A directory that doesn't exist yet or is empty is fine, since there is nothing to move
+pub fn check_music_dir(music_dir: &Path, home: &Path) -> Result<()> {
+    let canonical = |path: &Path| fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let dir = canonical(music_dir);
+    let problem = if dir.parent().is_none() {
+        "it is the filesystem root"
+    } else if dir == canonical(home) {
+        "it is your home directory"
+    } else if !looks_like_music_library(&dir) {
+        "it has neither an Artists folder nor any audio files"
+    } else {
+        return Ok(());
+    };
+    Err(anyhow::anyhow!(
+        "Refusing to use '{}' as the music directory: {}. Pass --force-unsafe-dir if this is really what you want.",
+        music_dir.display(),
+        problem
+    ))
+}
 
-    if !artists_path.exists() {
-        return Ok(track_paths);
-    }
+fn looks_like_music_library(dir: &Path) -> bool {
+    let Ok(mut entries) = fs::read_dir(dir) else {
+        return true;
+    };
+    entries.next().is_none()
+        || dir.join("Artists").is_dir()
+        || WalkDir::new(dir)
+            .max_depth(LIBRARY_PROBE_DEPTH)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .any(|e| e.file_type().is_file() && is_audio_file(e.path()))
+}
 
-    for artist_entry in fs::read_dir(&artists_path)?.filter_map(|e| e.ok()) {
-        let artist_path = artist_entry.path();
-        if artist_path.is_dir() {
-            for album_entry in fs::read_dir(&artist_path)?.filter_map(|e| e.ok()) {
-                let album_path = album_entry.path();
-                if album_path.is_dir() {
-                    for track_entry in fs::read_dir(&album_path)?.filter_map(|e| e.ok()) {
-                        let track_path = track_entry.path();
-                        if track_path.is_file() && is_audio_file(&track_path) {
-                            track_paths.push(track_path);
-                        }
-                    }
-                }
-            }
-        }
-    }
-    Ok(track_paths)
+/// Split an import command's positional paths into the import paths and the music directory
+/// Like `cp`, the last of two or more paths is the destination; a lone path is the only source
+/// and the music directory is the default. Every path is expanded with [`expand_path`]
+pub fn split_import_paths(mut paths: Vec<String>) -> (Vec<String>, String) {
+    let music_dir = if paths.len() > 1 { paths.pop() } else { None };
+    let import_paths = paths.iter().map(|path| expand_path(path)).collect();
+    (import_paths, resolve_music_dir(music_dir))
 }
 
-pub fn get_all_folder_paths(music_dir: &str) -> Result<Vec<std::path::PathBuf>> {
-    let music_dir = shellexpand::tilde(music_dir);
-    let mut folder_paths = Vec::new();
-
-    for entry in WalkDir::new(music_dir.as_ref())
-        .follow_links(true)
-        .into_iter()
-        .filter_map(|e| e.ok())
-    {
-        if entry.file_type().is_dir() {
-            folder_paths.push(entry.path().to_path_buf());
+/// Split the `cd` command's positional paths into the CD device and the music directory
+/// The device comes first and defaults to "auto"; a directory given on its own is taken as the
+/// music directory instead, with the drive detected
+pub fn split_cd_paths(
+    device: Option<String>,
+    music_dir: Option<String>,
+) -> (String, Option<String>) {
+    match (device, music_dir) {
+        (Some(device), None) if Path::new(&expand_path(&device)).is_dir() => {
+            ("auto".to_string(), Some(device))
         }
+        (device, music_dir) => (device.unwrap_or_else(|| "auto".to_string()), music_dir),
     }
-    Ok(folder_paths)
 }
 
 #[cfg(test)]
@@ -102,174 +109,94 @@ mod tests {
     use std::io::Write;
     use tempfile::tempdir;
 
-    #[test]
-    fn test_get_all_album_paths() -> Result<()> {
-        let tmp_dir = tempdir()?;
-        let music_root = tmp_dir.path().join("Music");
-        let artists_dir = music_root.join("Artists");
+    /// Held by tests that read or change HOME, so `~` expands the same way for the whole test
+    static HOME_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
 
-        fs::create_dir_all(&artists_dir)?;
-
-        // Create a dummy music library structure
-        let artist1_dir = artists_dir.join("Artist1");
-        fs::create_dir(&artist1_dir)?;
-        let album1_1_dir = artist1_dir.join("Album1_1");
-        fs::create_dir(&album1_1_dir)?;
-        fs::File::create(album1_1_dir.join("track1.mp3"))?.write_all(b"test")?;
-        let album1_2_dir = artist1_dir.join("Album1_2");
-        fs::create_dir(&album1_2_dir)?;
-        fs::File::create(album1_2_dir.join("track2.flac"))?.write_all(b"test")?;
-
-        let artist2_dir = artists_dir.join("Artist2");
-        fs::create_dir(&artist2_dir)?;
-        let album2_1_dir = artist2_dir.join("Album2_1");
-        fs::create_dir(&album2_1_dir)?;
-        fs::File::create(album2_1_dir.join("track3.m4a"))?.write_all(b"test")?;
-
-        // Add a non-album directory to ensure it's ignored
-        let singles_dir = artist1_dir.join("Singles");
-        fs::create_dir(&singles_dir)?;
-        fs::File::create(singles_dir.join("info.txt"))?.write_all(b"test")?;
-
-        let expected_paths = vec![
-            album1_1_dir.clone(),
-            album1_2_dir.clone(),
-            album2_1_dir.clone(),
-        ];
-
-        let mut actual_paths = get_all_album_paths(music_root.to_str().unwrap())?;
-        actual_paths.sort(); // Sort to ensure consistent order for comparison
-
-        assert_eq!(actual_paths, expected_paths);
-
-        Ok(())
+    #[test]
+    fn test_resolve_music_dir_expands_tilde() {
+        let _home = HOME_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        // This is the path handed to every command, including `cd`'s import_cd
+        let music_dir = resolve_music_dir(Some("~/Music".to_string()));
+        let music_path = Path::new(&music_dir);
+
+        assert!(music_path.is_absolute());
+        assert!(!music_dir.starts_with('~'));
+        if let Ok(home) = std::env::var("HOME") {
+            assert_eq!(music_path, Path::new(&home).join("Music"));
+        }
     }
 
     #[test]
-    fn test_get_all_track_paths() -> Result<()> {
-        let tmp_dir = tempdir()?;
-        let music_root = tmp_dir.path().join("Music");
-        let artists_dir = music_root.join("Artists");
-
-        fs::create_dir_all(&artists_dir)?;
-
-        // Create a dummy music library structure
-        let artist1_dir = artists_dir.join("Artist1");
-        fs::create_dir(&artist1_dir)?;
-        let album1_1_dir = artist1_dir.join("Album1_1");
-        fs::create_dir(&album1_1_dir)?;
-        fs::File::create(album1_1_dir.join("track1.mp3"))?.write_all(b"test")?;
-        fs::File::create(album1_1_dir.join("track2.flac"))?.write_all(b"test")?;
-        fs::File::create(album1_1_dir.join("info.txt"))?.write_all(b"test")?; // Non-audio file
-
-        let artist2_dir = artists_dir.join("Artist2");
-        fs::create_dir(&artist2_dir)?;
-        let album2_1_dir = artist2_dir.join("Album2_1");
-        fs::create_dir(&album2_1_dir)?;
-        fs::File::create(album2_1_dir.join("track3.m4a"))?.write_all(b"test")?;
-        fs::File::create(album2_1_dir.join("track4.ogg"))?.write_all(b"test")?;
-
-        let expected_paths = vec![
-            album1_1_dir.join("track1.mp3"),
-            album1_1_dir.join("track2.flac"),
-            album2_1_dir.join("track3.m4a"),
-            album2_1_dir.join("track4.ogg"),
-        ];
-
-        let mut actual_paths = get_all_track_paths(music_root.to_str().unwrap())?;
-        actual_paths.sort(); // Sort to ensure consistent order for comparison
-
-        assert_eq!(actual_paths, expected_paths);
-
-        Ok(())
+    fn test_split_import_paths_takes_the_last_of_several_as_the_music_dir() {
+        let (import_paths, music_dir) = split_import_paths(vec![
+            "/in/a".to_string(),
+            "/in/b".to_string(),
+            "/music".to_string(),
+        ]);
+        assert_eq!(import_paths, ["/in/a", "/in/b"]);
+        assert_eq!(music_dir, "/music");
+
+        // A lone path is a source, never the destination
+        let (import_paths, _) = split_import_paths(vec!["/in/a".to_string()]);
+        assert_eq!(import_paths, ["/in/a"]);
     }
 
     #[test]
-    fn test_get_all_folder_paths() -> Result<()> {
+    fn test_split_cd_paths_takes_a_lone_directory_as_the_music_dir() -> Result<()> {
         let tmp_dir = tempdir()?;
-        let music_root = tmp_dir.path().join("Music");
-        let artists_dir = music_root.join("Artists");
-
-        fs::create_dir_all(&artists_dir)?;
-
-        // Create a nested directory structure
-        let artist1_dir = artists_dir.join("Artist1");
-        fs::create_dir(&artist1_dir)?;
-        let album1_1_dir = artist1_dir.join("Album1_1");
-        fs::create_dir(&album1_1_dir)?;
-        let album1_2_dir = artist1_dir.join("Album1_2");
-        fs::create_dir(&album1_2_dir)?;
-
-        let artist2_dir = artists_dir.join("Artist2");
-        fs::create_dir(&artist2_dir)?;
-        let album2_1_dir = artist2_dir.join("Album2_1");
-        fs::create_dir(&album2_1_dir)?;
-
-        // Add some files
-        fs::File::create(album1_1_dir.join("track1.mp3"))?.write_all(b"test")?;
-        fs::File::create(album2_1_dir.join("track2.flac"))?.write_all(b"test")?;
-
-        let actual_paths = get_all_folder_paths(music_root.to_str().unwrap())?;
-        let mut expected_paths = vec![
-            music_root.clone(),
-            artists_dir.clone(),
-            artist1_dir.clone(),
-            album1_1_dir.clone(),
-            album1_2_dir.clone(),
-            artist2_dir.clone(),
-            album2_1_dir.clone(),
-        ];
-        expected_paths.sort();
-
-        let mut sorted_actual_paths = actual_paths.clone();
-        sorted_actual_paths.sort();
-
-        assert_eq!(sorted_actual_paths, expected_paths);
+        let music_dir = tmp_dir.path().to_str().unwrap().to_string();
+        let some = |path: &str| Some(path.to_string());
+
+        assert_eq!(split_cd_paths(None, None), ("auto".to_string(), None));
+        assert_eq!(
+            split_cd_paths(some("/dev/sr0"), None),
+            ("/dev/sr0".to_string(), None)
+        );
+        assert_eq!(
+            split_cd_paths(some("/dev/sr0"), some(&music_dir)),
+            ("/dev/sr0".to_string(), some(&music_dir))
+        );
+        assert_eq!(
+            split_cd_paths(some(&music_dir), None),
+            ("auto".to_string(), some(&music_dir))
+        );
 
         Ok(())
     }
 
     #[test]
-    fn test_contains_audio_files() -> Result<()> {
+    fn test_check_music_dir_rejects_root_and_home() -> Result<()> {
         let tmp_dir = tempdir()?;
-        let test_dir = tmp_dir.path();
-
-        // Test directory with audio files
-        let audio_dir = test_dir.join("audio");
-        fs::create_dir(&audio_dir)?;
-        fs::File::create(audio_dir.join("track1.mp3"))?.write_all(b"test")?;
-        fs::File::create(audio_dir.join("track2.flac"))?.write_all(b"test")?;
-
-        assert!(contains_audio_files(&audio_dir));
-
-        // Test directory without audio files
-        let no_audio_dir = test_dir.join("no_audio");
-        fs::create_dir(&no_audio_dir)?;
-        fs::File::create(no_audio_dir.join("info.txt"))?.write_all(b"test")?;
-        fs::File::create(no_audio_dir.join("cover.jpg"))?.write_all(b"test")?;
-
-        assert!(!contains_audio_files(&no_audio_dir));
-
-        // Test empty directory
-        let empty_dir = test_dir.join("empty");
-        fs::create_dir(&empty_dir)?;
-
-        assert!(!contains_audio_files(&empty_dir));
+        let home = tmp_dir.path().join("home");
+        fs::create_dir_all(home.join("Music").join("Artists"))?;
+        fs::File::create(home.join("Music").join("track.mp3"))?.write_all(b"audio")?;
+
+        // Even with audio files below it, the home directory is too broad to organize
+        assert!(check_music_dir(Path::new("/"), &home).is_err());
+        let error = check_music_dir(&home, &home).unwrap_err().to_string();
+        assert!(error.contains("home directory"));
+        assert!(error.contains("--force-unsafe-dir"));
+        assert!(check_music_dir(&home.join("Music"), &home).is_ok());
+        assert!(guard_music_dir("/", false).is_err());
+        assert!(guard_music_dir("/", true).is_ok());
+
+        // A folder of unrelated files is a likely typo; a new, empty one is not
+        let documents = home.join("Documents");
+        fs::create_dir(&documents)?;
+        assert!(check_music_dir(&documents, &home).is_ok());
+        fs::File::create(documents.join("notes.txt"))?.write_all(b"notes")?;
+        assert!(check_music_dir(&documents, &home).is_err());
 
         Ok(())
     }
 
     #[test]
-    fn test_get_all_album_paths_with_no_artists_dir() -> Result<()> {
-        let tmp_dir = tempdir()?;
-        let music_root = tmp_dir.path().join("Music");
-
-        // Don't create Artists directory
-        let album_paths = get_all_album_paths(music_root.to_str().unwrap())?;
-
-        assert_eq!(album_paths.len(), 0);
+    fn test_expand_path_makes_relative_paths_absolute() {
+        let expanded = expand_path("Incoming");
+        let expanded = Path::new(&expanded);
 
-        Ok(())
+        assert!(expanded.is_absolute());
+        assert!(expanded.ends_with("Incoming"));
     }
 
     #[test]
@@ -278,7 +205,9 @@ mod tests {
         let home_dir = tmp_dir.path().join("home").join("user");
         fs::create_dir_all(&home_dir)?;
 
-        // Set the HOME environment variable for this test
+        // Set the HOME environment variable for this test, putting it back afterwards
+        let _home = HOME_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let original_home = std::env::var_os("HOME");
         std::env::set_var("HOME", home_dir.to_str().unwrap());
 
         let music_root = home_dir.join("Music");
@@ -291,9 +220,13 @@ mod tests {
         fs::create_dir(&album_dir)?;
         fs::File::create(album_dir.join("track.mp3"))?.write_all(b"test")?;
 
-        // Test with tilde path
-        let tilde_path = "~/Music";
-        let album_paths = get_all_album_paths(tilde_path)?;
+        // Tilde paths are expanded once at the CLI boundary before reaching the helpers
+        let music_dir = resolve_music_dir(Some("~/Music".to_string()));
+        match original_home {
+            Some(home) => std::env::set_var("HOME", home),
+            None => std::env::remove_var("HOME"),
+        }
+        let album_paths = mfutil::utils::get_all_album_paths(&music_dir)?;
 
         assert_eq!(album_paths.len(), 1);
         assert_eq!(album_paths[0], album_dir);